@@ -0,0 +1,54 @@
+use crate::args::Args;
+use randolib::archive::{ArchiveFormat, ArchiveGen, EntryKind, Hazard};
+use somelib::error::Error;
+use std::{fs, io::Write, path::Path};
+
+/// `hello archive [file] [--format zip|tar] [--files N] [--depth N] [--max-size BYTES]
+/// [--hazard-rate P] [--hazard NAME]... [--seed N]`
+///
+/// Writes a random archive to `file`, in the format its extension names, and prints each
+/// entry with its hazard if it has one. Without a file the archive goes to stdout as
+/// `--format` (ZIP by default). `--hazard` picks which hostile entries to add, and implies a
+/// hazard rate of 0.25.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let hazards = args
+        .values("hazard")
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<Vec<Hazard>, _>>()?;
+    let default_rate = if hazards.is_empty() { 0.0 } else { 0.25 };
+    let mut gen = ArchiveGen::new()
+        .depth(args.value("depth")?.unwrap_or(3))?
+        .hazard_rate(args.value("hazard-rate")?.unwrap_or(default_rate));
+    if !hazards.is_empty() {
+        gen = gen.hazards(&hazards)?;
+    }
+    if let Some(files) = args.value("files")? {
+        gen = gen.files(1..=files)?;
+    }
+    if let Some(max_size) = args.value("max-size")? {
+        gen = gen.size(0..=max_size)?;
+    }
+    let archive = gen.archive(&mut args.rng()?);
+
+    let Some(file) = args.positional(0) else {
+        let format = args.value("format")?.unwrap_or(ArchiveFormat::Zip);
+        let mut out = std::io::stdout().lock();
+        archive.write_to(format, &mut out)?;
+        return Ok(out.flush()?);
+    };
+    let extension = Path::new(file).extension().and_then(|e| e.to_str());
+    let format = extension.unwrap_or("").parse::<ArchiveFormat>()?;
+    fs::write(file, archive.encode(format)?)?;
+    for entry in &archive.entries {
+        let kind = match entry.kind {
+            EntryKind::Dir => "dir",
+            EntryKind::File(_) => "file",
+            EntryKind::Symlink { .. } => "symlink",
+        };
+        let hazard = entry.hazard.map_or("-", |hazard| hazard.name());
+        println!("{}\t{}\t{}\t{}", kind, entry.size(), hazard, entry.path);
+    }
+    Ok(())
+}
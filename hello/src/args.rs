@@ -0,0 +1,110 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use somelib::error::Error;
+use std::{
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A tiny `--flag value` parser. Real applications would reach for a crate like `clap`, but
+/// our needs are small enough that std is plenty.
+pub struct Args {
+    /// Arguments that aren't flags or flag values, in order
+    positional: Vec<String>,
+    /// `(name, value)`, where `value` is `None` for boolean switches
+    flags: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    /// `switches` lists the flags that don't take a value, e.g. `["random-content"]`
+    pub fn parse(raw: &[String], switches: &[&str]) -> Result<Self, Error> {
+        let mut positional = Vec::new();
+        let mut flags = Vec::new();
+        let mut iter = raw.iter();
+        while let Some(arg) = iter.next() {
+            match arg.strip_prefix("--") {
+                Some(name) if switches.contains(&name) => flags.push((name.to_string(), None)),
+                Some(name) => {
+                    let value = iter.next().ok_or_else(|| {
+                        Error::InvalidParameter(format!("--{} needs a value", name))
+                    })?;
+                    flags.push((name.to_string(), Some(value.clone())));
+                }
+                None => positional.push(arg.clone()),
+            }
+        }
+        Ok(Args { positional, flags })
+    }
+
+    pub fn positional(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+
+    /// Whether a boolean switch was given
+    pub fn has(&self, name: &str) -> bool {
+        self.flags.iter().any(|(flag, _)| flag == name)
+    }
+
+    /// Parse the value of `--name` into any `T: FromStr`, e.g. `args.value::<usize>("depth")`
+    pub fn value<T>(&self, name: &str) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+    {
+        // Later flags win, like most command line tools
+        match self.flags.iter().rev().find(|(flag, _)| flag == name) {
+            Some((_, Some(value))) => value.parse::<T>().map(Some).map_err(|_| {
+                Error::InvalidParameter(format!("invalid value {:?} for --{}", value, name))
+            }),
+            _ => Ok(None),
+        }
+    }
+
+    /// `--name` as a number of seconds, `default` if it wasn't given. Negative, NaN and
+    /// too-large values are reported rather than panicking in `Duration`.
+    pub fn secs(&self, name: &str, default: f64) -> Result<Duration, Error> {
+        let secs = self.value(name)?.unwrap_or(default);
+        Duration::try_from_secs_f64(secs).map_err(|_| {
+            Error::InvalidParameter(format!(
+                "--{} must be a non-negative number of seconds, got {:?}",
+                name, secs
+            ))
+        })
+    }
+
+    /// `--start` as Unix seconds, or now if it wasn't given. Generators add to it, so it
+    /// stops at the end of year 9999 to leave them room.
+    pub fn start(&self) -> Result<SystemTime, Error> {
+        const LAST: u64 = 253_402_300_799;
+        let Some(secs) = self.value::<u64>("start")? else {
+            return Ok(SystemTime::now());
+        };
+        (secs <= LAST)
+            .then(|| UNIX_EPOCH.checked_add(Duration::from_secs(secs)))
+            .flatten()
+            .ok_or_else(|| {
+                Error::InvalidParameter(format!(
+                    "--start {} is after {}, the end of year 9999",
+                    secs, LAST
+                ))
+            })
+    }
+
+    /// Every value given for a repeatable flag, e.g. `--path /a --path /b`
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.flags
+            .iter()
+            .filter(|(flag, _)| flag == name)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect()
+    }
+
+    /// A seeded generator if `--seed` was given, otherwise one seeded from the OS.
+    /// `ChaCha20Rng` because its output is stable across `rand` releases, so a seed keeps
+    /// reproducing the same data.
+    pub fn rng(&self) -> Result<ChaCha20Rng, Error> {
+        Ok(match self.value::<u64>("seed")? {
+            Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+            None => ChaCha20Rng::from_entropy(),
+        })
+    }
+}
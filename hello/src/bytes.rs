@@ -0,0 +1,82 @@
+use crate::args::Args;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use randolib::strings;
+use somelib::error::Error;
+use std::{fs::File, io::Write};
+
+/// A multiple of both line widths, so every chunk but the last ends on a full line: 16
+/// bytes per hex dump line and 57 per base64 line, which encode to 76 characters like the
+/// `base64` tool
+const CHUNK: usize = 57 * 16 * 8;
+
+/// `hello bytes [file] [--count N] [--hex|--base64|--raw] [--seed N]`
+///
+/// Writes `--count` random bytes (1024 by default) to `file` or stdout. Raw bytes are the
+/// default for a file, a hex dump for stdout, so a stray run doesn't garble the terminal.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &["hex", "base64", "raw"])?;
+    let count = args.value::<usize>("count")?.unwrap_or(1024);
+    let file = args.positional(0);
+    let encoding = match (args.has("hex"), args.has("base64"), args.has("raw")) {
+        (false, false, false) if file.is_some() => Encoding::Raw,
+        (false, false, false) | (true, false, false) => Encoding::Hex,
+        (false, true, false) => Encoding::Base64,
+        (false, false, true) => Encoding::Raw,
+        _ => {
+            return Err(Error::InvalidParameter(
+                "pick one of --hex, --base64 or --raw".into(),
+            ))
+        }
+    };
+
+    let mut out: Box<dyn Write> = match file {
+        Some(file) => Box::new(File::create(file)?),
+        None => Box::new(std::io::stdout().lock()),
+    };
+    // Chunk by chunk, so a count in the gigabytes doesn't need gigabytes of memory
+    let mut rng = args.rng()?;
+    let mut offset = 0;
+    while offset < count {
+        let chunk = strings::bytes_with(CHUNK.min(count - offset), &mut rng);
+        match encoding {
+            Encoding::Raw => out.write_all(&chunk)?,
+            Encoding::Hex => hex_dump(&chunk, offset, &mut out)?,
+            Encoding::Base64 => {
+                for line in chunk.chunks(57) {
+                    writeln!(out, "{}", STANDARD.encode(line))?;
+                }
+            }
+        }
+        offset += chunk.len();
+    }
+    Ok(out.flush()?)
+}
+
+enum Encoding {
+    Hex,
+    Base64,
+    Raw,
+}
+
+/// Lines like `hexdump -C`: the offset, 16 bytes in hex, then the printable ones as text
+fn hex_dump(bytes: &[u8], offset: usize, out: &mut dyn Write) -> std::io::Result<()> {
+    for (i, line) in bytes.chunks(16).enumerate() {
+        let hex = line
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let text = line
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() {
+                    *b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        writeln!(out, "{:08x}  {:<47}  |{}|", offset + i * 16, hex, text)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use crate::args::Args;
+use randolib::{clickstream::ClickstreamGen, json_string};
+use somelib::error::Error;
+use std::{
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// `hello clickstream [--sessions N] [--users N] [--gap SECS] [--start UNIX_SECS] [--seed N]`
+///
+/// Prints page views on a small online shop as NDJSON, one object per view, session by
+/// session. Timestamps are Unix milliseconds, starting now unless `--start` says otherwise.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let gen = ClickstreamGen::shop()
+        .users(args.value("users")?.unwrap_or(100))?
+        .session_gap(args.secs("gap", 30.0)?);
+    let start = args.start()?;
+    let millis = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+
+    let mut out = std::io::stdout().lock();
+    let sessions = gen.sessions(start, args.rng()?);
+    for session in sessions.take(args.value("sessions")?.unwrap_or(10)) {
+        for event in &session.events {
+            let referrer = event.referrer.as_deref().map_or("null".into(), json_string);
+            writeln!(
+                out,
+                "{{\"session\":{},\"user\":{},\"seq\":{},\"page\":{},\"referrer\":{},\"ts\":{},\"dwell_ms\":{}}}",
+                json_string(&session.id),
+                json_string(&session.user_id),
+                event.seq,
+                json_string(&event.page),
+                referrer,
+                millis(event.at),
+                event.dwell.as_millis()
+            )?;
+        }
+    }
+    Ok(out.flush()?)
+}
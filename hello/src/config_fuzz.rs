@@ -0,0 +1,70 @@
+use crate::args::Args;
+use rand::seq::SliceRandom;
+use randolib::config_fuzz::{ConfigFuzzer, Defect, Field, Format};
+use somelib::error::Error;
+use std::{fs, path::Path};
+
+/// Used when no `--field` is given: the settings most services have
+const DEFAULT_SCHEMA: &[&str] = &[
+    "host:str",
+    "port:int(1..65535)",
+    "log_level:enum(trace|debug|info|warn|error)",
+    "workers?:int(1..64)",
+    "timeout_secs?:float",
+    "debug?:bool",
+    "allowed_origins?:list(str)",
+];
+
+/// `hello config-fuzz [dir] [--field NAME[?]:TYPE]... [--format env|toml|yaml|json]...
+/// [--files N] [--rate P] [--defect NAME]... [--prefix P] [--seed N]`
+///
+/// Without a directory, prints one config. With one, writes `--files` configs into it and
+/// prints each path with its defects, for starting an application against each in turn.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let mut fields = args.values("field");
+    if fields.is_empty() {
+        fields = DEFAULT_SCHEMA.to_vec();
+    }
+    let fields = fields
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<Vec<Field>, _>>()?;
+    let mut fuzzer = ConfigFuzzer::new(fields)?.defect_rate(args.value("rate")?.unwrap_or(0.25));
+    if let Some(prefix) = args.value::<String>("prefix")? {
+        fuzzer = fuzzer.env_prefix(&prefix);
+    }
+    let defects = args
+        .values("defect")
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<Vec<Defect>, _>>()?;
+    if !defects.is_empty() {
+        fuzzer = fuzzer.defects(&defects)?;
+    }
+    let mut formats = args
+        .values("format")
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<Vec<Format>, _>>()?;
+    if formats.is_empty() {
+        formats = Format::ALL.to_vec();
+    }
+    let mut rng = args.rng()?;
+
+    let Some(dir) = args.positional(0) else {
+        let format = *formats.choose(&mut rng).unwrap();
+        print!("{}", fuzzer.case(format, &mut rng).text);
+        return Ok(());
+    };
+    fs::create_dir_all(dir)?;
+    for i in 0..args.value("files")?.unwrap_or(10) {
+        let format = *formats.choose(&mut rng).unwrap();
+        let case = fuzzer.case(format, &mut rng);
+        let path = Path::new(dir).join(format!("case_{:04}.{}", i, format.extension()));
+        fs::write(&path, &case.text)?;
+        let names = case.defects.iter().map(Defect::name).collect::<Vec<_>>();
+        println!("{}\t{}", path.display(), names.join(","));
+    }
+    Ok(())
+}
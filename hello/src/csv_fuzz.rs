@@ -0,0 +1,49 @@
+use crate::args::Args;
+use randolib::csv::{CsvFuzzer, Quirk};
+use somelib::error::Error;
+use std::{fs, io::Write, path::Path};
+
+/// `hello csv-fuzz [dir] [--files N] [--rows N] [--columns N] [--rate P] [--quirk NAME]...
+/// [--no-header] [--seed N]`
+///
+/// Without a directory, writes one CSV file to stdout. With one, writes `--files` files
+/// into it and prints each path with the quirks it got, for feeding a parser under test.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &["no-header"])?;
+    let mut fuzzer = CsvFuzzer::new()
+        .rows(args.value("rows")?.unwrap_or(20))
+        .rate(args.value("rate")?.unwrap_or(0.2))
+        .header(!args.has("no-header"));
+    if let Some(columns) = args.value("columns")? {
+        fuzzer = fuzzer.columns(columns)?;
+    }
+    let quirks = args
+        .values("quirk")
+        .into_iter()
+        .map(str::parse)
+        .collect::<Result<Vec<Quirk>, _>>()?;
+    if !quirks.is_empty() {
+        fuzzer = fuzzer.quirks(&quirks)?;
+    }
+    let mut rng = args.rng()?;
+
+    let Some(dir) = args.positional(0) else {
+        std::io::stdout().write_all(&fuzzer.case(&mut rng).bytes)?;
+        return Ok(());
+    };
+    fs::create_dir_all(dir)?;
+    for i in 0..args.value("files")?.unwrap_or(10) {
+        let case = fuzzer.case(&mut rng);
+        let path = Path::new(dir).join(format!("case_{:04}.csv", i));
+        fs::write(&path, &case.bytes)?;
+        let mut names = case
+            .injected
+            .iter()
+            .map(|injected| injected.quirk.name())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        println!("{}\t{}", path.display(), names.join(","));
+    }
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use crate::args::Args;
+use randolib::http::HttpRequestGen;
+use somelib::error::Error;
+
+/// `hello http [--path TEMPLATE]... [--count N] [--host HOST] [--send BASE_URL] [--seed N]`
+///
+/// Prints randomized HTTP/1.1 requests. Path templates may use `{id}` and `{slug}`. With
+/// `--send`, and built with the `http-client` feature, the requests go to `BASE_URL`
+/// instead and each response's status and timing is printed.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let mut paths = args.values("path");
+    if paths.is_empty() {
+        paths = vec!["/", "/items/{id}", "/users/{id}/{slug}"];
+    }
+    let count = args.value("count")?.unwrap_or(10);
+    let host = args
+        .value("host")?
+        .unwrap_or_else(|| "localhost".to_string());
+
+    let gen = HttpRequestGen::new(&paths)?;
+    let mut rng = args.rng()?;
+    if let Some(base_url) = args.value::<String>("send")? {
+        return send(&base_url, (0..count).map(|_| gen.request(&mut rng)));
+    }
+    for _ in 0..count {
+        print!("{}", gen.request(&mut rng).to_http1(&host));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "http-client")]
+fn send(
+    base_url: &str,
+    requests: impl Iterator<Item = randolib::http::HttpRequest>,
+) -> Result<(), Error> {
+    let sender = randolib::http::HttpSender::new(base_url)?;
+    for request in requests {
+        let response = sender.send(&request)?;
+        println!(
+            "{} {} {} {}ms {}B",
+            request.method,
+            request.uri(),
+            response.status,
+            response.elapsed.as_millis(),
+            response.body_len
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "http-client"))]
+fn send(
+    _base_url: &str,
+    _requests: impl Iterator<Item = randolib::http::HttpRequest>,
+) -> Result<(), Error> {
+    Err(Error::InvalidParameter(
+        "--send needs hello built with the http-client feature".into(),
+    ))
+}
@@ -0,0 +1,24 @@
+use crate::args::Args;
+use randolib::{IdKind, RandoId};
+use somelib::error::Error;
+
+/// `hello id [uuid|ulid|nanoid] [--count N] [--alphabet CHARS] [--length N] [--seed N]`
+///
+/// Prints random identifiers, one per line, UUIDs by default. `--alphabet` and `--length`
+/// customize nanoids.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let mut kind = args.positional(0).unwrap_or("uuid").parse::<IdKind>()?;
+    if let IdKind::NanoId { alphabet, len } = &kind {
+        let custom = args.value::<String>("alphabet")?;
+        let len = args.value("length")?.unwrap_or(*len);
+        let alphabet = custom.unwrap_or_else(|| alphabet.iter().collect());
+        kind = IdKind::nanoid_with(&alphabet, len)?;
+    }
+    let rando = RandoId::with_rng(kind, args.rng()?);
+
+    for id in rando.get_random_vec(args.value("count")?.unwrap_or(1)) {
+        println!("{}", id);
+    }
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use crate::args::Args;
+use randolib::image::{ImageFormat, ImageGen, Pattern};
+use somelib::error::Error;
+use std::{io::Write, path::Path};
+
+/// `hello image [file] [--width N] [--height N] [--pattern noise|gradient|shapes]
+/// [--format png|bmp|ppm] [--noise AMOUNT] [--seed N]`
+///
+/// Writes a random image to `file`, in the format its extension names, or to stdout as
+/// `--format` (PNG by default)
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let gen = ImageGen::new(
+        args.value("width")?.unwrap_or(256),
+        args.value("height")?.unwrap_or(256),
+    )?
+    .pattern(args.value::<Pattern>("pattern")?.unwrap_or(Pattern::Shapes))
+    .noise(args.value("noise")?.unwrap_or(0.0));
+    let image = gen.image(&mut args.rng()?);
+
+    match args.positional(0) {
+        Some(file) => image.save(Path::new(file)),
+        None => {
+            let format = args.value("format")?.unwrap_or(ImageFormat::Png);
+            let mut out = std::io::stdout().lock();
+            image.write_to(format, &mut out)?;
+            Ok(out.flush()?)
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use crate::args::Args;
+use randolib::logs::{LogFormat, LogGen};
+use somelib::error::Error;
+use std::io::Write;
+
+/// `hello logs [--format combined|json|syslog] [--count N] [--rate PER_SEC] [--path PATH]...
+/// [--ipv6-rate P] [--host NAME] [--start UNIX_SECS] [--seed N]`
+///
+/// Prints web server access log lines, one request per line, starting now unless `--start`
+/// says otherwise.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let format = args.value("format")?.unwrap_or(LogFormat::Combined);
+    let mut gen = LogGen::new().ipv6_rate(args.value("ipv6-rate")?.unwrap_or(0.1));
+    let paths = args.values("path");
+    if !paths.is_empty() {
+        gen = gen.paths(&paths)?;
+    }
+    if let Some(host) = args.value::<String>("host")? {
+        gen = gen.host(&host);
+    }
+
+    let mut out = std::io::stdout().lock();
+    let entries = gen.entries(
+        args.start()?,
+        args.value("rate")?.unwrap_or(10.0),
+        args.rng()?,
+    )?;
+    for entry in entries.take(args.value("count")?.unwrap_or(20)) {
+        writeln!(out, "{}", entry.format(format))?;
+    }
+    Ok(out.flush()?)
+}
@@ -1,19 +1,99 @@
-use randolib::{GetRandoStuff, RandoA, RandoB};
+use args::Args;
+use output::{Emitter, Format};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use randolib::{json_string, GetRandoStuff, RandoA, RandoB};
 use somelib::error::Error;
 
+/// Binaries can have modules too, declared from the crate root (`main.rs`)
+mod archive;
+mod args;
+mod bytes;
+mod clickstream;
+mod config_fuzz;
+mod csv_fuzz;
+mod http;
+mod id;
+mod image;
+mod logs;
+mod maze;
+mod mktree;
+mod output;
+mod repl;
+mod stream;
+mod workload;
+
 /// A `main` fn allows us to compile an executable. This can be async.
 /// These can return any type that implements `Termination`
 /// Usually these return the unit `()` or `std::result::Result`
 fn main() -> Result<(), Error> {
-    let rando_a = RandoA::<char>::new();
-    let mut rando_b = RandoB::<char>::new();
+    // The first argument is the path to our binary, skip it
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    // Matching on `Option<&str>` lets us handle "no subcommand" in the same `match`
+    match args.first().map(String::as_str) {
+        Some("--interactive") => repl::run(&args[1..]),
+        Some("archive") => archive::run(&args[1..]),
+        Some("bytes") => bytes::run(&args[1..]),
+        Some("clickstream") => clickstream::run(&args[1..]),
+        Some("config-fuzz") => config_fuzz::run(&args[1..]),
+        Some("csv-fuzz") => csv_fuzz::run(&args[1..]),
+        Some("http") => http::run(&args[1..]),
+        Some("id") => id::run(&args[1..]),
+        Some("image") => image::run(&args[1..]),
+        Some("logs") => logs::run(&args[1..]),
+        Some("maze") => maze::run(&args[1..]),
+        Some("mktree") => mktree::run(&args[1..]),
+        Some("stream") => stream::run(&args[1..]),
+        Some("workload") => workload::run(&args[1..]),
+        _ => demo(&args),
+    }
+}
+
+/// The original demo: print some random chars until `RandoB` repeats itself, or `--count`
+/// draws are done. `--output json` or `--output ndjson` prints them for other programs to read.
+fn demo(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let format = args.value::<Format>("output")?.unwrap_or_default();
+    let count = args.value::<usize>("count")?;
+    // One `--seed` covers both, `RandoB` gets a generator seeded from `RandoA`'s
+    let mut rng = args.rng()?;
+    let mut rando_b = RandoB::<char, _>::with_rng(ChaCha20Rng::seed_from_u64(rng.gen()));
+    let rando_a = RandoA::<char, _>::with_rng(rng);
+    let mut out = Emitter::new(format, std::io::stdout().lock());
 
-    println!("RandoA says: {:?}", rando_a.get_random_vec(12));
-    println!("RandoA says: {:?}", rando_a.get_random_item());
+    let items = rando_a.get_random_vec(12);
+    let json = items
+        .iter()
+        .map(|c| json_string(&c.to_string()))
+        .collect::<Vec<_>>();
+    out.value(
+        "RandoA",
+        &format!("{:?}", items),
+        &format!("[{}]", json.join(",")),
+    )?;
+    let item = rando_a.get_random_item();
+    out.value(
+        "RandoA",
+        &format!("{:?}", item),
+        &json_string(&item.to_string()),
+    )?;
 
-    loop {
-        // Convert `MyResult` into `Result` so we can use the `?` operator
-        let item = Into::<Result<_, _>>::into(rando_b.get_random_item())?;
-        println!("RandoB says: {:?}", item);
+    for _ in 0..count.unwrap_or(usize::MAX) {
+        // Convert `MyResult` into `Result` so we can use the `?` operator, but only after the
+        // error has been written out, so JSON output is still a complete array
+        match rando_b.get_random_item().into_result() {
+            Ok(item) => out.value(
+                "RandoB",
+                &format!("{:?}", item),
+                &json_string(&item.to_string()),
+            )?,
+            Err(error) => {
+                out.error("RandoB", &error)?;
+                out.finish()?;
+                return Err(error);
+            }
+        }
     }
+    Ok(out.finish()?)
 }
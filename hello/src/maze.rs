@@ -0,0 +1,36 @@
+use crate::args::Args;
+use randolib::maze::Grid;
+use somelib::error::Error;
+
+/// `hello maze [--width N] [--height N] [--algorithm backtracker|prim|random]
+/// [--density P] [--seed N]`
+///
+/// Prints a random maze, or with `--algorithm random` a grid map with obstacles
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let width = args.value("width")?.unwrap_or(20);
+    let height = args.value("height")?.unwrap_or(10);
+    let algorithm = args
+        .value("algorithm")?
+        .unwrap_or_else(|| "backtracker".to_string());
+    let mut rng = args.rng()?;
+
+    let grid = match algorithm.as_str() {
+        "backtracker" => Grid::backtracker(width, height, &mut rng),
+        "prim" => Grid::prim(width, height, &mut rng),
+        "random" => Grid::random(
+            width,
+            height,
+            args.value("density")?.unwrap_or(0.3),
+            &mut rng,
+        )?,
+        other => {
+            return Err(Error::InvalidParameter(format!(
+                "unknown algorithm {:?}",
+                other
+            )))
+        }
+    };
+    print!("{}", grid);
+    Ok(())
+}
@@ -0,0 +1,40 @@
+use crate::args::Args;
+use randolib::file_tree::{FileTree, ManifestEntry};
+use somelib::error::Error;
+use std::path::Path;
+
+/// `hello mktree <dir> [--depth N] [--dirs N] [--files N] [--max-size BYTES]
+/// [--pattern PATTERN] [--random-content] [--seed N]`
+///
+/// Creates a random directory tree and prints its manifest, one entry per line
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &["random-content"])?;
+    let root = args
+        .positional(0)
+        .ok_or_else(|| Error::InvalidParameter("usage: hello mktree <dir>".into()))?;
+
+    let mut tree = FileTree::new().random_content(args.has("random-content"));
+    if let Some(depth) = args.value("depth")? {
+        tree = tree.max_depth(depth);
+    }
+    if let Some(dirs) = args.value("dirs")? {
+        tree = tree.dirs_per_dir(0..=dirs);
+    }
+    if let Some(files) = args.value("files")? {
+        tree = tree.files_per_dir(0..=files);
+    }
+    if let Some(max_size) = args.value("max-size")? {
+        tree = tree.file_size(0..=max_size);
+    }
+    if let Some(pattern) = args.value::<String>("pattern")? {
+        tree = tree.name_pattern(&pattern);
+    }
+
+    for entry in tree.create(Path::new(root), &mut args.rng()?)? {
+        match entry {
+            ManifestEntry::Dir { path } => println!("dir\t-\t{}", path.display()),
+            ManifestEntry::File { path, size } => println!("file\t{}\t{}", size, path.display()),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use randolib::json_string;
+use somelib::error::Error;
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// How generated values are printed, chosen with `--output`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// `RandoA says: ...` lines, for people
+    #[default]
+    Text,
+    /// One JSON array, written as values are generated and closed at the end
+    Json,
+    /// One JSON object per line, for streaming into `jq` and friends
+    Ndjson,
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => Err(Error::InvalidParameter(format!(
+                "unknown output {:?}, expected text, json or ndjson",
+                s
+            ))),
+        }
+    }
+}
+
+/// Writes `{"source": .., "value": ..}` records in the chosen format. Values come with both
+/// renderings since only the caller knows its types: `Debug` text, and JSON.
+pub struct Emitter<W: Write> {
+    format: Format,
+    out: W,
+    /// Whether a JSON array element has been written, i.e. the next one needs a comma
+    started: bool,
+}
+
+impl<W: Write> Emitter<W> {
+    pub fn new(format: Format, out: W) -> Self {
+        Emitter {
+            format,
+            out,
+            started: false,
+        }
+    }
+
+    pub fn value(&mut self, source: &str, text: &str, json: &str) -> Result<(), Error> {
+        let record = format!("{{\"source\":{},\"value\":{}}}", json_string(source), json);
+        self.write(&format!("{} says: {}", source, text), &record)
+    }
+
+    pub fn error(&mut self, source: &str, error: &Error) -> Result<(), Error> {
+        let message = json_string(&error.to_string());
+        let record = format!(
+            "{{\"source\":{},\"error\":{}}}",
+            json_string(source),
+            message
+        );
+        // In text mode the error is reported on stderr by `main`, like any other
+        match self.format {
+            Format::Text => Ok(()),
+            _ => self.write("", &record),
+        }
+    }
+
+    fn write(&mut self, text: &str, record: &str) -> Result<(), Error> {
+        match self.format {
+            Format::Text => writeln!(self.out, "{}", text)?,
+            Format::Ndjson => writeln!(self.out, "{}", record)?,
+            Format::Json => {
+                let separator = if self.started { "," } else { "[" };
+                writeln!(self.out, "{}{}", separator, record)?;
+                self.started = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Close the JSON array, an empty one if nothing was written
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.format == Format::Json {
+            writeln!(self.out, "{}", if self.started { "]" } else { "[]" })?;
+        }
+        self.out.flush()
+    }
+}
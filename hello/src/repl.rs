@@ -0,0 +1,191 @@
+use crate::args::Args;
+use rand::{Rng, SeedableRng};
+use somelib::error::Error;
+use std::io::{BufRead, IsTerminal, Write};
+
+/// More than this in one `gen` is surely a typo, and would take all the memory there is
+const MAX_COUNT: usize = 1_000_000;
+
+const HELP: &str = "\
+gen N [TYPE]  N (up to a million) random values, TYPE is one of u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 bool char
+              (default u32)
+seed N        restart the generator from seed N
+stats         how much has been generated, and the spread of the last numbers
+help          this
+quit          leave, as does end of input";
+
+/// `hello --interactive [--seed N]`
+///
+/// Reads one command per line from stdin and prints the results, a console for poking at
+/// random data without restarting. Mistakes are reported and the session goes on. The
+/// `> ` prompt is only shown on a terminal, so piped output is just the results.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &[])?;
+    let mut session = Session::new(args.rng()?);
+    let prompt = std::io::stdin().is_terminal();
+    let mut out = std::io::stdout().lock();
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        if prompt {
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+        let Some(line) = lines.next().transpose()? else {
+            break;
+        };
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            [] => {}
+            ["quit" | "exit"] => break,
+            words => match session.command(words) {
+                Ok(reply) => writeln!(out, "{}", reply)?,
+                Err(error) => eprintln!("error: {}", error),
+            },
+        }
+    }
+    Ok(out.flush()?)
+}
+
+/// Everything that lasts from one command to the next. `seed N` restarts the same kind of
+/// generator the session began with.
+struct Session<R> {
+    rng: R,
+    batches: usize,
+    values: usize,
+    /// The last batch as `f64`s, empty unless it was numbers
+    last: Vec<f64>,
+}
+
+impl<R: Rng + SeedableRng> Session<R> {
+    fn new(rng: R) -> Self {
+        Session {
+            rng,
+            batches: 0,
+            values: 0,
+            last: Vec::new(),
+        }
+    }
+
+    /// Run one command, returning what to print
+    fn command(&mut self, words: &[&str]) -> Result<String, Error> {
+        match words {
+            ["gen", count] => self.generate(parse(count)?, "u32"),
+            ["gen", count, ty] => self.generate(parse(count)?, ty),
+            ["seed", seed] => {
+                let seed = parse::<u64>(seed)?;
+                self.rng = R::seed_from_u64(seed);
+                Ok(format!("seeded with {}", seed))
+            }
+            ["stats"] => Ok(self.stats()),
+            ["help"] => Ok(HELP.to_string()),
+            _ => Err(Error::InvalidParameter(format!(
+                "unknown command {:?}, try help",
+                words.join(" ")
+            ))),
+        }
+    }
+
+    fn generate(&mut self, count: usize, ty: &str) -> Result<String, Error> {
+        if count > MAX_COUNT {
+            return Err(Error::InvalidParameter(format!(
+                "gen {} is more than the {} values one gen can make",
+                count, MAX_COUNT
+            )));
+        }
+        // One arm per type, all alike but for the type, which is what macros are for
+        macro_rules! numbers {
+            ($t:ty) => {{
+                let values = (0..count).map(|_| self.rng.gen()).collect::<Vec<$t>>();
+                self.last = values.iter().map(|v| *v as f64).collect();
+                format!("{:?}", values)
+            }};
+        }
+        let reply = match ty {
+            "u8" => numbers!(u8),
+            "u16" => numbers!(u16),
+            "u32" => numbers!(u32),
+            "u64" => numbers!(u64),
+            "i8" => numbers!(i8),
+            "i16" => numbers!(i16),
+            "i32" => numbers!(i32),
+            "i64" => numbers!(i64),
+            "f32" => numbers!(f32),
+            "f64" => numbers!(f64),
+            "bool" => {
+                self.last.clear();
+                format!(
+                    "{:?}",
+                    (0..count).map(|_| self.rng.gen()).collect::<Vec<bool>>()
+                )
+            }
+            "char" => {
+                self.last.clear();
+                format!(
+                    "{:?}",
+                    (0..count).map(|_| self.rng.gen()).collect::<Vec<char>>()
+                )
+            }
+            _ => {
+                return Err(Error::InvalidParameter(format!(
+                    "unknown type {:?}, try help",
+                    ty
+                )))
+            }
+        };
+        self.batches += 1;
+        self.values += count;
+        Ok(reply)
+    }
+
+    fn stats(&self) -> String {
+        let mut reply = format!("{} values in {} batches", self.values, self.batches);
+        if !self.last.is_empty() {
+            let min = self.last.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = self.last.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let mean = self.last.iter().sum::<f64>() / self.last.len() as f64;
+            reply += &format!("\nlast batch: min {} max {} mean {:.3}", min, max, mean);
+        }
+        reply
+    }
+}
+
+fn parse<T: std::str::FromStr>(word: &str) -> Result<T, Error> {
+    word.parse()
+        .map_err(|_| Error::InvalidParameter(format!("{:?} isn't a valid number", word)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::ChaCha20Rng;
+
+    fn session() -> Session<ChaCha20Rng> {
+        Session::new(ChaCha20Rng::seed_from_u64(279))
+    }
+
+    #[test]
+    fn it_reports_bad_commands_and_carries_on() {
+        let mut session = session();
+        for bad in [
+            &["gen", "100000000000000", "u8"][..],
+            &["gen", "ten"],
+            &["gen", "-1"],
+            &["gen", "3", "u128"],
+            &["seed", "forty-two"],
+            &["stats", "now"],
+            &["bogus"],
+        ] {
+            assert!(session.command(bad).is_err(), "{:?}", bad);
+        }
+        // Failed commands generate nothing, and the session still works
+        assert_eq!(
+            session.command(&["stats"]).unwrap(),
+            "0 values in 0 batches"
+        );
+        assert!(session.command(&["gen", "1000000", "u8"]).is_ok());
+        assert!(session
+            .command(&["stats"])
+            .unwrap()
+            .starts_with("1000000 values in 1 batches\nlast batch: min 0 max 255"));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::args::Args;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use randolib::{
+    rate_limit::RateLimiter,
+    sink::{Batcher, Sink, WriteSink},
+    traffic::{BurstPattern, RateController},
+};
+use somelib::error::Error;
+use std::time::{Duration, Instant};
+
+/// `hello stream [--rate N] [--burst-gap SECS] [--burst-min N] [--burst-shape A]
+/// [--burst-duration SECS] [--max-rate N [--max-burst N] [--jitter F]] [--count N]
+/// [--seed N] [--dry-run] [--batch N] [--redis URL [--redis-key KEY] [--redis-max-len N]]`
+///
+/// Prints one line per event, `offset_secs rate burst|base`, paced in real time to a
+/// baseline rate with random bursts on top. `--max-rate` caps bursts with a token bucket,
+/// holding events back until it has room. `--dry-run` prints the schedule without
+/// waiting. Lines go out `--batch` at a time; with `--redis`, and built with the `redis`
+/// feature, they're pushed onto a Redis list instead, waiting while it holds more than
+/// `--redis-max-len`.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &["dry-run"])?;
+    let pattern = BurstPattern::new(args.value("rate")?.unwrap_or(5.0))?
+        .burst_gap(args.secs("burst-gap", 20.0)?)?
+        .burst_size(
+            args.value("burst-min")?.unwrap_or(50.0),
+            args.value("burst-shape")?.unwrap_or(1.5),
+        )?
+        .burst_duration(args.secs("burst-duration", 3.0)?)?;
+    let count = args.value::<usize>("count")?;
+    let mut limiter = match args.value("max-rate")? {
+        Some(max_rate) => Some(
+            RateLimiter::new(max_rate, args.value("max-burst")?.unwrap_or(1))?
+                .with_jitter(args.value("jitter")?.unwrap_or(0.0))?,
+        ),
+        None => None,
+    };
+    // One `--seed` covers both, the limiter's jitter gets a generator seeded from the
+    // schedule's
+    let mut rng = args.rng()?;
+    let mut jitter = ChaCha20Rng::seed_from_u64(rng.gen());
+    let events = pattern.events(rng);
+
+    let mut batcher = Batcher::new(sink(&args)?, args.value("batch")?.unwrap_or(1))?;
+    let mut controller = RateController::new(events);
+    // The limiter works on the schedule rather than the clock, so a dry run holds events
+    // back exactly as a real one would
+    let start = Instant::now();
+    let mut at = Duration::ZERO;
+    // `take` needs a count, so an endless stream uses the largest there is
+    for _ in 0..count.unwrap_or(usize::MAX) {
+        let Some((scheduled, _)) = controller.next_delay(Duration::MAX) else {
+            break;
+        };
+        // An event the limiter held back holds back everything scheduled before it went
+        at = at.max(scheduled);
+        if let Some(limiter) = &mut limiter {
+            at += limiter.reserve_at(start + at, &mut jitter);
+        }
+        if !args.has("dry-run") {
+            std::thread::sleep(at.saturating_sub(start.elapsed()));
+        }
+        let phase = if controller.in_burst() {
+            "burst"
+        } else {
+            "base"
+        };
+        batcher.push(format!(
+            "{:.3} {:.1} {}",
+            at.as_secs_f64(),
+            controller.rate(),
+            phase
+        ))?;
+    }
+    batcher.flush()
+}
+
+#[cfg(feature = "redis")]
+fn sink(args: &Args) -> Result<Box<dyn Sink>, Error> {
+    let Some(url) = args.value::<String>("redis")? else {
+        return Ok(Box::new(WriteSink(std::io::stdout().lock())));
+    };
+    let key = args
+        .value("redis-key")?
+        .unwrap_or_else(|| "hello:stream".to_string());
+    let mut sink = randolib::sink::RedisSink::new(&url, &key)?;
+    if let Some(max_len) = args.value("redis-max-len")? {
+        sink = sink.max_len(max_len);
+    }
+    Ok(Box::new(sink))
+}
+
+#[cfg(not(feature = "redis"))]
+fn sink(args: &Args) -> Result<Box<dyn Sink>, Error> {
+    if args.has("redis") {
+        return Err(Error::InvalidParameter(
+            "--redis needs hello built with the redis feature".into(),
+        ));
+    }
+    Ok(Box::new(WriteSink(std::io::stdout().lock())))
+}
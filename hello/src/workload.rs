@@ -0,0 +1,56 @@
+use crate::args::Args;
+use rand::{Rng, RngCore};
+use randolib::workload::{self, Operation, Workload};
+use somelib::error::Error;
+
+/// `hello workload [--preset a|b|c|d] [--records N] [--count N] [--read P --update P
+/// --insert P] [--zipf S] [--min-value N] [--max-value N] [--load] [--values] [--seed N]`
+///
+/// Prints a YCSB-style key-value workload, one operation per line. `--load` prints the
+/// `INSERT`s that preload the records first, `--values` appends each value in hex.
+pub fn run(raw: &[String]) -> Result<(), Error> {
+    let args = Args::parse(raw, &["load", "values"])?;
+    let records = args.value("records")?.unwrap_or(1000);
+    let preset = args.value("preset")?.unwrap_or_else(|| "a".to_string());
+    let mut workload = Workload::preset(&preset, records)?;
+    if let (Some(read), Some(update), Some(insert)) = (
+        args.value("read")?,
+        args.value("update")?,
+        args.value("insert")?,
+    ) {
+        workload = workload.mix(read, update, insert)?;
+    }
+    if let Some(exponent) = args.value("zipf")? {
+        workload = workload.zipf(exponent)?;
+    }
+    let min = args.value("min-value")?.unwrap_or(100);
+    let max = args.value("max-value")?.unwrap_or(1000);
+    workload = workload.value_size(min..=max)?;
+    let count = args.value("count")?.unwrap_or(20);
+    let mut rng = args.rng()?;
+
+    let print = |op: &Operation| match op {
+        Operation::Update { value, .. } | Operation::Insert { value, .. } if args.has("values") => {
+            let hex = value
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>();
+            println!("{} {}", op, hex)
+        }
+        _ => println!("{}", op),
+    };
+    if args.has("load") {
+        for index in 0..workload.records() {
+            let mut value = vec![0; rng.gen_range(min..=max)];
+            rng.fill_bytes(&mut value);
+            print(&Operation::Insert {
+                key: workload::key(index),
+                value,
+            });
+        }
+    }
+    for op in workload.ops(rng).take(count) {
+        print(&op);
+    }
+    Ok(())
+}
@@ -1 +1,531 @@
+//! Integration tests live in `tests/` and can only use the public interface of a crate. For a
+//! binary that means running it, which cargo makes easy with `CARGO_BIN_EXE_<name>`.
+use std::process::{Command, Output};
 
+/// Run `hello` with `args` and return its output
+fn hello(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_hello"))
+        .args(args)
+        .output()
+        .expect("failed to run hello")
+}
+
+#[test]
+fn mktree_prints_a_manifest_of_what_it_created() {
+    let root = std::env::temp_dir().join(format!("hello_mktree_{}", std::process::id()));
+    let output = hello(&[
+        "mktree",
+        root.to_str().unwrap(),
+        "--seed",
+        "1",
+        "--depth",
+        "1",
+    ]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for line in stdout.lines() {
+        // `kind \t size \t path`
+        let path = line.split('\t').nth(2).unwrap();
+        assert!(root.join(path).exists());
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn http_is_reproducible_with_a_seed() {
+    let args = ["http", "--count", "5", "--seed", "9", "--path", "/v1/{id}"];
+    let first = hello(&args);
+    let second = hello(&args);
+
+    assert!(first.status.success());
+    assert_eq!(first.stdout, second.stdout);
+    let stdout = String::from_utf8(first.stdout).unwrap();
+    assert_eq!(stdout.matches(" HTTP/1.1\r\n").count(), 5);
+}
+
+#[cfg(feature = "http-client")]
+#[test]
+fn http_sends_requests_to_a_server() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    // Answers `200 ok` to every request on every connection, until the test ends
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            std::thread::spawn(move || loop {
+                let mut length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    line.clear();
+                }
+                if line != "\r\n" {
+                    return;
+                }
+                reader.read_exact(&mut vec![0; length]).unwrap();
+                let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                reader.get_mut().write_all(response).unwrap();
+            });
+        }
+    });
+
+    let url = format!("http://127.0.0.1:{}", port);
+    let output = hello(&["http", "--count", "3", "--seed", "1", "--send", &url]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 3);
+    // `METHOD URI STATUS TIME SIZE`
+    assert!(stdout
+        .lines()
+        .all(|l| l.contains(" 200 ") && l.ends_with(" 2B")));
+}
+
+#[test]
+fn maze_prints_a_bordered_maze() {
+    let output = hello(&["maze", "--width", "4", "--height", "3", "--seed", "2"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let rows = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(rows.len(), 7);
+    assert!(rows.iter().all(|row| row.len() == 9));
+    assert_eq!(rows[0], "#########");
+}
+
+#[test]
+fn stream_dry_run_prints_increasing_offsets() {
+    let args = ["stream", "--count", "50", "--seed", "3", "--dry-run"];
+    let output = hello(&args);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, hello(&args).stdout);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let offsets = stdout
+        .lines()
+        .map(|line| line.split(' ').next().unwrap().parse::<f64>().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(offsets.len(), 50);
+    assert!(offsets.windows(2).all(|w| w[0] <= w[1]));
+
+    let batched = hello(&[&args[..], &["--batch", "8"]].concat());
+    assert_eq!(batched.stdout, hello(&args).stdout);
+    assert!(!hello(&[&args[..], &["--batch", "0"]].concat())
+        .status
+        .success());
+}
+
+#[test]
+fn stream_max_rate_spaces_out_bursts() {
+    let args = [
+        "stream",
+        "--count",
+        "200",
+        "--burst-gap",
+        "1",
+        "--max-rate",
+        "20",
+        "--seed",
+        "3",
+        "--dry-run",
+    ];
+    let output = hello(&args);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, hello(&args).stdout);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let offsets = stdout
+        .lines()
+        .map(|line| line.split(' ').next().unwrap().parse::<f64>().unwrap())
+        .collect::<Vec<_>>();
+    // Offsets are printed to the millisecond, so allow for rounding
+    assert!(offsets.windows(2).all(|w| w[1] - w[0] >= 0.049));
+    assert!(!hello(&["stream", "--max-rate", "0", "--dry-run"])
+        .status
+        .success());
+}
+
+#[test]
+fn stream_rejects_durations_that_are_not_seconds() {
+    for (flag, value) in [
+        ("--burst-gap", "-1"),
+        ("--burst-duration", "nan"),
+        ("--burst-gap", "1e300"),
+    ] {
+        let output = hello(&["stream", flag, value, "--dry-run"]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(flag), "{}", stderr);
+        assert!(!stderr.contains("panicked"), "{}", stderr);
+    }
+}
+
+#[test]
+fn workload_loads_then_runs_operations() {
+    let args = [
+        "workload",
+        "--records",
+        "5",
+        "--count",
+        "10",
+        "--preset",
+        "b",
+        "--load",
+        "--seed",
+        "4",
+    ];
+    let output = hello(&args);
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout, hello(&args).stdout);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 15);
+    assert!(lines[..5]
+        .iter()
+        .all(|line| line.starts_with("INSERT user")));
+    assert!(lines[5..]
+        .iter()
+        .all(|line| line.starts_with("READ ") || line.starts_with("UPDATE ")));
+}
+
+#[test]
+fn csv_fuzz_writes_files_and_lists_their_quirks() {
+    let dir = std::env::temp_dir().join(format!("hello_csv_fuzz_{}", std::process::id()));
+    let output = hello(&[
+        "csv-fuzz",
+        dir.to_str().unwrap(),
+        "--files",
+        "3",
+        "--rate",
+        "1",
+        "--quirk",
+        "ragged-row",
+        "--seed",
+        "5",
+    ]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    for line in lines {
+        let (path, quirks) = line.split_once('\t').unwrap();
+        assert_eq!(quirks, "ragged-row");
+        assert!(std::fs::read(path).unwrap().starts_with(b"col1,"));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = hello(&["csv-fuzz", "--rows", "2", "--rate", "0"]).stdout;
+    assert_eq!(stdout.split(|b| *b == b'\n').count(), 4);
+    assert!(!hello(&["csv-fuzz", "--quirk", "nonsense"]).status.success());
+}
+
+#[test]
+fn demo_json_output_is_one_array() {
+    let output = hello(&["--output", "json", "--count", "3", "--seed", "4"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    // Two `RandoA` values and three `RandoB` ones, then the closing bracket
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("[{\"source\":\"RandoA\",\"value\":["));
+    assert!(lines[1..5]
+        .iter()
+        .all(|line| line.starts_with(",{\"source\":")));
+    assert_eq!(lines[5], "]");
+}
+
+#[test]
+fn demo_ndjson_output_is_one_object_per_line() {
+    let args = ["--output", "ndjson", "--count", "20", "--seed", "4"];
+    let output = hello(&args);
+
+    // A repeat ends the run early with an error record, either way every line is an object
+    assert_eq!(output.stdout, hello(&args).stdout);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    for line in stdout.lines() {
+        assert!(line.starts_with("{\"source\":\"Rando"));
+        assert!(line.ends_with('}'));
+    }
+    let errors = stdout.matches("\"error\":").count();
+    assert_eq!(output.status.success(), errors == 0);
+}
+
+#[test]
+fn config_fuzz_writes_configs_with_their_defects() {
+    let dir = std::env::temp_dir().join(format!("hello_config_fuzz_{}", std::process::id()));
+    let output = hello(&[
+        "config-fuzz",
+        dir.to_str().unwrap(),
+        "--files",
+        "3",
+        "--format",
+        "env",
+        "--rate",
+        "1",
+        "--defect",
+        "missing-required",
+        "--field",
+        "port:int(1..65535)",
+        "--prefix",
+        "SVC_",
+        "--seed",
+        "6",
+    ]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    for line in lines {
+        let (path, defects) = line.split_once('\t').unwrap();
+        assert!(path.ends_with(".env"));
+        assert_eq!(defects, "missing-required");
+        // The only field was required and is gone
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "");
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = hello(&[
+        "config-fuzz",
+        "--format",
+        "toml",
+        "--rate",
+        "0",
+        "--field",
+        "debug:bool",
+    ])
+    .stdout;
+    let stdout = String::from_utf8(stdout).unwrap();
+    assert!(stdout == "debug = true\n" || stdout == "debug = false\n");
+    assert!(!hello(&["config-fuzz", "--field", "port"]).status.success());
+}
+
+#[test]
+fn id_prints_one_id_per_line() {
+    let output = hello(&["id", "--count", "3", "--seed", "7"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout
+        .lines()
+        .all(|id| id.len() == 36 && &id[14..15] == "4"));
+
+    let args = [
+        "id",
+        "nanoid",
+        "--alphabet",
+        "01",
+        "--length",
+        "8",
+        "--seed",
+        "7",
+    ];
+    let stdout = String::from_utf8(hello(&args).stdout).unwrap();
+    assert_eq!(stdout.trim().len(), 8);
+    assert!(stdout.trim().chars().all(|c| c == '0' || c == '1'));
+    assert_eq!(hello(&args).stdout, stdout.as_bytes());
+    assert!(!hello(&["id", "guid"]).status.success());
+}
+
+#[test]
+fn image_writes_a_ppm_to_stdout_or_a_png_file() {
+    let args = [
+        "image", "--width", "4", "--height", "2", "--format", "ppm", "--seed", "3",
+    ];
+    let output = hello(&args);
+    assert!(output.status.success());
+    let header = b"P6\n4 2\n255\n";
+    assert_eq!(&output.stdout[..header.len()], header);
+    assert_eq!(output.stdout.len(), header.len() + 4 * 2 * 3);
+
+    let file = std::env::temp_dir().join(format!("hello_image_{}.png", std::process::id()));
+    let output = hello(&["image", file.to_str().unwrap(), "--pattern", "noise"]);
+    assert!(output.status.success());
+    let bytes = std::fs::read(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+}
+
+#[test]
+fn archive_writes_a_tar_and_lists_its_hazards() {
+    let file = std::env::temp_dir().join(format!("hello_archive_{}.tar", std::process::id()));
+    let output = hello(&[
+        "archive",
+        file.to_str().unwrap(),
+        "--files",
+        "20",
+        "--hazard",
+        "traversal",
+        "--hazard-rate",
+        "1",
+        "--seed",
+        "5",
+    ]);
+    let bytes = std::fs::read(&file).unwrap();
+    std::fs::remove_file(&file).unwrap();
+    assert!(output.status.success());
+    assert_eq!(&bytes[257..263], b"ustar\0");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hazards = stdout
+        .lines()
+        .filter(|line| line.split('\t').nth(2) == Some("traversal"))
+        .collect::<Vec<_>>();
+    assert!(!hazards.is_empty());
+    assert!(hazards.iter().all(|line| line.contains("../")));
+
+    let output = hello(&["archive", "--format", "zip", "--seed", "5"]);
+    assert!(output.stdout.starts_with(b"PK\x03\x04"));
+}
+
+#[test]
+fn clickstream_prints_one_json_object_per_page_view() {
+    let args = [
+        "clickstream",
+        "--sessions",
+        "5",
+        "--start",
+        "1700000000",
+        "--seed",
+        "11",
+    ];
+    let output = hello(&args);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.lines().count() >= 5);
+    for line in stdout.lines() {
+        assert!(line.starts_with("{\"session\":\""));
+        assert!(line.contains(",\"ts\":17") && line.ends_with('}'));
+    }
+    // Each session starts with a landing page, which has no referrer
+    let landings = stdout.matches("\"seq\":0,").count();
+    assert_eq!(landings, 5);
+    assert_eq!(landings, stdout.matches("\"referrer\":null").count());
+    assert_eq!(stdout, String::from_utf8(hello(&args).stdout).unwrap());
+
+    for bad in [["--gap", "-1"], ["--start", "9223372036854775807"]] {
+        let output = hello(&[&["clickstream"][..], &bad].concat());
+        assert!(!output.status.success());
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(bad[0]), "{}", stderr);
+    }
+}
+
+#[test]
+fn logs_prints_one_line_per_request_in_each_format() {
+    let run = |format: &str| {
+        let args = [
+            "logs", "--format", format, "--count", "50", "--start", "0", "--seed", "3",
+        ];
+        let output = hello(&args);
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let combined = run("combined");
+    assert_eq!(combined.lines().count(), 50);
+    assert!(combined
+        .lines()
+        .all(|line| line.contains(" - - [01/Jan/1970:") && line.ends_with('"')));
+    // The same seed gives the same requests whatever the format
+    let json = run("json");
+    assert!(json
+        .lines()
+        .all(|line| line.starts_with("{\"ts\":\"1970-01-01T")));
+    let statuses = |out: &str, key: &str| out.matches(key).count();
+    assert_eq!(
+        statuses(&combined, "\" 200 "),
+        statuses(&json, "\"status\":200,")
+    );
+    assert!(run("syslog").lines().all(|line| line.starts_with('<')));
+    assert!(!hello(&["logs", "--format", "xml"]).status.success());
+    let output = hello(&["logs", "--start", "18446744073709551615"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--start"));
+}
+
+#[test]
+fn interactive_mode_reads_commands_from_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_hello"))
+        .args(["--interactive", "--seed", "7"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run hello");
+    // Dropping `stdin` at the end of the statement closes it, though `quit` comes first
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"gen 3 u8\nbogus\nseed 42\ngen 2 bool\nstats\nquit\ngen 1\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    assert_eq!(lines[0].matches(',').count(), 2);
+    assert_eq!(lines[1], "seeded with 42");
+    assert_eq!(lines[3], "5 values in 2 batches");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unknown command \"bogus\""));
+}
+
+#[test]
+fn bytes_writes_hex_base64_and_raw() {
+    let hex = hello(&["bytes", "--count", "40", "--seed", "5"]);
+    assert!(hex.status.success());
+    let hex = String::from_utf8(hex.stdout).unwrap();
+    let lines = hex.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[2].starts_with("00000020  "));
+
+    let base64 = hello(&["bytes", "--count", "60", "--base64", "--seed", "5"]);
+    let base64 = String::from_utf8(base64.stdout).unwrap();
+    assert_eq!(base64.lines().map(str::len).collect::<Vec<_>>(), [76, 4]);
+
+    let raw = hello(&["bytes", "--count", "8000", "--raw", "--seed", "5"]);
+    assert_eq!(raw.stdout.len(), 8000);
+    // The same seed gives the same bytes whatever the encoding
+    assert!(lines[0].starts_with(&format!("00000000  {:02x} ", raw.stdout[0])));
+
+    assert!(!hello(&["bytes", "--hex", "--raw"]).status.success());
+}
+
+#[test]
+fn csv_fuzz_takes_a_nan_rate_as_zero() {
+    let output = hello(&["csv-fuzz", "--rate", "nan", "--seed", "1"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn config_fuzz_takes_a_nan_rate_as_zero() {
+    let output = hello(&["config-fuzz", "--rate", "nan", "--seed", "1"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn archive_takes_a_nan_hazard_rate_as_zero() {
+    let output = hello(&["archive", "--hazard-rate", "nan", "--seed", "1"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn logs_take_a_nan_ipv6_rate_as_zero() {
+    let output = hello(&["logs", "--ipv6-rate", "nan", "--count", "20", "--seed", "1"]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8(output.stdout).unwrap().contains("::"));
+}
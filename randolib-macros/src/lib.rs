@@ -0,0 +1,111 @@
+//! Attribute macros for randolib. Use them through `randolib`, which re-exports them: the
+//! generated code refers to `::randolib::property`.
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse::Parser, parse_macro_input, punctuated::Punctuated, Expr, FnArg, ItemFn, Lit,
+    MetaNameValue, Pat, ReturnType, Token,
+};
+
+/// Turn a function into a property test: a `#[test]` that runs the body `cases` times
+/// (100 by default), each time with fresh arguments drawn through
+/// `randolib::property::Arbitrary`.
+///
+/// ```ignore
+/// #[rando_test(cases = 500)]
+/// fn reversing_twice_is_identity(v: Vec<u8>) {
+///     let mut w = v.clone();
+///     w.reverse();
+///     w.reverse();
+///     assert_eq!(v, w);
+/// }
+/// ```
+///
+/// A failing case prints its seed and arguments, see `randolib::property::run` for how to
+/// rerun it on its own.
+#[proc_macro_attribute]
+pub fn rando_test(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    match expand(args.into(), item) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(args: proc_macro2::TokenStream, item: ItemFn) -> syn::Result<proc_macro2::TokenStream> {
+    let mut cases = quote!(100u32);
+    let args = Punctuated::<MetaNameValue, Token![,]>::parse_terminated.parse2(args)?;
+    for arg in args {
+        match (arg.path.get_ident(), &arg.value) {
+            (Some(name), Expr::Lit(lit)) if name == "cases" => match &lit.lit {
+                Lit::Int(n) => {
+                    let n = n.base10_parse::<u32>()?;
+                    cases = quote!(#n);
+                }
+                other => return Err(syn::Error::new_spanned(other, "expected a number of cases")),
+            },
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    arg.path,
+                    "the only option is `cases = N`",
+                ))
+            }
+        }
+    }
+
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = item;
+    if let ReturnType::Type(_, ty) = &sig.output {
+        return Err(syn::Error::new_spanned(
+            ty,
+            "property tests can't return a value",
+        ));
+    }
+
+    let case = format_ident!("__rando_case");
+    let mut draws = Vec::new();
+    let mut names = Vec::new();
+    for input in &sig.inputs {
+        let FnArg::Typed(typed) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "property tests can't take `self`",
+            ));
+        };
+        let Pat::Ident(ident) = &*typed.pat else {
+            return Err(syn::Error::new_spanned(
+                &typed.pat,
+                "expected a plain argument name",
+            ));
+        };
+        let (pat, ty, name) = (&typed.pat, &typed.ty, &ident.ident);
+        draws.push(quote! {
+            let #pat: #ty = #case.arbitrary();
+        });
+        names.push(quote! {
+            (stringify!(#name), &#name as &dyn ::std::fmt::Debug)
+        });
+    }
+
+    let name = &sig.ident;
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis fn #name() {
+            ::randolib::property::run(
+                concat!(module_path!(), "::", stringify!(#name)),
+                #cases,
+                |#case: &mut ::randolib::property::Case| {
+                    #(#draws)*
+                    #case.record(&[#(#names),*]);
+                    #block
+                },
+            );
+        }
+    })
+}
@@ -0,0 +1,65 @@
+//! Benchmarks for the hot paths of `Rando*`, so a change like dropping the `[T; 32]` array
+//! from `get_random_vec` shows up as a number rather than a hunch. Run with
+//! `cargo bench -p randolib`; criterion keeps the last run and reports the change.
+//!
+//! Everything draws from a seeded `ChaCha20Rng`, the generator `from_seed` uses, so the
+//! comparisons are between our code and not between generators.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use randolib::{GetRandoStuff, RandoA, RandoB, RepeatPolicy};
+
+const SEED: u64 = 276;
+
+/// `get_random_vec` against the loop we'd write by hand with `rand`
+fn random_vec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random_vec");
+    for len in [1, 8, 32] {
+        let rando = RandoA::<u64, _>::from_seed(SEED);
+        group.bench_with_input(BenchmarkId::new("get_random_vec", len), &len, |b, &len| {
+            b.iter(|| rando.get_random_vec(black_box(len)))
+        });
+        let mut rng = ChaCha20Rng::seed_from_u64(SEED);
+        group.bench_with_input(BenchmarkId::new("rand_loop", len), &len, |b, &len| {
+            b.iter(|| {
+                (0..black_box(len))
+                    .map(|_| rng.gen::<u64>())
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// What `RandoB`'s repeat check costs over `RandoA`, which has none. `u64` repeats are
+/// vanishingly rare, so this measures the check and not the retries.
+fn repeat_check(c: &mut Criterion) {
+    let mut group = c.benchmark_group("repeat_check");
+    let rando = RandoA::<u64, _>::from_seed(SEED);
+    group.bench_function("rando_a", |b| b.iter(|| rando.get_random_item()));
+    for lookback in [1, 16, 256] {
+        let mut rando = RandoB::<u64, _>::from_seed(SEED).lookback(lookback);
+        group.bench_with_input(BenchmarkId::new("rando_b", lookback), &lookback, |b, _| {
+            b.iter(|| rando.get_random_item())
+        });
+    }
+    let mut rando = RandoB::<u64, _>::from_seed(SEED).policy(RepeatPolicy::Allow);
+    group.bench_function("rando_b_allow", |b| b.iter(|| rando.get_random_item()));
+    group.finish();
+}
+
+/// A random `char` is rejection sampled to skip the surrogate range, a `u64` is one draw
+fn char_vs_u64(c: &mut Criterion) {
+    let mut group = c.benchmark_group("char_vs_u64");
+    let chars = RandoA::<char, _>::from_seed(SEED);
+    group.bench_function("char", |b| b.iter(|| chars.get_random_item()));
+    let numbers = RandoA::<u64, _>::from_seed(SEED);
+    group.bench_function("u64", |b| b.iter(|| numbers.get_random_item()));
+    group.bench_function("char_vec_32", |b| b.iter(|| chars.get_random_vec(32)));
+    group.bench_function("u64_vec_32", |b| b.iter(|| numbers.get_random_vec(32)));
+    group.finish();
+}
+
+criterion_group!(benches, random_vec, repeat_check, char_vs_u64);
+criterion_main!(benches);
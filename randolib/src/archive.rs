@@ -0,0 +1,643 @@
+//! Random ZIP and tar archives for testing extraction code: nested directories of random
+//! files and, optionally, hostile entries like `../../evil` that a careful extractor has to
+//! refuse. Every hostile entry is flagged with its `Hazard`, so a test knows exactly which
+//! entries should have been rejected.
+//!
+//! Entries are stored uncompressed, which every reader supports. Extract into a scratch
+//! directory: an extractor that falls for a hazard writes outside it.
+
+use crate::{checksum::crc32, probability};
+use rand::{distributions::Alphanumeric, prelude::*};
+use somelib::error::Error;
+use std::{
+    io::{self, Write},
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+/// How to encode an archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArchiveFormat {
+    Zip,
+    /// POSIX ustar
+    Tar,
+}
+
+impl ArchiveFormat {
+    pub const ALL: [ArchiveFormat; 2] = [ArchiveFormat::Zip, ArchiveFormat::Tar];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ArchiveFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown archive format {:?}", s)))
+    }
+}
+
+/// A way an entry can try to escape the extraction directory or confuse the extractor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hazard {
+    /// `../` components climbing out, the classic "zip slip"
+    Traversal,
+    /// An absolute path like `/tmp/...`
+    Absolute,
+    /// `..\` components, harmless on Unix but a traversal on Windows
+    Backslash,
+    /// A symlink pointing outside, so a later entry written through it lands outside too
+    Symlink,
+    /// The same path as an earlier file, with different content
+    Duplicate,
+}
+
+impl Hazard {
+    pub const ALL: [Hazard; 5] = [
+        Hazard::Traversal,
+        Hazard::Absolute,
+        Hazard::Backslash,
+        Hazard::Symlink,
+        Hazard::Duplicate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Hazard::Traversal => "traversal",
+            Hazard::Absolute => "absolute",
+            Hazard::Backslash => "backslash",
+            Hazard::Symlink => "symlink",
+            Hazard::Duplicate => "duplicate",
+        }
+    }
+}
+
+impl FromStr for Hazard {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Hazard::ALL
+            .into_iter()
+            .find(|hazard| hazard.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown archive hazard {:?}", s)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Dir,
+    File(Vec<u8>),
+    Symlink { target: String },
+}
+
+/// One archive member
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// `/`-separated, directories without a trailing `/`
+    pub path: String,
+    pub kind: EntryKind,
+    /// `None` for an ordinary entry
+    pub hazard: Option<Hazard>,
+}
+
+impl Entry {
+    pub fn size(&self) -> u64 {
+        match &self.kind {
+            EntryKind::File(data) => data.len() as u64,
+            _ => 0,
+        }
+    }
+
+    /// Unix permissions and file type, as both formats record them
+    fn mode(&self) -> u32 {
+        match self.kind {
+            EntryKind::Dir => 0o040755,
+            EntryKind::File(_) => 0o100644,
+            EntryKind::Symlink { .. } => 0o120777,
+        }
+    }
+
+    /// The name as stored: directories get a trailing `/`
+    fn stored_name(&self) -> String {
+        match self.kind {
+            EntryKind::Dir => format!("{}/", self.path),
+            _ => self.path.clone(),
+        }
+    }
+
+    /// A file's content, or for a symlink its target, which is how both formats store it
+    fn data(&self) -> &[u8] {
+        match &self.kind {
+            EntryKind::Dir => &[],
+            EntryKind::File(data) => data,
+            EntryKind::Symlink { target } => target.as_bytes(),
+        }
+    }
+}
+
+/// A generated archive, in the order its entries are written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archive {
+    pub entries: Vec<Entry>,
+}
+
+/// Every entry gets this modification time, 2020-01-01 00:00 UTC, so archives made from the
+/// same seed are byte for byte the same
+const MTIME: u32 = 1_577_836_800;
+
+impl Archive {
+    pub fn hazards(&self) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(|entry| entry.hazard.is_some())
+    }
+
+    pub fn encode(&self, format: ArchiveFormat) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        self.write_to(format, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Fails if an entry doesn't fit the format, like a tar path over 255 bytes
+    pub fn write_to<W>(&self, format: ArchiveFormat, out: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        match format {
+            ArchiveFormat::Zip => self.write_zip(out),
+            ArchiveFormat::Tar => self.write_tar(out),
+        }
+    }
+
+    fn write_tar<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for entry in &self.entries {
+            out.write_all(&tar_header(entry)?)?;
+            let data = match entry.kind {
+                // A symlink's target lives in the header
+                EntryKind::Symlink { .. } => &[],
+                _ => entry.data(),
+            };
+            out.write_all(data)?;
+            out.write_all(&vec![0; data.len().next_multiple_of(512) - data.len()])?;
+        }
+        // Two empty blocks mark the end
+        out.write_all(&[0; 1024])
+    }
+
+    fn write_zip<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        // MS-DOS time and date: midnight, then years since 1980, month and day
+        let (time, date) = (0u16, ((2020u16 - 1980) << 9) | (1 << 5) | 1);
+        let mut offset = 0u32;
+        let mut central = Vec::new();
+        for entry in &self.entries {
+            let name = entry.stored_name();
+            let data = entry.data();
+            let crc = crc32(data);
+            let too_big = || io::Error::new(io::ErrorKind::InvalidInput, "entry too big for zip");
+            let size = u32::try_from(data.len()).map_err(|_| too_big())?;
+            let name_len = u16::try_from(name.len()).map_err(|_| too_big())?;
+
+            // Fields shared by the local header and the central directory: version needed
+            // (1.0, plain stored data), flags (bit 11 says names are UTF-8), method (0,
+            // stored), time, date, CRC, compressed and uncompressed sizes, name length and
+            // no extra field
+            let mut common = Vec::with_capacity(26);
+            common.extend_from_slice(&10u16.to_le_bytes());
+            common.extend_from_slice(&0x0800u16.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+            common.extend_from_slice(&time.to_le_bytes());
+            common.extend_from_slice(&date.to_le_bytes());
+            common.extend_from_slice(&crc.to_le_bytes());
+            common.extend_from_slice(&size.to_le_bytes());
+            common.extend_from_slice(&size.to_le_bytes());
+            common.extend_from_slice(&name_len.to_le_bytes());
+            common.extend_from_slice(&0u16.to_le_bytes());
+
+            let mut local = Vec::with_capacity(30 + name.len());
+            local.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+            local.extend_from_slice(&common);
+            local.extend_from_slice(name.as_bytes());
+            out.write_all(&local)?;
+            out.write_all(data)?;
+
+            // Made by Unix (3) so readers honour the mode in the external attributes, where
+            // the MS-DOS directory bit goes in the low byte
+            central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            central.extend_from_slice(&((3u16 << 8) | 20).to_le_bytes());
+            central.extend_from_slice(&common);
+            // No comment, disk 0, no internal attributes
+            central.extend_from_slice(&[0; 6]);
+            let dos = if entry.kind == EntryKind::Dir {
+                0x10
+            } else {
+                0
+            };
+            central.extend_from_slice(&((entry.mode() << 16) | dos).to_le_bytes());
+            central.extend_from_slice(&offset.to_le_bytes());
+            central.extend_from_slice(name.as_bytes());
+
+            offset += local.len() as u32 + size;
+        }
+        out.write_all(&central)?;
+
+        // End of central directory: this disk and the one the directory starts on, entry
+        // counts on this disk and in all, the directory's size and offset, no comment
+        let count = self.entries.len() as u16;
+        out.write_all(&0x0605_4b50u32.to_le_bytes())?;
+        out.write_all(&[0; 4])?;
+        out.write_all(&count.to_le_bytes())?;
+        out.write_all(&count.to_le_bytes())?;
+        out.write_all(&(central.len() as u32).to_le_bytes())?;
+        out.write_all(&offset.to_le_bytes())?;
+        out.write_all(&0u16.to_le_bytes())
+    }
+}
+
+/// A 512-byte ustar header. Numbers are octal text; names over 100 bytes are split at a `/`
+/// into the 155-byte prefix field and the name field.
+fn tar_header(entry: &Entry) -> io::Result<[u8; 512]> {
+    let mut header = [0u8; 512];
+    let name = entry.stored_name();
+    let (prefix, name) = if name.len() <= 100 {
+        ("", name.as_str())
+    } else {
+        // The last `/` that leaves a short enough name, but not one ending the path
+        name.trim_end_matches('/')
+            .char_indices()
+            .rfind(|&(i, c)| c == '/' && i <= 155 && name.len() - i - 1 <= 100)
+            .map(|(i, _)| (&name[..i], &name[i + 1..]))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} is too long for a tar header", name),
+                )
+            })?
+    };
+    let octal = |field: &mut [u8], value: u64| {
+        // Zero-padded to fill the field, less the terminating NUL
+        let text = format!("{:0width$o}", value, width = field.len() - 1);
+        field[..text.len()].copy_from_slice(text.as_bytes());
+    };
+
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], (entry.mode() & 0o7777) as u64);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], entry.size());
+    octal(&mut header[136..148], MTIME as u64);
+    header[156] = match &entry.kind {
+        EntryKind::File(_) => b'0',
+        EntryKind::Symlink { target } => {
+            if target.len() > 100 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "symlink target too long for a tar header",
+                ));
+            }
+            header[157..157 + target.len()].copy_from_slice(target.as_bytes());
+            b'2'
+        }
+        EntryKind::Dir => b'5',
+    };
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    // The checksum is the byte sum of the header with the checksum field read as spaces
+    header[148..156].fill(b' ');
+    let sum = header.iter().map(|&b| b as u64).sum::<u64>();
+    let text = format!("{:06o}\0 ", sum);
+    header[148..156].copy_from_slice(text.as_bytes());
+    Ok(header)
+}
+
+/// Deep enough for any extractor, and short enough that paths always fit a tar header
+const MAX_DEPTH: usize = 8;
+
+/// Generates archives
+pub struct ArchiveGen {
+    files: RangeInclusive<usize>,
+    depth: usize,
+    size: RangeInclusive<usize>,
+    hazard_rate: f64,
+    hazards: Vec<Hazard>,
+}
+
+impl ArchiveGen {
+    /// 1 to 20 files of up to 4KiB, up to 3 directories deep, and no hazards
+    pub fn new() -> Self {
+        ArchiveGen {
+            files: 1..=20,
+            depth: 3,
+            size: 0..=4096,
+            hazard_rate: 0.0,
+            hazards: Hazard::ALL.to_vec(),
+        }
+    }
+
+    pub fn files(mut self, files: RangeInclusive<usize>) -> Result<Self, Error> {
+        if files.is_empty() {
+            return Err(Error::InvalidParameter("empty file count range".into()));
+        }
+        self.files = files;
+        Ok(self)
+    }
+
+    /// How many directories deep files can be, 0 for all at the top
+    pub fn depth(mut self, depth: usize) -> Result<Self, Error> {
+        if depth > MAX_DEPTH {
+            return Err(Error::InvalidParameter(format!(
+                "archive depth {} is more than {}",
+                depth, MAX_DEPTH
+            )));
+        }
+        self.depth = depth;
+        Ok(self)
+    }
+
+    /// File sizes in bytes
+    pub fn size(mut self, size: RangeInclusive<usize>) -> Result<Self, Error> {
+        if size.is_empty() {
+            return Err(Error::InvalidParameter("empty file size range".into()));
+        }
+        self.size = size;
+        Ok(self)
+    }
+
+    /// The chance each file is followed by a hostile entry
+    pub fn hazard_rate(mut self, rate: f64) -> Self {
+        self.hazard_rate = probability(rate);
+        self
+    }
+
+    /// Which hazards to use, at least one
+    pub fn hazards(mut self, hazards: &[Hazard]) -> Result<Self, Error> {
+        if hazards.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one hazard is needed".into(),
+            ));
+        }
+        self.hazards = hazards.to_vec();
+        Ok(self)
+    }
+
+    pub fn archive<R>(&self, rng: &mut R) -> Archive
+    where
+        R: Rng + ?Sized,
+    {
+        let mut entries = Vec::new();
+        // Paths of the directories so far, with their depth; the root is ""
+        let mut dirs = vec![(String::new(), 0)];
+        let mut files = Vec::<String>::new();
+        for _ in 0..rng.gen_range(self.files.clone()) {
+            // Half the time a new directory under one that has room for it
+            let (mut dir, mut depth) = dirs.choose(rng).unwrap().clone();
+            if depth < self.depth && rng.gen_bool(0.5) {
+                dir = join(&dir, &name(rng));
+                depth += 1;
+                dirs.push((dir.clone(), depth));
+                entries.push(Entry {
+                    path: dir.clone(),
+                    kind: EntryKind::Dir,
+                    hazard: None,
+                });
+            }
+            let path = join(&dir, &format!("{}.bin", name(rng)));
+            entries.push(Entry {
+                path: path.clone(),
+                kind: EntryKind::File(content(self.size.clone(), rng)),
+                hazard: None,
+            });
+            files.push(path);
+
+            if rng.gen_bool(self.hazard_rate) {
+                let hazard = *self.hazards.choose(rng).unwrap();
+                entries.push(self.hostile(hazard, &dir, &files, rng));
+            }
+        }
+        Archive { entries }
+    }
+
+    fn hostile<R>(&self, hazard: Hazard, dir: &str, files: &[String], rng: &mut R) -> Entry
+    where
+        R: Rng + ?Sized,
+    {
+        // Enough `..`s to climb out from where the entry sits, and then some
+        let climb = dir.split('/').filter(|c| !c.is_empty()).count() + rng.gen_range(1..=3);
+        let file = format!("{}.bin", name(rng));
+        let (path, kind) = match hazard {
+            Hazard::Traversal => (
+                join(dir, &format!("{}{}", "../".repeat(climb), file)),
+                EntryKind::File(content(self.size.clone(), rng)),
+            ),
+            Hazard::Absolute => (
+                format!("/tmp/{}", file),
+                EntryKind::File(content(self.size.clone(), rng)),
+            ),
+            Hazard::Backslash => (
+                join(dir, &format!("{}{}", "..\\".repeat(climb), file)),
+                EntryKind::File(content(self.size.clone(), rng)),
+            ),
+            Hazard::Symlink => (
+                join(dir, &name(rng)),
+                EntryKind::Symlink {
+                    target: if rng.gen_bool(0.5) {
+                        vec![".."; climb].join("/")
+                    } else {
+                        "/etc".to_string()
+                    },
+                },
+            ),
+            Hazard::Duplicate => (
+                files.choose(rng).unwrap().clone(),
+                EntryKind::File(content(self.size.clone(), rng)),
+            ),
+        };
+        Entry {
+            path,
+            kind,
+            hazard: Some(hazard),
+        }
+    }
+}
+
+impl Default for ArchiveGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+fn name<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    rng.sample_iter(Alphanumeric)
+        .take(8)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect()
+}
+
+fn content<R>(size: RangeInclusive<usize>, rng: &mut R) -> Vec<u8>
+where
+    R: Rng + ?Sized,
+{
+    let mut data = vec![0; rng.gen_range(size)];
+    rng.fill_bytes(&mut data);
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Walk a tar's headers, returning each name with its type flag and size
+    fn tar_members(bytes: &[u8]) -> Vec<(String, u8, usize)> {
+        let mut members = Vec::new();
+        let mut at = 0;
+        while bytes[at..at + 512].iter().any(|&b| b != 0) {
+            let header = &bytes[at..at + 512];
+            let field = |range: std::ops::Range<usize>| {
+                let text = &header[range];
+                let end = text.iter().position(|&b| b == 0).unwrap_or(text.len());
+                String::from_utf8(text[..end].to_vec()).unwrap()
+            };
+            let size = usize::from_str_radix(&field(124..136), 8).unwrap();
+            let sum = header[..148].iter().chain(&header[156..]);
+            let sum = sum.map(|&b| b as u32).sum::<u32>() + 8 * b' ' as u32;
+            assert_eq!(u32::from_str_radix(&field(148..154), 8).unwrap(), sum);
+            members.push((field(0..100), header[156], size));
+            at += 512 + size.next_multiple_of(512);
+        }
+        members
+    }
+
+    #[test]
+    fn it_makes_nested_archives_without_hazards() {
+        let mut rng = StdRng::seed_from_u64(273);
+        let gen = ArchiveGen::new().files(5..=30).unwrap().depth(2).unwrap();
+        for _ in 0..50 {
+            let archive = gen.archive(&mut rng);
+            assert_eq!(archive.hazards().count(), 0);
+            for entry in &archive.entries {
+                assert!(entry.path.split('/').count() <= 3, "{}", entry.path);
+                assert!(!entry.path.starts_with('/') && !entry.path.contains(".."));
+                // Every file's directory came before it
+                if let Some((dir, _)) = entry.path.rsplit_once('/') {
+                    assert!(archive
+                        .entries
+                        .iter()
+                        .any(|e| e.path == dir && e.kind == EntryKind::Dir));
+                }
+            }
+        }
+        assert!(ArchiveGen::new().depth(9).is_err());
+    }
+
+    #[test]
+    fn it_flags_each_hazard() {
+        let mut rng = StdRng::seed_from_u64(274);
+        for hazard in Hazard::ALL {
+            let archive = ArchiveGen::new()
+                .hazard_rate(1.0)
+                .hazards(&[hazard])
+                .unwrap()
+                .archive(&mut rng);
+            assert!(archive.hazards().count() > 0);
+            for entry in archive.hazards() {
+                assert_eq!(entry.hazard, Some(hazard));
+                let escapes = entry.path.starts_with('/')
+                    || entry.path.contains("../")
+                    || entry.path.contains("..\\");
+                match hazard {
+                    Hazard::Symlink => assert!(matches!(entry.kind, EntryKind::Symlink { .. })),
+                    Hazard::Duplicate => assert!(archive
+                        .entries
+                        .iter()
+                        .any(|e| e.path == entry.path && e.hazard.is_none())),
+                    _ => assert!(escapes, "{}", entry.path),
+                }
+            }
+        }
+        assert!(ArchiveGen::new().hazards(&[]).is_err());
+    }
+
+    #[test]
+    fn it_writes_tar() {
+        let archive = ArchiveGen::new()
+            .hazard_rate(0.5)
+            .archive(&mut StdRng::seed_from_u64(275));
+        let tar = archive.encode(ArchiveFormat::Tar).unwrap();
+        assert_eq!(tar.len() % 512, 0);
+        let members = tar_members(&tar);
+        assert_eq!(members.len(), archive.entries.len());
+        for ((name, flag, size), entry) in members.iter().zip(&archive.entries) {
+            assert_eq!(*name, entry.stored_name());
+            assert_eq!(*size as u64, entry.size());
+            assert_eq!(
+                *flag,
+                match entry.kind {
+                    EntryKind::Dir => b'5',
+                    EntryKind::File(_) => b'0',
+                    EntryKind::Symlink { .. } => b'2',
+                }
+            );
+        }
+
+        // Long paths go in the prefix
+        let long = Entry {
+            path: format!("{}/{}", "d".repeat(120), "f".repeat(90)),
+            kind: EntryKind::File(vec![1]),
+            hazard: None,
+        };
+        let header = tar_header(&long).unwrap();
+        assert_eq!(&header[345..465], "d".repeat(120).as_bytes());
+        let too_long = Entry {
+            path: "f".repeat(101),
+            ..long
+        };
+        assert!(tar_header(&too_long).is_err());
+    }
+
+    #[test]
+    fn it_writes_zip() {
+        let archive = ArchiveGen::new()
+            .hazard_rate(0.5)
+            .archive(&mut StdRng::seed_from_u64(276));
+        let zip = archive.encode(ArchiveFormat::Zip).unwrap();
+        assert_eq!(&zip[..4], b"PK\x03\x04");
+        // The end record points back at the central directory and counts every entry
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(end[..4], 0x0605_4b50u32.to_le_bytes());
+        let count = u16::from_le_bytes([end[10], end[11]]) as usize;
+        assert_eq!(count, archive.entries.len());
+        let offset = u32::from_le_bytes(end[16..20].try_into().unwrap()) as usize;
+        assert_eq!(zip[offset..offset + 4], 0x0201_4b50u32.to_le_bytes());
+
+        // The first entry's data follows its local header, and the CRC covers it
+        let first = &archive.entries[0];
+        let name_len = u16::from_le_bytes([zip[26], zip[27]]) as usize;
+        assert_eq!(&zip[30..30 + name_len], first.stored_name().as_bytes());
+        assert_eq!(zip[14..18], crc32(first.data()).to_le_bytes());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = ArchiveGen::new().hazard_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(273);
+        let archive = gen.archive(&mut rng);
+        assert!(archive.entries.iter().all(|entry| entry.hazard.is_none()));
+    }
+}
@@ -0,0 +1,340 @@
+//! Random PCM audio for testing audio pipelines: white or pink noise from `noise`, or a
+//! mixture of sine tones whose frequencies are reported back, so a resampler or spectrum
+//! analyzer can be checked against what went in. Buffers can be written as 16-bit WAV.
+
+use crate::{noise, probability};
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{
+    f64::consts::TAU, io::Write, ops::RangeInclusive, path::Path, str::FromStr, time::Duration,
+};
+
+/// What to generate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    White,
+    Pink,
+    /// A few sine tones added together
+    Sines,
+}
+
+impl Signal {
+    pub const ALL: [Signal; 3] = [Signal::White, Signal::Pink, Signal::Sines];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Signal::White => "white",
+            Signal::Pink => "pink",
+            Signal::Sines => "sines",
+        }
+    }
+}
+
+impl FromStr for Signal {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Signal::ALL
+            .into_iter()
+            .find(|signal| signal.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown audio signal {:?}", s)))
+    }
+}
+
+/// One tone of a `Signal::Sines` mixture
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sine {
+    /// In Hz
+    pub frequency: f64,
+    /// Before the mixture is scaled to the generator's peak amplitude
+    pub amplitude: f64,
+    /// In radians
+    pub phase: f64,
+}
+
+/// Generated audio
+#[derive(Debug, Clone, PartialEq)]
+pub struct Audio {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Interleaved, one sample per channel per frame, in -1..=1
+    pub samples: Vec<f32>,
+    /// The tones of a `Signal::Sines` mixture, empty for noise
+    pub sines: Vec<Sine>,
+}
+
+impl Audio {
+    /// Samples per channel
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames() as f64 / self.sample_rate as f64)
+    }
+
+    /// The samples as 16-bit integers, what most hardware and WAV files use
+    pub fn to_pcm16(&self) -> Vec<i16> {
+        self.samples
+            .iter()
+            .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+            .collect()
+    }
+
+    pub fn encode_wav(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // Writing to a `Vec` can't fail
+        self.write_wav(&mut bytes).unwrap();
+        bytes
+    }
+
+    /// A canonical 16-bit PCM WAV file: a RIFF header, a `fmt ` chunk and a `data` chunk
+    pub fn write_wav<W>(&self, out: &mut W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        let block_align = self.channels as u32 * 2;
+        let data_len = self.samples.len() as u32 * 2;
+        out.write_all(b"RIFF")?;
+        out.write_all(&(4 + 8 + 16 + 8 + data_len).to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        // Format 1 is integer PCM
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&self.channels.to_le_bytes())?;
+        out.write_all(&self.sample_rate.to_le_bytes())?;
+        out.write_all(&(self.sample_rate * block_align).to_le_bytes())?;
+        out.write_all(&(block_align as u16).to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&data_len.to_le_bytes())?;
+        for sample in self.to_pcm16() {
+            out.write_all(&sample.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.encode_wav())?;
+        Ok(())
+    }
+}
+
+/// An hour of 48kHz stereo, plenty for tests, and `u32` WAV sizes stay well clear of overflow
+const MAX_SAMPLES: u64 = 48_000 * 2 * 3600;
+
+/// Well past studio rates, and keeps the WAV header's byte rate in range
+const MAX_SAMPLE_RATE: u32 = 768_000;
+
+/// Generates audio of one format and length
+pub struct AudioGen {
+    sample_rate: u32,
+    frames: usize,
+    channels: u16,
+    signal: Signal,
+    amplitude: f64,
+    sines: RangeInclusive<usize>,
+}
+
+impl AudioGen {
+    /// Mono pink noise peaking at 0.8, leaving some headroom
+    pub fn new(sample_rate: u32, duration: Duration) -> Result<Self, Error> {
+        if !(1..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+            return Err(Error::InvalidParameter(format!(
+                "sample rate {}Hz must be between 1Hz and {}Hz",
+                sample_rate, MAX_SAMPLE_RATE
+            )));
+        }
+        let frames = (duration.as_secs_f64() * sample_rate as f64).round() as u64;
+        if frames > MAX_SAMPLES {
+            return Err(Error::InvalidParameter(format!(
+                "{:?} at {}Hz is more than {} samples",
+                duration, sample_rate, MAX_SAMPLES
+            )));
+        }
+        Ok(AudioGen {
+            sample_rate,
+            frames: frames as usize,
+            channels: 1,
+            signal: Signal::Pink,
+            amplitude: 0.8,
+            sines: 1..=4,
+        })
+    }
+
+    /// 1 to 8 channels. Noise is independent per channel, sine mixtures are the same on all.
+    pub fn channels(mut self, channels: u16) -> Result<Self, Error> {
+        if !(1..=8).contains(&channels) || self.frames as u64 * channels as u64 > MAX_SAMPLES {
+            return Err(Error::InvalidParameter(format!(
+                "{} channels must be between 1 and 8, within {} samples",
+                channels, MAX_SAMPLES
+            )));
+        }
+        self.channels = channels;
+        Ok(self)
+    }
+
+    pub fn signal(mut self, signal: Signal) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// The peak sample magnitude
+    pub fn amplitude(mut self, amplitude: f64) -> Self {
+        self.amplitude = probability(amplitude);
+        self
+    }
+
+    /// How many tones a `Signal::Sines` mixture has
+    pub fn sines(mut self, count: RangeInclusive<usize>) -> Result<Self, Error> {
+        if count.is_empty() || *count.start() == 0 {
+            return Err(Error::InvalidParameter(
+                "a sine mixture needs at least one tone".into(),
+            ));
+        }
+        self.sines = count;
+        Ok(self)
+    }
+
+    pub fn audio<R>(&self, rng: &mut R) -> Audio
+    where
+        R: Rng + ?Sized,
+    {
+        let (channels, sines) = match self.signal {
+            Signal::White => (self.noise(noise::white, rng), Vec::new()),
+            Signal::Pink => (self.noise(noise::pink, rng), Vec::new()),
+            Signal::Sines => {
+                let (series, sines) = self.mixture(rng);
+                (vec![series; self.channels as usize], sines)
+            }
+        };
+        let mut samples = Vec::with_capacity(self.frames * self.channels as usize);
+        for frame in 0..self.frames {
+            samples.extend(channels.iter().map(|channel| channel[frame] as f32));
+        }
+        Audio {
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            samples,
+            sines,
+        }
+    }
+
+    fn noise<R, F>(&self, color: F, rng: &mut R) -> Vec<Vec<f64>>
+    where
+        R: Rng + ?Sized,
+        F: Fn(usize, f64, &mut R) -> Vec<f64>,
+    {
+        (0..self.channels)
+            .map(|_| color(self.frames, self.amplitude, rng))
+            .collect()
+    }
+
+    fn mixture<R>(&self, rng: &mut R) -> (Vec<f64>, Vec<Sine>)
+    where
+        R: Rng + ?Sized,
+    {
+        // Log-uniform over the audible range, staying under the Nyquist frequency so nothing
+        // aliases
+        let top = (self.sample_rate as f64 * 0.45).min(20_000.0);
+        let bottom = 20.0f64.min(top / 2.0);
+        let sines = (0..rng.gen_range(self.sines.clone()))
+            .map(|_| Sine {
+                frequency: rng.gen_range(bottom.ln()..=top.ln()).exp(),
+                amplitude: rng.gen_range(0.1..=1.0),
+                phase: rng.gen_range(0.0..TAU),
+            })
+            .collect::<Vec<_>>();
+        let mut series = (0..self.frames)
+            .map(|i| {
+                let t = i as f64 / self.sample_rate as f64;
+                sines
+                    .iter()
+                    .map(|s| s.amplitude * (TAU * s.frequency * t + s.phase).sin())
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>();
+        let peak = series.iter().fold(0.0f64, |peak, x| peak.max(x.abs()));
+        if peak > 0.0 {
+            for x in series.iter_mut() {
+                *x *= self.amplitude / peak;
+            }
+        }
+        (series, sines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mime::sniff;
+
+    #[test]
+    fn it_makes_audio_of_the_right_shape() {
+        let mut rng = StdRng::seed_from_u64(272);
+        for signal in Signal::ALL {
+            let audio = AudioGen::new(8000, Duration::from_millis(250))
+                .unwrap()
+                .channels(2)
+                .unwrap()
+                .signal(signal)
+                .amplitude(0.5)
+                .audio(&mut rng);
+            assert_eq!((audio.frames(), audio.samples.len()), (2000, 4000));
+            assert_eq!(audio.duration(), Duration::from_millis(250));
+            let peak = audio.samples.iter().fold(0.0f32, |p, s| p.max(s.abs()));
+            assert!((peak - 0.5).abs() < 1e-6, "{}", peak);
+            assert_eq!(audio.sines.is_empty(), signal != Signal::Sines);
+        }
+        assert!(AudioGen::new(0, Duration::from_secs(1)).is_err());
+        assert!(AudioGen::new(48_000, Duration::from_secs(8000)).is_err());
+    }
+
+    #[test]
+    fn it_mixes_the_reported_tones() {
+        let mut rng = StdRng::seed_from_u64(273);
+        let gen = AudioGen::new(44_100, Duration::from_secs(1))
+            .unwrap()
+            .signal(Signal::Sines)
+            .sines(1..=1)
+            .unwrap();
+        for _ in 0..5 {
+            let audio = gen.audio(&mut rng);
+            // A pure tone crosses zero twice a cycle
+            let crossings = audio
+                .samples
+                .windows(2)
+                .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+                .count() as f64;
+            let frequency = audio.sines[0].frequency;
+            assert!((crossings / 2.0 - frequency).abs() <= 1.0, "{}", frequency);
+        }
+    }
+
+    #[test]
+    fn it_writes_wav() {
+        let audio = AudioGen::new(16_000, Duration::from_millis(10))
+            .unwrap()
+            .channels(2)
+            .unwrap()
+            .audio(&mut StdRng::seed_from_u64(274));
+        let wav = audio.encode_wav();
+        assert_eq!(sniff(&wav).unwrap().mime, "audio/wav");
+        assert_eq!(wav.len(), 44 + 160 * 2 * 2);
+        assert_eq!(&wav[36..40], b"data");
+        // Channels, then sample rate
+        assert_eq!(wav[22..24], 2u16.to_le_bytes());
+        assert_eq!(wav[24..28], 16_000u32.to_le_bytes());
+        let first = audio.to_pcm16()[0];
+        assert_eq!(wav[44..46], first.to_le_bytes());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let mut rng = StdRng::seed_from_u64(2722);
+        let gen = AudioGen::new(8000, Duration::from_millis(10))
+            .unwrap()
+            .amplitude(f64::NAN);
+        assert!(gen.audio(&mut rng).samples.iter().all(|s| *s == 0.0));
+    }
+}
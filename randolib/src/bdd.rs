@@ -0,0 +1,234 @@
+//! Ready-made "Given a random ..." steps for Gherkin-style (Cucumber) test suites, backed by
+//! the `fake` module. `Fixtures` matches step text itself, so it works with any runner:
+//! forward unmatched steps from your own step definitions, e.g. with the `cucumber` crate
+//!
+//! ```ignore
+//! #[given(regex = "^(.*random.*)$")]
+//! fn random_fixture(world: &mut MyWorld, step: String) {
+//!     world.fixtures.step(&step).unwrap();
+//! }
+//! ```
+//!
+//! Supported steps, with an optional leading `Given`, `And` or `But`:
+//!
+//! - `a random user`, `a random user named "Ada Lovelace"`, `3 random users`
+//! - `a random order`, for the most recent user (one is created if there is none)
+//! - `a random date`, `a random date between 2020 and 2024`
+
+use crate::{fake, seed::Seed};
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use somelib::error::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: String,
+    pub email: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    pub id: u64,
+    /// The `email` of the user who placed it
+    pub user: String,
+    pub items: u32,
+    pub total_cents: u64,
+}
+
+/// A calendar date, proleptic Gregorian
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// The date `days` days after 1970-01-01, using Howard Hinnant's `civil_from_days`
+    fn from_epoch_days(days: i64) -> Self {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + i64::from(month <= 2)) as i32;
+        Date { year, month, day }
+    }
+
+    /// Days from 1970-01-01 to January 1st of `year`
+    fn epoch_days_of_year(year: i32) -> i64 {
+        let y = i64::from(year) - 1;
+        365 * (y - 1969) + y.div_euclid(4) - y.div_euclid(100) + y.div_euclid(400) - 477
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The fixtures created by the steps of one scenario. Seed it per scenario (e.g. with
+/// `Seed::from_label(scenario_name)`) and reruns see the same data.
+pub struct Fixtures {
+    rng: ChaCha20Rng,
+    pub users: Vec<User>,
+    pub orders: Vec<Order>,
+    pub dates: Vec<Date>,
+}
+
+impl Fixtures {
+    pub fn new<S: Into<Seed>>(seed: S) -> Self {
+        Fixtures {
+            rng: seed.into().rng(),
+            users: Vec::new(),
+            orders: Vec::new(),
+            dates: Vec::new(),
+        }
+    }
+
+    /// Run a step by its text. `Ok(false)` means the step isn't one of ours; malformed
+    /// arguments to a step that is are an error.
+    pub fn step(&mut self, text: &str) -> Result<bool, Error> {
+        let text = text.trim();
+        let text = ["Given ", "And ", "But "]
+            .iter()
+            .find_map(|keyword| text.strip_prefix(keyword))
+            .unwrap_or(text)
+            .trim();
+
+        if text == "a random user" {
+            self.given_user();
+        } else if let Some(name) = text.strip_prefix("a random user named ") {
+            self.given_user_named(name.trim_matches('"'));
+        } else if let Some(n) = text.strip_suffix(" random users") {
+            for _ in 0..parse::<usize>(n, text)? {
+                self.given_user();
+            }
+        } else if text == "a random order" {
+            self.given_order();
+        } else if text == "a random date" {
+            self.given_date(1970, 2037)?;
+        } else if let Some(range) = text.strip_prefix("a random date between ") {
+            let (from, to) = range.split_once(" and ").ok_or_else(|| bad_step(text))?;
+            self.given_date(parse(from, text)?, parse(to, text)?)?;
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    pub fn given_user(&mut self) -> &User {
+        let name = fake::full_name(&mut self.rng);
+        self.given_user_named(&name)
+    }
+
+    /// A user with a fixed name and a matching random email
+    pub fn given_user_named(&mut self, name: &str) -> &User {
+        let local = name
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(".");
+        let email = format!("{}{}@example.com", local, self.rng.gen_range(1..1000));
+        self.users.push(User {
+            name: name.to_string(),
+            email,
+        });
+        self.users.last().unwrap()
+    }
+
+    /// An order for the most recent user
+    pub fn given_order(&mut self) -> &Order {
+        if self.users.is_empty() {
+            self.given_user();
+        }
+        let items = self.rng.gen_range(1..=5);
+        let order = Order {
+            id: self.rng.gen_range(100_000..1_000_000),
+            user: self.users.last().unwrap().email.clone(),
+            items,
+            total_cents: (0..items).map(|_| self.rng.gen_range(199..20_000)).sum(),
+        };
+        self.orders.push(order);
+        self.orders.last().unwrap()
+    }
+
+    /// A date in the years `from..=to`
+    pub fn given_date(&mut self, from: i32, to: i32) -> Result<Date, Error> {
+        if from > to {
+            return Err(Error::InvalidParameter(format!(
+                "no dates between {} and {}",
+                from, to
+            )));
+        }
+        let days = Date::epoch_days_of_year(from)..Date::epoch_days_of_year(to + 1);
+        let date = Date::from_epoch_days(self.rng.gen_range(days));
+        self.dates.push(date);
+        Ok(date)
+    }
+
+    pub fn user(&self) -> Option<&User> {
+        self.users.last()
+    }
+
+    pub fn order(&self) -> Option<&Order> {
+        self.orders.last()
+    }
+
+    pub fn date(&self) -> Option<Date> {
+        self.dates.last().copied()
+    }
+}
+
+fn bad_step(text: &str) -> Error {
+    Error::InvalidParameter(format!("can't parse step {:?}", text))
+}
+
+fn parse<T: std::str::FromStr>(value: &str, text: &str) -> Result<T, Error> {
+    value.trim().parse().map_err(|_| bad_step(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_runs_steps() {
+        let mut fixtures = Fixtures::new(Seed::from_label("checkout"));
+        let scenario = [
+            "Given a random user named \"Ada Lovelace\"",
+            "And a random order",
+            "And 2 random users",
+            "And a random date between 2020 and 2021",
+        ];
+        for step in scenario {
+            assert!(fixtures.step(step).unwrap(), "{}", step);
+        }
+
+        assert_eq!(fixtures.users.len(), 3);
+        assert_eq!(fixtures.users[0].name, "Ada Lovelace");
+        assert!(fixtures.users[0].email.starts_with("ada.lovelace"));
+        assert_eq!(fixtures.order().unwrap().user, fixtures.users[0].email);
+        assert!((2020..=2021).contains(&fixtures.date().unwrap().year));
+
+        assert!(!fixtures.step("Given a random spaceship").unwrap());
+        assert!(fixtures
+            .step("Given a random date between 2020 and soon")
+            .is_err());
+    }
+
+    #[test]
+    fn it_converts_days_to_dates() {
+        assert_eq!(Date::from_epoch_days(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_epoch_days(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_epoch_days(-1).to_string(), "1969-12-31");
+        for year in [1900, 1970, 2000, 2024] {
+            let first = Date::from_epoch_days(Date::epoch_days_of_year(year));
+            assert_eq!((first.year, first.month, first.day), (year, 1, 1));
+        }
+    }
+}
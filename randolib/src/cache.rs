@@ -0,0 +1,240 @@
+//! Synthetic memory access traces for comparing cache replacement policies.
+//!
+//! Traces come from the LRU stack model: the generator keeps the blocks it has touched in
+//! order of recency, and each access picks a *stack distance*, the number of other
+//! distinct blocks touched since the block was last used. Distance 0 repeats the last
+//! block; a distance past the end of the stack touches a new block. An LRU cache of `c`
+//! blocks hits exactly the accesses with distance below `c`, so the distance
+//! distribution sets temporal locality directly. Sequential runs add spatial locality.
+
+use crate::probability;
+use rand::prelude::*;
+use rand_distr::{Geometric, WeightedIndex, Zipf};
+use somelib::error::Error;
+
+/// How far back in the recency stack each access reaches
+#[derive(Debug, Clone, PartialEq)]
+pub enum StackDistance {
+    /// Every distance up to the footprint equally likely: almost no locality
+    Uniform,
+    /// `P(d) ∝ 1 / (d + 1)^s`: a few hot blocks and a long tail; higher `s` is more local
+    Zipf(f64),
+    /// `P(d) = p (1 - p)^d`: mean distance `(1 - p) / p`
+    Geometric(f64),
+    /// `weights[d]` is the relative chance of distance `d`, for matching a measured profile
+    Weights(Vec<f64>),
+}
+
+enum Sampler {
+    Uniform,
+    Zipf(Zipf<f64>),
+    Geometric(Geometric),
+    Weights(WeightedIndex<f64>),
+}
+
+impl Sampler {
+    fn new(distance: &StackDistance, footprint: usize) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidParameter(msg.into());
+        Ok(match distance {
+            StackDistance::Uniform => Sampler::Uniform,
+            StackDistance::Zipf(s) => {
+                if !s.is_finite() || *s < 0.0 {
+                    return Err(invalid("zipf exponent must be finite and non-negative"));
+                }
+                Sampler::Zipf(Zipf::new(footprint as u64, *s).unwrap())
+            }
+            StackDistance::Geometric(p) => {
+                Sampler::Geometric(Geometric::new(*p).map_err(|_| invalid("p must be in [0, 1]"))?)
+            }
+            StackDistance::Weights(weights) => Sampler::Weights(
+                WeightedIndex::new(weights)
+                    .map_err(|_| invalid("weights must be non-negative and not all zero"))?,
+            ),
+        })
+    }
+
+    fn sample<R>(&self, footprint: usize, rng: &mut R) -> usize
+    where
+        R: Rng + ?Sized,
+    {
+        match self {
+            Sampler::Uniform => rng.gen_range(0..footprint),
+            // `Zipf` starts at 1
+            Sampler::Zipf(zipf) => zipf.sample(rng) as usize - 1,
+            Sampler::Geometric(geometric) => geometric.sample(rng).min(usize::MAX as u64) as usize,
+            Sampler::Weights(weights) => weights.sample(rng),
+        }
+    }
+}
+
+/// Generates traces of block numbers in `0..footprint`. Multiply by a block size for
+/// byte addresses.
+pub struct TraceGen {
+    footprint: usize,
+    distance: Sampler,
+    sequential: f64,
+}
+
+impl TraceGen {
+    /// Traces touching at most `footprint` distinct blocks, with Zipf(1) distances
+    pub fn new(footprint: usize) -> Result<Self, Error> {
+        if footprint == 0 {
+            return Err(Error::InvalidParameter(
+                "footprint must be at least 1".into(),
+            ));
+        }
+        Ok(TraceGen {
+            footprint,
+            distance: Sampler::new(&StackDistance::Zipf(1.0), footprint)?,
+            sequential: 0.0,
+        })
+    }
+
+    pub fn distances(mut self, distance: StackDistance) -> Result<Self, Error> {
+        self.distance = Sampler::new(&distance, self.footprint)?;
+        Ok(self)
+    }
+
+    /// Chance of each access going to the block after the previous one (wrapping at the
+    /// footprint), as in array scans. These accesses bypass the distance distribution, so
+    /// the measured profile drifts from the target as this goes up.
+    pub fn sequential(mut self, rate: f64) -> Self {
+        self.sequential = probability(rate);
+        self
+    }
+
+    pub fn trace<R>(&self, len: usize, rng: &mut R) -> Vec<u64>
+    where
+        R: Rng + ?Sized,
+    {
+        // Untouched blocks in random order, so new blocks aren't accidentally sequential
+        let mut unused = (0..self.footprint).collect::<Vec<_>>();
+        unused.shuffle(rng);
+        // Most recent first
+        let mut stack: Vec<usize> = Vec::new();
+        let mut trace = Vec::with_capacity(len);
+        for _ in 0..len {
+            let block = match stack.first() {
+                Some(last) if rng.gen_bool(self.sequential) => {
+                    let next = (last + 1) % self.footprint;
+                    match stack.iter().position(|b| *b == next) {
+                        Some(d) => stack.remove(d),
+                        None => {
+                            unused.retain(|b| *b != next);
+                            next
+                        }
+                    }
+                }
+                _ => {
+                    let d = self.distance.sample(self.footprint, rng);
+                    if d < stack.len() {
+                        stack.remove(d)
+                    } else {
+                        // Past the end: a new block, or the least recent once all are used
+                        unused.pop().unwrap_or_else(|| stack.pop().unwrap())
+                    }
+                }
+            };
+            stack.insert(0, block);
+            trace.push(block as u64);
+        }
+        trace
+    }
+}
+
+/// The stack distance of each access, `None` for the first touch of a block
+pub fn stack_distances(trace: &[u64]) -> Vec<Option<usize>> {
+    let mut stack: Vec<u64> = Vec::new();
+    trace
+        .iter()
+        .map(|block| {
+            let distance = stack.iter().position(|b| b == block);
+            if let Some(d) = distance {
+                stack.remove(d);
+            }
+            stack.insert(0, *block);
+            distance
+        })
+        .collect()
+}
+
+/// Fraction of accesses an LRU cache of `capacity` blocks would hit, a baseline for
+/// other policies
+pub fn lru_hit_ratio(trace: &[u64], capacity: usize) -> f64 {
+    if trace.is_empty() {
+        return 0.0;
+    }
+    let hits = stack_distances(trace)
+        .iter()
+        .filter(|d| d.is_some_and(|d| d < capacity))
+        .count();
+    hits as f64 / trace.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_hits_the_target_distances() {
+        let mut rng = StdRng::seed_from_u64(258);
+        let gen = TraceGen::new(10)
+            .unwrap()
+            .distances(StackDistance::Weights(vec![0.5, 0.3, 0.0, 0.2]))
+            .unwrap();
+
+        let trace = gen.trace(20_000, &mut rng);
+        let mut counts = [0usize; 10];
+        for d in stack_distances(&trace).into_iter().flatten() {
+            counts[d] += 1;
+        }
+        let total = counts.iter().sum::<usize>() as f64;
+        for (d, target) in [0.5, 0.3, 0.0, 0.2].iter().enumerate() {
+            let share = counts[d] as f64 / total;
+            assert!((share - target).abs() < 0.02, "{:?}", counts);
+        }
+        // Only distances 0, 1 and 3 ever happen, so at most 4 blocks get touched
+        let touched = trace.iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(touched.len(), 4);
+        assert_eq!(lru_hit_ratio(&trace, 4), total / trace.len() as f64);
+    }
+
+    #[test]
+    fn locality_raises_the_hit_ratio() {
+        let mut rng = StdRng::seed_from_u64(258);
+        let hit_ratio = |distance| {
+            let gen = TraceGen::new(1000).unwrap().distances(distance).unwrap();
+            lru_hit_ratio(&gen.trace(20_000, &mut StdRng::seed_from_u64(1)), 50)
+        };
+
+        let uniform = hit_ratio(StackDistance::Uniform);
+        let zipf = hit_ratio(StackDistance::Zipf(1.2));
+        let geometric = hit_ratio(StackDistance::Geometric(0.2));
+        assert!(uniform < 0.1, "{}", uniform);
+        assert!(
+            uniform < zipf && zipf < geometric,
+            "{} {} {}",
+            uniform,
+            zipf,
+            geometric
+        );
+
+        let scan = TraceGen::new(100)
+            .unwrap()
+            .sequential(1.0)
+            .trace(300, &mut rng);
+        assert!(scan.windows(2).all(|w| w[1] == (w[0] + 1) % 100));
+        assert_eq!(lru_hit_ratio(&scan, 99), 0.0);
+
+        assert!(TraceGen::new(0).is_err());
+        let gen = TraceGen::new(10).unwrap();
+        assert!(gen.distances(StackDistance::Weights(vec![0.0])).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = TraceGen::new(100).unwrap().sequential(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(258);
+        assert_eq!(gen.trace(50, &mut rng).len(), 50);
+    }
+}
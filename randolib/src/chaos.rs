@@ -0,0 +1,272 @@
+//! Chaos testing: decide at random whether a call should fail or be slowed down. `Faults`
+//! holds the probabilities; with the `tower` feature, `FaultLayer` applies them to any
+//! tower service, driven by a seeded generator so a failing run can be replayed.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{fmt, ops::RangeInclusive, time::Duration};
+
+/// What to do to one call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    None,
+    /// Let the call through, but only respond after this long
+    Delay(Duration),
+    /// Fail with `InjectedFault` without calling through
+    Error,
+}
+
+/// Fault probabilities. Errors are rolled first, so with `error_rate` 0.1 and
+/// `latency_rate` 0.5, 10% of calls fail and half of the rest are delayed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Faults {
+    error_rate: f64,
+    latency_rate: f64,
+    latency: RangeInclusive<Duration>,
+}
+
+impl Faults {
+    /// No faults until some are configured
+    pub fn new() -> Self {
+        Faults {
+            error_rate: 0.0,
+            latency_rate: 0.0,
+            latency: Duration::ZERO..=Duration::ZERO,
+        }
+    }
+
+    /// Rates are clamped to `0..=1`, and NaN counts as 0
+    pub fn error_rate(mut self, rate: f64) -> Self {
+        self.error_rate = probability(rate);
+        self
+    }
+
+    /// Delay a `rate` fraction of calls by a uniformly random time in `latency`, which
+    /// must not be empty. `d..=d` always delays by `d`.
+    pub fn latency(mut self, rate: f64, latency: RangeInclusive<Duration>) -> Result<Self, Error> {
+        if latency.is_empty() {
+            return Err(Error::InvalidParameter(format!(
+                "latency range {:?} is empty, the start must not be after the end",
+                latency
+            )));
+        }
+        self.latency_rate = probability(rate);
+        self.latency = latency;
+        Ok(self)
+    }
+
+    pub fn roll<R>(&self, rng: &mut R) -> Fault
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_bool(self.error_rate) {
+            Fault::Error
+        } else if rng.gen_bool(self.latency_rate) {
+            Fault::Delay(rng.gen_range(self.latency.clone()))
+        } else {
+            Fault::None
+        }
+    }
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The error returned for calls picked to fail
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InjectedFault;
+
+impl fmt::Display for InjectedFault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("injected fault")
+    }
+}
+
+impl std::error::Error for InjectedFault {}
+
+#[cfg(feature = "tower")]
+pub use self::tower::{BoxError, FaultLayer, FaultService};
+
+#[cfg(feature = "tower")]
+mod tower {
+    use super::{Fault, Faults, InjectedFault};
+    use crate::seed::Seed;
+    use rand_chacha::ChaCha20Rng;
+    use std::{
+        future::Future,
+        pin::Pin,
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    };
+    use tower_layer::Layer;
+    use tower_service::Service;
+
+    /// Errors from the wrapped service and `InjectedFault` have to share one type, so
+    /// they're boxed, the same way tower's own middleware does it
+    pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+    /// Wraps services in `FaultService`. Every service made by one layer (and every clone
+    /// of those) draws from the same seeded generator, so a sequence of requests sees the
+    /// same faults on every run.
+    #[derive(Clone)]
+    pub struct FaultLayer {
+        faults: Arc<Faults>,
+        rng: Arc<Mutex<ChaCha20Rng>>,
+    }
+
+    impl FaultLayer {
+        pub fn new<S: Into<Seed>>(faults: Faults, seed: S) -> Self {
+            FaultLayer {
+                faults: Arc::new(faults),
+                rng: Arc::new(Mutex::new(seed.into().rng())),
+            }
+        }
+    }
+
+    impl<S> Layer<S> for FaultLayer {
+        type Service = FaultService<S>;
+
+        fn layer(&self, inner: S) -> Self::Service {
+            FaultService {
+                inner,
+                layer: self.clone(),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct FaultService<S> {
+        inner: S,
+        layer: FaultLayer,
+    }
+
+    impl<S, Req> Service<Req> for FaultService<S>
+    where
+        S: Service<Req>,
+        S::Response: Send + 'static,
+        S::Error: Into<BoxError>,
+        S::Future: Send + 'static,
+    {
+        type Response = S::Response;
+        type Error = BoxError;
+        type Future = Pin<Box<dyn Future<Output = Result<S::Response, BoxError>> + Send>>;
+
+        fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.inner.poll_ready(cx).map_err(Into::into)
+        }
+
+        fn call(&mut self, req: Req) -> Self::Future {
+            let fault = {
+                // A panic mid-roll can't corrupt the generator, so ignore poisoning
+                let mut rng = self.layer.rng.lock().unwrap_or_else(|e| e.into_inner());
+                self.layer.faults.roll(&mut *rng)
+            };
+            if fault == Fault::Error {
+                return Box::pin(std::future::ready(Err(InjectedFault.into())));
+            }
+            let response = self.inner.call(req);
+            Box::pin(async move {
+                if let Fault::Delay(delay) = fault {
+                    tokio::time::sleep(delay).await;
+                }
+                response.await.map_err(Into::into)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_rolls_faults_at_the_configured_rates() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let ms = Duration::from_millis;
+        let faults = Faults::new()
+            .error_rate(0.1)
+            .latency(0.5, ms(10)..=ms(20))
+            .unwrap();
+
+        let rolls = (0..10_000)
+            .map(|_| faults.roll(&mut rng))
+            .collect::<Vec<_>>();
+        let errors = rolls.iter().filter(|f| **f == Fault::Error).count();
+        let delays = rolls
+            .iter()
+            .filter_map(|f| match f {
+                Fault::Delay(d) => Some(*d),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert!((900..1100).contains(&errors), "{}", errors);
+        assert!((4300..4700).contains(&delays.len()), "{}", delays.len());
+        assert!(delays.iter().all(|d| (ms(10)..=ms(20)).contains(d)));
+        assert_eq!(Faults::new().roll(&mut rng), Fault::None);
+
+        let nan = Faults::new()
+            .error_rate(f64::NAN)
+            .latency(f64::NAN, ms(1)..=ms(2))
+            .unwrap();
+        assert_eq!(nan.roll(&mut rng), Fault::None);
+        assert!(Faults::new().latency(0.5, ms(2)..=ms(1)).is_err());
+        let always = Faults::new().error_rate(f64::INFINITY);
+        assert_eq!(always.roll(&mut rng), Fault::Error);
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test(start_paused = true)]
+    async fn it_injects_faults_into_tower_services() {
+        use std::{
+            future::{ready, Ready},
+            task::{Context, Poll},
+        };
+        use tower_layer::Layer;
+        use tower_service::Service;
+
+        struct Echo;
+        impl Service<u32> for Echo {
+            type Response = u32;
+            type Error = std::convert::Infallible;
+            type Future = Ready<Result<u32, Self::Error>>;
+
+            fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                Poll::Ready(Ok(()))
+            }
+
+            fn call(&mut self, req: u32) -> Self::Future {
+                ready(Ok(req))
+            }
+        }
+
+        let second = Duration::from_secs(1);
+        let faults = Faults::new()
+            .error_rate(0.3)
+            .latency(1.0, second..=second)
+            .unwrap();
+        let mut service = FaultLayer::new(faults, 7).layer(Echo);
+
+        let (mut ok, mut failed) = (0, 0);
+        for i in 0..100 {
+            let start = tokio::time::Instant::now();
+            match service.call(i).await {
+                Ok(echo) => {
+                    assert_eq!(echo, i);
+                    // Time is paused, so this is exact
+                    assert_eq!(start.elapsed(), second);
+                    ok += 1;
+                }
+                Err(e) => {
+                    assert!(e.is::<InjectedFault>());
+                    failed += 1;
+                }
+            }
+        }
+        assert!(ok > 50 && failed > 15, "{} {}", ok, failed);
+    }
+}
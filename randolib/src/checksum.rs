@@ -0,0 +1,42 @@
+//! The checksums the binary formats written here need: CRC-32 for PNG chunks and ZIP
+//! entries, Adler-32 for zlib streams. Both go bit by bit or byte by byte, since the data is
+//! test-sized and speed isn't the point.
+
+/// CRC-32 as used by PNG, ZIP and gzip (the reflected IEEE polynomial)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// zlib's checksum of the uncompressed data
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_known_checksums() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!((crc32(b""), adler32(b"")), (0, 1));
+    }
+}
@@ -0,0 +1,326 @@
+//! Synthetic clickstreams: users browsing a site one session at a time. A session lands on
+//! an entry page, follows weighted links between pages (a Markov chain), lingers on each
+//! page for a log-normal dwell time, and ends when it takes the exit.
+
+use crate::{fake, markov::MarkovChain};
+use rand::{distributions::WeightedIndex, prelude::*};
+use rand_distr::{Exp1, LogNormal};
+use somelib::error::Error;
+use std::time::{Duration, SystemTime};
+
+/// A page of the site
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub path: String,
+    /// Half of all visits stay shorter than this
+    pub median_dwell: Duration,
+}
+
+impl Page {
+    pub fn new(path: &str, median_dwell: Duration) -> Self {
+        Page {
+            path: path.to_string(),
+            median_dwell,
+        }
+    }
+}
+
+/// One page view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClickEvent {
+    /// Position in the session, from 0
+    pub seq: usize,
+    pub page: String,
+    /// The page before, `None` for the landing page
+    pub referrer: Option<String>,
+    pub at: SystemTime,
+    /// Time until the next click, or until leaving for the last page
+    pub dwell: Duration,
+}
+
+/// One visit by one user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+    pub events: Vec<ClickEvent>,
+}
+
+/// Generates sessions on a site described by its pages and the links between them
+pub struct ClickstreamGen {
+    pages: Vec<Page>,
+    /// Pages `0..n`, then `n` for having left, which only leads back to itself
+    chain: MarkovChain<usize>,
+    entries: WeightedIndex<f64>,
+    dwell_sigma: f64,
+    max_pages: usize,
+    users: usize,
+    session_gap: Duration,
+}
+
+impl ClickstreamGen {
+    /// `links[i]` weighs where a visitor on `pages[i]` goes next: one column per page, then
+    /// a last one for leaving the site. Weights only have to be proportional, as in
+    /// `MarkovChain`. Sessions land on the first page unless `entries` says otherwise.
+    pub fn new(pages: Vec<Page>, mut links: Vec<Vec<f64>>) -> Result<Self, Error> {
+        let n = pages.len();
+        if n == 0 {
+            return Err(Error::InvalidParameter(
+                "a site needs at least one page".into(),
+            ));
+        }
+        if links.iter().any(|row| row.len() != n + 1) {
+            return Err(Error::InvalidParameter(format!(
+                "each page needs {} link weights, one per page and one for leaving",
+                n + 1
+            )));
+        }
+        let mut gone = vec![0.0; n + 1];
+        gone[n] = 1.0;
+        links.push(gone);
+        let chain = MarkovChain::new((0..=n).collect(), links)?;
+        let mut entries = vec![0.0; n];
+        entries[0] = 1.0;
+        Ok(ClickstreamGen {
+            pages,
+            chain,
+            entries: WeightedIndex::new(entries).unwrap(),
+            dwell_sigma: 1.0,
+            max_pages: 50,
+            users: 100,
+            session_gap: Duration::from_secs(30),
+        })
+    }
+
+    /// A small online shop, where some visitors make it through the cart and checkout
+    pub fn shop() -> Self {
+        let secs = Duration::from_secs;
+        let pages = vec![
+            Page::new("/", secs(8)),
+            Page::new("/search", secs(12)),
+            Page::new("/category", secs(15)),
+            Page::new("/product", secs(30)),
+            Page::new("/cart", secs(20)),
+            Page::new("/checkout", secs(60)),
+            Page::new("/order-confirmed", secs(10)),
+            Page::new("/help", secs(45)),
+        ];
+        #[rustfmt::skip]
+        let links = vec![
+            //   /    search  cat   prod  cart  chk   done  help  leave
+            vec![0.0, 3.0,    4.0,  2.0,  0.5,  0.0,  0.0,  0.5,  2.0],
+            vec![0.5, 1.0,    1.0,  5.0,  0.3,  0.0,  0.0,  0.2,  2.0],
+            vec![0.5, 1.0,    1.0,  5.0,  0.3,  0.0,  0.0,  0.1,  2.0],
+            vec![0.5, 1.5,    1.5,  2.0,  2.0,  0.0,  0.0,  0.3,  3.0],
+            vec![0.2, 0.5,    0.5,  1.0,  0.0,  3.0,  0.0,  0.2,  1.5],
+            vec![0.0, 0.0,    0.0,  0.0,  1.0,  0.0,  6.0,  0.5,  2.5],
+            vec![1.0, 0.0,    0.0,  0.5,  0.0,  0.0,  0.0,  0.0,  6.0],
+            vec![1.0, 1.0,    0.0,  0.5,  0.0,  0.0,  0.0,  0.5,  3.0],
+        ];
+        // Most land on the home page, the rest from search engines and ads
+        ClickstreamGen::new(pages, links)
+            .and_then(|gen| gen.entries(&[5.0, 1.0, 2.0, 3.0, 0.0, 0.0, 0.0, 0.5]))
+            .unwrap()
+    }
+
+    /// How often sessions land on each page, one weight per page
+    pub fn entries(mut self, weights: &[f64]) -> Result<Self, Error> {
+        if weights.len() != self.pages.len() {
+            return Err(Error::InvalidParameter(format!(
+                "expected {} entry weights, got {}",
+                self.pages.len(),
+                weights.len()
+            )));
+        }
+        self.entries = WeightedIndex::new(weights)
+            .map_err(|err| Error::InvalidParameter(format!("entry weights: {}", err)))?;
+        Ok(self)
+    }
+
+    /// The spread of dwell times around each page's median, the log-normal's sigma
+    pub fn dwell_sigma(mut self, sigma: f64) -> Result<Self, Error> {
+        if !sigma.is_finite() || sigma < 0.0 {
+            return Err(Error::InvalidParameter(format!(
+                "dwell sigma {} must be finite and non-negative",
+                sigma
+            )));
+        }
+        self.dwell_sigma = sigma;
+        Ok(self)
+    }
+
+    /// Cut sessions off after this many pages, at least 1, in case the links loop
+    pub fn max_pages(mut self, max: usize) -> Self {
+        self.max_pages = max.max(1);
+        self
+    }
+
+    /// How many distinct users `sessions` draws from, so users come back
+    pub fn users(mut self, users: usize) -> Result<Self, Error> {
+        if users == 0 {
+            return Err(Error::InvalidParameter(
+                "at least one user is needed".into(),
+            ));
+        }
+        self.users = users;
+        Ok(self)
+    }
+
+    /// The mean time between session starts in `sessions`
+    pub fn session_gap(mut self, gap: Duration) -> Self {
+        self.session_gap = gap;
+        self
+    }
+
+    pub fn pages(&self) -> &[Page] {
+        &self.pages
+    }
+
+    /// One session by `user_id`, landing at `start`
+    pub fn session<R>(&self, user_id: &str, start: SystemTime, rng: &mut R) -> Session
+    where
+        R: Rng + ?Sized,
+    {
+        let gone = self.pages.len();
+        let mut chain = self.chain.clone();
+        // Every index is a state, so this can't fail
+        chain.set_state(&self.entries.sample(rng)).unwrap();
+
+        let mut events = Vec::new();
+        let mut at = start;
+        let mut referrer = None::<String>;
+        while *chain.state() != gone && events.len() < self.max_pages {
+            let page = &self.pages[*chain.state()];
+            let median = page.median_dwell.as_secs_f64().max(f64::MIN_POSITIVE);
+            // `unwrap` is fine, sigma was checked to be finite and non-negative
+            let dwell = LogNormal::new(median.ln(), self.dwell_sigma)
+                .unwrap()
+                .sample(rng);
+            let dwell = Duration::from_secs_f64(dwell.min(86_400.0));
+            events.push(ClickEvent {
+                seq: events.len(),
+                page: page.path.clone(),
+                referrer: referrer.replace(page.path.clone()),
+                at,
+                dwell,
+            });
+            at += dwell;
+            chain.step(rng);
+        }
+        Session {
+            id: format!("{:016x}", rng.gen::<u64>()),
+            user_id: user_id.to_string(),
+            events,
+        }
+    }
+
+    /// An endless iterator of sessions from a pool of `users` users, starting from `start`
+    /// with exponential gaps, so starts come in order but sessions overlap
+    pub fn sessions<R>(&self, start: SystemTime, mut rng: R) -> Sessions<'_, R>
+    where
+        R: Rng,
+    {
+        let users = (0..self.users).map(|_| fake::username(&mut rng)).collect();
+        Sessions {
+            gen: self,
+            rng,
+            users,
+            next_start: start,
+        }
+    }
+}
+
+impl Default for ClickstreamGen {
+    fn default() -> Self {
+        Self::shop()
+    }
+}
+
+/// Iterator over a `ClickstreamGen`'s sessions
+pub struct Sessions<'a, R> {
+    gen: &'a ClickstreamGen,
+    rng: R,
+    users: Vec<String>,
+    next_start: SystemTime,
+}
+
+impl<R: Rng> Iterator for Sessions<'_, R> {
+    type Item = Session;
+
+    fn next(&mut self) -> Option<Session> {
+        let user = self.users.choose(&mut self.rng).unwrap();
+        let session = self.gen.session(user, self.next_start, &mut self.rng);
+        let gap = self.rng.sample::<f64, _>(Exp1) * self.gen.session_gap.as_secs_f64();
+        self.next_start += Duration::from_secs_f64(gap);
+        Some(session)
+    }
+
+    /// Endless, so at least `usize::MAX` items and no upper bound
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{collections::HashSet, time::UNIX_EPOCH};
+
+    #[test]
+    fn it_follows_the_links() {
+        let gen = ClickstreamGen::shop().users(10).unwrap();
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let sessions = gen
+            .sessions(start, StdRng::seed_from_u64(274))
+            .take(500)
+            .collect::<Vec<_>>();
+        let index = |path: &str| gen.pages().iter().position(|p| p.path == path).unwrap();
+
+        let mut last_start = start;
+        let mut confirmed = 0;
+        for session in &sessions {
+            assert!(!session.events.is_empty());
+            let first = &session.events[0];
+            assert!(first.at >= last_start && first.referrer.is_none());
+            last_start = first.at;
+            for pair in session.events.windows(2) {
+                assert_eq!(pair[1].seq, pair[0].seq + 1);
+                assert_eq!(pair[1].referrer.as_ref(), Some(&pair[0].page));
+                assert_eq!(pair[1].at, pair[0].at + pair[0].dwell);
+                // Only pages with a link between them follow each other
+                let (from, to) = (index(&pair[0].page), index(&pair[1].page));
+                assert!(from != index("/checkout") || to != index("/"));
+                assert!(to != index("/checkout") || from == index("/cart"));
+            }
+            if session.events.iter().any(|e| e.page == "/order-confirmed") {
+                confirmed += 1;
+            }
+        }
+        // Conversion is rare but happens
+        assert!((1..100).contains(&confirmed), "{}", confirmed);
+        let users = sessions.iter().map(|s| &s.user_id).collect::<HashSet<_>>();
+        assert!(users.len() <= 10);
+    }
+
+    #[test]
+    fn it_checks_the_site() {
+        assert!(ClickstreamGen::new(Vec::new(), Vec::new()).is_err());
+        let pages = vec![Page::new("/", Duration::from_secs(5))];
+        assert!(ClickstreamGen::new(pages.clone(), vec![vec![1.0]]).is_err());
+        assert!(ClickstreamGen::new(pages.clone(), vec![vec![0.0, 0.0]]).is_err());
+
+        // A page that always links to itself runs into the cap
+        let gen = ClickstreamGen::new(pages, vec![vec![1.0, 0.0]])
+            .unwrap()
+            .max_pages(7)
+            .dwell_sigma(0.0)
+            .unwrap();
+        let session = gen.session("u", UNIX_EPOCH, &mut StdRng::seed_from_u64(275));
+        assert_eq!(session.events.len(), 7);
+        assert!(session
+            .events
+            .iter()
+            .all(|e| (e.dwell.as_secs_f64() - 5.0).abs() < 1e-9));
+        assert!(ClickstreamGen::shop().entries(&[1.0]).is_err());
+    }
+}
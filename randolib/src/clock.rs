@@ -0,0 +1,189 @@
+//! A fake clock for time-dependent code (timeouts, retries, rate limits) whose time moves
+//! in random but reproducible steps, so that code meets jittery timing in tests without
+//! actually sleeping.
+//!
+//! Code under test takes a `&dyn Clock` (or a generic `C: Clock`): production passes a
+//! `SystemClock`, tests a `JitterClock`.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use std::{
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// A source of time. Times are offsets from the clock's own start, since `Instant`s can't
+/// be made up.
+pub trait Clock {
+    fn now(&self) -> Duration;
+    fn sleep(&self, duration: Duration);
+}
+
+/// Real time
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        SystemClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration)
+    }
+}
+
+/// A clock that never waits. Every `now` moves time forward by a random step, as if some
+/// work happened between two readings, and every `sleep` overshoots by a random amount, as
+/// real sleeps do. Steps are drawn in seconds; negative draws count as zero, so time never
+/// runs backwards.
+///
+/// Seeded, so a failure seen under one seed repeats.
+pub struct JitterClock {
+    state: Mutex<State>,
+    step: Draw,
+    oversleep: Draw,
+}
+
+/// `Distribution` has a generic method, so it can't be a trait object; a closure that
+/// samples it can
+type Draw = Box<dyn Fn(&mut ChaCha20Rng) -> f64 + Send + Sync>;
+
+struct State {
+    now: Duration,
+    rng: ChaCha20Rng,
+}
+
+impl JitterClock {
+    /// Without jitter until `step` or `oversleep` are set
+    pub fn new(seed: u64) -> Self {
+        JitterClock {
+            state: Mutex::new(State {
+                now: Duration::ZERO,
+                rng: ChaCha20Rng::seed_from_u64(seed),
+            }),
+            step: Box::new(|_| 0.0),
+            oversleep: Box::new(|_| 0.0),
+        }
+    }
+
+    /// Seconds added on each `now`
+    pub fn step<D>(mut self, distribution: D) -> Self
+    where
+        D: Distribution<f64> + Send + Sync + 'static,
+    {
+        self.step = Box::new(move |rng| distribution.sample(rng));
+        self
+    }
+
+    /// Seconds added on top of each `sleep`
+    pub fn oversleep<D>(mut self, distribution: D) -> Self
+    where
+        D: Distribution<f64> + Send + Sync + 'static,
+    {
+        self.oversleep = Box::new(move |rng| distribution.sample(rng));
+        self
+    }
+
+    /// Move time forward by exactly `duration`
+    pub fn advance(&self, duration: Duration) {
+        self.state().now += duration;
+    }
+
+    /// The current time without moving it, for assertions
+    pub fn peek(&self) -> Duration {
+        self.state().now
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // Nothing in here can panic halfway through an update
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A draw from `distribution` as a duration, zero if negative or not a number
+fn jitter(draw: &Draw, rng: &mut ChaCha20Rng) -> Duration {
+    Duration::try_from_secs_f64(draw(rng)).unwrap_or(Duration::ZERO)
+}
+
+impl Clock for JitterClock {
+    fn now(&self) -> Duration {
+        let mut state = self.state();
+        let step = jitter(&self.step, &mut state.rng);
+        state.now += step;
+        state.now
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let mut state = self.state();
+        let extra = jitter(&self.oversleep, &mut state.rng);
+        state.now += duration + extra;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_distr::{Exp, Uniform};
+
+    /// Stands in for code under test: retry until `deadline`, doubling the wait
+    fn attempts_before<C: Clock>(clock: &C, deadline: Duration) -> u32 {
+        let mut wait = Duration::from_millis(100);
+        let mut attempts = 0;
+        while clock.now() < deadline {
+            attempts += 1;
+            clock.sleep(wait);
+            wait *= 2;
+        }
+        attempts
+    }
+
+    #[test]
+    fn it_jitters_reproducibly() {
+        let clock = || {
+            JitterClock::new(2542)
+                .step(Uniform::new(0.0, 0.01))
+                .oversleep(Exp::new(20.0).unwrap())
+        };
+        let (a, b) = (clock(), clock());
+        assert_eq!(
+            attempts_before(&a, Duration::from_secs(3)),
+            attempts_before(&b, Duration::from_secs(3))
+        );
+        assert_eq!(a.peek(), b.peek());
+        // 0.1 + 0.2 + 0.4 + 0.8 + 1.6 passes 3s, plus jitter
+        assert!(a.peek() > Duration::from_millis(3100));
+    }
+
+    #[test]
+    fn it_never_runs_backwards() {
+        let clock = JitterClock::new(2542)
+            .step(Uniform::new(-1.0, 1.0))
+            .oversleep(Uniform::new(-1.0, 1.0));
+        let mut last = clock.now();
+        for _ in 0..1000 {
+            clock.sleep(Duration::ZERO);
+            let now = clock.now();
+            assert!(now >= last);
+            last = now;
+        }
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.peek(), last + Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,165 @@
+//! Configuration for the default generator, read from the environment so CI can force a
+//! whole test suite to be deterministic without touching code:
+//!
+//! - `RANDOLIB_SEED`: a `u64`, a 64 digit hex seed as printed by `RANDOLIB_LOG_SEED`, or
+//!   any other string, which is used as a label (see `Seed::from_label`)
+//! - `RANDOLIB_BACKEND`: `chacha20` (the default), `chacha12`, `chacha8` or `std`
+//! - `RANDOLIB_LOG_SEED`: when set to anything but `0` or `false`, print the seed on
+//!   stderr when the default generator is created
+
+use crate::seed::Seed;
+use rand::prelude::*;
+use rand_chacha::{ChaCha12Rng, ChaCha20Rng, ChaCha8Rng};
+use somelib::error::Error;
+use std::{fmt, str::FromStr};
+
+/// The generator algorithms a seed can drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// Stable across `rand` releases, so seeded runs reproduce after upgrades
+    #[default]
+    ChaCha20,
+    /// Faster, fewer rounds, still stable across releases
+    ChaCha12,
+    ChaCha8,
+    /// `rand`'s `StdRng`, whose algorithm may change between releases
+    Std,
+}
+
+impl Backend {
+    /// A generator of this kind seeded from `seed`
+    pub fn rng(&self, seed: Seed) -> Box<dyn RngCore + Send> {
+        let bytes = seed.bytes();
+        match self {
+            Backend::ChaCha20 => Box::new(ChaCha20Rng::from_seed(bytes)),
+            Backend::ChaCha12 => Box::new(ChaCha12Rng::from_seed(bytes)),
+            Backend::ChaCha8 => Box::new(ChaCha8Rng::from_seed(bytes)),
+            Backend::Std => Box::new(StdRng::from_seed(bytes)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Backend::ChaCha20 => "chacha20",
+            Backend::ChaCha12 => "chacha12",
+            Backend::ChaCha8 => "chacha8",
+            Backend::Std => "std",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "chacha20" => Ok(Backend::ChaCha20),
+            "chacha12" => Ok(Backend::ChaCha12),
+            "chacha8" => Ok(Backend::ChaCha8),
+            "std" => Ok(Backend::Std),
+            _ => Err(Error::InvalidParameter(format!("unknown backend {:?}", s))),
+        }
+    }
+}
+
+/// Everything the default generator is built from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Config {
+    /// `None` means seed from the OS
+    pub seed: Option<Seed>,
+    pub backend: Backend,
+    pub log_seed: bool,
+}
+
+impl Config {
+    /// Read the `RANDOLIB_*` variables
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like `from_env` with a custom lookup, which keeps tests away from the real
+    /// (process-wide, shared between test threads) environment
+    pub fn from_vars<F>(var: F) -> Result<Self, Error>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let seed = var("RANDOLIB_SEED").map(|value| parse_seed(&value));
+        let backend = match var("RANDOLIB_BACKEND") {
+            Some(name) => name.parse()?,
+            None => Backend::default(),
+        };
+        let log_seed = var("RANDOLIB_LOG_SEED").is_some_and(|value| {
+            !matches!(value.to_ascii_lowercase().as_str(), "" | "0" | "false")
+        });
+        Ok(Config {
+            seed,
+            backend,
+            log_seed,
+        })
+    }
+
+    /// The seed to use: the configured one or a fresh one from the OS. Drawing a `Seed`
+    /// rather than seeding the generator from entropy directly means there's always a seed
+    /// to log.
+    pub fn resolve_seed(&self) -> Seed {
+        self.seed
+            .unwrap_or_else(|| Seed::from_bytes(rand::rngs::OsRng.gen()))
+    }
+}
+
+fn parse_seed(value: &str) -> Seed {
+    if let Ok(n) = value.parse::<u64>() {
+        return Seed::from_u64(n);
+    }
+    value
+        .parse::<Seed>()
+        .unwrap_or_else(|_| Seed::from_label(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> Result<Config, Error> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<HashMap<_, _>>();
+        Config::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn it_reads_config_from_vars() {
+        assert_eq!(config(&[]).unwrap(), Config::default());
+
+        let forced = config(&[
+            ("RANDOLIB_SEED", "42"),
+            ("RANDOLIB_BACKEND", "ChaCha8"),
+            ("RANDOLIB_LOG_SEED", "1"),
+        ])
+        .unwrap();
+        assert_eq!(forced.seed, Some(Seed::from_u64(42)));
+        assert_eq!(forced.backend, Backend::ChaCha8);
+        assert!(forced.log_seed);
+
+        let labeled = config(&[("RANDOLIB_SEED", "nightly"), ("RANDOLIB_LOG_SEED", "0")]);
+        let labeled = labeled.unwrap();
+        assert_eq!(labeled.seed, Some(Seed::from_label("nightly")));
+        assert!(!labeled.log_seed);
+
+        assert!(config(&[("RANDOLIB_BACKEND", "mt19937")]).is_err());
+    }
+
+    #[test]
+    fn it_accepts_logged_seeds() {
+        // What `RANDOLIB_LOG_SEED` prints can be pasted back into `RANDOLIB_SEED`
+        let seed = Config::default().resolve_seed();
+        let hex = seed.to_string();
+
+        assert_eq!(config(&[("RANDOLIB_SEED", &hex)]).unwrap().seed, Some(seed));
+    }
+}
@@ -0,0 +1,711 @@
+//! Configuration for an application under test: environment variables and TOML, YAML or JSON
+//! files generated from a small schema. Most are valid; the rest are *nearly* valid, with a
+//! missing required key, a value of the wrong type, a typo'd key or a syntax slip, which is
+//! what real deployments get wrong and what startup code should reject with a useful error.
+//!
+//! Each `ConfigCase` keeps the values it was generated from, so a valid case can be checked
+//! against what the application actually loaded.
+
+use crate::probability;
+use rand::{distributions::Alphanumeric, prelude::*};
+use somelib::error::Error;
+use std::{
+    fmt::{self, Display, Formatter},
+    ops::RangeInclusive,
+    str::FromStr,
+};
+
+/// The type of a config value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    Bool,
+    Int(RangeInclusive<i64>),
+    Float,
+    Str,
+    /// One of a fixed set of strings, e.g. log levels
+    Enum(Vec<String>),
+    List(Box<Kind>),
+}
+
+/// Parses the compact syntax used on the command line: `bool`, `int`, `int(1..65535)`,
+/// `float`, `str`, `enum(a|b|c)` and `list(KIND)`
+impl FromStr for Kind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidParameter(format!("unknown config type {:?}", s));
+        let (name, arg) = match s.split_once('(') {
+            Some((name, rest)) => (name, Some(rest.strip_suffix(')').ok_or_else(invalid)?)),
+            None => (s, None),
+        };
+        match (name, arg) {
+            ("bool", None) => Ok(Kind::Bool),
+            ("int", None) => Ok(Kind::Int(i64::MIN..=i64::MAX)),
+            ("int", Some(range)) => {
+                let (min, max) = range.split_once("..").ok_or_else(invalid)?;
+                let min = min.parse::<i64>().map_err(|_| invalid())?;
+                let max = max.parse::<i64>().map_err(|_| invalid())?;
+                if min > max {
+                    return Err(invalid());
+                }
+                Ok(Kind::Int(min..=max))
+            }
+            ("float", None) => Ok(Kind::Float),
+            ("str", None) => Ok(Kind::Str),
+            ("enum", Some(choices)) if !choices.is_empty() => {
+                Ok(Kind::Enum(choices.split('|').map(String::from).collect()))
+            }
+            ("list", Some(inner)) => Ok(Kind::List(Box::new(inner.parse()?))),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A named value an application expects to find in its config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub kind: Kind,
+    pub required: bool,
+}
+
+/// `name:KIND` for a required field, `name?:KIND` for an optional one
+impl FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, kind) = s.split_once(':').ok_or_else(|| {
+            Error::InvalidParameter(format!("expected name:type for a field, got {:?}", s))
+        })?;
+        let (name, required) = match name.strip_suffix('?') {
+            Some(name) => (name, false),
+            None => (name, true),
+        };
+        // Keys stay bare in every format, so they never need quoting
+        let bare = |c: char| c.is_ascii_alphanumeric() || c == '_';
+        if name.is_empty() || !name.chars().all(bare) {
+            return Err(Error::InvalidParameter(format!(
+                "field names are letters, digits and `_`, got {:?}",
+                name
+            )));
+        }
+        Ok(Field {
+            name: name.into(),
+            kind: kind.parse()?,
+            required,
+        })
+    }
+}
+
+/// A generated value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    List(Vec<Value>),
+}
+
+/// The file (or environment) format a case is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// `KEY=value` lines, as in a `.env` file
+    Env,
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Env, Format::Toml, Format::Yaml, Format::Json];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Env => "env",
+            Format::Toml => "toml",
+            Format::Yaml => "yaml",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Format::ALL
+            .into_iter()
+            .find(|format| format.extension() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown config format {:?}", s)))
+    }
+}
+
+/// A way of making a config nearly valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Defect {
+    /// A required key is left out
+    MissingRequired,
+    /// A value of another type, e.g. `"8080"` for a number or `yes` for a bool
+    WrongType,
+    /// The right type but not an allowed value: outside an `int` range or not in an `enum`
+    OutOfRange,
+    /// An extra key, usually a typo of a real one
+    UnknownKey,
+    /// The same key twice with different values
+    DuplicateKey,
+    /// A key with an empty value
+    EmptyValue,
+    /// The file doesn't parse: a trailing comma, an unclosed quote, a tab in YAML, ...
+    Syntax,
+}
+
+impl Defect {
+    pub const ALL: [Defect; 7] = [
+        Defect::MissingRequired,
+        Defect::WrongType,
+        Defect::OutOfRange,
+        Defect::UnknownKey,
+        Defect::DuplicateKey,
+        Defect::EmptyValue,
+        Defect::Syntax,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Defect::MissingRequired => "missing-required",
+            Defect::WrongType => "wrong-type",
+            Defect::OutOfRange => "out-of-range",
+            Defect::UnknownKey => "unknown-key",
+            Defect::DuplicateKey => "duplicate-key",
+            Defect::EmptyValue => "empty-value",
+            Defect::Syntax => "syntax",
+        }
+    }
+}
+
+impl Display for Defect {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Defect {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Defect::ALL
+            .into_iter()
+            .find(|defect| defect.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown config defect {:?}", s)))
+    }
+}
+
+/// A generated config and what went into it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigCase {
+    pub format: Format,
+    pub text: String,
+    /// The values before any defect was applied
+    pub values: Vec<(String, Value)>,
+    pub defects: Vec<Defect>,
+}
+
+impl ConfigCase {
+    pub fn is_valid(&self) -> bool {
+        self.defects.is_empty()
+    }
+
+    /// `KEY=value` pairs from an `Env` case, ready for `Command::envs`. Lines without an `=`
+    /// are skipped, as a shell would reject them.
+    pub fn vars(&self) -> Vec<(String, String)> {
+        self.text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect()
+    }
+}
+
+/// Generates configs for a schema
+pub struct ConfigFuzzer {
+    fields: Vec<Field>,
+    defect_rate: f64,
+    defects: Vec<Defect>,
+    env_prefix: String,
+}
+
+impl ConfigFuzzer {
+    /// A fuzzer for `fields`, a quarter of whose cases have a defect
+    pub fn new(fields: Vec<Field>) -> Result<Self, Error> {
+        if fields.is_empty() {
+            return Err(Error::InvalidParameter(
+                "a config schema needs at least one field".into(),
+            ));
+        }
+        Ok(ConfigFuzzer {
+            fields,
+            defect_rate: 0.25,
+            defects: Defect::ALL.to_vec(),
+            env_prefix: "APP_".into(),
+        })
+    }
+
+    /// Chance of a case having a defect, and again of it having a second one
+    pub fn defect_rate(mut self, rate: f64) -> Self {
+        self.defect_rate = probability(rate);
+        self
+    }
+
+    /// Which kinds to use, at least one
+    pub fn defects(mut self, defects: &[Defect]) -> Result<Self, Error> {
+        if defects.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one defect is needed".into(),
+            ));
+        }
+        self.defects = defects.to_vec();
+        Ok(self)
+    }
+
+    /// Prepended to upper-cased field names in `Env` cases, `APP_` by default
+    pub fn env_prefix(mut self, prefix: &str) -> Self {
+        self.env_prefix = prefix.into();
+        self
+    }
+
+    pub fn case<R>(&self, format: Format, rng: &mut R) -> ConfigCase
+    where
+        R: Rng + ?Sized,
+    {
+        let mut values = Vec::new();
+        // Literal values as they'll be written, paired with the field they came from
+        let mut entries = Vec::new();
+        for field in &self.fields {
+            if field.required || rng.gen_bool(0.7) {
+                let value = random_value(&field.kind, rng);
+                entries.push((self.key(format, &field.name), literal(format, &value)));
+                values.push((field.name.clone(), value));
+            }
+        }
+
+        let mut defects = Vec::new();
+        let mut syntax = false;
+        while rng.gen_bool(self.defect_rate) && defects.len() < 2 {
+            let defect = *self.defects.choose(rng).unwrap();
+            if defects.contains(&defect) {
+                break;
+            }
+            let applied = match defect {
+                Defect::Syntax => {
+                    // Applied last, once the text exists
+                    syntax = true;
+                    true
+                }
+                _ => self.apply(defect, format, &mut entries, rng),
+            };
+            if applied {
+                defects.push(defect);
+            }
+        }
+
+        let mut text = render(format, &entries);
+        if syntax {
+            text = break_syntax(format, &text, rng);
+        }
+        ConfigCase {
+            format,
+            text,
+            values,
+            defects,
+        }
+    }
+
+    fn key(&self, format: Format, name: &str) -> String {
+        match format {
+            Format::Env => format!("{}{}", self.env_prefix, name.to_uppercase()),
+            _ => name.into(),
+        }
+    }
+
+    /// Apply `defect` to the entries if this schema allows it, e.g. there's no `OutOfRange`
+    /// without a bounded `int` or an `enum`
+    fn apply<R>(
+        &self,
+        defect: Defect,
+        format: Format,
+        entries: &mut Vec<(String, String)>,
+        rng: &mut R,
+    ) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        // Fields currently present, with their position in `entries`
+        let present = self
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let key = self.key(format, &field.name);
+                let index = entries.iter().position(|(k, _)| *k == key)?;
+                Some((field, index))
+            })
+            .collect::<Vec<_>>();
+
+        match defect {
+            Defect::MissingRequired => {
+                let required = present.iter().filter(|(field, _)| field.required);
+                let Some(&(_, index)) = required.choose(rng) else {
+                    return false;
+                };
+                entries.remove(index);
+            }
+            Defect::WrongType => {
+                let candidates = present
+                    .iter()
+                    .filter_map(|&(field, index)| Some((wrong_type(format, &field.kind)?, index)))
+                    .collect::<Vec<_>>();
+                let Some((literal, index)) = candidates.choose(rng) else {
+                    return false;
+                };
+                entries[*index].1 = literal.clone();
+            }
+            Defect::OutOfRange => {
+                let candidates = present
+                    .iter()
+                    .filter_map(|&(field, index)| Some((out_of_range(&field.kind)?, index)))
+                    .collect::<Vec<_>>();
+                let Some((value, index)) = candidates.choose(rng) else {
+                    return false;
+                };
+                entries[*index].1 = literal(format, value);
+            }
+            Defect::UnknownKey => {
+                let (field, _) = present.choose(rng).copied().unwrap_or((&self.fields[0], 0));
+                let value = random_value(&field.kind, rng);
+                let key = self.key(format, &typo(&field.name, rng));
+                if entries.iter().any(|(k, _)| *k == key) {
+                    return false;
+                }
+                let at = rng.gen_range(0..=entries.len());
+                entries.insert(at, (key, literal(format, &value)));
+            }
+            Defect::DuplicateKey => {
+                let Some(&(field, index)) = present.choose(rng) else {
+                    return false;
+                };
+                let key = entries[index].0.clone();
+                let value = literal(format, &random_value(&field.kind, rng));
+                let at = rng.gen_range(index + 1..=entries.len());
+                entries.insert(at, (key, value));
+            }
+            Defect::EmptyValue => {
+                let Some(&(_, index)) = present.choose(rng) else {
+                    return false;
+                };
+                entries[index].1 = match format {
+                    // A YAML key with nothing after the colon is `null`
+                    Format::Env | Format::Yaml => String::new(),
+                    Format::Toml | Format::Json => "\"\"".into(),
+                };
+            }
+            Defect::Syntax => unreachable!("syntax defects are applied to the text"),
+        }
+        true
+    }
+}
+
+fn token<R>(len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    rng.sample_iter(Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn random_value<R>(kind: &Kind, rng: &mut R) -> Value
+where
+    R: Rng + ?Sized,
+{
+    match kind {
+        Kind::Bool => Value::Bool(rng.gen()),
+        Kind::Int(range) => Value::Int(rng.gen_range(range.clone())),
+        // Two decimals keep every format's float syntax simple
+        Kind::Float => Value::Float(rng.gen_range(0..100_000) as f64 / 100.0),
+        Kind::Str => Value::Str(token(rng.gen_range(1..=12), rng)),
+        Kind::Enum(choices) => Value::Str(choices.choose(rng).unwrap().clone()),
+        Kind::List(inner) => {
+            let len = rng.gen_range(0..=4);
+            Value::List((0..len).map(|_| random_value(inner, rng)).collect())
+        }
+    }
+}
+
+/// `s` as a double-quoted string, which TOML, YAML and JSON all read the same way
+fn quoted(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn literal(format: Format, value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Int(n) => n.to_string(),
+        // `Debug` always includes a `.`, so `3.0` doesn't turn into the integer `3`
+        Value::Float(x) => format!("{:?}", x),
+        Value::Str(s) if format == Format::Env => s.clone(),
+        Value::Str(s) => quoted(s),
+        Value::List(items) => {
+            let items = items.iter().map(|item| literal(format, item));
+            match format {
+                Format::Env => items.collect::<Vec<_>>().join(","),
+                _ => format!("[{}]", items.collect::<Vec<_>>().join(", ")),
+            }
+        }
+    }
+}
+
+/// A literal of some other type than `kind`, if there is one in `format`. Everything in the
+/// environment is a string, so there a string field can't have the wrong type.
+fn wrong_type(format: Format, kind: &Kind) -> Option<String> {
+    let env = format == Format::Env;
+    Some(match kind {
+        Kind::Bool if env => "yes".into(),
+        Kind::Bool => "\"true\"".into(),
+        Kind::Int(_) if env => "eighty".into(),
+        Kind::Int(_) => "\"8080\"".into(),
+        Kind::Float if env => "1,5".into(),
+        Kind::Float => "\"1.5\"".into(),
+        Kind::Str | Kind::Enum(_) if env => return None,
+        Kind::Str | Kind::Enum(_) => "42".into(),
+        Kind::List(_) if env => return None,
+        Kind::List(_) => "\"a,b\"".into(),
+    })
+}
+
+fn out_of_range(kind: &Kind) -> Option<Value> {
+    match kind {
+        Kind::Int(range) if *range.end() < i64::MAX => Some(Value::Int(range.end() + 1)),
+        Kind::Int(range) if *range.start() > i64::MIN => Some(Value::Int(range.start() - 1)),
+        Kind::Enum(choices) => {
+            // Upper-cased is the likeliest near miss, unless the schema already allows it
+            let upper = choices[0].to_uppercase();
+            let value = if choices.contains(&upper) {
+                format!("{}_", choices[0])
+            } else {
+                upper
+            };
+            Some(Value::Str(value))
+        }
+        _ => None,
+    }
+}
+
+/// `name` with two letters swapped, a letter dropped or a letter doubled
+fn typo<R>(name: &str, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let mut chars = name.chars().collect::<Vec<_>>();
+    let i = rng.gen_range(0..chars.len());
+    match rng.gen_range(0..3) {
+        0 if chars.len() > 1 => {
+            let j = if i + 1 < chars.len() { i + 1 } else { i - 1 };
+            chars.swap(i, j);
+        }
+        1 if chars.len() > 1 => {
+            chars.remove(i);
+        }
+        _ => chars.insert(i, chars[i]),
+    }
+    let typo = chars.into_iter().collect::<String>();
+    // Swapping two equal letters changes nothing
+    if typo == name {
+        format!("{}_", name)
+    } else {
+        typo
+    }
+}
+
+fn render(format: Format, entries: &[(String, String)]) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Env => entries
+            .iter()
+            .for_each(|(key, value)| out.push_str(&format!("{}={}\n", key, value))),
+        Format::Toml => entries
+            .iter()
+            .for_each(|(key, value)| out.push_str(&format!("{} = {}\n", key, value))),
+        Format::Yaml => entries.iter().for_each(|(key, value)| {
+            let line = format!("{}: {}", key, value);
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }),
+        Format::Json => {
+            let fields = entries
+                .iter()
+                .map(|(key, value)| format!("  {}: {}", quoted(key), value))
+                .collect::<Vec<_>>();
+            out = format!("{{\n{}\n}}\n", fields.join(",\n"));
+        }
+    }
+    out
+}
+
+fn break_syntax<R>(format: Format, text: &str, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let mut lines = text.lines().map(String::from).collect::<Vec<_>>();
+    match (format, rng.gen_range(0..3)) {
+        // A line that isn't an assignment at all
+        (Format::Env, 0) => lines.push("export".into()),
+        // Names can't start with a digit
+        (Format::Env, 1) => lines.push("1PORT=80".into()),
+        (Format::Env, _) => lines.push("SECRET=\"unterminated".into()),
+        (Format::Toml, 0) => lines.push("name = \"unterminated".into()),
+        (Format::Toml, 1) => lines.push("[section".into()),
+        (Format::Toml, _) => lines.push("key =".into()),
+        // YAML forbids tabs for indentation
+        (Format::Yaml, 0) => lines.push("nested:\n\tkey: value".into()),
+        (Format::Yaml, 1) => lines.push("list: [a, b".into()),
+        (Format::Yaml, _) => lines.push("key: \"unterminated".into()),
+        (Format::Json, 0) => {
+            // A trailing comma after the last member
+            let last = lines.len() - 2;
+            lines[last].push(',');
+        }
+        (Format::Json, 1) => {
+            lines.pop();
+        }
+        (Format::Json, _) => lines.insert(1, "  'single': 'quotes',".into()),
+    }
+    let mut out = lines.join("\n");
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Vec<Field> {
+        [
+            "port:int(1..65535)",
+            "host:str",
+            "debug?:bool",
+            "ratio?:float",
+            "level:enum(debug|info|warn)",
+            "tags?:list(str)",
+        ]
+        .iter()
+        .map(|field| field.parse().unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn it_parses_schemas() {
+        let field = "tags?:list(int(0..9))".parse::<Field>().unwrap();
+        assert!(!field.required);
+        assert_eq!(field.kind, Kind::List(Box::new(Kind::Int(0..=9))));
+        assert!("bad name:str".parse::<Field>().is_err());
+        assert!("x:int(9..0)".parse::<Field>().is_err());
+        assert!("x:enum()".parse::<Field>().is_err());
+        assert!(ConfigFuzzer::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn it_writes_valid_json_with_the_recorded_values() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let fuzzer = ConfigFuzzer::new(schema()).unwrap().defect_rate(0.0);
+        for _ in 0..50 {
+            let case = fuzzer.case(Format::Json, &mut rng);
+            assert!(case.is_valid());
+            let json = serde_json::from_str::<serde_json::Value>(&case.text).unwrap();
+            for (name, value) in &case.values {
+                match value {
+                    Value::Int(n) => assert_eq!(json[name].as_i64(), Some(*n)),
+                    Value::Str(s) => assert_eq!(json[name].as_str(), Some(s.as_str())),
+                    Value::Bool(b) => assert_eq!(json[name].as_bool(), Some(*b)),
+                    Value::Float(x) => assert_eq!(json[name].as_f64(), Some(*x)),
+                    Value::List(items) => {
+                        assert_eq!(json[name].as_array().unwrap().len(), items.len())
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_breaks_json_syntax() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let fuzzer = ConfigFuzzer::new(schema())
+            .unwrap()
+            .defect_rate(1.0)
+            .defects(&[Defect::Syntax])
+            .unwrap();
+        for _ in 0..20 {
+            let case = fuzzer.case(Format::Json, &mut rng);
+            assert_eq!(case.defects, vec![Defect::Syntax]);
+            assert!(serde_json::from_str::<serde_json::Value>(&case.text).is_err());
+        }
+    }
+
+    #[test]
+    fn it_applies_defects_in_every_format() {
+        let mut rng = StdRng::seed_from_u64(51);
+        for defect in Defect::ALL {
+            let fuzzer = ConfigFuzzer::new(schema())
+                .unwrap()
+                .defect_rate(1.0)
+                .defects(&[defect])
+                .unwrap();
+            for format in Format::ALL {
+                let case = fuzzer.case(format, &mut rng);
+                assert_eq!(case.defects, vec![defect], "{:?}", format);
+                if format == Format::Env {
+                    let vars = case.vars().len();
+                    match defect {
+                        Defect::MissingRequired => assert_eq!(vars, case.values.len() - 1),
+                        Defect::UnknownKey | Defect::DuplicateKey => {
+                            assert_eq!(vars, case.values.len() + 1)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_prefixes_env_vars() {
+        let mut rng = StdRng::seed_from_u64(52);
+        let fuzzer = ConfigFuzzer::new(schema())
+            .unwrap()
+            .defect_rate(0.0)
+            .env_prefix("SVC_");
+        let case = fuzzer.case(Format::Env, &mut rng);
+        let vars = case.vars();
+        assert!(vars.iter().any(|(key, _)| key == "SVC_PORT"));
+        assert!(vars.iter().all(|(key, _)| key.starts_with("SVC_")));
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let fuzzer = ConfigFuzzer::new(schema()).unwrap().defect_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(267);
+        for _ in 0..20 {
+            assert!(fuzzer.case(Format::Json, &mut rng).defects.is_empty());
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+use somelib::error::Error;
+
+/// Standard normal CDF
+fn phi(z: f64) -> f64 {
+    0.5 * libm::erfc(-z / std::f64::consts::SQRT_2)
+}
+
+/// One column of a copula's output. Internally a marginal maps a standard normal draw `z`
+/// to the column's value, so the normal marginal can skip the round trip through `[0, 1]`.
+pub struct Marginal(Box<dyn Fn(f64) -> f64>);
+
+impl Marginal {
+    /// Any distribution given by its quantile function (inverse CDF) on `(0, 1)`
+    pub fn from_quantile<F>(quantile: F) -> Self
+    where
+        F: Fn(f64) -> f64 + 'static,
+    {
+        Marginal(Box::new(move |z| quantile(phi(z))))
+    }
+
+    pub fn normal(mean: f64, std_dev: f64) -> Self {
+        Marginal(Box::new(move |z| mean + std_dev * z))
+    }
+
+    pub fn uniform(low: f64, high: f64) -> Self {
+        Self::from_quantile(move |u| low + u * (high - low))
+    }
+
+    pub fn exponential(rate: f64) -> Self {
+        Self::from_quantile(move |u| -(1.0 - u).ln() / rate)
+    }
+
+    /// Resample from observed data, so a real column's shape can be reused as is
+    pub fn empirical(samples: &[f64]) -> Result<Self, Error> {
+        if samples.is_empty() || samples.iter().any(|x| x.is_nan()) {
+            return Err(Error::InvalidParameter(
+                "empirical marginals need at least one sample and no NaNs".into(),
+            ));
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Ok(Self::from_quantile(move |u| {
+            let i = (u * sorted.len() as f64) as usize;
+            sorted[i.min(sorted.len() - 1)]
+        }))
+    }
+}
+
+/// Gaussian copula: draws correlated standard normals, then maps each one through its own
+/// marginal. The columns keep whatever distributions you give them but move together
+/// according to the correlation matrix (exactly for normal marginals, in rank order for
+/// the rest).
+pub struct GaussianCopula {
+    /// Lower triangular `L` with `L * L^T = correlation`
+    cholesky: Vec<Vec<f64>>,
+    marginals: Vec<Marginal>,
+}
+
+impl GaussianCopula {
+    /// `correlation` must be symmetric, positive definite and have ones on the diagonal,
+    /// with one row per marginal
+    pub fn new(correlation: &[Vec<f64>], marginals: Vec<Marginal>) -> Result<Self, Error> {
+        let n = marginals.len();
+        let invalid = |msg: &str| Error::InvalidParameter(msg.to_string());
+        if n == 0 {
+            return Err(invalid("a copula needs at least one marginal"));
+        }
+        if correlation.len() != n || correlation.iter().any(|row| row.len() != n) {
+            return Err(invalid(
+                "the correlation matrix must be n x n for n marginals",
+            ));
+        }
+        for (i, row) in correlation.iter().enumerate() {
+            if (row[i] - 1.0).abs() > 1e-9 {
+                return Err(invalid("the correlation matrix needs ones on its diagonal"));
+            }
+            let asymmetric = row
+                .iter()
+                .zip(correlation)
+                .take(i)
+                .any(|(x, other)| (x - other[i]).abs() > 1e-9);
+            if asymmetric {
+                return Err(invalid("the correlation matrix must be symmetric"));
+            }
+        }
+
+        // Cholesky-Banachiewicz, row by row
+        let mut l = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            for j in 0..=i {
+                let sum = (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+                if i == j {
+                    let d = correlation[i][i] - sum;
+                    if d <= 0.0 {
+                        return Err(invalid("the correlation matrix must be positive definite"));
+                    }
+                    l[i][j] = d.sqrt();
+                } else {
+                    l[i][j] = (correlation[i][j] - sum) / l[j][j];
+                }
+            }
+        }
+
+        Ok(GaussianCopula {
+            cholesky: l,
+            marginals,
+        })
+    }
+
+    /// Correlated standard normals, before the marginals are applied
+    fn normals<R>(&self, rng: &mut R) -> Vec<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        let independent = (0..self.marginals.len())
+            .map(|_| rng.sample::<f64, _>(StandardNormal))
+            .collect::<Vec<_>>();
+        self.cholesky
+            .iter()
+            .map(|row| row.iter().zip(&independent).map(|(l, z)| l * z).sum())
+            .collect()
+    }
+
+    /// Correlated uniforms on `(0, 1)`, the copula itself
+    pub fn uniforms<R>(&self, rng: &mut R) -> Vec<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        self.normals(rng).into_iter().map(phi).collect()
+    }
+}
+
+impl Distribution<Vec<f64>> for GaussianCopula {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Vec<f64> {
+        self.normals(rng)
+            .into_iter()
+            .zip(&self.marginals)
+            .map(|(z, marginal)| (marginal.0)(z))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn correlation(xs: &[f64], ys: &[f64]) -> f64 {
+        let n = xs.len() as f64;
+        let (mx, my) = (xs.iter().sum::<f64>() / n, ys.iter().sum::<f64>() / n);
+        let cov = xs
+            .iter()
+            .zip(ys)
+            .map(|(x, y)| (x - mx) * (y - my))
+            .sum::<f64>();
+        let vx = xs.iter().map(|x| (x - mx).powi(2)).sum::<f64>();
+        let vy = ys.iter().map(|y| (y - my).powi(2)).sum::<f64>();
+        cov / (vx * vy).sqrt()
+    }
+
+    #[test]
+    fn it_correlates_columns_with_their_own_marginals() {
+        let mut rng = StdRng::seed_from_u64(81);
+        let copula = GaussianCopula::new(
+            &[vec![1.0, 0.8], vec![0.8, 1.0]],
+            vec![Marginal::normal(100.0, 15.0), Marginal::uniform(0.0, 1.0)],
+        )
+        .unwrap();
+
+        let rows = copula
+            .sample_iter(&mut rng)
+            .take(20_000)
+            .collect::<Vec<_>>();
+        let xs = rows.iter().map(|r| r[0]).collect::<Vec<_>>();
+        let ys = rows.iter().map(|r| r[1]).collect::<Vec<_>>();
+
+        assert!(ys.iter().all(|y| (0.0..=1.0).contains(y)));
+        let mean = xs.iter().sum::<f64>() / xs.len() as f64;
+        assert!((mean - 100.0).abs() < 0.5, "{}", mean);
+        // The uniform column is a monotone transform of a normal, which shrinks the
+        // linear correlation a little but keeps it strongly positive
+        let rho = correlation(&xs, &ys);
+        assert!((0.7..0.85).contains(&rho), "{}", rho);
+    }
+
+    #[test]
+    fn it_rejects_invalid_correlation_matrices() {
+        let two = || vec![Marginal::uniform(0.0, 1.0), Marginal::exponential(1.0)];
+
+        assert!(GaussianCopula::new(&[vec![1.0, 0.5], vec![0.4, 1.0]], two()).is_err());
+        assert!(GaussianCopula::new(&[vec![1.0, 1.5], vec![1.5, 1.0]], two()).is_err());
+        assert!(GaussianCopula::new(&[vec![1.0]], two()).is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Damage a clean dataset in known ways: duplicate rows, null cells, outliers and swapped
+//! fields, with a record of every change, to test the data-quality checks that are
+//! supposed to catch them.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Corruption {
+    /// A copy of the previous row, like a retried write
+    Duplicate,
+    /// One cell replaced with its type's null
+    Null,
+    /// One cell replaced with an implausible value
+    Outlier,
+    /// Two cells of a row exchanged, like a column-order mix-up
+    SwappedFields,
+}
+
+impl Corruption {
+    pub const ALL: [Corruption; 4] = [
+        Corruption::Duplicate,
+        Corruption::Null,
+        Corruption::Outlier,
+        Corruption::SwappedFields,
+    ];
+}
+
+/// Cell types the injector knows how to break
+pub trait Corruptible: Clone {
+    /// The type's missing value
+    fn null() -> Self;
+
+    /// An implausible value standing in for `self`, or `None` if there's no sensible one
+    /// (e.g. for a null)
+    fn outlier<R>(&self, rng: &mut R) -> Option<Self>
+    where
+        R: Rng + ?Sized;
+}
+
+/// Null is NaN, which is how a missing float usually shows up in numeric code
+impl Corruptible for f64 {
+    fn null() -> Self {
+        f64::NAN
+    }
+
+    fn outlier<R>(&self, rng: &mut R) -> Option<Self>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.is_nan() {
+            return None;
+        }
+        let base = if *self == 0.0 { 1.0 } else { *self };
+        // Hundreds to tens of thousands of times too big, sometimes with the sign flipped
+        let sign = if rng.gen_bool(0.2) { -1.0 } else { 1.0 };
+        Some(sign * base * 10f64.powf(rng.gen_range(2.0..4.5)))
+    }
+}
+
+/// Null is 0, the usual default where a missing integer gets filled in
+impl Corruptible for i64 {
+    fn null() -> Self {
+        0
+    }
+
+    fn outlier<R>(&self, rng: &mut R) -> Option<Self>
+    where
+        R: Rng + ?Sized,
+    {
+        let base = if *self == 0 { 1 } else { *self };
+        Some(match rng.gen_range(0..3) {
+            0 => base.saturating_mul(rng.gen_range(100..10_000)),
+            1 => base.saturating_neg(),
+            _ => *[i64::MIN, i64::MAX, -1].choose(rng).unwrap(),
+        })
+    }
+}
+
+/// Null is the empty string; an outlier is the text repeated far past any sane length
+impl Corruptible for String {
+    fn null() -> Self {
+        String::new()
+    }
+
+    fn outlier<R>(&self, rng: &mut R) -> Option<Self>
+    where
+        R: Rng + ?Sized,
+    {
+        let unit = if self.is_empty() { "x" } else { self.as_str() };
+        Some(unit.repeat(rng.gen_range(100..1000)))
+    }
+}
+
+/// Null is `None`, outliers break the inner value
+impl<T: Corruptible> Corruptible for Option<T> {
+    fn null() -> Self {
+        None
+    }
+
+    fn outlier<R>(&self, rng: &mut R) -> Option<Self>
+    where
+        R: Rng + ?Sized,
+    {
+        self.as_ref()?.outlier(rng).map(Some)
+    }
+}
+
+/// One change, `row` indexing the corrupted data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Injected {
+    pub row: usize,
+    pub kind: Corruption,
+    /// The cells touched, empty for a duplicate
+    pub columns: Vec<usize>,
+}
+
+/// The damaged data, `mask[i]` being true for every row that was changed or added
+#[derive(Debug, Clone, PartialEq)]
+pub struct Corrupted<T> {
+    pub data: Vec<T>,
+    pub mask: Vec<bool>,
+    pub injected: Vec<Injected>,
+}
+
+/// Injects corruptions into rows of cells at a given rate
+pub struct Injector {
+    rate: f64,
+    kinds: Vec<Corruption>,
+}
+
+impl Injector {
+    /// `rate` is the chance of each input row getting one corruption
+    pub fn new(rate: f64) -> Self {
+        Injector {
+            rate: probability(rate),
+            kinds: Corruption::ALL.to_vec(),
+        }
+    }
+
+    /// Which corruptions to use, at least one
+    pub fn kinds(mut self, kinds: &[Corruption]) -> Result<Self, Error> {
+        if kinds.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one kind of corruption is needed".into(),
+            ));
+        }
+        self.kinds = kinds.to_vec();
+        Ok(self)
+    }
+
+    pub fn rows<T, R>(&self, rows: &[Vec<T>], rng: &mut R) -> Corrupted<Vec<T>>
+    where
+        T: Corruptible,
+        R: Rng + ?Sized,
+    {
+        let mut out = Corrupted {
+            data: Vec::with_capacity(rows.len()),
+            mask: Vec::with_capacity(rows.len()),
+            injected: Vec::new(),
+        };
+        for row in rows {
+            out.data.push(row.clone());
+            out.mask.push(false);
+            if !rng.gen_bool(self.rate) {
+                continue;
+            }
+            // A kind that can't apply to this row (a swap in a one-column row, an outlier
+            // in a row of nulls) is skipped rather than retried, so the rate is an upper
+            // bound for such data
+            let kind = *self.kinds.choose(rng).unwrap();
+            let at = out.data.len() - 1;
+            let columns = match kind {
+                Corruption::Duplicate => {
+                    out.data.push(row.clone());
+                    out.mask.push(false);
+                    Some(Vec::new())
+                }
+                Corruption::Null if !row.is_empty() => {
+                    let column = rng.gen_range(0..row.len());
+                    out.data[at][column] = T::null();
+                    Some(vec![column])
+                }
+                Corruption::Outlier => {
+                    let candidates = row
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, cell)| Some((i, cell.outlier(rng)?)))
+                        .collect::<Vec<_>>();
+                    candidates.choose(rng).map(|(column, value)| {
+                        out.data[at][*column] = value.clone();
+                        vec![*column]
+                    })
+                }
+                Corruption::SwappedFields if row.len() >= 2 => {
+                    let picked = rand::seq::index::sample(rng, row.len(), 2);
+                    let (a, b) = (picked.index(0), picked.index(1));
+                    out.data[at].swap(a, b);
+                    Some(vec![a.min(b), a.max(b)])
+                }
+                _ => None,
+            };
+            if let Some(columns) = columns {
+                let row = out.data.len() - 1;
+                out.mask[row] = true;
+                out.injected.push(Injected { row, kind, columns });
+            }
+        }
+        out
+    }
+
+    /// A single column: each value is a one-cell row, so there are no swaps
+    pub fn series<T, R>(&self, values: &[T], rng: &mut R) -> Corrupted<T>
+    where
+        T: Corruptible,
+        R: Rng + ?Sized,
+    {
+        let rows = values.iter().map(|v| vec![v.clone()]).collect::<Vec<_>>();
+        let Corrupted {
+            data,
+            mask,
+            injected,
+        } = self.rows(&rows, rng);
+        Corrupted {
+            data: data.into_iter().map(|mut row| row.remove(0)).collect(),
+            mask,
+            injected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Vec<Vec<Option<String>>> {
+        (0..500)
+            .map(|i| {
+                vec![
+                    Some(format!("user{}", i)),
+                    Some(format!("user{}@example.com", i)),
+                    Some("active".to_string()),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_labels_every_change() {
+        let mut rng = StdRng::seed_from_u64(2532);
+        let clean = table();
+        let dirty = Injector::new(0.1).rows(&clean, &mut rng);
+
+        let duplicates = dirty
+            .injected
+            .iter()
+            .filter(|i| i.kind == Corruption::Duplicate)
+            .count();
+        assert_eq!(dirty.data.len(), clean.len() + duplicates);
+        assert!((20..80).contains(&dirty.injected.len()));
+        assert_eq!(
+            dirty.mask.iter().filter(|m| **m).count(),
+            dirty.injected.len()
+        );
+
+        // Unmasked rows are exactly the clean rows, in order
+        let kept = dirty
+            .data
+            .iter()
+            .zip(&dirty.mask)
+            .filter(|(_, m)| !**m)
+            .map(|(row, _)| row)
+            .collect::<Vec<_>>();
+        let untouched = clean.iter().filter(|row| kept.contains(row)).count();
+        assert_eq!(untouched, kept.len());
+
+        for injected in &dirty.injected {
+            let row = &dirty.data[injected.row];
+            match injected.kind {
+                Corruption::Duplicate => assert_eq!(row, &dirty.data[injected.row - 1]),
+                Corruption::Null => assert!(row[injected.columns[0]].is_none()),
+                Corruption::Outlier => {
+                    assert!(row[injected.columns[0]].as_ref().unwrap().len() > 500)
+                }
+                Corruption::SwappedFields => assert_eq!(injected.columns.len(), 2),
+            }
+        }
+    }
+
+    #[test]
+    fn it_corrupts_series() {
+        let mut rng = StdRng::seed_from_u64(2532);
+        let values = (0..1000).map(|i| 50.0 + (i % 7) as f64).collect::<Vec<_>>();
+        let dirty = Injector::new(0.05)
+            .kinds(&[
+                Corruption::Null,
+                Corruption::Outlier,
+                Corruption::SwappedFields,
+            ])
+            .unwrap()
+            .series(&values, &mut rng);
+
+        assert_eq!(dirty.data.len(), values.len());
+        for (i, value) in dirty.data.iter().enumerate() {
+            let normal = (50.0..=56.0).contains(value);
+            assert_eq!(normal, !dirty.mask[i], "{} at {}", value, i);
+        }
+        assert!(dirty
+            .injected
+            .iter()
+            .all(|i| i.kind != Corruption::SwappedFields));
+        assert!(Injector::new(0.1).kinds(&[]).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let mut rng = StdRng::seed_from_u64(2533);
+        let dirty = Injector::new(f64::NAN).rows(&table(), &mut rng);
+        assert!(dirty.injected.is_empty());
+    }
+}
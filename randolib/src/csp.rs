@@ -0,0 +1,332 @@
+//! Random constraint satisfaction instances for benchmarking solvers: graph coloring and
+//! k-SAT, written in the DIMACS formats most solvers read.
+//!
+//! Purely random instances might have no solution, which makes a solver that answers
+//! "unsatisfiable" hard to check. Both generators can instead *plant* a solution: draw it
+//! first and only add constraints it satisfies. The instance is then known to be solvable,
+//! and the planted solution comes back with it.
+
+use crate::probability;
+use rand::{distributions::Bernoulli, prelude::*};
+use somelib::error::Error;
+use std::fmt::Write;
+
+/// A graph to color with `colors` colors so that no edge joins two nodes of the same color
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coloring {
+    pub nodes: usize,
+    /// `(a, b)` with `a < b`, each pair at most once
+    pub edges: Vec<(usize, usize)>,
+    pub colors: usize,
+    /// A valid coloring, one color per node, when the instance was planted
+    pub planted: Option<Vec<usize>>,
+}
+
+impl Coloring {
+    /// Whether `colors` gives every node a color in range and no edge one color at both ends
+    pub fn is_solution(&self, colors: &[usize]) -> bool {
+        colors.len() == self.nodes
+            && colors.iter().all(|c| *c < self.colors)
+            && self.edges.iter().all(|(a, b)| colors[*a] != colors[*b])
+    }
+
+    /// The DIMACS graph format: `p edge NODES EDGES`, then `e A B` per edge, counting nodes
+    /// from 1
+    pub fn to_dimacs(&self) -> String {
+        // `write!` into a `String` can't fail, so the `unwrap`s are fine
+        let mut out = format!("c {}-coloring\n", self.colors);
+        writeln!(out, "p edge {} {}", self.nodes, self.edges.len()).unwrap();
+        for (a, b) in &self.edges {
+            writeln!(out, "e {} {}", a + 1, b + 1).unwrap();
+        }
+        out
+    }
+}
+
+/// Generates `Coloring`s on Erdős–Rényi graphs: every pair of nodes is joined with the same
+/// probability
+pub struct ColoringGen {
+    nodes: usize,
+    colors: usize,
+    density: f64,
+    planted: bool,
+}
+
+impl ColoringGen {
+    /// An average degree of about 4 and no planted solution
+    pub fn new(nodes: usize, colors: usize) -> Result<Self, Error> {
+        if colors == 0 {
+            return Err(Error::InvalidParameter(
+                "a coloring needs at least one color".into(),
+            ));
+        }
+        Ok(ColoringGen {
+            nodes,
+            colors,
+            density: (4.0 / nodes.saturating_sub(1).max(1) as f64).min(1.0),
+            planted: false,
+        })
+    }
+
+    /// The chance any two nodes are joined
+    pub fn density(mut self, density: f64) -> Self {
+        self.density = probability(density);
+        self
+    }
+
+    /// Set the density for an expected `degree` edges per node
+    pub fn average_degree(self, degree: f64) -> Self {
+        let pairs = self.nodes.saturating_sub(1).max(1) as f64;
+        self.density(degree / pairs)
+    }
+
+    /// Plant a solution. Only pairs of different colors can be joined, which leaves the
+    /// expected degree lower by a factor of `1 - 1/colors`.
+    pub fn planted(mut self, planted: bool) -> Self {
+        self.planted = planted;
+        self
+    }
+
+    pub fn instance<R>(&self, rng: &mut R) -> Coloring
+    where
+        R: Rng + ?Sized,
+    {
+        let planted = self.planted.then(|| {
+            (0..self.nodes)
+                .map(|_| rng.gen_range(0..self.colors))
+                .collect::<Vec<_>>()
+        });
+        // `unwrap` is fine, the density is clamped to 0..=1
+        let join = Bernoulli::new(self.density).unwrap();
+        let mut edges = Vec::new();
+        for a in 0..self.nodes {
+            for b in a + 1..self.nodes {
+                let allowed = planted.as_ref().is_none_or(|colors| colors[a] != colors[b]);
+                if allowed && join.sample(rng) {
+                    edges.push((a, b));
+                }
+            }
+        }
+        Coloring {
+            nodes: self.nodes,
+            edges,
+            colors: self.colors,
+            planted,
+        }
+    }
+}
+
+/// A formula in conjunctive normal form: every clause must have at least one true literal
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sat {
+    pub variables: usize,
+    /// DIMACS literals: `3` is variable 3, `-3` its negation, counting from 1
+    pub clauses: Vec<Vec<i64>>,
+    /// A satisfying assignment, `planted[i]` for variable `i + 1`, when the instance was
+    /// planted
+    pub planted: Option<Vec<bool>>,
+}
+
+impl Sat {
+    pub fn is_satisfied_by(&self, assignment: &[bool]) -> bool {
+        assignment.len() == self.variables
+            && self
+                .clauses
+                .iter()
+                .all(|clause| clause.iter().any(|lit| satisfies(*lit, assignment)))
+    }
+
+    /// Clauses per variable, the number that decides how hard random k-SAT is
+    pub fn ratio(&self) -> f64 {
+        self.clauses.len() as f64 / self.variables.max(1) as f64
+    }
+
+    /// The DIMACS CNF format: `p cnf VARIABLES CLAUSES`, then each clause's literals ending
+    /// in `0`
+    pub fn to_dimacs(&self) -> String {
+        let mut out = format!("p cnf {} {}\n", self.variables, self.clauses.len());
+        for clause in &self.clauses {
+            for lit in clause {
+                write!(out, "{} ", lit).unwrap();
+            }
+            out.push_str("0\n");
+        }
+        out
+    }
+}
+
+fn satisfies(lit: i64, assignment: &[bool]) -> bool {
+    assignment[lit.unsigned_abs() as usize - 1] == (lit > 0)
+}
+
+/// Generates random k-SAT: each clause has `k` distinct variables, each negated half the
+/// time
+pub struct SatGen {
+    variables: usize,
+    k: usize,
+    ratio: f64,
+    planted: bool,
+}
+
+impl SatGen {
+    /// The clause/variable ratio starts where random k-SAT is hardest, where instances go
+    /// from almost always to almost never satisfiable: 1 for 2-SAT, about 4.26 for 3-SAT,
+    /// and roughly `2^k ln 2` for larger `k`.
+    pub fn new(variables: usize, k: usize) -> Result<Self, Error> {
+        if k == 0 || k > variables {
+            return Err(Error::InvalidParameter(format!(
+                "clauses of {} literals need 1 to {} of them, the variable count",
+                k, variables
+            )));
+        }
+        let ratio = match k {
+            1 | 2 => 1.0,
+            3 => 4.26,
+            _ => 2f64.powi(k as i32) * std::f64::consts::LN_2,
+        };
+        Ok(SatGen {
+            variables,
+            k,
+            ratio,
+            planted: false,
+        })
+    }
+
+    /// Clauses per variable. Below the threshold instances are easy and usually
+    /// satisfiable, above it they're easy to refute, so sweeping this maps a solver's range.
+    pub fn ratio(mut self, ratio: f64) -> Result<Self, Error> {
+        if !(ratio.is_finite() && ratio >= 0.0) {
+            return Err(Error::InvalidParameter(format!(
+                "clause ratio {} must be finite and non-negative",
+                ratio
+            )));
+        }
+        self.ratio = ratio;
+        Ok(self)
+    }
+
+    /// Plant a satisfying assignment, throwing away clauses it doesn't satisfy. That's one
+    /// clause in `2^k`, so it costs little, but planted instances are a little easier than
+    /// purely random ones at the same ratio.
+    pub fn planted(mut self, planted: bool) -> Self {
+        self.planted = planted;
+        self
+    }
+
+    pub fn instance<R>(&self, rng: &mut R) -> Sat
+    where
+        R: Rng + ?Sized,
+    {
+        let planted = self.planted.then(|| {
+            (0..self.variables)
+                .map(|_| rng.gen())
+                .collect::<Vec<bool>>()
+        });
+        let count = (self.ratio * self.variables as f64).round() as usize;
+        let mut clauses = Vec::with_capacity(count);
+        while clauses.len() < count {
+            let clause = rand::seq::index::sample(rng, self.variables, self.k)
+                .into_iter()
+                .map(|v| {
+                    if rng.gen() {
+                        v as i64 + 1
+                    } else {
+                        -(v as i64 + 1)
+                    }
+                })
+                .collect::<Vec<_>>();
+            let kept = planted
+                .as_ref()
+                .is_none_or(|assignment| clause.iter().any(|lit| satisfies(*lit, assignment)));
+            if kept {
+                clauses.push(clause);
+            }
+        }
+        Sat {
+            variables: self.variables,
+            clauses,
+            planted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_plants_colorings() {
+        let mut rng = StdRng::seed_from_u64(279);
+        let gen = ColoringGen::new(200, 3).unwrap().average_degree(6.0);
+        let random = gen.instance(&mut rng);
+        // 6 per node, each edge counted at both ends
+        let expected = 200.0 * 6.0 / 2.0;
+        assert!((random.edges.len() as f64 - expected).abs() < 100.0);
+        assert!(random.planted.is_none());
+        assert!(random.edges.iter().all(|(a, b)| a < b && *b < 200));
+
+        let planted = gen.planted(true).instance(&mut rng);
+        assert!(planted.is_solution(planted.planted.as_ref().unwrap()));
+        assert!(!planted.is_solution(&[0; 200]));
+        let dimacs = planted.to_dimacs();
+        assert!(dimacs.contains(&format!("p edge 200 {}\n", planted.edges.len())));
+        assert_eq!(
+            dimacs.lines().filter(|l| l.starts_with("e ")).count(),
+            planted.edges.len()
+        );
+    }
+
+    #[test]
+    fn it_plants_sat_assignments() {
+        let mut rng = StdRng::seed_from_u64(280);
+        let sat = SatGen::new(100, 3)
+            .unwrap()
+            .ratio(5.0)
+            .unwrap()
+            .planted(true)
+            .instance(&mut rng);
+        assert_eq!(sat.clauses.len(), 500);
+        assert!((sat.ratio() - 5.0).abs() < 1e-9);
+        assert!(sat.is_satisfied_by(sat.planted.as_ref().unwrap()));
+        for clause in &sat.clauses {
+            let mut vars = clause.iter().map(|lit| lit.abs()).collect::<Vec<_>>();
+            vars.sort();
+            vars.dedup();
+            assert_eq!(vars.len(), 3);
+            assert!(vars.iter().all(|v| (1..=100).contains(v)));
+        }
+        let dimacs = sat.to_dimacs();
+        assert!(dimacs.starts_with("p cnf 100 500\n"));
+        assert!(dimacs.lines().skip(1).all(|l| l.ends_with(" 0")));
+
+        assert!(SatGen::new(2, 3).is_err());
+        assert!(SatGen::new(5, 0).is_err());
+        assert!(SatGen::new(5, 3).unwrap().ratio(-1.0).is_err());
+    }
+
+    #[test]
+    fn it_makes_unsatisfiable_instances_above_the_threshold() {
+        // 10 variables is few enough to try every assignment
+        let mut rng = StdRng::seed_from_u64(281);
+        let gen = SatGen::new(10, 3).unwrap().ratio(12.0).unwrap();
+        let satisfiable = |sat: &Sat| {
+            (0..1u32 << 10).any(|bits| {
+                let assignment = (0..10).map(|i| bits >> i & 1 == 1).collect::<Vec<_>>();
+                sat.is_satisfied_by(&assignment)
+            })
+        };
+        let unsat = (0..20)
+            .filter(|_| !satisfiable(&gen.instance(&mut rng)))
+            .count();
+        assert!(unsat >= 15, "{}", unsat);
+        let planted = gen.planted(true).instance(&mut rng);
+        assert!(satisfiable(&planted));
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let mut rng = StdRng::seed_from_u64(282);
+        let gen = ColoringGen::new(10, 3).unwrap().density(f64::NAN);
+        assert!(gen.instance(&mut rng).edges.is_empty());
+    }
+}
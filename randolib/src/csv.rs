@@ -0,0 +1,448 @@
+//! CSV files full of the things that trip up CSV parsers: quoted delimiters, doubled quotes,
+//! newlines inside fields, byte-order marks, ragged rows, mixed line endings and text that
+//! isn't UTF-8 at all.
+//!
+//! Each `CsvCase` comes with the records it is meant to encode, so a parser under test can
+//! be checked field by field rather than just for not crashing. Fields are bytes because
+//! some quirks produce Latin-1 or invalid UTF-8.
+
+use crate::{probability, strings, unicode::Hazard};
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{fmt, str::FromStr};
+
+/// An edge case to inject. The first four are legal RFC 4180; the rest are things real
+/// files do anyway, which a lenient parser should still read as the intended records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quirk {
+    /// A field containing the delimiter, so it has to be quoted
+    QuotedDelimiter,
+    /// A field containing `"`, written doubled inside quotes
+    EscapedQuote,
+    /// A quoted field spanning lines, with `\n` or `\r\n` inside
+    EmbeddedNewline,
+    /// An empty field, sometimes written as `""`
+    EmptyField,
+    /// A UTF-8 byte-order mark before the first row
+    Bom,
+    /// A row with fewer or more fields than the header
+    RaggedRow,
+    /// A row ending in `\n` where the rest end in `\r\n`
+    MixedLineEnding,
+    /// A `"` in the middle of an unquoted field, which strict parsers reject
+    StrayQuote,
+    /// Valid but awkward Unicode: bidi controls, zero-width characters, NUL, ...
+    UnicodeHazard,
+    /// Accented text in Latin-1 instead of UTF-8, the classic spreadsheet export
+    Latin1,
+    /// Bytes that aren't valid in any UTF-8 sequence
+    InvalidUtf8,
+}
+
+impl Quirk {
+    pub const ALL: [Quirk; 11] = [
+        Quirk::QuotedDelimiter,
+        Quirk::EscapedQuote,
+        Quirk::EmbeddedNewline,
+        Quirk::EmptyField,
+        Quirk::Bom,
+        Quirk::RaggedRow,
+        Quirk::MixedLineEnding,
+        Quirk::StrayQuote,
+        Quirk::UnicodeHazard,
+        Quirk::Latin1,
+        Quirk::InvalidUtf8,
+    ];
+
+    /// Whether a file with this quirk is still valid RFC 4180
+    pub fn is_rfc4180(&self) -> bool {
+        matches!(
+            self,
+            Quirk::QuotedDelimiter
+                | Quirk::EscapedQuote
+                | Quirk::EmbeddedNewline
+                | Quirk::EmptyField
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Quirk::QuotedDelimiter => "quoted-delimiter",
+            Quirk::EscapedQuote => "escaped-quote",
+            Quirk::EmbeddedNewline => "embedded-newline",
+            Quirk::EmptyField => "empty-field",
+            Quirk::Bom => "bom",
+            Quirk::RaggedRow => "ragged-row",
+            Quirk::MixedLineEnding => "mixed-line-ending",
+            Quirk::StrayQuote => "stray-quote",
+            Quirk::UnicodeHazard => "unicode-hazard",
+            Quirk::Latin1 => "latin1",
+            Quirk::InvalidUtf8 => "invalid-utf8",
+        }
+    }
+}
+
+impl fmt::Display for Quirk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Quirk {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Quirk::ALL
+            .into_iter()
+            .find(|quirk| quirk.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown quirk {:?}", s)))
+    }
+}
+
+/// A quirk and the record it went into, 0 being the header when there is one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Injected {
+    pub record: usize,
+    pub quirk: Quirk,
+}
+
+/// A generated file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvCase {
+    /// The file contents
+    pub bytes: Vec<u8>,
+    /// What the file encodes: the fields of each record, unquoted and unescaped
+    pub records: Vec<Vec<Vec<u8>>>,
+    pub injected: Vec<Injected>,
+}
+
+/// A field and how to write it
+struct Field {
+    bytes: Vec<u8>,
+    /// Quote even when nothing in the field needs it
+    force_quotes: bool,
+    /// Write as is, even if it contains a quote (`StrayQuote`)
+    raw: bool,
+}
+
+/// Generates `CsvCase`s
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvFuzzer {
+    columns: usize,
+    rows: usize,
+    header: bool,
+    rate: f64,
+    quirks: Vec<Quirk>,
+}
+
+impl CsvFuzzer {
+    /// 5 columns, a header and 20 rows, each with a 1 in 5 chance of a quirk
+    pub fn new() -> Self {
+        CsvFuzzer {
+            columns: 5,
+            rows: 20,
+            header: true,
+            rate: 0.2,
+            quirks: Quirk::ALL.to_vec(),
+        }
+    }
+
+    pub fn columns(mut self, columns: usize) -> Result<Self, Error> {
+        if columns == 0 {
+            return Err(Error::InvalidParameter(
+                "a CSV needs at least 1 column".into(),
+            ));
+        }
+        self.columns = columns;
+        Ok(self)
+    }
+
+    /// Data rows, not counting the header
+    pub fn rows(mut self, rows: usize) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Chance of each data row getting a quirk, and of the file starting with a BOM
+    pub fn rate(mut self, rate: f64) -> Self {
+        self.rate = probability(rate);
+        self
+    }
+
+    /// Only inject these, e.g. `Quirk::ALL` filtered by `is_rfc4180` for a strict parser
+    pub fn quirks(mut self, quirks: &[Quirk]) -> Result<Self, Error> {
+        if quirks.is_empty() {
+            return Err(Error::InvalidParameter("no quirks to inject".into()));
+        }
+        self.quirks = quirks.to_vec();
+        Ok(self)
+    }
+
+    pub fn case<R>(&self, rng: &mut R) -> CsvCase
+    where
+        R: Rng + ?Sized,
+    {
+        let mut bytes = Vec::new();
+        let mut records = Vec::new();
+        let mut injected = Vec::new();
+
+        // The BOM is the only whole-file quirk, so it gets its own roll
+        if self.quirks.contains(&Quirk::Bom) && rng.gen_bool(self.rate) {
+            bytes.extend_from_slice("\u{feff}".as_bytes());
+            injected.push(Injected {
+                record: 0,
+                quirk: Quirk::Bom,
+            });
+        }
+        if self.header {
+            let names = (1..=self.columns)
+                .map(|i| plain(format!("col{}", i).into_bytes()))
+                .collect::<Vec<_>>();
+            write_record(&mut bytes, &mut records, names, b"\r\n");
+        }
+
+        let row_quirks = self
+            .quirks
+            .iter()
+            .copied()
+            .filter(|quirk| *quirk != Quirk::Bom)
+            .collect::<Vec<_>>();
+        for _ in 0..self.rows {
+            let mut fields = (0..self.columns)
+                .map(|_| plain(self.value(rng)))
+                .collect::<Vec<_>>();
+            let mut ending: &[u8] = b"\r\n";
+            if let Some(quirk) = row_quirks.choose(rng).filter(|_| rng.gen_bool(self.rate)) {
+                let column = rng.gen_range(0..fields.len());
+                let field = &mut fields[column];
+                match quirk {
+                    Quirk::QuotedDelimiter => insert(&mut field.bytes, b",", rng),
+                    Quirk::EscapedQuote => insert(&mut field.bytes, b"\"", rng),
+                    Quirk::EmbeddedNewline => {
+                        let newline: &[u8] = if rng.gen() { b"\n" } else { b"\r\n" };
+                        insert(&mut field.bytes, newline, rng)
+                    }
+                    Quirk::EmptyField => {
+                        field.bytes.clear();
+                        field.force_quotes = rng.gen();
+                    }
+                    Quirk::RaggedRow => {
+                        if fields.len() > 1 && rng.gen() {
+                            fields.truncate(rng.gen_range(1..fields.len()));
+                        } else {
+                            for _ in 0..rng.gen_range(1..=3) {
+                                fields.push(plain(self.value(rng)));
+                            }
+                        }
+                    }
+                    Quirk::MixedLineEnding => ending = b"\n",
+                    Quirk::StrayQuote => {
+                        // Needs a character on each side to be in the middle
+                        field.bytes = strings::alphanumeric_with(rng.gen_range(2..8), rng).into();
+                        let at = rng.gen_range(1..field.bytes.len());
+                        field.bytes.insert(at, b'"');
+                        field.raw = true;
+                    }
+                    Quirk::UnicodeHazard => {
+                        let hazard = Hazard::ALL.choose(rng).unwrap();
+                        insert(&mut field.bytes, hazard.sample(8, rng).as_bytes(), rng)
+                    }
+                    Quirk::Latin1 => {
+                        // é, ü, ñ, ß, ø, £ and ° in Latin-1
+                        let char = *[0xe9, 0xfc, 0xf1, 0xdf, 0xf8, 0xa3, 0xb0]
+                            .choose(rng)
+                            .unwrap();
+                        insert(&mut field.bytes, &[char], rng)
+                    }
+                    Quirk::InvalidUtf8 => {
+                        // A lone continuation byte, a truncated sequence, bytes that never
+                        // appear in UTF-8 and an overlong encoding of `/`
+                        let bad: &[&[u8]] =
+                            &[b"\x80", b"\xc3", b"\xe2\x82", b"\xff", b"\xfe", b"\xc0\xaf"];
+                        insert(&mut field.bytes, bad.choose(rng).unwrap(), rng)
+                    }
+                    Quirk::Bom => unreachable!("the BOM is per file"),
+                }
+                injected.push(Injected {
+                    record: records.len(),
+                    quirk: *quirk,
+                });
+            }
+            write_record(&mut bytes, &mut records, fields, ending);
+        }
+        CsvCase {
+            bytes,
+            records,
+            injected,
+        }
+    }
+
+    /// A word or a number, like most real columns
+    fn value<R>(&self, rng: &mut R) -> Vec<u8>
+    where
+        R: Rng + ?Sized,
+    {
+        let value = if rng.gen_bool(0.3) {
+            format!("{:.2}", rng.gen_range(-1000.0..1000.0))
+        } else {
+            strings::alphanumeric_with(rng.gen_range(1..10), rng)
+        };
+        value.into_bytes()
+    }
+}
+
+impl Default for CsvFuzzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn plain(bytes: Vec<u8>) -> Field {
+    Field {
+        bytes,
+        force_quotes: false,
+        raw: false,
+    }
+}
+
+/// Put `piece` at a random position in `bytes`
+fn insert<R>(bytes: &mut Vec<u8>, piece: &[u8], rng: &mut R)
+where
+    R: Rng + ?Sized,
+{
+    let at = rng.gen_range(0..=bytes.len());
+    bytes.splice(at..at, piece.iter().copied());
+}
+
+/// Append a record to the file, quoting fields as RFC 4180 says: when they contain the
+/// delimiter, a quote or a line break, with quotes inside doubled
+fn write_record(
+    bytes: &mut Vec<u8>,
+    records: &mut Vec<Vec<Vec<u8>>>,
+    fields: Vec<Field>,
+    ending: &[u8],
+) {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            bytes.push(b',');
+        }
+        let special = field
+            .bytes
+            .iter()
+            .any(|b| matches!(b, b',' | b'"' | b'\r' | b'\n'));
+        if field.raw || !(special || field.force_quotes) {
+            bytes.extend_from_slice(&field.bytes);
+        } else {
+            bytes.push(b'"');
+            for b in &field.bytes {
+                if *b == b'"' {
+                    bytes.push(b'"');
+                }
+                bytes.push(*b);
+            }
+            bytes.push(b'"');
+        }
+    }
+    bytes.extend_from_slice(ending);
+    records.push(fields.into_iter().map(|field| field.bytes).collect());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A lenient parser: RFC 4180 quoting, `\n` or `\r\n` line endings, a leading BOM is
+    /// skipped and a quote inside an unquoted field is kept as is
+    fn parse(mut bytes: &[u8]) -> Vec<Vec<Vec<u8>>> {
+        if let Some(rest) = bytes.strip_prefix("\u{feff}".as_bytes()) {
+            bytes = rest;
+        }
+        let mut records = Vec::new();
+        let mut record = Vec::new();
+        let mut field = Vec::new();
+        let mut i = 0;
+        let mut quoted = false;
+        while i < bytes.len() {
+            let b = bytes[i];
+            if quoted {
+                if b == b'"' && bytes.get(i + 1) == Some(&b'"') {
+                    field.push(b'"');
+                    i += 1;
+                } else if b == b'"' {
+                    quoted = false;
+                } else {
+                    field.push(b);
+                }
+            } else {
+                match b {
+                    b'"' if field.is_empty() => quoted = true,
+                    b',' => record.push(std::mem::take(&mut field)),
+                    b'\r' if bytes.get(i + 1) == Some(&b'\n') => {}
+                    b'\n' => {
+                        record.push(std::mem::take(&mut field));
+                        records.push(std::mem::take(&mut record));
+                    }
+                    _ => field.push(b),
+                }
+            }
+            i += 1;
+        }
+        records
+    }
+
+    #[test]
+    fn it_encodes_its_records() {
+        let mut rng = StdRng::seed_from_u64(260);
+        let fuzzer = CsvFuzzer::new().rows(200).rate(0.5);
+
+        for _ in 0..20 {
+            let case = fuzzer.case(&mut rng);
+            assert_eq!(parse(&case.bytes), case.records);
+        }
+        let case = fuzzer.case(&mut rng);
+        assert_eq!(case.records.len(), 201);
+        assert!(case.injected.len() > 50);
+        for injected in &case.injected {
+            if injected.quirk == Quirk::RaggedRow {
+                assert_ne!(case.records[injected.record].len(), 5);
+            }
+        }
+    }
+
+    #[test]
+    fn it_injects_only_the_chosen_quirks() {
+        let mut rng = StdRng::seed_from_u64(260);
+        let strict = Quirk::ALL
+            .into_iter()
+            .filter(Quirk::is_rfc4180)
+            .collect::<Vec<_>>();
+        let fuzzer = CsvFuzzer::new()
+            .quirks(&strict)
+            .unwrap()
+            .rate(1.0)
+            .header(false);
+
+        let case = fuzzer.case(&mut rng);
+        assert_eq!(case.injected.len(), 20);
+        assert!(case.injected.iter().all(|i| i.quirk.is_rfc4180()));
+        assert!(String::from_utf8(case.bytes).is_ok());
+
+        let clean = CsvFuzzer::new().rate(0.0).case(&mut rng);
+        assert!(clean.injected.is_empty());
+        assert!(clean.bytes.starts_with(b"col1,col2,col3,col4,col5\r\n"));
+        assert_eq!("latin1".parse::<Quirk>().unwrap(), Quirk::Latin1);
+        assert!(CsvFuzzer::new().quirks(&[]).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let mut rng = StdRng::seed_from_u64(260);
+        let case = CsvFuzzer::new().rate(f64::NAN).case(&mut rng);
+        assert!(case.injected.is_empty());
+    }
+}
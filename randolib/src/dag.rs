@@ -0,0 +1,306 @@
+//! Random directed acyclic graphs for testing task schedulers and build systems.
+//!
+//! Nodes are laid out in layers and edges only point from earlier layers to later ones,
+//! which is what keeps the graph acyclic. Every node past the first layer depends on at
+//! least one node in the layer just before it, so the number of layers is exactly the
+//! depth, and no layer holds more than the maximum width. Node ids are shuffled
+//! afterwards so that `0..n` is not already a valid order.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+/// A DAG of tasks with a cost each; an edge `(a, b)` means `b` depends on `a`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dag {
+    costs: Vec<f64>,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl Dag {
+    pub fn len(&self) -> usize {
+        self.costs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.costs.is_empty()
+    }
+
+    pub fn cost(&self, node: usize) -> f64 {
+        self.costs[node]
+    }
+
+    /// Nodes that depend on `node`
+    pub fn successors(&self, node: usize) -> &[usize] {
+        &self.successors[node]
+    }
+
+    /// Nodes `node` depends on
+    pub fn predecessors(&self, node: usize) -> &[usize] {
+        &self.predecessors[node]
+    }
+
+    /// Every edge `(from, to)`, sorted
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges = self
+            .successors
+            .iter()
+            .enumerate()
+            .flat_map(|(from, to)| to.iter().map(move |to| (from, *to)))
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges
+    }
+
+    /// Every node after all of its dependencies (Kahn's algorithm). Among the nodes ready
+    /// at each step the lowest id goes first, so the order is deterministic.
+    pub fn topological_order(&self) -> Vec<usize> {
+        let mut waiting = self.predecessors.iter().map(Vec::len).collect::<Vec<_>>();
+        let mut ready = (0..self.len())
+            .filter(|n| waiting[*n] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::with_capacity(self.len());
+        while let Some(Reverse(node)) = ready.pop() {
+            order.push(node);
+            for next in &self.successors[node] {
+                waiting[*next] -= 1;
+                if waiting[*next] == 0 {
+                    ready.push(Reverse(*next));
+                }
+            }
+        }
+        order
+    }
+
+    /// The most expensive chain of dependencies and its total cost. No schedule can
+    /// finish faster than this, however many workers it has.
+    pub fn critical_path(&self) -> (f64, Vec<usize>) {
+        // Cost of the most expensive chain ending at each node, and where it came from
+        let mut best = vec![0.0f64; self.len()];
+        let mut from = vec![None; self.len()];
+        for node in self.topological_order() {
+            let before = self.predecessors[node]
+                .iter()
+                .max_by(|a, b| best[**a].total_cmp(&best[**b]));
+            best[node] = self.costs[node] + before.map_or(0.0, |p| best[*p]);
+            from[node] = before.copied();
+        }
+        let Some(mut node) = (0..self.len()).max_by(|a, b| best[*a].total_cmp(&best[*b])) else {
+            return (0.0, Vec::new());
+        };
+        let total = best[node];
+        let mut path = vec![node];
+        while let Some(previous) = from[node] {
+            path.push(previous);
+            node = previous;
+        }
+        path.reverse();
+        (total, path)
+    }
+
+    /// The number of nodes on the longest chain of dependencies
+    pub fn depth(&self) -> usize {
+        let mut depth = vec![0; self.len()];
+        for node in self.topological_order() {
+            depth[node] = 1 + self.predecessors[node]
+                .iter()
+                .map(|p| depth[*p])
+                .max()
+                .unwrap_or(0);
+        }
+        depth.into_iter().max().unwrap_or(0)
+    }
+}
+
+type Draw = Box<dyn Fn(&mut dyn RngCore) -> f64>;
+
+/// Generates `Dag`s
+pub struct DagGen {
+    nodes: usize,
+    density: f64,
+    max_width: usize,
+    max_depth: usize,
+    cost: Draw,
+}
+
+impl DagGen {
+    /// `nodes` tasks costing 1 each, with no limit on the shape
+    pub fn new(nodes: usize) -> Self {
+        DagGen {
+            nodes,
+            density: 0.1,
+            max_width: usize::MAX,
+            max_depth: usize::MAX,
+            cost: Box::new(|_| 1.0),
+        }
+    }
+
+    /// Chance of each extra edge from a node to one in a later layer, on top of the one
+    /// edge per node that holds the layers together. 0 gives thin chains, 1 makes every
+    /// node depend on everything before it.
+    pub fn density(mut self, density: f64) -> Self {
+        self.density = probability(density);
+        self
+    }
+
+    /// At most `width` nodes per layer, i.e. tasks that can run in parallel
+    pub fn max_width(mut self, width: usize) -> Result<Self, Error> {
+        if width == 0 {
+            return Err(Error::InvalidParameter(
+                "max width must be at least 1".into(),
+            ));
+        }
+        self.max_width = width;
+        Ok(self)
+    }
+
+    /// At most `depth` nodes on any chain of dependencies
+    pub fn max_depth(mut self, depth: usize) -> Result<Self, Error> {
+        if depth == 0 {
+            return Err(Error::InvalidParameter(
+                "max depth must be at least 1".into(),
+            ));
+        }
+        self.max_depth = depth;
+        Ok(self)
+    }
+
+    /// Draw each node's cost from `dist`, e.g. `rand_distr::LogNormal` for a few slow
+    /// tasks among many quick ones, or a `WeightedIndex` mapped to fixed costs
+    pub fn costs<D>(mut self, dist: D) -> Self
+    where
+        D: Distribution<f64> + 'static,
+    {
+        self.cost = Box::new(move |rng| dist.sample(rng));
+        self
+    }
+
+    /// Fails if `max_width * max_depth` can't hold all the nodes
+    pub fn dag<R>(&self, rng: &mut R) -> Result<Dag, Error>
+    where
+        R: Rng,
+    {
+        let n = self.nodes;
+        let min_layers = n.div_ceil(self.max_width);
+        let max_layers = n.min(self.max_depth);
+        if min_layers > max_layers {
+            return Err(Error::InvalidParameter(format!(
+                "{} nodes don't fit in {} layers of {}",
+                n, self.max_depth, self.max_width
+            )));
+        }
+
+        // One node per layer, then the rest spread over the layers with room
+        let layer_count = if n == 0 {
+            0
+        } else {
+            rng.gen_range(min_layers..=max_layers)
+        };
+        let mut sizes = vec![1; layer_count];
+        for _ in layer_count..n {
+            let open = (0..layer_count)
+                .filter(|l| sizes[*l] < self.max_width)
+                .collect::<Vec<_>>();
+            sizes[*open.choose(rng).unwrap()] += 1;
+        }
+
+        // Random ids, handed out layer by layer
+        let mut ids = (0..n).collect::<Vec<_>>();
+        ids.shuffle(rng);
+        let mut layers = Vec::with_capacity(layer_count);
+        let mut next = ids.into_iter();
+        for size in sizes {
+            layers.push(next.by_ref().take(size).collect::<Vec<_>>());
+        }
+
+        let mut successors = vec![Vec::new(); n];
+        let mut predecessors = vec![Vec::new(); n];
+        for (l, layer) in layers.iter().enumerate().skip(1) {
+            for to in layer {
+                let anchor = *layers[l - 1].choose(rng).unwrap();
+                for from in layers[..l].iter().flatten() {
+                    if *from == anchor || rng.gen_bool(self.density) {
+                        successors[*from].push(*to);
+                        predecessors[*to].push(*from);
+                    }
+                }
+            }
+        }
+        let costs = (0..n).map(|_| (self.cost)(rng)).collect();
+        Ok(Dag {
+            costs,
+            successors,
+            predecessors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_respects_the_shape_limits() {
+        let mut rng = StdRng::seed_from_u64(259);
+        let gen = DagGen::new(40)
+            .density(0.2)
+            .max_width(5)
+            .unwrap()
+            .max_depth(12)
+            .unwrap();
+
+        for _ in 0..20 {
+            let dag = gen.dag(&mut rng).unwrap();
+            assert_eq!(dag.len(), 40);
+            assert!((8..=12).contains(&dag.depth()), "{}", dag.depth());
+
+            let order = dag.topological_order();
+            assert_eq!(order.len(), 40);
+            let mut position = vec![0; 40];
+            for (i, node) in order.iter().enumerate() {
+                position[*node] = i;
+            }
+            for (from, to) in dag.edges() {
+                assert!(position[from] < position[to]);
+            }
+        }
+        assert!(DagGen::new(10)
+            .max_width(2)
+            .unwrap()
+            .max_depth(4)
+            .unwrap()
+            .dag(&mut rng)
+            .is_err());
+        assert!(DagGen::new(0).dag(&mut rng).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_finds_the_critical_path() {
+        let mut rng = StdRng::seed_from_u64(259);
+        let chain = DagGen::new(5).max_width(1).unwrap().dag(&mut rng).unwrap();
+        assert_eq!(chain.critical_path().0, 5.0);
+        assert_eq!(chain.critical_path().1, chain.topological_order());
+
+        let dag = DagGen::new(30)
+            .costs(rand_distr::Uniform::new(1.0, 10.0))
+            .dag(&mut rng)
+            .unwrap();
+        let (total, path) = dag.critical_path();
+        assert_eq!(total, path.iter().map(|n| dag.cost(*n)).sum::<f64>());
+        for step in path.windows(2) {
+            assert!(dag.successors(step[0]).contains(&step[1]));
+        }
+        assert!(dag.predecessors(path[0]).is_empty());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let nan = DagGen::new(20).density(f64::NAN);
+        let zero = DagGen::new(20).density(0.0);
+        let dag = |gen: DagGen| gen.dag(&mut StdRng::seed_from_u64(259)).unwrap();
+        assert_eq!(dag(nan).edges(), dag(zero).edges());
+    }
+}
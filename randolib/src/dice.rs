@@ -0,0 +1,292 @@
+//! Dice notation as used by tabletop games: `3d6+2` is three six-sided dice plus two, `d20`
+//! a single twenty-sided die, `4d6kh3` four dice keeping the highest three and `d%` a
+//! percentile die.
+
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+/// Which dice count towards the total
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keep {
+    /// `khN`, e.g. rolling with advantage is `2d20kh1`
+    Highest(u32),
+    /// `klN`, disadvantage is `2d20kl1`
+    Lowest(u32),
+    /// `dhN`
+    DropHighest(u32),
+    /// `dlN`, the classic ability score roll is `4d6dl1`
+    DropLowest(u32),
+}
+
+/// A parsed dice expression, ready to roll any number of times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiceRoll {
+    pub count: u32,
+    pub sides: u32,
+    pub keep: Option<Keep>,
+    pub modifier: i64,
+}
+
+/// One die of a roll
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Die {
+    pub value: u32,
+    /// Whether it counts towards the total, `false` for dice dropped by a `Keep`
+    pub kept: bool,
+}
+
+/// The outcome of rolling a `DiceRoll`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Roll {
+    /// In the order they were rolled
+    pub dice: Vec<Die>,
+    pub modifier: i64,
+    pub total: i64,
+}
+
+/// Plenty for any table, and keeps a typo like `1000000d6` from eating memory
+const MAX_DICE: u32 = 1000;
+
+impl DiceRoll {
+    pub fn new(count: u32, sides: u32) -> Result<Self, Error> {
+        if count == 0 || count > MAX_DICE {
+            return Err(Error::InvalidParameter(format!(
+                "dice count {} must be between 1 and {}",
+                count, MAX_DICE
+            )));
+        }
+        if sides == 0 {
+            return Err(Error::InvalidParameter(
+                "dice need at least one side".into(),
+            ));
+        }
+        Ok(DiceRoll {
+            count,
+            sides,
+            keep: None,
+            modifier: 0,
+        })
+    }
+
+    pub fn keep(mut self, keep: Keep) -> Result<Self, Error> {
+        let n = match keep {
+            Keep::Highest(n) | Keep::Lowest(n) | Keep::DropHighest(n) | Keep::DropLowest(n) => n,
+        };
+        if n > self.count {
+            return Err(Error::InvalidParameter(format!(
+                "can't keep or drop {} of {} dice",
+                n, self.count
+            )));
+        }
+        self.keep = Some(keep);
+        Ok(self)
+    }
+
+    pub fn modifier(mut self, modifier: i64) -> Self {
+        self.modifier = modifier;
+        self
+    }
+
+    /// How many dice count towards the total
+    pub fn kept(&self) -> u32 {
+        match self.keep {
+            None => self.count,
+            Some(Keep::Highest(n) | Keep::Lowest(n)) => n,
+            Some(Keep::DropHighest(n) | Keep::DropLowest(n)) => self.count - n,
+        }
+    }
+
+    /// The lowest possible total
+    pub fn min(&self) -> i64 {
+        self.kept() as i64 + self.modifier
+    }
+
+    /// The highest possible total
+    pub fn max(&self) -> i64 {
+        self.kept() as i64 * self.sides as i64 + self.modifier
+    }
+
+    pub fn roll<R>(&self, rng: &mut R) -> Roll
+    where
+        R: Rng + ?Sized,
+    {
+        let values = (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides))
+            .collect::<Vec<_>>();
+
+        // Rank dice by value, ties broken by position so exactly `n` get dropped
+        let mut order = (0..values.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| (values[i], i));
+        let mut kept = vec![true; values.len()];
+        let dropped = match self.keep {
+            None => 0..0,
+            Some(Keep::Highest(n)) => 0..values.len() - n as usize,
+            Some(Keep::Lowest(n)) => n as usize..values.len(),
+            Some(Keep::DropHighest(n)) => values.len() - n as usize..values.len(),
+            Some(Keep::DropLowest(n)) => 0..n as usize,
+        };
+        for &i in &order[dropped] {
+            kept[i] = false;
+        }
+
+        let dice = values
+            .into_iter()
+            .zip(kept)
+            .map(|(value, kept)| Die { value, kept })
+            .collect::<Vec<_>>();
+        let sum = dice
+            .iter()
+            .filter(|die| die.kept)
+            .map(|die| die.value as i64)
+            .sum::<i64>();
+        Roll {
+            dice,
+            modifier: self.modifier,
+            total: sum + self.modifier,
+        }
+    }
+}
+
+impl FromStr for DiceRoll {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidParameter(format!("invalid dice notation {:?}", s));
+        let number = |digits: &str| digits.parse::<u32>().map_err(|_| invalid());
+        let notation = s.trim().to_ascii_lowercase();
+
+        let (count, rest) = notation.split_once('d').ok_or_else(invalid)?;
+        let count = if count.is_empty() { 1 } else { number(count)? };
+
+        // The modifier comes last, after the sides and any keep suffix
+        let (rest, modifier) = match rest.find(['+', '-']) {
+            Some(at) => {
+                let modifier = rest[at..].parse::<i64>().map_err(|_| invalid())?;
+                (&rest[..at], modifier)
+            }
+            None => (rest, 0),
+        };
+
+        let keep_at = rest.find(['k', 'd']).unwrap_or(rest.len());
+        let (sides, keep) = rest.split_at(keep_at);
+        let sides = match sides {
+            "%" => 100,
+            sides => number(sides)?,
+        };
+        let keep = match keep.get(..2) {
+            None => None,
+            Some(kind) => {
+                let n = number(&keep[2..])?;
+                Some(match kind {
+                    "kh" => Keep::Highest(n),
+                    "kl" => Keep::Lowest(n),
+                    "dh" => Keep::DropHighest(n),
+                    "dl" => Keep::DropLowest(n),
+                    _ => return Err(invalid()),
+                })
+            }
+        };
+        if keep.is_none() && !rest[keep_at..].is_empty() {
+            return Err(invalid());
+        }
+
+        let roll = DiceRoll::new(count, sides)?.modifier(modifier);
+        match keep {
+            Some(keep) => roll.keep(keep),
+            None => Ok(roll),
+        }
+    }
+}
+
+/// Writes the canonical notation, which parses back to the same `DiceRoll`
+impl Display for DiceRoll {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.sides)?;
+        match self.keep {
+            None => {}
+            Some(Keep::Highest(n)) => write!(f, "kh{}", n)?,
+            Some(Keep::Lowest(n)) => write!(f, "kl{}", n)?,
+            Some(Keep::DropHighest(n)) => write!(f, "dh{}", n)?,
+            Some(Keep::DropLowest(n)) => write!(f, "dl{}", n)?,
+        }
+        match self.modifier {
+            0 => Ok(()),
+            m => write!(f, "{:+}", m),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_dice_notation() {
+        let roll = "3d6+2".parse::<DiceRoll>().unwrap();
+        assert_eq!(
+            (roll.count, roll.sides, roll.keep, roll.modifier),
+            (3, 6, None, 2)
+        );
+        let roll = "d20".parse::<DiceRoll>().unwrap();
+        assert_eq!((roll.count, roll.sides), (1, 20));
+        let roll = "2d10kh1".parse::<DiceRoll>().unwrap();
+        assert_eq!(roll.keep, Some(Keep::Highest(1)));
+        let roll = "4D6dl1-1".parse::<DiceRoll>().unwrap();
+        assert_eq!((roll.keep, roll.modifier), (Some(Keep::DropLowest(1)), -1));
+        assert_eq!("d%".parse::<DiceRoll>().unwrap().sides, 100);
+
+        for notation in ["3d6+2", "1d20", "2d10kh1", "4d6dl1-1", "8d8kl3"] {
+            assert_eq!(notation.parse::<DiceRoll>().unwrap().to_string(), notation);
+        }
+        for bad in [
+            "", "3", "d", "3d0", "0d6", "2d6kh3", "2d6kx1", "d6+", "2d6k1", "d6x",
+        ] {
+            assert!(bad.parse::<DiceRoll>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn it_rolls_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(53);
+        let roll = "3d6+2".parse::<DiceRoll>().unwrap();
+        assert_eq!((roll.min(), roll.max()), (5, 20));
+
+        let mut sum = 0;
+        for _ in 0..10_000 {
+            let result = roll.roll(&mut rng);
+            assert_eq!(result.dice.len(), 3);
+            assert!((roll.min()..=roll.max()).contains(&result.total));
+            sum += result.total;
+        }
+        // 3d6 averages 10.5, then the +2
+        let mean = sum as f64 / 10_000.0;
+        assert!((mean - 12.5).abs() < 0.1, "{}", mean);
+    }
+
+    #[test]
+    fn it_keeps_the_right_dice() {
+        let mut rng = StdRng::seed_from_u64(54);
+        let roll = "4d6kh3".parse::<DiceRoll>().unwrap();
+        for _ in 0..1000 {
+            let result = roll.roll(&mut rng);
+            let dropped = result
+                .dice
+                .iter()
+                .filter(|die| !die.kept)
+                .collect::<Vec<_>>();
+            assert_eq!(dropped.len(), 1);
+            // The dropped die is a lowest one
+            assert!(result.dice.iter().all(|die| die.value >= dropped[0].value));
+            let kept = result
+                .dice
+                .iter()
+                .filter(|d| d.kept)
+                .map(|d| d.value as i64);
+            assert_eq!(kept.sum::<i64>(), result.total);
+        }
+    }
+}
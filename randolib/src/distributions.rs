@@ -0,0 +1,447 @@
+//! Distributions that `rand` and `rand_distr` don't ship. Everything here implements
+//! `rand::distributions::Distribution`, so it works with `rng.sample(..)` and
+//! `dist.sample_iter(..)` like any other distribution.
+use rand::{distributions::WeightedIndex, prelude::*};
+use rand_distr::LogNormal;
+use somelib::error::Error;
+
+/// Check that `edges` are finite and strictly increasing, with one more edge than `values`
+fn check_edges(edges: &[f64], values: &[f64], what: &str) -> Result<(), Error> {
+    if edges.len() < 2 || edges.len() != values.len() + 1 {
+        return Err(Error::InvalidParameter(format!(
+            "expected {} + 1 edges, got {}",
+            values.len(),
+            edges.len()
+        )));
+    }
+    if edges.iter().any(|x| !x.is_finite()) || edges.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(Error::InvalidParameter(
+            "edges must be finite and strictly increasing".into(),
+        ));
+    }
+    if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+        return Err(Error::InvalidParameter(format!(
+            "{} must be finite and non-negative",
+            what
+        )));
+    }
+    Ok(())
+}
+
+/// A histogram-shaped distribution: interval `[edges[i], edges[i + 1])` gets probability
+/// proportional to `weights[i]` and values are uniform within an interval. This is the
+/// "sketch the shape with a few bars" distribution.
+#[derive(Debug, Clone)]
+pub struct PiecewiseConstant {
+    edges: Vec<f64>,
+    intervals: WeightedIndex<f64>,
+}
+
+impl PiecewiseConstant {
+    /// `weights` are per interval, so there is one fewer weight than edges. They don't need
+    /// to be normalized.
+    pub fn new(edges: &[f64], weights: &[f64]) -> Result<Self, Error> {
+        check_edges(edges, weights, "weights")?;
+        let intervals =
+            WeightedIndex::new(weights).map_err(|err| Error::InvalidParameter(err.to_string()))?;
+        Ok(PiecewiseConstant {
+            edges: edges.to_vec(),
+            intervals,
+        })
+    }
+}
+
+impl Distribution<f64> for PiecewiseConstant {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let i = self.intervals.sample(rng);
+        rng.gen_range(self.edges[i]..self.edges[i + 1])
+    }
+}
+
+/// A distribution whose density is a straight line between each pair of points, i.e. the
+/// density at `points[i]` is proportional to `densities[i]`. Smoother than
+/// `PiecewiseConstant` for shapes like "ramps up to a peak at 30 then tails off".
+#[derive(Debug, Clone)]
+pub struct PiecewiseLinear {
+    points: Vec<f64>,
+    densities: Vec<f64>,
+    /// Segments weighted by the area of their trapezoid
+    segments: WeightedIndex<f64>,
+}
+
+impl PiecewiseLinear {
+    pub fn new(points: &[f64], densities: &[f64]) -> Result<Self, Error> {
+        // `check_edges` wants one fewer value than edges, `densities` has one per point
+        if densities.is_empty() {
+            return Err(Error::InvalidParameter("expected at least 2 points".into()));
+        }
+        check_edges(points, &densities[1..], "densities")?;
+        if !densities[0].is_finite() || densities[0] < 0.0 {
+            return Err(Error::InvalidParameter(
+                "densities must be finite and non-negative".into(),
+            ));
+        }
+        let areas = points
+            .windows(2)
+            .zip(densities.windows(2))
+            .map(|(x, y)| (x[1] - x[0]) * (y[0] + y[1]) / 2.0);
+        let segments =
+            WeightedIndex::new(areas).map_err(|err| Error::InvalidParameter(err.to_string()))?;
+        Ok(PiecewiseLinear {
+            points: points.to_vec(),
+            densities: densities.to_vec(),
+            segments,
+        })
+    }
+}
+
+impl Distribution<f64> for PiecewiseLinear {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        let i = self.segments.sample(rng);
+        let (x0, width) = (self.points[i], self.points[i + 1] - self.points[i]);
+        let (y0, y1) = (self.densities[i], self.densities[i + 1]);
+
+        // Invert the CDF of a linear density on `[0, width]`:
+        // `F(t) = y0 t + (y1 - y0) t^2 / (2 width)`. Solving `F(t) = u * area` with the
+        // quadratic formula, rearranged so it doesn't divide by zero when `y0 == y1`.
+        let area = width * (y0 + y1) / 2.0;
+        let c = rng.gen::<f64>() * area;
+        let a = (y1 - y0) / (2.0 * width);
+        let t = 2.0 * c / (y0 + (y0 * y0 + 4.0 * a * c).max(0.0).sqrt());
+        // Guard against rounding pushing us out of the segment
+        x0 + t.clamp(0.0, width)
+    }
+}
+
+/// Restricts any distribution to `[low, high]` by re-drawing samples that fall outside.
+/// The shape inside the range is preserved, only rescaled.
+#[derive(Debug, Clone)]
+pub struct Truncated<D> {
+    inner: D,
+    low: f64,
+    high: f64,
+    max_attempts: usize,
+}
+
+impl<D> Truncated<D>
+where
+    D: Distribution<f64>,
+{
+    pub fn new(inner: D, low: f64, high: f64) -> Result<Self, Error> {
+        if low.is_nan() || high.is_nan() || low > high {
+            return Err(Error::InvalidParameter(format!(
+                "invalid truncation range [{}, {}]",
+                low, high
+            )));
+        }
+        Ok(Truncated {
+            inner,
+            low,
+            high,
+            max_attempts: 10_000,
+        })
+    }
+
+    /// How many draws `sample` makes before giving up, see `try_sample`
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    /// Sample, or `None` if `max_attempts` draws all landed outside the range
+    pub fn try_sample<R>(&self, rng: &mut R) -> Option<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        (0..self.max_attempts)
+            .map(|_| self.inner.sample(rng))
+            .find(|x| (self.low..=self.high).contains(x))
+    }
+}
+
+impl<D> Distribution<f64> for Truncated<D>
+where
+    D: Distribution<f64>,
+{
+    /// Panics if the range holds so little of the inner distribution's mass that
+    /// `max_attempts` draws all miss it. Use `try_sample` to handle that case yourself.
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f64 {
+        self.try_sample(rng).unwrap_or_else(|| {
+            panic!(
+                "no sample in [{}, {}] after {} attempts",
+                self.low, self.high, self.max_attempts
+            )
+        })
+    }
+}
+
+/// The 99th percentile of the standard normal distribution
+const Z_99: f64 = 2.326_347_874_040_841;
+
+/// Sizes in bytes of files, objects or messages. Real sizes are close to log-normal: most
+/// are small, a few are huge, and the huge ones hold most of the bytes. Uniform sizes get
+/// both wrong, so benchmarks built on them miss small-file overheads and large-file
+/// throughput alike.
+#[derive(Debug, Clone, Copy)]
+pub struct SizeDistribution {
+    log_normal: LogNormal<f64>,
+    median: u64,
+    sigma: f64,
+    min: u64,
+    max: u64,
+}
+
+impl SizeDistribution {
+    /// Half the sizes are below `median`. `sigma` sets the tail: each standard deviation
+    /// multiplies the size by `e^sigma`, so 0.5 is tight and 3 spans several powers of ten.
+    pub fn new(median: u64, sigma: f64) -> Result<Self, Error> {
+        if median == 0 || !sigma.is_finite() || sigma < 0.0 {
+            return Err(Error::InvalidParameter(format!(
+                "size median {} must be positive and sigma {} finite and non-negative",
+                median, sigma
+            )));
+        }
+        Ok(SizeDistribution {
+            log_normal: LogNormal::new((median as f64).ln(), sigma).unwrap(),
+            median,
+            sigma,
+            min: 0,
+            max: u64::MAX,
+        })
+    }
+
+    /// The tail given as the size only 1% exceed, which is easier to read off a real
+    /// histogram than `sigma`
+    pub fn from_percentiles(median: u64, p99: u64) -> Result<Self, Error> {
+        if p99 < median {
+            return Err(Error::InvalidParameter(format!(
+                "p99 {} is below the median {}",
+                p99, median
+            )));
+        }
+        SizeDistribution::new(median, (p99 as f64 / median as f64).ln() / Z_99)
+    }
+
+    /// Files on a typical disk: a 4KiB median, 1% over 4MiB
+    pub fn files() -> Self {
+        SizeDistribution::from_percentiles(4 << 10, 4 << 20).unwrap()
+    }
+
+    /// Objects in a typical blob store: a 64KiB median, 1% over 64MiB
+    pub fn objects() -> Self {
+        SizeDistribution::from_percentiles(64 << 10, 64 << 20).unwrap()
+    }
+
+    /// Clamp sizes to `[min, max]`, e.g. an upload limit. Clamped samples pile up at the
+    /// bounds, like real uploads at a size limit do; use `Truncated` to re-draw instead.
+    pub fn bounds(mut self, min: u64, max: u64) -> Result<Self, Error> {
+        if min > max {
+            return Err(Error::InvalidParameter(format!(
+                "invalid size bounds [{}, {}]",
+                min, max
+            )));
+        }
+        self.min = min;
+        self.max = max;
+        Ok(self)
+    }
+
+    pub fn median(&self) -> u64 {
+        self.median
+    }
+
+    pub fn sigma(&self) -> f64 {
+        self.sigma
+    }
+
+    /// The mean before clamping, well above the median when the tail is long
+    pub fn mean(&self) -> f64 {
+        self.median as f64 * (self.sigma * self.sigma / 2.0).exp()
+    }
+}
+
+impl Distribution<u64> for SizeDistribution {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        // `as` saturates, so a sample beyond `u64::MAX` becomes `u64::MAX`
+        let size = self.log_normal.sample(rng).round() as u64;
+        size.clamp(self.min, self.max)
+    }
+}
+
+/// The power-of-two bucket `size` falls in: 0 for 0, then `b` for `2^(b-1) ..= 2^b - 1`.
+/// Equal-width buckets put nearly everything in the first one for log-normal sizes;
+/// exponential ones give each order of magnitude its own.
+pub fn size_bucket(size: u64) -> u32 {
+    u64::BITS - size.leading_zeros()
+}
+
+/// `Distribution::sample` is generic, so `dyn Distribution` isn't possible. Boxing a closure
+/// that takes `dyn RngCore` lets us store differently typed components side by side.
+type Component<T> = Box<dyn Fn(&mut dyn RngCore) -> T>;
+
+/// Picks one of several distributions by weight for every sample, e.g. 95% "normal"
+/// latencies and 5% from a heavy tail
+pub struct Mixture<T> {
+    components: Vec<Component<T>>,
+    weights: WeightedIndex<f64>,
+}
+
+pub struct MixtureBuilder<T> {
+    components: Vec<Component<T>>,
+    weights: Vec<f64>,
+}
+
+impl<T> Mixture<T> {
+    pub fn builder() -> MixtureBuilder<T> {
+        MixtureBuilder {
+            components: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+}
+
+impl<T> MixtureBuilder<T> {
+    /// Add a component. Weights are relative and don't need to sum to 1.
+    pub fn add<D>(mut self, weight: f64, dist: D) -> Self
+    where
+        D: Distribution<T> + 'static,
+    {
+        self.components
+            .push(Box::new(move |rng: &mut dyn RngCore| dist.sample(rng)));
+        self.weights.push(weight);
+        self
+    }
+
+    pub fn build(self) -> Result<Mixture<T>, Error> {
+        let weights = WeightedIndex::new(&self.weights)
+            .map_err(|err| Error::InvalidParameter(err.to_string()))?;
+        Ok(Mixture {
+            components: self.components,
+            weights,
+        })
+    }
+}
+
+impl<T> Distribution<T> for Mixture<T> {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> T {
+        let component = &self.components[self.weights.sample(rng)];
+        // `&mut &mut R` is `Sized` and implements `RngCore`, so it coerces to `dyn RngCore`
+        component(&mut &mut *rng)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::distributions::Uniform;
+    use rand_distr::{Normal, Pareto};
+    use std::collections::HashSet;
+
+    #[test]
+    fn piecewise_constant_follows_the_weights() {
+        let mut rng = StdRng::seed_from_u64(71);
+        let dist = PiecewiseConstant::new(&[0.0, 1.0, 2.0, 10.0], &[1.0, 0.0, 3.0]).unwrap();
+
+        let samples = dist.sample_iter(&mut rng).take(40_000).collect::<Vec<_>>();
+
+        assert!(samples.iter().all(|x| (0.0..10.0).contains(x)));
+        // The empty bar is never hit
+        assert!(!samples.iter().any(|x| (1.0..2.0).contains(x)));
+        let low = samples.iter().filter(|x| **x < 1.0).count() as f64 / 40_000.0;
+        assert!((low - 0.25).abs() < 0.01, "{}", low);
+    }
+
+    #[test]
+    fn piecewise_linear_follows_the_ramp() {
+        let mut rng = StdRng::seed_from_u64(72);
+        // A triangle peaking at 1: density 2x on [0, 1], so P(X < 0.5) = 0.25
+        let dist = PiecewiseLinear::new(&[0.0, 1.0], &[0.0, 2.0]).unwrap();
+
+        let below = dist
+            .sample_iter(&mut rng)
+            .take(40_000)
+            .filter(|x| *x < 0.5)
+            .count() as f64
+            / 40_000.0;
+
+        assert!((below - 0.25).abs() < 0.01, "{}", below);
+    }
+
+    #[test]
+    fn truncation_keeps_samples_in_range() {
+        let mut rng = StdRng::seed_from_u64(73);
+        let dist = Truncated::new(Normal::new(0.0, 1.0).unwrap(), -0.5, 2.0).unwrap();
+
+        let samples = dist.sample_iter(&mut rng).take(10_000).collect::<Vec<_>>();
+
+        assert!(samples.iter().all(|x| (-0.5..=2.0).contains(x)));
+        // A range with no mass gives up instead of spinning forever
+        let empty = Truncated::new(Uniform::new(0.0, 1.0), 5.0, 6.0).unwrap();
+        assert_eq!(empty.max_attempts(100).try_sample(&mut rng), None);
+    }
+
+    #[test]
+    fn mixture_follows_component_weights() {
+        let mut rng = StdRng::seed_from_u64(74);
+        // Latencies: mostly ~10ms, sometimes a heavy tail starting at 100ms
+        let dist = Mixture::builder()
+            .add(0.95, Normal::new(10.0, 1.0).unwrap())
+            .add(0.05, Pareto::new(100.0, 1.5).unwrap())
+            .build()
+            .unwrap();
+
+        let slow = dist
+            .sample_iter(&mut rng)
+            .take(40_000)
+            .filter(|x| *x >= 100.0)
+            .count() as f64
+            / 40_000.0;
+
+        assert!((slow - 0.05).abs() < 0.005, "{}", slow);
+    }
+
+    #[test]
+    fn sizes_follow_the_median_and_tail() {
+        let mut rng = StdRng::seed_from_u64(75);
+        let files = SizeDistribution::files();
+        let mut sizes = files.sample_iter(&mut rng).take(20_000).collect::<Vec<_>>();
+        sizes.sort_unstable();
+
+        let median = sizes[10_000] as f64;
+        let p99 = sizes[19_800] as f64;
+        assert!((median / 4096.0 - 1.0).abs() < 0.1, "{}", median);
+        assert!((p99 / (4 << 20) as f64 - 1.0).abs() < 0.25, "{}", p99);
+
+        // Log-normal sizes spread over many power-of-two buckets
+        let buckets = sizes
+            .iter()
+            .map(|s| size_bucket(*s))
+            .collect::<HashSet<_>>();
+        assert!(buckets.len() > 15, "{}", buckets.len());
+
+        let capped = SizeDistribution::new(1000, 3.0)
+            .unwrap()
+            .bounds(10, 5000)
+            .unwrap();
+        assert!((0..1000).all(|_| (10..=5000).contains(&capped.sample(&mut rng))));
+    }
+
+    #[test]
+    fn size_buckets_are_powers_of_two() {
+        assert_eq!(size_bucket(0), 0);
+        assert_eq!(size_bucket(1), 1);
+        assert_eq!((size_bucket(4), size_bucket(7), size_bucket(8)), (3, 3, 4));
+        assert_eq!(size_bucket(u64::MAX), 64);
+        assert!(SizeDistribution::new(0, 1.0).is_err());
+        assert!(SizeDistribution::from_percentiles(100, 10).is_err());
+        assert!(SizeDistribution::files().bounds(2, 1).is_err());
+    }
+
+    #[test]
+    fn it_rejects_bad_shapes() {
+        assert!(PiecewiseConstant::new(&[0.0, 1.0], &[1.0, 1.0]).is_err());
+        assert!(PiecewiseConstant::new(&[1.0, 0.0], &[1.0]).is_err());
+        assert!(PiecewiseConstant::new(&[0.0, 1.0], &[-1.0]).is_err());
+        assert!(PiecewiseLinear::new(&[0.0, 1.0], &[0.0, 0.0]).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+//! Random arithmetic expressions for differential testing: generate an expression, print
+//! it, feed the text to the calculator or compiler under test and compare its answer with
+//! `Expr::eval`.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{fmt, ops::RangeInclusive};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    /// The exponent is always a small non-negative literal, so expressions stay
+    /// polynomial in `x`
+    Pow,
+}
+
+impl Op {
+    /// Binding strength, higher binds tighter. Unary minus sits between `*` and `^`.
+    fn precedence(&self) -> u8 {
+        match self {
+            Op::Add | Op::Sub => 1,
+            Op::Mul | Op::Div => 2,
+            Op::Pow => 4,
+        }
+    }
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "/",
+            Op::Pow => "^",
+        }
+    }
+}
+
+const NEG_PRECEDENCE: u8 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Lit(i64),
+    /// The variable `x`
+    Var,
+    Neg(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluate in floating point. Division by zero gives an infinity or NaN, as it would
+    /// in most calculators.
+    pub fn eval(&self, x: f64) -> f64 {
+        match self {
+            Expr::Lit(n) => *n as f64,
+            Expr::Var => x,
+            Expr::Neg(e) => -e.eval(x),
+            Expr::Bin(op, l, r) => {
+                let (l, r) = (l.eval(x), r.eval(x));
+                match op {
+                    Op::Add => l + r,
+                    Op::Sub => l - r,
+                    Op::Mul => l * r,
+                    Op::Div => l / r,
+                    Op::Pow => l.powf(r),
+                }
+            }
+        }
+    }
+
+    /// Evaluate in 64-bit integers, `None` on overflow or division by zero. Division
+    /// truncates towards zero, like Rust and C.
+    pub fn eval_int(&self, x: i64) -> Option<i64> {
+        match self {
+            Expr::Lit(n) => Some(*n),
+            Expr::Var => Some(x),
+            Expr::Neg(e) => e.eval_int(x)?.checked_neg(),
+            Expr::Bin(op, l, r) => {
+                let (l, r) = (l.eval_int(x)?, r.eval_int(x)?);
+                match op {
+                    Op::Add => l.checked_add(r),
+                    Op::Sub => l.checked_sub(r),
+                    Op::Mul => l.checked_mul(r),
+                    Op::Div => l.checked_div(r),
+                    Op::Pow => l.checked_pow(u32::try_from(r).ok()?),
+                }
+            }
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        match self {
+            Expr::Lit(_) | Expr::Var => 0,
+            Expr::Neg(e) => 1 + e.depth(),
+            Expr::Bin(_, l, r) => 1 + l.depth().max(r.depth()),
+        }
+    }
+
+    /// How tightly the printed form binds, to decide where parentheses go. A negative
+    /// literal prints with a leading minus, so it binds like a negation.
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Lit(n) if *n < 0 => NEG_PRECEDENCE,
+            Expr::Lit(_) | Expr::Var => u8::MAX,
+            Expr::Neg(_) => NEG_PRECEDENCE,
+            Expr::Bin(op, _, _) => op.precedence(),
+        }
+    }
+}
+
+/// Write `e`, in parentheses if `needs_parens`
+fn write_operand(f: &mut fmt::Formatter<'_>, e: &Expr, needs_parens: bool) -> fmt::Result {
+    if needs_parens {
+        write!(f, "({})", e)
+    } else {
+        write!(f, "{}", e)
+    }
+}
+
+/// Infix with as few parentheses as keep the tree unambiguous. `+ - * /` associate left
+/// and `^` right, so `a - (b - c)` and `(a ^ b) ^ c` keep theirs. Parentheses are also
+/// kept where grouping only matters for rounding, e.g. `a + (b + c)`, so the text always
+/// parses back to the same tree.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Lit(n) => write!(f, "{}", n),
+            Expr::Var => write!(f, "x"),
+            Expr::Neg(e) => {
+                f.write_str("-")?;
+                // `--x` reads as a decrement in C-like languages
+                write_operand(f, e, e.precedence() <= NEG_PRECEDENCE)
+            }
+            Expr::Bin(op, l, r) => {
+                let p = op.precedence();
+                let right_assoc = *op == Op::Pow;
+                write_operand(
+                    f,
+                    l,
+                    l.precedence() < p || (right_assoc && l.precedence() == p),
+                )?;
+                write!(f, " {} ", op.symbol())?;
+                write_operand(
+                    f,
+                    r,
+                    r.precedence() < p || (!right_assoc && r.precedence() == p),
+                )
+            }
+        }
+    }
+}
+
+/// Generates random `Expr`s
+pub struct ExprGen {
+    max_depth: usize,
+    ops: Vec<Op>,
+    literals: RangeInclusive<i64>,
+    var_rate: f64,
+    neg_rate: f64,
+    max_exponent: i64,
+}
+
+impl ExprGen {
+    pub fn new() -> Self {
+        ExprGen {
+            max_depth: 4,
+            ops: vec![Op::Add, Op::Sub, Op::Mul, Op::Div, Op::Pow],
+            literals: -10..=10,
+            var_rate: 0.3,
+            neg_rate: 0.1,
+            max_exponent: 3,
+        }
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Operators to use, at least one
+    pub fn ops(mut self, ops: &[Op]) -> Result<Self, Error> {
+        if ops.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one operator is needed".into(),
+            ));
+        }
+        self.ops = ops.to_vec();
+        Ok(self)
+    }
+
+    pub fn literals(mut self, range: RangeInclusive<i64>) -> Self {
+        self.literals = range;
+        self
+    }
+
+    /// Chance of a leaf being `x` rather than a literal, 0 for constant expressions
+    pub fn var_rate(mut self, rate: f64) -> Self {
+        self.var_rate = probability(rate);
+        self
+    }
+
+    pub fn neg_rate(mut self, rate: f64) -> Self {
+        self.neg_rate = probability(rate);
+        self
+    }
+
+    /// Largest exponent for `^`
+    pub fn max_exponent(mut self, max: u32) -> Self {
+        self.max_exponent = i64::from(max);
+        self
+    }
+
+    pub fn expr<R>(&self, rng: &mut R) -> Expr
+    where
+        R: Rng + ?Sized,
+    {
+        self.expr_at(self.max_depth, rng)
+    }
+
+    fn expr_at<R>(&self, depth: usize, rng: &mut R) -> Expr
+    where
+        R: Rng + ?Sized,
+    {
+        // Stop early now and then so trees aren't all full to `max_depth`
+        if depth == 0 || rng.gen_bool(0.2) {
+            return self.leaf(rng);
+        }
+        if rng.gen_bool(self.neg_rate) {
+            return Expr::Neg(Box::new(self.expr_at(depth - 1, rng)));
+        }
+        let op = *self.ops.choose(rng).unwrap();
+        let left = self.expr_at(depth - 1, rng);
+        let right = match op {
+            Op::Pow => Expr::Lit(rng.gen_range(0..=self.max_exponent)),
+            _ => self.expr_at(depth - 1, rng),
+        };
+        Expr::Bin(op, Box::new(left), Box::new(right))
+    }
+
+    fn leaf<R>(&self, rng: &mut R) -> Expr
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_bool(self.var_rate) {
+            Expr::Var
+        } else {
+            Expr::Lit(rng.gen_range(self.literals.clone()))
+        }
+    }
+}
+
+impl Default for ExprGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bin(op: Op, l: Expr, r: Expr) -> Expr {
+        Expr::Bin(op, Box::new(l), Box::new(r))
+    }
+
+    #[test]
+    fn it_prints_minimal_parentheses() {
+        let (one, two) = (Expr::Lit(1), Expr::Lit(2));
+        let sum = bin(Op::Add, one.clone(), Expr::Var);
+
+        assert_eq!(
+            bin(Op::Mul, sum.clone(), two.clone()).to_string(),
+            "(1 + x) * 2"
+        );
+        assert_eq!(
+            bin(Op::Add, two.clone(), bin(Op::Mul, one.clone(), Expr::Var)).to_string(),
+            "2 + 1 * x"
+        );
+        assert_eq!(
+            bin(Op::Sub, two.clone(), sum.clone()).to_string(),
+            "2 - (1 + x)"
+        );
+        assert_eq!(
+            bin(Op::Sub, sum.clone(), two.clone()).to_string(),
+            "1 + x - 2"
+        );
+        let pow = bin(Op::Pow, Expr::Var, two.clone());
+        assert_eq!(
+            bin(Op::Pow, pow.clone(), two.clone()).to_string(),
+            "(x ^ 2) ^ 2"
+        );
+        assert_eq!(
+            bin(Op::Pow, Expr::Lit(-3), two.clone()).to_string(),
+            "(-3) ^ 2"
+        );
+        assert_eq!(Expr::Neg(Box::new(pow)).to_string(), "-x ^ 2");
+        assert_eq!(Expr::Neg(Box::new(Expr::Lit(-1))).to_string(), "-(-1)");
+    }
+
+    #[test]
+    fn it_evaluates() {
+        // (x + 1) ^ 2 / 2 at x = 3
+        let e = bin(
+            Op::Div,
+            bin(Op::Pow, bin(Op::Add, Expr::Var, Expr::Lit(1)), Expr::Lit(2)),
+            Expr::Lit(2),
+        );
+        assert_eq!(e.eval(3.0), 8.0);
+        assert_eq!(e.eval_int(3), Some(8));
+        assert_eq!(bin(Op::Div, Expr::Lit(1), Expr::Lit(0)).eval_int(0), None);
+        assert_eq!(bin(Op::Mul, Expr::Var, Expr::Var).eval_int(i64::MAX), None);
+    }
+
+    #[test]
+    fn it_generates_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(45);
+        let gen = ExprGen::new()
+            .max_depth(5)
+            .literals(0..=9)
+            .neg_rate(0.0)
+            .ops(&[Op::Add, Op::Pow])
+            .unwrap();
+
+        for _ in 0..200 {
+            let e = gen.expr(&mut rng);
+            assert!(e.depth() <= 5);
+            // Only additions and small powers of non-negative terms, so never negative
+            assert!(e.eval(1.0) >= 0.0, "{}", e);
+            assert!(!e.to_string().contains('*'));
+        }
+        assert!(ExprGen::new().ops(&[]).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = ExprGen::new().var_rate(f64::NAN).neg_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(245);
+        for _ in 0..20 {
+            assert!(!gen.expr(&mut rng).to_string().contains('x'));
+        }
+    }
+}
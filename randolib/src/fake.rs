@@ -0,0 +1,615 @@
+//! Fake data: people, their email addresses and the clients they use
+use rand::{distributions::WeightedIndex, prelude::*};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceClass {
+    Desktop,
+    Mobile,
+    Tablet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Os {
+    Windows,
+    MacOs,
+    Linux,
+    ChromeOs,
+    Android,
+    Ios,
+    IpadOs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Browser {
+    Chrome,
+    Safari,
+    Edge,
+    Firefox,
+    SamsungInternet,
+}
+
+/// Implementing `Display` gives us `to_string()` for free via the blanket `ToString` impl
+impl Display for Os {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Os::Windows => "Windows",
+            Os::MacOs => "macOS",
+            Os::Linux => "Linux",
+            Os::ChromeOs => "ChromeOS",
+            Os::Android => "Android",
+            Os::Ios => "iOS",
+            Os::IpadOs => "iPadOS",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for Browser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Browser::Chrome => "Chrome",
+            Browser::Safari => "Safari",
+            Browser::Edge => "Edge",
+            Browser::Firefox => "Firefox",
+            Browser::SamsungInternet => "Samsung Internet",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Plausible device, OS and browser combinations with approximate global traffic shares.
+/// Listing combinations rather than weighting each part independently keeps out nonsense
+/// like Safari on Windows.
+const PROFILES: [(DeviceClass, Os, Browser, f64); 17] = [
+    (DeviceClass::Desktop, Os::Windows, Browser::Chrome, 26.0),
+    (DeviceClass::Desktop, Os::Windows, Browser::Edge, 6.0),
+    (DeviceClass::Desktop, Os::Windows, Browser::Firefox, 3.0),
+    (DeviceClass::Desktop, Os::MacOs, Browser::Chrome, 5.0),
+    (DeviceClass::Desktop, Os::MacOs, Browser::Safari, 4.0),
+    (DeviceClass::Desktop, Os::MacOs, Browser::Firefox, 0.5),
+    (DeviceClass::Desktop, Os::Linux, Browser::Chrome, 1.2),
+    (DeviceClass::Desktop, Os::Linux, Browser::Firefox, 0.8),
+    (DeviceClass::Desktop, Os::ChromeOs, Browser::Chrome, 1.0),
+    (DeviceClass::Mobile, Os::Android, Browser::Chrome, 26.0),
+    (
+        DeviceClass::Mobile,
+        Os::Android,
+        Browser::SamsungInternet,
+        4.0,
+    ),
+    (DeviceClass::Mobile, Os::Android, Browser::Firefox, 0.3),
+    (DeviceClass::Mobile, Os::Ios, Browser::Safari, 15.0),
+    (DeviceClass::Mobile, Os::Ios, Browser::Chrome, 2.5),
+    (DeviceClass::Tablet, Os::IpadOs, Browser::Safari, 1.8),
+    (DeviceClass::Tablet, Os::Android, Browser::Chrome, 1.5),
+    (
+        DeviceClass::Tablet,
+        Os::Android,
+        Browser::SamsungInternet,
+        0.4,
+    ),
+];
+
+/// Common screen resolutions per device class, `(width, height, weight)`. Mobile and tablet
+/// sizes are CSS pixels in portrait orientation.
+const DESKTOP_SCREENS: [(u32, u32, f64); 6] = [
+    (1920, 1080, 23.0),
+    (1366, 768, 8.0),
+    (1536, 864, 10.0),
+    (1440, 900, 6.0),
+    (2560, 1440, 5.0),
+    (1280, 720, 4.0),
+];
+const MOBILE_SCREENS: [(u32, u32, f64); 5] = [
+    (390, 844, 8.0),
+    (393, 873, 6.0),
+    (412, 915, 7.0),
+    (360, 800, 9.0),
+    (414, 896, 5.0),
+];
+const TABLET_SCREENS: [(u32, u32, f64); 4] = [
+    (768, 1024, 10.0),
+    (810, 1080, 8.0),
+    (820, 1180, 6.0),
+    (800, 1280, 5.0),
+];
+
+/// One fake client: what it is, what it runs and the user agent it would send
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceProfile {
+    pub class: DeviceClass,
+    pub os: Os,
+    pub browser: Browser,
+    /// Major version of the browser
+    pub browser_version: u32,
+    pub screen: (u32, u32),
+    pub user_agent: String,
+}
+
+/// Generates `DeviceProfile`s weighted by market share
+pub struct DeviceProfileGen {
+    profiles: WeightedIndex<f64>,
+    desktop: WeightedIndex<f64>,
+    mobile: WeightedIndex<f64>,
+    tablet: WeightedIndex<f64>,
+}
+
+impl DeviceProfileGen {
+    pub fn new() -> Self {
+        // `unwrap` is fine, the tables above are non-empty with positive weights
+        let screens = |table: &[(u32, u32, f64)]| {
+            WeightedIndex::new(table.iter().map(|(_, _, w)| *w)).unwrap()
+        };
+        DeviceProfileGen {
+            profiles: WeightedIndex::new(PROFILES.iter().map(|p| p.3)).unwrap(),
+            desktop: screens(&DESKTOP_SCREENS),
+            mobile: screens(&MOBILE_SCREENS),
+            tablet: screens(&TABLET_SCREENS),
+        }
+    }
+
+    pub fn profile<R>(&self, rng: &mut R) -> DeviceProfile
+    where
+        R: Rng + ?Sized,
+    {
+        let (class, os, browser, _) = PROFILES[self.profiles.sample(rng)];
+        let (w, h, _) = match class {
+            DeviceClass::Desktop => DESKTOP_SCREENS[self.desktop.sample(rng)],
+            DeviceClass::Mobile => MOBILE_SCREENS[self.mobile.sample(rng)],
+            DeviceClass::Tablet => TABLET_SCREENS[self.tablet.sample(rng)],
+        };
+        // Most users are on one of the last few releases
+        let browser_version = match browser {
+            Browser::Chrome | Browser::Edge => rng.gen_range(118..=126),
+            Browser::Firefox => rng.gen_range(115..=127),
+            Browser::Safari => rng.gen_range(15..=17),
+            Browser::SamsungInternet => rng.gen_range(22..=25),
+        };
+        let user_agent = user_agent(class, os, browser, browser_version, rng);
+        DeviceProfile {
+            class,
+            os,
+            browser,
+            browser_version,
+            screen: (w, h),
+            user_agent,
+        }
+    }
+
+    /// Just the user agent string
+    pub fn user_agent<R>(&self, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        self.profile(rng).user_agent
+    }
+}
+
+impl Default for DeviceProfileGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build a user agent in the format the real browser uses. Modern browsers freeze most of
+/// the platform details (e.g. Android is always `Android 10; K`), so only a few parts vary.
+fn user_agent<R>(class: DeviceClass, os: Os, browser: Browser, version: u32, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    const WEBKIT: &str = "AppleWebKit/537.36 (KHTML, like Gecko)";
+    const SAFARI_WEBKIT: &str = "AppleWebKit/605.1.15 (KHTML, like Gecko)";
+
+    // Safari's version tracks the OS it ships with, other browsers update independently
+    let os_version = match browser {
+        Browser::Safari => version,
+        _ => rng.gen_range(15..=17),
+    };
+    let platform = match os {
+        Os::Windows => "Windows NT 10.0; Win64; x64".to_string(),
+        Os::MacOs => "Macintosh; Intel Mac OS X 10_15_7".to_string(),
+        Os::Linux => "X11; Linux x86_64".to_string(),
+        Os::ChromeOs => "X11; CrOS x86_64 14541.0.0".to_string(),
+        Os::Android => "Linux; Android 10; K".to_string(),
+        Os::Ios => format!(
+            "iPhone; CPU iPhone OS {}_{} like Mac OS X",
+            os_version,
+            rng.gen_range(0..6)
+        ),
+        Os::IpadOs => format!(
+            "iPad; CPU OS {}_{} like Mac OS X",
+            os_version,
+            rng.gen_range(0..6)
+        ),
+    };
+    let mobile = if class == DeviceClass::Mobile {
+        "Mobile "
+    } else {
+        ""
+    };
+
+    match (browser, os) {
+        (Browser::Safari, Os::Ios | Os::IpadOs) => format!(
+            "Mozilla/5.0 ({}) {} Version/{}.{} Mobile/15E148 Safari/604.1",
+            platform,
+            SAFARI_WEBKIT,
+            version,
+            rng.gen_range(0..6)
+        ),
+        (Browser::Safari, _) => format!(
+            "Mozilla/5.0 ({}) {} Version/{}.{} Safari/605.1.15",
+            platform,
+            SAFARI_WEBKIT,
+            version,
+            rng.gen_range(0..6)
+        ),
+        // Chrome on iOS is Safari underneath with a `CriOS` token
+        (Browser::Chrome, Os::Ios) => format!(
+            "Mozilla/5.0 ({}) {} CriOS/{}.0.0.0 Mobile/15E148 Safari/604.1",
+            platform, SAFARI_WEBKIT, version
+        ),
+        (Browser::Chrome, _) => format!(
+            "Mozilla/5.0 ({}) {} Chrome/{}.0.0.0 {}Safari/537.36",
+            platform, WEBKIT, version, mobile
+        ),
+        (Browser::Edge, _) => format!(
+            "Mozilla/5.0 ({}) {} Chrome/{v}.0.0.0 Safari/537.36 Edg/{v}.0.0.0",
+            platform,
+            WEBKIT,
+            v = version
+        ),
+        (Browser::Firefox, Os::Android) => format!(
+            "Mozilla/5.0 (Android 10; Mobile; rv:{v}.0) Gecko/{v}.0 Firefox/{v}.0",
+            v = version
+        ),
+        (Browser::Firefox, _) => format!(
+            "Mozilla/5.0 ({}; rv:{v}.0) Gecko/20100101 Firefox/{v}.0",
+            platform,
+            v = version
+        ),
+        (Browser::SamsungInternet, _) => format!(
+            "Mozilla/5.0 ({}) {} SamsungBrowser/{}.0 Chrome/{}.0.0.0 {}Safari/537.36",
+            platform,
+            WEBKIT,
+            version,
+            rng.gen_range(115..=121),
+            mobile
+        ),
+    }
+}
+
+const FIRST_NAMES: [&str; 40] = [
+    "James", "Mary", "Wei", "Fatima", "Carlos", "Aiko", "Olga", "Mohammed", "Priya", "Lucas",
+    "Emma", "Noah", "Sofia", "Liam", "Amara", "Mateo", "Yuki", "Ahmed", "Chloe", "Ivan", "Hannah",
+    "Diego", "Mei", "Omar", "Laura", "Kenji", "Aisha", "Pedro", "Nina", "Arjun", "Elena", "Tomas",
+    "Zara", "Felix", "Ingrid", "Kofi", "Rosa", "Sven", "Leila", "Hugo",
+];
+
+const LAST_NAMES: [&str; 40] = [
+    "Smith",
+    "Wang",
+    "Garcia",
+    "Muller",
+    "Kim",
+    "Silva",
+    "Ivanov",
+    "Khan",
+    "Tanaka",
+    "Rossi",
+    "Nguyen",
+    "Johnson",
+    "Lopez",
+    "Dubois",
+    "Singh",
+    "Kowalski",
+    "Okafor",
+    "Jensen",
+    "Sato",
+    "Hernandez",
+    "Brown",
+    "Chen",
+    "Novak",
+    "Costa",
+    "Ali",
+    "Schmidt",
+    "Park",
+    "Martin",
+    "Ahmed",
+    "Larsen",
+    "Moreau",
+    "Yilmaz",
+    "Petrov",
+    "Mensah",
+    "Fischer",
+    "Suzuki",
+    "Romero",
+    "Haddad",
+    "Walker",
+    "Li",
+];
+
+/// Reserved example domains (RFC 2606) so fake addresses can never reach a real inbox
+const EMAIL_DOMAINS: [&str; 4] = ["example.com", "example.org", "example.net", "mail.example"];
+
+pub fn first_name<R>(rng: &mut R) -> &'static str
+where
+    R: Rng + ?Sized,
+{
+    FIRST_NAMES.choose(rng).unwrap()
+}
+
+pub fn last_name<R>(rng: &mut R) -> &'static str
+where
+    R: Rng + ?Sized,
+{
+    LAST_NAMES.choose(rng).unwrap()
+}
+
+/// `"First Last"`
+pub fn full_name<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    format!("{} {}", first_name(rng), last_name(rng))
+}
+
+/// `first.last42@example.com` style addresses on reserved example domains
+pub fn email<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let user = username(rng);
+    format!("{}@{}", user, EMAIL_DOMAINS.choose(rng).unwrap())
+}
+
+/// `jane.doe42` style user names, usable as user ids in logs and event streams
+pub fn username<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    format!(
+        "{}.{}{}",
+        first_name(rng).to_lowercase(),
+        last_name(rng).to_lowercase(),
+        rng.gen_range(1..1000)
+    )
+}
+
+/// Valid but unusual email addresses, for exercising validators beyond `first.last@`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EmailKind {
+    /// `jane+news@example.com`, which many sign-up forms wrongly reject
+    PlusTag,
+    /// A quoted local part with spaces, `@`, escaped quotes and backslashes
+    QuotedLocal,
+    /// The unquoted specials RFC 5322 allows: `o'brien`, `x{y}`, `a!b#c`, ...
+    Specials,
+    /// Mixed case, which is preserved in the local part (the domain is case-insensitive)
+    MixedCase,
+    /// An internationalized domain, either in Unicode or its `xn--` (punycode) form
+    IdnDomain,
+    /// A Unicode local part (RFC 6531 `SMTPUTF8`)
+    UnicodeLocal,
+    /// An IPv4 or IPv6 address literal instead of a domain
+    IpLiteral,
+    /// Many subdomain labels
+    Subdomains,
+    /// Exactly at the limits: a 64 octet local part, a 63 octet label and 254 octets in
+    /// total
+    MaxLength,
+}
+
+impl EmailKind {
+    pub const ALL: [EmailKind; 9] = [
+        EmailKind::PlusTag,
+        EmailKind::QuotedLocal,
+        EmailKind::Specials,
+        EmailKind::MixedCase,
+        EmailKind::IdnDomain,
+        EmailKind::UnicodeLocal,
+        EmailKind::IpLiteral,
+        EmailKind::Subdomains,
+        EmailKind::MaxLength,
+    ];
+}
+
+/// IDN labels and their punycode, all under the reserved `.example` TLD
+pub(crate) const IDN_DOMAINS: [(&str, &str); 3] = [
+    ("bücher.example", "xn--bcher-kva.example"),
+    ("münchen.example", "xn--mnchen-3ya.example"),
+    ("café.example", "xn--caf-dma.example"),
+];
+
+/// Documentation addresses (RFC 5737 and RFC 3849), never routable
+const IP_LITERALS: [&str; 4] = [
+    "[192.0.2.1]",
+    "[198.51.100.23]",
+    "[IPv6:2001:db8::1]",
+    "[IPv6:2001:db8:0:0:0:0:2:1]",
+];
+
+/// A valid but unusual address of a random kind
+pub fn weird_email<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let kind = *EmailKind::ALL.choose(rng).unwrap();
+    weird_email_of(kind, rng)
+}
+
+pub fn weird_email_of<R>(kind: EmailKind, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let first = first_name(rng).to_lowercase();
+    let last = last_name(rng).to_lowercase();
+    let domain = *EMAIL_DOMAINS.choose(rng).unwrap();
+    match kind {
+        EmailKind::PlusTag => {
+            let tag = ["news", "spam", "2024", "a+b", ""].choose(rng).unwrap();
+            format!("{}+{}@{}", first, tag, domain)
+        }
+        EmailKind::QuotedLocal => {
+            let local = [
+                format!("{} {}", first, last),
+                format!("{}@{}", first, last),
+                format!("{}..{}", first, last),
+                format!("{}\\\"{}\\\"", first, last),
+                format!("{}\\\\{}", first, last),
+                " ".to_string(),
+            ];
+            format!("\"{}\"@{}", local.choose(rng).unwrap(), domain)
+        }
+        EmailKind::Specials => {
+            let special = *b"!#$%&'*+-/=?^_`{|}~".choose(rng).unwrap() as char;
+            format!("{}{}{}@{}", first, special, last, domain)
+        }
+        EmailKind::MixedCase => {
+            let local = format!("{}.{}", first, last)
+                .chars()
+                .map(|c| if rng.gen() { c.to_ascii_uppercase() } else { c })
+                .collect::<String>();
+            format!("{}@{}", local, domain.to_uppercase())
+        }
+        EmailKind::IdnDomain => {
+            let (unicode, ascii) = IDN_DOMAINS.choose(rng).unwrap();
+            format!("{}@{}", first, if rng.gen() { unicode } else { ascii })
+        }
+        EmailKind::UnicodeLocal => {
+            let local = ["josé", "zoë.müller", "用户", "почта", "δοκιμή"];
+            format!("{}@{}", local.choose(rng).unwrap(), domain)
+        }
+        EmailKind::IpLiteral => format!("{}@{}", first, IP_LITERALS.choose(rng).unwrap()),
+        EmailKind::Subdomains => {
+            let labels = (0..rng.gen_range(3..8))
+                .map(|_| {
+                    *["a", "mx", "eu-west", "x1", "mail", "b-c"]
+                        .choose(rng)
+                        .unwrap()
+                })
+                .collect::<Vec<_>>();
+            format!("{}@{}.{}", first, labels.join("."), domain)
+        }
+        EmailKind::MaxLength => {
+            let local = padded(&format!("{}.{}", first, last), 64, rng);
+            // 254 = 64 + `@` + 189, and labels plus their dots fill what `domain` doesn't
+            let mut left = 189 - domain.len();
+            let mut labels = Vec::new();
+            while left > 0 {
+                let mut len = 63.min(left - 1);
+                // A single octet left over would need an empty label
+                if left - (len + 1) == 1 {
+                    len -= 1;
+                }
+                labels.push(padded("", len, rng));
+                left -= len + 1;
+            }
+            format!("{}@{}.{}", local, labels.join("."), domain)
+        }
+    }
+}
+
+/// `start` filled up to `len` with lowercase letters and digits
+fn padded<R>(start: &str, len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let mut s = start.chars().take(len).collect::<String>();
+    while s.len() < len {
+        s.push(*b"abcdefghijklmnopqrstuvwxyz0123456789".choose(rng).unwrap() as char);
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_generates_consistent_profiles() {
+        let mut rng = StdRng::seed_from_u64(51);
+        let gen = DeviceProfileGen::new();
+
+        for _ in 0..500 {
+            let profile = gen.profile(&mut rng);
+            let ua = &profile.user_agent;
+
+            assert!(ua.starts_with("Mozilla/5.0 ("));
+            match profile.os {
+                Os::Windows => assert!(ua.contains("Windows NT")),
+                Os::Android => assert!(ua.contains("Android")),
+                Os::Ios => assert!(ua.contains("iPhone")),
+                Os::IpadOs => assert!(ua.contains("iPad")),
+                _ => {}
+            }
+            if profile.class == DeviceClass::Mobile {
+                assert!(ua.contains("Mobile"));
+                assert!(profile.screen.0 < profile.screen.1);
+            }
+            if profile.browser == Browser::Safari {
+                assert!(matches!(profile.os, Os::MacOs | Os::Ios | Os::IpadOs));
+            }
+        }
+    }
+
+    #[test]
+    fn it_follows_market_share() {
+        let mut rng = StdRng::seed_from_u64(52);
+        let gen = DeviceProfileGen::new();
+
+        let chrome = (0..10_000)
+            .filter(|_| gen.profile(&mut rng).browser == Browser::Chrome)
+            .count();
+
+        // Chrome is about 63% of the weights
+        assert!((5800..6800).contains(&chrome), "{}", chrome);
+    }
+
+    #[test]
+    fn it_generates_emails_on_example_domains() {
+        let mut rng = StdRng::seed_from_u64(53);
+
+        let email = email(&mut rng);
+        let (local, domain) = email.split_once('@').unwrap();
+
+        assert!(local.contains('.'));
+        assert!(EMAIL_DOMAINS.contains(&domain));
+    }
+
+    #[test]
+    fn it_generates_weird_but_valid_emails() {
+        let mut rng = StdRng::seed_from_u64(261);
+
+        for kind in EmailKind::ALL {
+            for _ in 0..50 {
+                let email = weird_email_of(kind, &mut rng);
+                // The local part can contain `@` when quoted, the domain never does
+                let (local, domain) = email.rsplit_once('@').unwrap();
+                assert!(local.len() <= 64 && email.len() <= 254, "{}", email);
+                assert!(!domain.is_empty() && !local.is_empty(), "{}", email);
+                if !domain.starts_with('[') {
+                    assert!(
+                        domain.split('.').all(|l| (1..=63).contains(&l.len())),
+                        "{}",
+                        email
+                    );
+                }
+                match kind {
+                    EmailKind::QuotedLocal => {
+                        assert!(local.starts_with('"') && local.ends_with('"'))
+                    }
+                    EmailKind::PlusTag => assert!(local.contains('+')),
+                    EmailKind::IpLiteral => assert!(domain.starts_with('[')),
+                    EmailKind::UnicodeLocal => assert!(!local.is_ascii()),
+                    EmailKind::MaxLength => {
+                        assert_eq!((local.len(), email.len()), (64, 254));
+                        assert!(domain.split('.').any(|l| l.len() == 63));
+                    }
+                    _ => assert!(local.is_ascii() && !local.contains('"')),
+                }
+            }
+        }
+        assert!(weird_email(&mut rng).contains('@'));
+    }
+}
@@ -0,0 +1,208 @@
+use rand::{distributions::Alphanumeric, prelude::*};
+use somelib::error::Error;
+use std::{
+    fs,
+    io::Write,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+/// What `FileTree::create` made, relative to the root it was given
+#[derive(Debug, Clone, PartialEq)]
+pub enum ManifestEntry {
+    Dir { path: PathBuf },
+    File { path: PathBuf, size: u64 },
+}
+
+/// A description of a random directory tree. Configure it with the builder-style setters,
+/// then call `create` to write it to disk.
+#[derive(Debug, Clone)]
+pub struct FileTree {
+    max_depth: usize,
+    dirs_per_dir: RangeInclusive<usize>,
+    files_per_dir: RangeInclusive<usize>,
+    file_size: RangeInclusive<u64>,
+    name_pattern: String,
+    random_content: bool,
+}
+
+impl FileTree {
+    /// A small tree: up to 2 levels deep, 0-3 subdirectories and 1-5 files per directory,
+    /// files of up to 4KiB filled with zeros
+    pub fn new() -> Self {
+        FileTree {
+            max_depth: 2,
+            dirs_per_dir: 0..=3,
+            files_per_dir: 1..=5,
+            file_size: 0..=4096,
+            name_pattern: "{}".into(),
+            random_content: false,
+        }
+    }
+
+    /// How many levels of subdirectories below the root
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn dirs_per_dir(mut self, range: RangeInclusive<usize>) -> Self {
+        self.dirs_per_dir = range;
+        self
+    }
+
+    pub fn files_per_dir(mut self, range: RangeInclusive<usize>) -> Self {
+        self.files_per_dir = range;
+        self
+    }
+
+    /// File sizes in bytes
+    pub fn file_size(mut self, range: RangeInclusive<u64>) -> Self {
+        self.file_size = range;
+        self
+    }
+
+    /// File names are made from this pattern with `{}` replaced by a random alphanumeric
+    /// token, e.g. `"log_{}.txt"`. Directories always use the bare token.
+    pub fn name_pattern(mut self, pattern: &str) -> Self {
+        self.name_pattern = pattern.into();
+        self
+    }
+
+    /// Fill files with random bytes instead of zeros
+    pub fn random_content(mut self, random: bool) -> Self {
+        self.random_content = random;
+        self
+    }
+
+    /// Create the tree under `root`, which must not exist yet or be an empty directory,
+    /// and return what was created in creation order
+    pub fn create<R>(&self, root: &Path, rng: &mut R) -> Result<Vec<ManifestEntry>, Error>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.dirs_per_dir.is_empty()
+            || self.files_per_dir.is_empty()
+            || self.file_size.is_empty()
+        {
+            return Err(Error::InvalidParameter("empty range in file tree".into()));
+        }
+        if !self.name_pattern.contains("{}") {
+            return Err(Error::InvalidParameter(
+                "name pattern needs a `{}` placeholder".into(),
+            ));
+        }
+        // Refuse to scribble into a directory that already has something in it
+        if root.exists() && fs::read_dir(root)?.next().is_some() {
+            return Err(Error::InvalidParameter(format!(
+                "{} is not empty",
+                root.display()
+            )));
+        }
+        fs::create_dir_all(root)?;
+
+        let mut manifest = Vec::new();
+        self.fill(root, Path::new(""), 0, rng, &mut manifest)?;
+        Ok(manifest)
+    }
+
+    /// Recursively populate `root/relative`
+    fn fill<R>(
+        &self,
+        root: &Path,
+        relative: &Path,
+        depth: usize,
+        rng: &mut R,
+        manifest: &mut Vec<ManifestEntry>,
+    ) -> Result<(), Error>
+    where
+        R: Rng + ?Sized,
+    {
+        for _ in 0..rng.gen_range(self.files_per_dir.clone()) {
+            let name = self.name_pattern.replacen("{}", &token(rng), 1);
+            let path = relative.join(name);
+            let size = rng.gen_range(self.file_size.clone());
+            write_file(&root.join(&path), size, self.random_content, rng)?;
+            manifest.push(ManifestEntry::File { path, size });
+        }
+
+        if depth < self.max_depth {
+            for _ in 0..rng.gen_range(self.dirs_per_dir.clone()) {
+                let path = relative.join(token(rng));
+                fs::create_dir(root.join(&path))?;
+                manifest.push(ManifestEntry::Dir { path: path.clone() });
+                self.fill(root, &path, depth + 1, rng, manifest)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `Default` is the conventional companion to an argument-less `new`
+impl Default for FileTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Random 8 character name, long enough that collisions within a directory are very unlikely
+fn token<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    (0..8).map(|_| rng.sample(Alphanumeric) as char).collect()
+}
+
+/// Write `size` bytes in chunks so large files don't need a large buffer
+fn write_file<R>(path: &Path, size: u64, random: bool, rng: &mut R) -> Result<(), Error>
+where
+    R: Rng + ?Sized,
+{
+    let mut file = fs::File::create(path)?;
+    let mut buf = [0u8; 8192];
+    let mut remaining = size;
+    while remaining > 0 {
+        let n = remaining.min(buf.len() as u64) as usize;
+        if random {
+            rng.fill_bytes(&mut buf[..n]);
+        }
+        file.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_creates_the_files_in_the_manifest() {
+        let root = std::env::temp_dir().join(format!("randolib_tree_{}", std::process::id()));
+        let mut rng = StdRng::seed_from_u64(11);
+
+        let manifest = FileTree::new()
+            .max_depth(2)
+            .dirs_per_dir(1..=2)
+            .file_size(1..=100)
+            .name_pattern("f_{}.bin")
+            .random_content(true)
+            .create(&root, &mut rng)
+            .unwrap();
+
+        assert!(!manifest.is_empty());
+        for entry in &manifest {
+            match entry {
+                ManifestEntry::Dir { path } => assert!(root.join(path).is_dir()),
+                ManifestEntry::File { path, size } => {
+                    assert_eq!(fs::metadata(root.join(path)).unwrap().len(), *size);
+                    assert!(path.to_string_lossy().ends_with(".bin"));
+                }
+            }
+        }
+
+        // A second run into the same place is refused
+        assert!(FileTree::new().create(&root, &mut rng).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
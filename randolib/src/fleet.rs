@@ -0,0 +1,447 @@
+//! A simulated fleet of IoT sensors for testing ingestion pipelines. Each device reports on
+//! its own schedule with its own baseline, drift, noise and failure rate, and the fleet's
+//! readings come out as one stream in timestamp order, like they'd arrive at a collector.
+//!
+//! Every device draws from its own generator, seeded from the fleet's seed and its index, so
+//! a device's readings don't change when the fleet grows or when other devices are read.
+
+use crate::{probability, seed::Seed};
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use rand_distr::Normal;
+use somelib::error::Error;
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    ops::Range,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+/// What went wrong with a reading, the ground truth a pipeline's fault detection can be
+/// checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fault {
+    /// The device reported, but without a value
+    Missing,
+    /// The device repeated its previous value
+    Stuck,
+    /// A value far off the device's curve
+    Spike,
+}
+
+impl Fault {
+    pub const ALL: [Fault; 3] = [Fault::Missing, Fault::Stuck, Fault::Spike];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fault::Missing => "missing",
+            Fault::Stuck => "stuck",
+            Fault::Spike => "spike",
+        }
+    }
+}
+
+impl FromStr for Fault {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Fault::ALL
+            .into_iter()
+            .find(|fault| fault.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown fault {:?}", s)))
+    }
+}
+
+/// One device's profile
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    /// e.g. `"sensor-0007"`
+    pub id: String,
+    /// Where this device's readings come from
+    pub seed: Seed,
+    /// The value the device starts out reading
+    pub baseline: f64,
+    /// How far the value moves per hour
+    pub drift: f64,
+    /// The standard deviation of each reading around the drifting baseline
+    pub noise: f64,
+    /// The chance any one reading is faulty
+    pub failure_rate: f64,
+    /// What a faulty reading can be, picked evenly
+    pub faults: Vec<Fault>,
+    pub interval: Duration,
+    /// When the first reading comes after the fleet's start, so devices don't all report at
+    /// once
+    pub offset: Duration,
+}
+
+impl Device {
+    /// The value a healthy reading centers on, `elapsed` after the device's first reading
+    pub fn expected(&self, elapsed: Duration) -> f64 {
+        self.baseline + self.drift * elapsed.as_secs_f64() / 3600.0
+    }
+
+    /// This device's readings alone, starting from the fleet's `start`
+    pub fn readings(&self, start: SystemTime) -> DeviceReadings {
+        DeviceReadings {
+            // `noise` was drawn from a non-negative range, so this can't fail
+            noise: Normal::new(0.0, self.noise).unwrap(),
+            rng: self.seed.rng(),
+            device: self.clone(),
+            first: start + self.offset,
+            seq: 0,
+            last: None,
+        }
+    }
+}
+
+/// One report from one device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub device_id: String,
+    /// Position in the device's own readings, from 0, so gaps and reordering can be spotted
+    pub seq: u64,
+    pub at: SystemTime,
+    /// `None` for a `Fault::Missing` reading
+    pub value: Option<f64>,
+    pub fault: Option<Fault>,
+}
+
+/// Generates fleets. Each device's profile is drawn from the ranges set here.
+pub struct FleetGen {
+    devices: usize,
+    interval: Duration,
+    baseline: Range<f64>,
+    drift: f64,
+    noise: Range<f64>,
+    failure_rate: Range<f64>,
+    faults: Vec<Fault>,
+}
+
+impl FleetGen {
+    /// Devices reading every 10 seconds around 15 to 25 (say, degrees), drifting up to 0.1
+    /// an hour either way, with noise of 0.1 to 0.5 and up to 1% of readings faulty
+    pub fn new(devices: usize) -> Result<Self, Error> {
+        if devices == 0 {
+            return Err(Error::InvalidParameter(
+                "a fleet needs at least one device".into(),
+            ));
+        }
+        Ok(FleetGen {
+            devices,
+            interval: Duration::from_secs(10),
+            baseline: 15.0..25.0,
+            drift: 0.1,
+            noise: 0.1..0.5,
+            failure_rate: 0.0..0.01,
+            faults: Fault::ALL.to_vec(),
+        })
+    }
+
+    /// How often every device reports
+    pub fn interval(mut self, interval: Duration) -> Result<Self, Error> {
+        if interval.is_zero() {
+            return Err(Error::InvalidParameter(
+                "the reporting interval can't be zero".into(),
+            ));
+        }
+        self.interval = interval;
+        Ok(self)
+    }
+
+    pub fn baseline(mut self, baseline: Range<f64>) -> Result<Self, Error> {
+        if !(baseline.start.is_finite() && baseline.end.is_finite()) || baseline.is_empty() {
+            return Err(Error::InvalidParameter(format!(
+                "baseline range {:?} must be finite and non-empty",
+                baseline
+            )));
+        }
+        self.baseline = baseline;
+        Ok(self)
+    }
+
+    /// The most a device's value drifts per hour, up or down. 0 for no drift.
+    pub fn drift(mut self, max_per_hour: f64) -> Result<Self, Error> {
+        if !max_per_hour.is_finite() || max_per_hour < 0.0 {
+            return Err(Error::InvalidParameter(format!(
+                "drift {} must be finite and non-negative",
+                max_per_hour
+            )));
+        }
+        self.drift = max_per_hour;
+        Ok(self)
+    }
+
+    /// Noise standard deviations to draw from. `0.0..0.0` isn't an empty range here, it
+    /// gives noiseless devices.
+    pub fn noise(mut self, noise: Range<f64>) -> Result<Self, Error> {
+        if !(noise.start >= 0.0 && noise.start <= noise.end && noise.end.is_finite()) {
+            return Err(Error::InvalidParameter(format!(
+                "noise range {:?} must be finite, in order and non-negative",
+                noise
+            )));
+        }
+        self.noise = noise;
+        Ok(self)
+    }
+
+    /// Failure probabilities to draw from, clamped to 0 to 1 with NaN as 0. An end below
+    /// the start is raised to it.
+    pub fn failure_rate(mut self, rate: Range<f64>) -> Self {
+        let start = probability(rate.start);
+        self.failure_rate = start..probability(rate.end).max(start);
+        self
+    }
+
+    /// Which faults failures can be, all of them unless set
+    pub fn faults(mut self, faults: &[Fault]) -> Result<Self, Error> {
+        if faults.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one kind of fault is needed".into(),
+            ));
+        }
+        self.faults = faults.to_vec();
+        Ok(self)
+    }
+
+    /// Device `index` of the fleet seeded with `seed`
+    pub fn device(&self, seed: Seed, index: usize) -> Device {
+        let mut rng = Seed::from_label(&format!("{}/device/{}/profile", seed, index)).rng();
+        // `gen_range` panics on an empty range, and a zero range is fine here
+        let mut draw = |range: &Range<f64>| {
+            if range.is_empty() {
+                range.start
+            } else {
+                rng.gen_range(range.clone())
+            }
+        };
+        let baseline = draw(&self.baseline);
+        let drift = draw(&(-self.drift..self.drift));
+        let noise = draw(&self.noise);
+        let failure_rate = draw(&self.failure_rate);
+        Device {
+            id: format!("sensor-{:04}", index),
+            seed: Seed::from_label(&format!("{}/device/{}/readings", seed, index)),
+            baseline,
+            drift,
+            noise,
+            failure_rate,
+            faults: self.faults.clone(),
+            interval: self.interval,
+            offset: self.interval.mul_f64(rng.gen::<f64>()),
+        }
+    }
+
+    pub fn fleet(&self, seed: Seed) -> Fleet {
+        Fleet {
+            devices: (0..self.devices).map(|i| self.device(seed, i)).collect(),
+        }
+    }
+}
+
+/// A generated fleet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fleet {
+    pub devices: Vec<Device>,
+}
+
+impl Fleet {
+    /// An endless stream of every device's readings from `start`, merged in timestamp
+    /// order. Ties go to the lower device index.
+    pub fn readings(&self, start: SystemTime) -> FleetReadings {
+        let devices = self
+            .devices
+            .iter()
+            .map(|device| device.readings(start))
+            .collect::<Vec<_>>();
+        let next = devices
+            .iter()
+            .enumerate()
+            .map(|(i, readings)| Reverse((readings.next_at(), i)))
+            .collect();
+        FleetReadings { devices, next }
+    }
+}
+
+/// Iterator over one device's readings
+pub struct DeviceReadings {
+    device: Device,
+    rng: ChaCha20Rng,
+    noise: Normal<f64>,
+    first: SystemTime,
+    seq: u64,
+    last: Option<f64>,
+}
+
+impl DeviceReadings {
+    /// When the next reading is due
+    fn next_at(&self) -> SystemTime {
+        self.first + self.device.interval.mul_f64(self.seq as f64)
+    }
+}
+
+impl Iterator for DeviceReadings {
+    type Item = Reading;
+
+    fn next(&mut self) -> Option<Reading> {
+        let at = self.next_at();
+        let elapsed = at.duration_since(self.first).unwrap_or_default();
+        let value = self.device.expected(elapsed) + self.noise.sample(&mut self.rng);
+        let fault = if self.rng.gen_bool(self.device.failure_rate) {
+            self.device.faults.choose(&mut self.rng).copied()
+        } else {
+            None
+        };
+        let value = match fault {
+            None => Some(value),
+            Some(Fault::Missing) => None,
+            // Nothing to repeat before the first value
+            Some(Fault::Stuck) => self.last.or(Some(value)),
+            // Well clear of the noise, and of the baseline for a noiseless device
+            Some(Fault::Spike) => {
+                let size = (self.device.noise * 10.0)
+                    .max(self.device.baseline.abs() * 0.5)
+                    .max(1.0);
+                let sign = if self.rng.gen() { 1.0 } else { -1.0 };
+                Some(value + sign * size)
+            }
+        };
+        if value.is_some() {
+            self.last = value;
+        }
+        let reading = Reading {
+            device_id: self.device.id.clone(),
+            seq: self.seq,
+            at,
+            value,
+            fault,
+        };
+        self.seq += 1;
+        Some(reading)
+    }
+
+    /// Endless
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// Iterator over a whole fleet's readings, see `Fleet::readings`
+pub struct FleetReadings {
+    devices: Vec<DeviceReadings>,
+    /// A min-heap of when each device reports next. `BinaryHeap` is a max-heap, `Reverse`
+    /// flips the order.
+    next: BinaryHeap<Reverse<(SystemTime, usize)>>,
+}
+
+impl Iterator for FleetReadings {
+    type Item = Reading;
+
+    fn next(&mut self) -> Option<Reading> {
+        let Reverse((_, i)) = self.next.pop()?;
+        let reading = self.devices[i].next();
+        self.next.push(Reverse((self.devices[i].next_at(), i)));
+        reading
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn it_merges_readings_in_order() {
+        let fleet = FleetGen::new(20)
+            .unwrap()
+            .failure_rate(0.05..0.1)
+            .fleet(Seed::from_label("fleet"));
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let readings = fleet.readings(start).take(2_000).collect::<Vec<_>>();
+
+        assert!(readings.windows(2).all(|pair| pair[0].at <= pair[1].at));
+        // 2,000 readings over 20 devices is 100 each, give or take the one in progress
+        for device in &fleet.devices {
+            let own = readings
+                .iter()
+                .filter(|r| r.device_id == device.id)
+                .collect::<Vec<_>>();
+            assert!((99..=101).contains(&own.len()), "{}", own.len());
+            assert!(own.iter().enumerate().all(|(i, r)| r.seq == i as u64));
+        }
+        let faults = readings.iter().filter(|r| r.fault.is_some()).count();
+        assert!((50..300).contains(&faults), "{}", faults);
+        assert!(readings
+            .iter()
+            .all(|r| r.value.is_none() == (r.fault == Some(Fault::Missing))));
+    }
+
+    #[test]
+    fn it_keeps_devices_independent_of_the_fleet() {
+        let seed = Seed::from_label("independent");
+        let small = FleetGen::new(2).unwrap().fleet(seed);
+        let large = FleetGen::new(50).unwrap().fleet(seed);
+        assert_eq!(small.devices[..], large.devices[..2]);
+
+        let start = UNIX_EPOCH;
+        let alone = small.devices[1]
+            .readings(start)
+            .take(10)
+            .collect::<Vec<_>>();
+        let merged = large
+            .readings(start)
+            .filter(|r| r.device_id == small.devices[1].id)
+            .take(10)
+            .collect::<Vec<_>>();
+        assert_eq!(alone, merged);
+        assert_ne!(FleetGen::new(2).unwrap().fleet(Seed::from_u64(1)), small);
+    }
+
+    #[test]
+    fn it_drifts_without_noise() {
+        let fleet = FleetGen::new(1)
+            .unwrap()
+            .interval(Duration::from_secs(3600))
+            .unwrap()
+            .noise(0.0..0.0)
+            .unwrap()
+            .drift(2.0)
+            .unwrap()
+            .failure_rate(0.0..0.0)
+            .fleet(Seed::from_u64(276));
+        let device = &fleet.devices[0];
+        assert!(device.drift.abs() <= 2.0 && device.offset < device.interval);
+        let values = device
+            .readings(UNIX_EPOCH)
+            .take(5)
+            .map(|r| r.value.unwrap())
+            .collect::<Vec<_>>();
+        for (hour, value) in values.iter().enumerate() {
+            let expected = device.baseline + device.drift * hour as f64;
+            assert!((value - expected).abs() < 1e-9);
+        }
+
+        assert!(FleetGen::new(0).is_err());
+        assert!(FleetGen::new(1).unwrap().interval(Duration::ZERO).is_err());
+        assert!(FleetGen::new(1).unwrap().faults(&[]).is_err());
+        assert_eq!("stuck".parse::<Fault>().unwrap(), Fault::Stuck);
+    }
+
+    #[test]
+    fn it_cleans_nan_out_of_failure_rates() {
+        for rate in [f64::NAN..0.5, 0.5..f64::NAN, f64::NAN..f64::NAN, 0.8..0.2] {
+            let fleet = FleetGen::new(5)
+                .unwrap()
+                .failure_rate(rate.clone())
+                .fleet(Seed::from_u64(2762));
+            for device in &fleet.devices {
+                assert!((0.0..=0.8).contains(&device.failure_rate), "{:?}", rate);
+            }
+        }
+    }
+}
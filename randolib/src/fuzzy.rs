@@ -0,0 +1,200 @@
+//! String pairs with a known edit distance, as ground truth for fuzzy matching, spell
+//! checking and diff algorithms.
+
+use rand::prelude::*;
+use somelib::error::Error;
+use std::ops::RangeInclusive;
+
+/// How many times to re-roll edits before giving up. Random edits can cancel out (delete
+/// a character, then insert the same one back), so some attempts fall short.
+const MAX_ATTEMPTS: usize = 1000;
+
+/// Levenshtein distance in characters: the fewest single-character insertions, deletions
+/// and substitutions turning `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    // Only the previous row of the DP table is needed
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Similarity in `[0, 1]`, `1 - distance / longer length`
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+/// Generates `(original, edited)` string pairs. The original is random over `alphabet` and
+/// the edited copy has random insertions, deletions and substitutions applied; every pair
+/// is checked against `levenshtein` before it's returned.
+pub struct EditPairGen {
+    alphabet: Vec<char>,
+    length: RangeInclusive<usize>,
+}
+
+impl EditPairGen {
+    pub fn new() -> Self {
+        EditPairGen {
+            alphabet: ('a'..='z').collect(),
+            length: 5..=20,
+        }
+    }
+
+    /// Characters to build strings from. Small alphabets make edits cancel more often.
+    pub fn alphabet(mut self, alphabet: &str) -> Self {
+        self.alphabet = alphabet.chars().collect();
+        self
+    }
+
+    /// Length of the original string, in characters
+    pub fn length(mut self, length: RangeInclusive<usize>) -> Self {
+        self.length = length;
+        self
+    }
+
+    /// A pair exactly `distance` edits apart
+    pub fn pair_at_distance<R>(
+        &self,
+        distance: usize,
+        rng: &mut R,
+    ) -> Result<(String, String), Error>
+    where
+        R: Rng + ?Sized,
+    {
+        if self.alphabet.len() < 2 && distance > 0 {
+            return Err(Error::InvalidParameter(
+                "edits need an alphabet of at least 2 characters".into(),
+            ));
+        }
+        for _ in 0..MAX_ATTEMPTS {
+            let original = self.random_string(rng);
+            let edited = self.edit(&original, distance, rng);
+            if levenshtein(&original, &edited) == distance {
+                return Ok((original, edited));
+            }
+        }
+        Err(Error::InvalidParameter(format!(
+            "couldn't reach edit distance {} with this alphabet and length",
+            distance
+        )))
+    }
+
+    /// A pair whose `similarity` falls in `band`, e.g. `0.7..=0.9` for near misses
+    pub fn pair_in_band<R>(
+        &self,
+        band: RangeInclusive<f64>,
+        rng: &mut R,
+    ) -> Result<(String, String), Error>
+    where
+        R: Rng + ?Sized,
+    {
+        for _ in 0..MAX_ATTEMPTS {
+            let original = self.random_string(rng);
+            // Aim for the middle of the band, measured against the original's length
+            let len = original.chars().count() as f64;
+            let low = ((1.0 - band.end()) * len).ceil() as usize;
+            let high = ((1.0 - band.start()) * len).floor() as usize;
+            if low > high {
+                continue;
+            }
+            let edited = self.edit(&original, rng.gen_range(low..=high), rng);
+            if band.contains(&similarity(&original, &edited)) {
+                return Ok((original, edited));
+            }
+        }
+        Err(Error::InvalidParameter(format!(
+            "couldn't generate a pair with similarity in {:?}",
+            band
+        )))
+    }
+
+    fn random_string<R>(&self, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        let len = rng.gen_range(self.length.clone());
+        (0..len)
+            .filter_map(|_| self.alphabet.choose(rng).copied())
+            .collect()
+    }
+
+    /// Apply `edits` random edits. Each one is an insert, a delete or a substitution with
+    /// a different character, so it changes the string, but the total can still come out
+    /// below `edits`.
+    fn edit<R>(&self, original: &str, edits: usize, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        let mut chars = original.chars().collect::<Vec<_>>();
+        for _ in 0..edits {
+            let Some(&c) = self.alphabet.choose(rng) else {
+                break;
+            };
+            match rng.gen_range(0..3) {
+                0 if !chars.is_empty() => {
+                    chars.remove(rng.gen_range(0..chars.len()));
+                }
+                1 if !chars.is_empty() => {
+                    let at = rng.gen_range(0..chars.len());
+                    let other = self.alphabet.iter().filter(|&&a| a != chars[at]);
+                    if let Some(&replacement) = other.choose(rng) {
+                        chars[at] = replacement;
+                    }
+                }
+                _ => chars.insert(rng.gen_range(0..=chars.len()), c),
+            }
+        }
+        chars.into_iter().collect()
+    }
+}
+
+impl Default for EditPairGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_computes_levenshtein() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abab", "baba"), 2);
+        assert_eq!(levenshtein("héllo", "hello"), 1);
+        assert_eq!(similarity("", ""), 1.0);
+    }
+
+    #[test]
+    fn it_generates_pairs_at_a_distance() {
+        let mut rng = StdRng::seed_from_u64(36);
+        let gen = EditPairGen::new().alphabet("ACGT").length(10..=30);
+
+        for distance in 0..8 {
+            let (a, b) = gen.pair_at_distance(distance, &mut rng).unwrap();
+            assert_eq!(levenshtein(&a, &b), distance);
+        }
+        for _ in 0..20 {
+            let (a, b) = gen.pair_in_band(0.6..=0.8, &mut rng).unwrap();
+            assert!((0.6..=0.8).contains(&similarity(&a, &b)));
+        }
+        assert!(EditPairGen::new()
+            .alphabet("x")
+            .pair_at_distance(1, &mut rng)
+            .is_err());
+    }
+}
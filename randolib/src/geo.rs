@@ -0,0 +1,204 @@
+use rand::{distributions::WeightedIndex, prelude::*};
+
+/// A city from the embedded dataset. Populations are approximate urban-area figures,
+/// which is all we need to weight samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct City {
+    pub name: &'static str,
+    /// ISO 3166-1 alpha-2
+    pub country: &'static str,
+    pub lat: f64,
+    pub lon: f64,
+    pub population: u64,
+}
+
+/// `const fn` so the table below can be built at compile time
+const fn city(
+    name: &'static str,
+    country: &'static str,
+    lat: f64,
+    lon: f64,
+    population: u64,
+) -> City {
+    City {
+        name,
+        country,
+        lat,
+        lon,
+        population,
+    }
+}
+
+/// Large cities of the world with approximate urban-area populations
+pub const CITIES: &[City] = &[
+    city("Tokyo", "JP", 35.6895, 139.6917, 37_400_000),
+    city("Delhi", "IN", 28.7041, 77.1025, 31_200_000),
+    city("Shanghai", "CN", 31.2304, 121.4737, 27_800_000),
+    city("Sao Paulo", "BR", -23.5505, -46.6333, 22_200_000),
+    city("Mexico City", "MX", 19.4326, -99.1332, 21_900_000),
+    city("Dhaka", "BD", 23.8103, 90.4125, 21_700_000),
+    city("Cairo", "EG", 30.0444, 31.2357, 21_300_000),
+    city("Beijing", "CN", 39.9042, 116.4074, 20_900_000),
+    city("Mumbai", "IN", 19.0760, 72.8777, 20_700_000),
+    city("Osaka", "JP", 34.6937, 135.5023, 19_100_000),
+    city("Chongqing", "CN", 29.4316, 106.9123, 16_400_000),
+    city("Karachi", "PK", 24.8607, 67.0011, 16_500_000),
+    city("Istanbul", "TR", 41.0082, 28.9784, 15_400_000),
+    city("Kinshasa", "CD", -4.4419, 15.2663, 14_900_000),
+    city("Lagos", "NG", 6.5244, 3.3792, 14_800_000),
+    city("Buenos Aires", "AR", -34.6037, -58.3816, 15_200_000),
+    city("Kolkata", "IN", 22.5726, 88.3639, 14_900_000),
+    city("Manila", "PH", 14.5995, 120.9842, 14_200_000),
+    city("Tianjin", "CN", 39.3434, 117.3616, 13_800_000),
+    city("Guangzhou", "CN", 23.1291, 113.2644, 13_600_000),
+    city("Rio de Janeiro", "BR", -22.9068, -43.1729, 13_500_000),
+    city("Lahore", "PK", 31.5204, 74.3587, 13_100_000),
+    city("Bangalore", "IN", 12.9716, 77.5946, 12_800_000),
+    city("Shenzhen", "CN", 22.5431, 114.0579, 12_600_000),
+    city("Moscow", "RU", 55.7558, 37.6173, 12_600_000),
+    city("Chennai", "IN", 13.0827, 80.2707, 11_200_000),
+    city("Bogota", "CO", 4.7110, -74.0721, 11_000_000),
+    city("Paris", "FR", 48.8566, 2.3522, 11_000_000),
+    city("Jakarta", "ID", -6.2088, 106.8456, 10_900_000),
+    city("Lima", "PE", -12.0464, -77.0428, 10_900_000),
+    city("Bangkok", "TH", 13.7563, 100.5018, 10_700_000),
+    city("Hyderabad", "IN", 17.3850, 78.4867, 10_300_000),
+    city("Seoul", "KR", 37.5665, 126.9780, 9_900_000),
+    city("Nagoya", "JP", 35.1815, 136.9066, 9_500_000),
+    city("London", "GB", 51.5074, -0.1278, 9_500_000),
+    city("Chengdu", "CN", 30.5728, 104.0668, 9_300_000),
+    city("Tehran", "IR", 35.6892, 51.3890, 9_300_000),
+    city("Nanjing", "CN", 32.0603, 118.7969, 9_100_000),
+    city("Ho Chi Minh City", "VN", 10.8231, 106.6297, 8_800_000),
+    city("Luanda", "AO", -8.8390, 13.2894, 8_600_000),
+    city("New York", "US", 40.7128, -74.0060, 8_800_000),
+    city("Wuhan", "CN", 30.5928, 114.3055, 8_400_000),
+    city("Hong Kong", "HK", 22.3193, 114.1694, 7_500_000),
+    city("Ahmedabad", "IN", 23.0225, 72.5714, 8_400_000),
+    city("Kuala Lumpur", "MY", 3.1390, 101.6869, 8_400_000),
+    city("Riyadh", "SA", 24.7136, 46.6753, 7_500_000),
+    city("Baghdad", "IQ", 33.3152, 44.3661, 7_500_000),
+    city("Santiago", "CL", -33.4489, -70.6693, 6_800_000),
+    city("Surat", "IN", 21.1702, 72.8311, 7_500_000),
+    city("Madrid", "ES", 40.4168, -3.7038, 6_700_000),
+    city("Pune", "IN", 18.5204, 73.8567, 6_800_000),
+    city("Dar es Salaam", "TZ", -6.7924, 39.2083, 7_000_000),
+    city("Toronto", "CA", 43.6532, -79.3832, 6_300_000),
+    city("Khartoum", "SD", 15.5007, 32.5599, 6_100_000),
+    city("Johannesburg", "ZA", -26.2041, 28.0473, 6_100_000),
+    city("Singapore", "SG", 1.3521, 103.8198, 5_900_000),
+    city("Barcelona", "ES", 41.3851, 2.1734, 5_600_000),
+    city("Philadelphia", "US", 39.9526, -75.1652, 5_800_000),
+    city("Saint Petersburg", "RU", 59.9311, 30.3609, 5_500_000),
+    city("Los Angeles", "US", 34.0522, -118.2437, 12_500_000),
+    city("Chicago", "US", 41.8781, -87.6298, 8_900_000),
+    city("Houston", "US", 29.7604, -95.3698, 6_400_000),
+    city("Yangon", "MM", 16.8409, 96.1735, 5_400_000),
+    city("Alexandria", "EG", 31.2001, 29.9187, 5_400_000),
+    city("Abidjan", "CI", 5.3600, -4.0083, 5_300_000),
+    city("Nairobi", "KE", -1.2921, 36.8219, 5_100_000),
+    city("Sydney", "AU", -33.8688, 151.2093, 5_000_000),
+    city("Melbourne", "AU", -37.8136, 144.9631, 5_000_000),
+    city("Berlin", "DE", 52.5200, 13.4050, 3_600_000),
+    city("Rome", "IT", 41.9028, 12.4964, 4_300_000),
+    city("Addis Ababa", "ET", 8.9806, 38.7578, 5_000_000),
+    city("Accra", "GH", 5.6037, -0.1870, 2_600_000),
+    city("Casablanca", "MA", 33.5731, -7.5898, 3_800_000),
+    city("Monterrey", "MX", 25.6866, -100.3161, 5_300_000),
+    city("Guadalajara", "MX", 20.6597, -103.3496, 5_200_000),
+    city("Belo Horizonte", "BR", -19.9167, -43.9345, 6_100_000),
+    city("Ankara", "TR", 39.9334, 32.8597, 5_300_000),
+    city("Salvador", "BR", -12.9777, -38.5016, 3_900_000),
+    city("Warsaw", "PL", 52.2297, 21.0122, 1_800_000),
+    city("Amsterdam", "NL", 52.3676, 4.9041, 1_200_000),
+    city("Stockholm", "SE", 59.3293, 18.0686, 1_600_000),
+    city("Auckland", "NZ", -36.8485, 174.7633, 1_700_000),
+    city("Vancouver", "CA", 49.2827, -123.1207, 2_600_000),
+    city("San Francisco", "US", 37.7749, -122.4194, 3_300_000),
+    city("Seattle", "US", 47.6062, -122.3321, 3_500_000),
+];
+
+/// Samples cities with probability proportional to their population, so a batch of
+/// synthetic users is spread over the world roughly like real people are
+pub struct CitySampler {
+    cities: &'static [City],
+    dist: WeightedIndex<u64>,
+}
+
+impl CitySampler {
+    /// A sampler over the whole embedded dataset
+    pub fn new() -> Self {
+        Self::with_cities(CITIES).expect("the embedded dataset has positive populations")
+    }
+
+    /// A sampler over a subset, e.g. one country. `None` if no city has a population.
+    pub fn with_cities(cities: &'static [City]) -> Option<Self> {
+        let dist = WeightedIndex::new(cities.iter().map(|c| c.population)).ok()?;
+        Some(CitySampler { cities, dist })
+    }
+
+    pub fn city<R>(&self, rng: &mut R) -> &'static City
+    where
+        R: Rng + ?Sized,
+    {
+        &self.cities[self.dist.sample(rng)]
+    }
+
+    /// A point within `radius_km` of a population-weighted city center, uniform over the
+    /// disk so points don't bunch up in the middle
+    pub fn location<R>(&self, radius_km: f64, rng: &mut R) -> (&'static City, f64, f64)
+    where
+        R: Rng + ?Sized,
+    {
+        let city = self.city(rng);
+        // `sqrt` of a uniform radius gives a uniform density over the disk's area
+        let r = radius_km * rng.gen::<f64>().sqrt();
+        let theta = rng.gen_range(0.0..std::f64::consts::TAU);
+        // One degree of latitude is ~111km, a degree of longitude shrinks with `cos(lat)`
+        let lat = city.lat + r * theta.sin() / 111.0;
+        let lon = city.lon + r * theta.cos() / (111.0 * city.lat.to_radians().cos());
+        (city, lat, lon)
+    }
+}
+
+impl Default for CitySampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_favors_bigger_cities() {
+        let mut rng = StdRng::seed_from_u64(41);
+        let sampler = CitySampler::new();
+
+        let mut tokyo = 0;
+        let mut amsterdam = 0;
+        for _ in 0..20_000 {
+            match sampler.city(&mut rng).name {
+                "Tokyo" => tokyo += 1,
+                "Amsterdam" => amsterdam += 1,
+                _ => {}
+            }
+        }
+
+        // Tokyo is ~30x bigger in the dataset
+        assert!(tokyo > amsterdam * 10, "{} vs {}", tokyo, amsterdam);
+    }
+
+    #[test]
+    fn it_keeps_locations_near_the_city() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let sampler = CitySampler::new();
+
+        for _ in 0..1000 {
+            let (city, lat, lon) = sampler.location(25.0, &mut rng);
+            assert!((lat - city.lat).abs() < 0.25);
+            assert!((lon - city.lon).abs() < 0.5);
+        }
+    }
+}
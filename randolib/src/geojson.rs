@@ -0,0 +1,499 @@
+//! Random GeoJSON (RFC 7946) for testing geospatial services: points, line strings and
+//! polygons inside a bounding box. Polygons are star-shaped around a center, so they're
+//! always simple, wound counterclockwise as the RFC asks, and no bigger than a circle of
+//! `max_radius`.
+//!
+//! A share of polygons can be deliberately self-intersecting, flagged in the feature's
+//! `"valid"` property, so a service's validation can be checked against the ground truth.
+//!
+//! Coordinates are degrees, `[longitude, latitude]` in that order, rounded to 6 decimal
+//! places (about 10cm). Areas and intersections are worked out on the flat lon/lat plane,
+//! which is what GeoJSON validity is defined on.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{f64::consts::TAU, ops::RangeInclusive, str::FromStr};
+
+/// `[longitude, latitude]`
+pub type Position = [f64; 2];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GeometryKind {
+    Point,
+    LineString,
+    Polygon,
+}
+
+impl GeometryKind {
+    pub const ALL: [GeometryKind; 3] = [
+        GeometryKind::Point,
+        GeometryKind::LineString,
+        GeometryKind::Polygon,
+    ];
+
+    /// The GeoJSON `"type"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeometryKind::Point => "Point",
+            GeometryKind::LineString => "LineString",
+            GeometryKind::Polygon => "Polygon",
+        }
+    }
+}
+
+/// Case-insensitive, so `polygon` works on a command line
+impl FromStr for GeometryKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GeometryKind::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown geometry type {:?}", s)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Geometry {
+    Point(Position),
+    LineString(Vec<Position>),
+    /// Linear rings, the exterior first. Each ring is closed: its last position repeats
+    /// the first.
+    Polygon(Vec<Vec<Position>>),
+}
+
+impl Geometry {
+    pub fn kind(&self) -> GeometryKind {
+        match self {
+            Geometry::Point(_) => GeometryKind::Point,
+            Geometry::LineString(_) => GeometryKind::LineString,
+            Geometry::Polygon(_) => GeometryKind::Polygon,
+        }
+    }
+
+    /// Whether this follows RFC 7946 and is a simple shape: line strings have at least two
+    /// positions, polygon rings are closed with at least four, run counterclockwise for the
+    /// exterior and don't cross themselves
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Geometry::Point(_) => true,
+            Geometry::LineString(line) => line.len() >= 2,
+            Geometry::Polygon(rings) => {
+                !rings.is_empty()
+                    && rings
+                        .iter()
+                        .enumerate()
+                        .all(|(i, ring)| is_valid_ring(ring, i == 0))
+            }
+        }
+    }
+
+    /// The enclosed area in square degrees, holes subtracted. 0 for points and lines.
+    pub fn area(&self) -> f64 {
+        match self {
+            Geometry::Polygon(rings) => rings.iter().map(|ring| signed_area(ring)).sum(),
+            _ => 0.0,
+        }
+    }
+
+    /// The GeoJSON geometry object
+    pub fn to_json(&self) -> String {
+        let coordinates = match self {
+            Geometry::Point(position) => position_json(position),
+            Geometry::LineString(line) => positions_json(line),
+            Geometry::Polygon(rings) => {
+                let rings = rings.iter().map(|ring| positions_json(ring));
+                format!("[{}]", rings.collect::<Vec<_>>().join(","))
+            }
+        };
+        format!(
+            "{{\"type\":\"{}\",\"coordinates\":{}}}",
+            self.kind().name(),
+            coordinates
+        )
+    }
+}
+
+fn position_json(position: &Position) -> String {
+    format!("[{},{}]", position[0], position[1])
+}
+
+fn positions_json(positions: &[Position]) -> String {
+    let positions = positions.iter().map(position_json).collect::<Vec<_>>();
+    format!("[{}]", positions.join(","))
+}
+
+/// Twice the cross product of `ab` and `ac`: positive when `a, b, c` turn counterclockwise
+fn cross(a: Position, b: Position, c: Position) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+/// The shoelace formula, positive for a counterclockwise closed ring
+fn signed_area(ring: &[Position]) -> f64 {
+    ring.windows(2)
+        .map(|w| w[0][0] * w[1][1] - w[1][0] * w[0][1])
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Closed, at least a triangle, simple, and counterclockwise for the exterior ring, or
+/// clockwise for a hole
+fn is_valid_ring(ring: &[Position], exterior: bool) -> bool {
+    ring.len() >= 4
+        && ring.first() == ring.last()
+        && is_simple(ring)
+        && (signed_area(ring) > 0.0) == exterior
+}
+
+/// Whether segments `ab` and `cd` touch, including at their ends
+fn segments_touch(a: Position, b: Position, c: Position, d: Position) -> bool {
+    let (d1, d2) = (cross(c, d, a), cross(c, d, b));
+    let (d3, d4) = (cross(a, b, c), cross(a, b, d));
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    // Collinear cases: a point on the other segment
+    let on = |p: Position, q: Position, r: Position| {
+        cross(p, q, r) == 0.0
+            && r[0] >= p[0].min(q[0])
+            && r[0] <= p[0].max(q[0])
+            && r[1] >= p[1].min(q[1])
+            && r[1] <= p[1].max(q[1])
+    };
+    on(c, d, a) || on(c, d, b) || on(a, b, c) || on(a, b, d)
+}
+
+/// Whether a closed ring's edges only meet their neighbors, at the shared vertex
+fn is_simple(ring: &[Position]) -> bool {
+    let n = ring.len() - 1;
+    (0..n).all(|i| {
+        (i + 1..n)
+            // Neighbors share a vertex, the first and last edge too
+            .filter(|&j| j != i + 1 && !(i == 0 && j == n - 1))
+            .all(|j| !segments_touch(ring[i], ring[i + 1], ring[j], ring[j + 1]))
+    })
+}
+
+/// A GeoJSON feature. Its properties are its index and whether its geometry is valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub id: usize,
+    pub geometry: Geometry,
+    /// False for a deliberately broken geometry
+    pub valid: bool,
+}
+
+impl Feature {
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"type\":\"Feature\",\"id\":{},\"geometry\":{},\"properties\":{{\"valid\":{}}}}}",
+            self.id,
+            self.geometry.to_json(),
+            self.valid
+        )
+    }
+}
+
+/// The top-level object most services take
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureCollection {
+    pub features: Vec<Feature>,
+}
+
+impl FeatureCollection {
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"type\":\"FeatureCollection\",\"features\":[");
+        for (i, feature) in self.features.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str(&feature.to_json());
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+/// Generates features
+pub struct GeoJsonGen {
+    /// `[min_lon, min_lat, max_lon, max_lat]`, the GeoJSON `bbox` order
+    bbox: [f64; 4],
+    kinds: Vec<GeometryKind>,
+    vertices: RangeInclusive<usize>,
+    max_radius: f64,
+    invalid_rate: f64,
+}
+
+impl GeoJsonGen {
+    /// Any kind, anywhere on Earth, with 4 to 12 vertices within a degree of their center
+    /// and no invalid polygons
+    pub fn new() -> Self {
+        GeoJsonGen {
+            bbox: [-180.0, -90.0, 180.0, 90.0],
+            kinds: GeometryKind::ALL.to_vec(),
+            vertices: 4..=12,
+            max_radius: 1.0,
+            invalid_rate: 0.0,
+        }
+    }
+
+    /// Where features go, `[min_lon, min_lat, max_lon, max_lat]`
+    pub fn bbox(mut self, bbox: [f64; 4]) -> Result<Self, Error> {
+        let [min_lon, min_lat, max_lon, max_lat] = bbox;
+        let lon_ok = -180.0 <= min_lon && min_lon < max_lon && max_lon <= 180.0;
+        let lat_ok = -90.0 <= min_lat && min_lat < max_lat && max_lat <= 90.0;
+        if !(lon_ok && lat_ok) {
+            return Err(Error::InvalidParameter(format!(
+                "bounding box {:?} must be non-empty and within -180..180, -90..90",
+                bbox
+            )));
+        }
+        self.bbox = bbox;
+        Ok(self)
+    }
+
+    /// Which geometries to make, picked evenly
+    pub fn kinds(mut self, kinds: &[GeometryKind]) -> Result<Self, Error> {
+        if kinds.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one geometry type is needed".into(),
+            ));
+        }
+        self.kinds = kinds.to_vec();
+        Ok(self)
+    }
+
+    /// Positions in a line string and distinct vertices in a polygon, from 3
+    pub fn vertices(mut self, vertices: RangeInclusive<usize>) -> Result<Self, Error> {
+        if vertices.is_empty() || *vertices.start() < 3 {
+            return Err(Error::InvalidParameter(format!(
+                "vertex range {:?} must be non-empty and from 3",
+                vertices
+            )));
+        }
+        self.vertices = vertices;
+        Ok(self)
+    }
+
+    /// How far, in degrees, a polygon's vertices or a line's steps reach. This bounds a
+    /// polygon's area by `pi * max_radius^2`. Shrunk to fit a smaller bounding box.
+    pub fn max_radius(mut self, degrees: f64) -> Result<Self, Error> {
+        if !(degrees.is_finite() && degrees > 0.0) {
+            return Err(Error::InvalidParameter(format!(
+                "radius {} must be positive",
+                degrees
+            )));
+        }
+        self.max_radius = degrees;
+        Ok(self)
+    }
+
+    /// The share of polygons made self-intersecting
+    pub fn invalid_rate(mut self, rate: f64) -> Self {
+        self.invalid_rate = probability(rate);
+        self
+    }
+
+    pub fn feature<R>(&self, id: usize, rng: &mut R) -> Feature
+    where
+        R: Rng + ?Sized,
+    {
+        let (geometry, valid) = match self.kinds.choose(rng).unwrap() {
+            GeometryKind::Point => (Geometry::Point(self.point(0.0, rng)), true),
+            GeometryKind::LineString => (Geometry::LineString(self.line(rng)), true),
+            GeometryKind::Polygon => {
+                let valid = !rng.gen_bool(self.invalid_rate);
+                (Geometry::Polygon(vec![self.ring(valid, rng)]), valid)
+            }
+        };
+        Feature {
+            id,
+            geometry,
+            valid,
+        }
+    }
+
+    pub fn collection<R>(&self, features: usize, rng: &mut R) -> FeatureCollection
+    where
+        R: Rng + ?Sized,
+    {
+        FeatureCollection {
+            features: (0..features).map(|id| self.feature(id, rng)).collect(),
+        }
+    }
+
+    /// The radius actually used, so shapes fit in the box
+    fn radius(&self) -> f64 {
+        let [min_lon, min_lat, max_lon, max_lat] = self.bbox;
+        self.max_radius
+            .min((max_lon - min_lon) / 2.0)
+            .min((max_lat - min_lat) / 2.0)
+    }
+
+    /// A point at least `margin` inside the box
+    fn point<R>(&self, margin: f64, rng: &mut R) -> Position
+    where
+        R: Rng + ?Sized,
+    {
+        let [min_lon, min_lat, max_lon, max_lat] = self.bbox;
+        let lon = rng.gen_range(min_lon + margin..=max_lon - margin);
+        let lat = rng.gen_range(min_lat + margin..=max_lat - margin);
+        [round(lon), round(lat)]
+    }
+
+    /// A random walk, clamped to the box. Steps are at least a tenth of the radius so
+    /// consecutive positions don't repeat.
+    fn line<R>(&self, rng: &mut R) -> Vec<Position>
+    where
+        R: Rng + ?Sized,
+    {
+        let [min_lon, min_lat, max_lon, max_lat] = self.bbox;
+        let radius = self.radius();
+        let mut at = self.point(radius, rng);
+        let mut line = vec![at];
+        for _ in 1..rng.gen_range(self.vertices.clone()) {
+            let step = rng.gen_range(radius / 10.0..=radius);
+            let heading = rng.gen_range(0.0..TAU);
+            at = [
+                round((at[0] + step * heading.cos()).clamp(min_lon, max_lon)),
+                round((at[1] + step * heading.sin()).clamp(min_lat, max_lat)),
+            ];
+            line.push(at);
+        }
+        line
+    }
+
+    /// A closed star-shaped ring: one vertex per slice of the circle, at a random angle in
+    /// its slice and a random distance from the center. Swapping two neighbors usually
+    /// makes it cross itself, and we retry until it does.
+    fn ring<R>(&self, valid: bool, rng: &mut R) -> Vec<Position>
+    where
+        R: Rng + ?Sized,
+    {
+        let radius = self.radius();
+        let n = rng.gen_range(self.vertices.clone());
+        loop {
+            let center = self.point(radius, rng);
+            let mut ring = (0..n)
+                .map(|i| {
+                    let angle = (i as f64 + rng.gen_range(0.1..0.9)) / n as f64 * TAU;
+                    let r = rng.gen_range(radius / 5.0..=radius);
+                    [
+                        round(center[0] + r * angle.cos()),
+                        round(center[1] + r * angle.sin()),
+                    ]
+                })
+                .collect::<Vec<_>>();
+            if !valid {
+                let i = rng.gen_range(0..n - 1);
+                ring.swap(i, i + 1);
+            }
+            ring.push(ring[0]);
+            // Rounding could in theory pinch a tiny ring, so check either way
+            if is_valid_ring(&ring, true) == valid {
+                return ring;
+            }
+        }
+    }
+}
+
+impl Default for GeoJsonGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 6 decimal places, and -0 becomes 0 so it doesn't show up as `-0` in the JSON
+fn round(degrees: f64) -> f64 {
+    (degrees * 1e6).round() / 1e6 + 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn it_makes_valid_geometry_in_the_box() {
+        let bbox = [4.7, 52.2, 5.1, 52.5];
+        let gen = GeoJsonGen::new()
+            .bbox(bbox)
+            .unwrap()
+            .max_radius(0.05)
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(278);
+        let collection = gen.collection(300, &mut rng);
+        for feature in &collection.features {
+            assert!(
+                feature.valid && feature.geometry.is_valid(),
+                "{:?}",
+                feature
+            );
+            let positions = match &feature.geometry {
+                Geometry::Point(p) => vec![*p],
+                Geometry::LineString(line) => line.clone(),
+                Geometry::Polygon(rings) => rings[0].clone(),
+            };
+            assert!(positions.iter().all(|[lon, lat]| {
+                (bbox[0]..=bbox[2]).contains(lon) && (bbox[1]..=bbox[3]).contains(lat)
+            }));
+            let area = feature.geometry.area();
+            assert!((0.0..=PI * 0.05 * 0.05).contains(&area), "{}", area);
+        }
+        let kinds = collection
+            .features
+            .iter()
+            .map(|f| f.geometry.kind())
+            .collect::<Vec<_>>();
+        assert!(GeometryKind::ALL.iter().all(|kind| kinds.contains(kind)));
+        let json = serde_json::from_str::<serde_json::Value>(&collection.to_json()).unwrap();
+        assert_eq!(json["features"].as_array().unwrap().len(), 300);
+    }
+
+    #[test]
+    fn it_breaks_polygons_on_request() {
+        let gen = GeoJsonGen::new()
+            .kinds(&[GeometryKind::Polygon])
+            .unwrap()
+            .invalid_rate(0.5);
+        let mut rng = StdRng::seed_from_u64(279);
+        let features = gen.collection(200, &mut rng).features;
+        let invalid = features.iter().filter(|f| !f.valid).count();
+        assert!((70..130).contains(&invalid), "{}", invalid);
+        assert!(features.iter().all(|f| f.valid == f.geometry.is_valid()));
+    }
+
+    #[test]
+    fn it_checks_rings() {
+        let square = vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.0, 0.0]];
+        assert!(Geometry::Polygon(vec![square.clone()]).is_valid());
+        assert_eq!(Geometry::Polygon(vec![square.clone()]).area(), 1.0);
+        let clockwise = square.iter().rev().copied().collect();
+        assert!(!Geometry::Polygon(vec![clockwise]).is_valid());
+        let bowtie = vec![[0.0, 0.0], [1.0, 1.0], [1.0, 0.0], [0.0, 1.0], [0.0, 0.0]];
+        assert!(!Geometry::Polygon(vec![bowtie]).is_valid());
+        let open = square[..4].to_vec();
+        assert!(!Geometry::Polygon(vec![open]).is_valid());
+
+        assert_eq!(
+            Geometry::Point([1.5, -2.0]).to_json(),
+            r#"{"type":"Point","coordinates":[1.5,-2]}"#
+        );
+        assert!(GeoJsonGen::new().bbox([10.0, 0.0, 5.0, 1.0]).is_err());
+        assert!(GeoJsonGen::new().vertices(2..=5).is_err());
+        assert_eq!(
+            "linestring".parse::<GeometryKind>().unwrap(),
+            GeometryKind::LineString
+        );
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = GeoJsonGen::new().invalid_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(279);
+        for id in 0..20 {
+            assert!(gen.feature(id, &mut rng).valid);
+        }
+    }
+}
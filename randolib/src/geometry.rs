@@ -0,0 +1,309 @@
+//! Uniform random directions and rotations. Sampling each component independently and
+//! normalizing is a common mistake: it piles directions up towards the corners of the cube
+//! the components were drawn from.
+//!
+//! Also uniform points inside and on the boundary of simple shapes, for procedural
+//! generation and for testing geometry code.
+
+use rand::prelude::*;
+use rand_distr::{StandardNormal, UnitCircle, UnitSphere, WeightedIndex};
+use somelib::error::Error;
+use std::f64::consts::TAU;
+
+/// A uniformly random point on the unit circle
+pub fn unit_circle<R>(rng: &mut R) -> [f64; 2]
+where
+    R: Rng + ?Sized,
+{
+    rng.sample(UnitCircle)
+}
+
+/// A uniformly random point on the unit sphere, i.e. a random 3D direction
+pub fn unit_sphere<R>(rng: &mut R) -> [f64; 3]
+where
+    R: Rng + ?Sized,
+{
+    rng.sample(UnitSphere)
+}
+
+/// A uniformly random unit vector in any number of dimensions. A vector of independent
+/// standard normals is rotationally symmetric, so normalizing it gives a uniform direction.
+pub fn unit_vector<R>(dims: usize, rng: &mut R) -> Vec<f64>
+where
+    R: Rng + ?Sized,
+{
+    loop {
+        let v = (0..dims)
+            .map(|_| rng.sample::<f64, _>(StandardNormal))
+            .collect::<Vec<_>>();
+        let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+        // All zeros is vanishingly unlikely but can't be normalized
+        if norm > 0.0 {
+            return v.into_iter().map(|x| x / norm).collect();
+        }
+    }
+}
+
+/// A unit quaternion `w + xi + yj + zk`, representing a 3D rotation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn norm(&self) -> f64 {
+        (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+    }
+
+    /// Rotate `v` by this quaternion, `q v q*`, in the expanded form
+    /// `v + 2w(u x v) + 2u x (u x v)` where `u` is the vector part
+    pub fn rotate(&self, v: [f64; 3]) -> [f64; 3] {
+        let u = [self.x, self.y, self.z];
+        let uv = cross(u, v);
+        let uuv = cross(u, uv);
+        [
+            v[0] + 2.0 * (self.w * uv[0] + uuv[0]),
+            v[1] + 2.0 * (self.w * uv[1] + uuv[1]),
+            v[2] + 2.0 * (self.w * uv[2] + uuv[2]),
+        ]
+    }
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// A uniformly random rotation (uniform over SO(3)), using Shoemake's method. Picking
+/// random Euler angles instead would cluster rotations around the poles.
+pub fn random_rotation<R>(rng: &mut R) -> Quaternion
+where
+    R: Rng + ?Sized,
+{
+    let (u1, u2, u3) = (rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>());
+    let (a, b) = ((1.0 - u1).sqrt(), u1.sqrt());
+    Quaternion {
+        w: a * (TAU * u2).sin(),
+        x: a * (TAU * u2).cos(),
+        y: b * (TAU * u3).sin(),
+        z: b * (TAU * u3).cos(),
+    }
+}
+
+/// A uniformly random point inside a disk. The square root matters: picking the radius
+/// uniformly would crowd points around the center.
+pub fn in_disk<R>(center: [f64; 2], radius: f64, rng: &mut R) -> [f64; 2]
+where
+    R: Rng + ?Sized,
+{
+    let r = radius * rng.gen::<f64>().sqrt();
+    let theta = rng.gen_range(0.0..TAU);
+    [center[0] + r * theta.cos(), center[1] + r * theta.sin()]
+}
+
+/// A uniformly random point on a circle
+pub fn on_circle<R>(center: [f64; 2], radius: f64, rng: &mut R) -> [f64; 2]
+where
+    R: Rng + ?Sized,
+{
+    let [x, y] = unit_circle(rng);
+    [center[0] + radius * x, center[1] + radius * y]
+}
+
+/// A uniformly random point inside a triangle, from barycentric coordinates. Draws that
+/// land in the far half of the parallelogram spanned by the edges are folded back in.
+pub fn in_triangle<R>(a: [f64; 2], b: [f64; 2], c: [f64; 2], rng: &mut R) -> [f64; 2]
+where
+    R: Rng + ?Sized,
+{
+    let (mut u, mut v) = (rng.gen::<f64>(), rng.gen::<f64>());
+    if u + v > 1.0 {
+        u = 1.0 - u;
+        v = 1.0 - v;
+    }
+    [
+        a[0] + u * (b[0] - a[0]) + v * (c[0] - a[0]),
+        a[1] + u * (b[1] - a[1]) + v * (c[1] - a[1]),
+    ]
+}
+
+/// A uniformly random point inside an axis-aligned box of any dimension
+pub fn in_box<R>(min: &[f64], max: &[f64], rng: &mut R) -> Vec<f64>
+where
+    R: Rng + ?Sized,
+{
+    min.iter()
+        .zip(max)
+        .map(|(lo, hi)| lo + rng.gen::<f64>() * (hi - lo))
+        .collect()
+}
+
+/// Twice the signed area of triangle `abc`, positive when it turns counterclockwise
+fn cross2(a: [f64; 2], b: [f64; 2], c: [f64; 2]) -> f64 {
+    (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+}
+
+fn distance(a: [f64; 2], b: [f64; 2]) -> f64 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+/// A convex polygon, ready for sampling. It is split into a fan of triangles from the
+/// first vertex, and a triangle is picked in proportion to its area before sampling
+/// inside it.
+#[derive(Debug, Clone)]
+pub struct ConvexPolygon {
+    vertices: Vec<[f64; 2]>,
+    triangles: WeightedIndex<f64>,
+    edges: WeightedIndex<f64>,
+}
+
+impl ConvexPolygon {
+    /// `vertices` in order, either clockwise or counterclockwise
+    pub fn new(vertices: &[[f64; 2]]) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::InvalidParameter(msg.to_string());
+        let n = vertices.len();
+        if n < 3 {
+            return Err(invalid("a polygon needs at least 3 vertices"));
+        }
+        // Convex means every corner turns the same way
+        let turns = (0..n)
+            .map(|i| cross2(vertices[i], vertices[(i + 1) % n], vertices[(i + 2) % n]))
+            .collect::<Vec<_>>();
+        if !(turns.iter().all(|t| *t > 0.0) || turns.iter().all(|t| *t < 0.0)) {
+            return Err(invalid(
+                "the polygon must be convex with no repeated vertices",
+            ));
+        }
+
+        let areas = (1..n - 1).map(|i| cross2(vertices[0], vertices[i], vertices[i + 1]).abs());
+        let lengths = (0..n).map(|i| distance(vertices[i], vertices[(i + 1) % n]));
+        Ok(ConvexPolygon {
+            vertices: vertices.to_vec(),
+            // Every area and length is positive after the convexity check
+            triangles: WeightedIndex::new(areas).unwrap(),
+            edges: WeightedIndex::new(lengths).unwrap(),
+        })
+    }
+
+    /// A uniformly random point inside the polygon
+    pub fn sample_inside<R>(&self, rng: &mut R) -> [f64; 2]
+    where
+        R: Rng + ?Sized,
+    {
+        let i = self.triangles.sample(rng) + 1;
+        let v = &self.vertices;
+        in_triangle(v[0], v[i], v[i + 1], rng)
+    }
+
+    /// A uniformly random point on the polygon's boundary
+    pub fn sample_boundary<R>(&self, rng: &mut R) -> [f64; 2]
+    where
+        R: Rng + ?Sized,
+    {
+        let i = self.edges.sample(rng);
+        let (a, b) = (
+            self.vertices[i],
+            self.vertices[(i + 1) % self.vertices.len()],
+        );
+        let t = rng.gen::<f64>();
+        [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_samples_unit_vectors_without_bias() {
+        let mut rng = StdRng::seed_from_u64(28);
+        let n = 20_000;
+        // For a uniform direction in 4D each squared component averages 1/4
+        let mut sums = [0.0; 4];
+        for _ in 0..n {
+            let v = unit_vector(4, &mut rng);
+            assert!((v.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-12);
+            for (sum, x) in sums.iter_mut().zip(&v) {
+                *sum += x * x;
+            }
+        }
+        assert!(sums.iter().all(|s| (s / n as f64 - 0.25).abs() < 0.01));
+
+        let [x, y] = unit_circle(&mut rng);
+        assert!((x * x + y * y - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn it_rotates_without_scaling() {
+        let mut rng = StdRng::seed_from_u64(29);
+        let mut mean_z = 0.0;
+        for _ in 0..10_000 {
+            let q = random_rotation(&mut rng);
+            assert!((q.norm() - 1.0).abs() < 1e-12);
+            let v = q.rotate([0.0, 0.0, 1.0]);
+            assert!((v.iter().map(|x| x * x).sum::<f64>() - 1.0).abs() < 1e-9);
+            mean_z += v[2];
+        }
+        // Uniform rotations send the z axis anywhere on the sphere, so it averages out
+        assert!((mean_z / 10_000.0).abs() < 0.03);
+
+        let quarter_turn = Quaternion {
+            w: std::f64::consts::FRAC_1_SQRT_2,
+            x: 0.0,
+            y: 0.0,
+            z: std::f64::consts::FRAC_1_SQRT_2,
+        };
+        let v = quarter_turn.rotate([1.0, 0.0, 0.0]);
+        assert!(v[0].abs() < 1e-12 && (v[1] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn it_samples_disks_and_triangles_uniformly() {
+        let mut rng = StdRng::seed_from_u64(30);
+        let n = 20_000;
+        // Half of a disk's area lies within radius r / sqrt(2)
+        let inner = (0..n)
+            .map(|_| in_disk([1.0, 1.0], 2.0, &mut rng))
+            .filter(|p| distance(*p, [1.0, 1.0]) < 2.0 / 2f64.sqrt())
+            .count();
+        assert!((inner as f64 / n as f64 - 0.5).abs() < 0.02);
+
+        // The centroid of uniform points is the triangle's centroid
+        let (a, b, c) = ([0.0, 0.0], [3.0, 0.0], [0.0, 3.0]);
+        let mut sum = [0.0, 0.0];
+        for _ in 0..n {
+            let p = in_triangle(a, b, c, &mut rng);
+            assert!(p[0] >= 0.0 && p[1] >= 0.0 && p[0] + p[1] <= 3.0 + 1e-12);
+            sum = [sum[0] + p[0], sum[1] + p[1]];
+        }
+        assert!((sum[0] / n as f64 - 1.0).abs() < 0.03);
+        assert!((sum[1] / n as f64 - 1.0).abs() < 0.03);
+    }
+
+    #[test]
+    fn it_samples_convex_polygons() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let corners = [[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]];
+        let square = ConvexPolygon::new(&corners).unwrap();
+
+        for _ in 0..1000 {
+            let p = square.sample_inside(&mut rng);
+            assert!((0.0..=2.0).contains(&p[0]) && (0.0..=2.0).contains(&p[1]));
+            let q = square.sample_boundary(&mut rng);
+            assert!(q.iter().any(|x| *x == 0.0 || (x - 2.0).abs() < 1e-12));
+        }
+        let in_box = in_box(&[0.0, 5.0, -1.0], &[1.0, 6.0, 1.0], &mut rng);
+        assert!(in_box[1] >= 5.0 && in_box[1] < 6.0);
+
+        // A dart shape isn't convex
+        let dart = [[0.0, 0.0], [2.0, 1.0], [0.0, 2.0], [1.0, 1.0]];
+        assert!(ConvexPolygon::new(&dart).is_err());
+        assert!(ConvexPolygon::new(&[[0.0, 0.0], [1.0, 1.0]]).is_err());
+    }
+}
@@ -0,0 +1,288 @@
+//! A process-wide default generator, for code where threading an `rng` parameter through
+//! every function isn't worth it. Tests can pin it down for a block of code with
+//! `with_seed_scope`:
+//!
+//! ```
+//! use rand::Rng;
+//!
+//! let a = randolib::with_seed_scope(7, || randolib::global().gen::<u32>());
+//! let b = randolib::with_seed_scope(7, || randolib::global().gen::<u32>());
+//! assert_eq!(a, b);
+//! ```
+//!
+//! Both the shared generator and scoped ones follow the `RANDOLIB_*` environment
+//! variables described in `config`.
+
+use crate::{
+    config::Config,
+    repro::{Repro, TrackedRng},
+    seed::Seed,
+};
+use rand::prelude::*;
+use std::{
+    cell::RefCell,
+    sync::{Mutex, Once, OnceLock},
+};
+
+/// The environment is read once, on first use
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The shared generator, created on first use
+static GLOBAL: OnceLock<Mutex<TrackedRng>> = OnceLock::new();
+
+thread_local! {
+    /// Scoped overrides for this thread, innermost last. A `RefCell` because thread locals
+    /// are only ever handed out as shared references.
+    static SCOPES: RefCell<Vec<TrackedRng>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A handle to the default generator. It holds no state itself: every draw goes to the
+/// innermost `with_seed_scope` on this thread, or to the shared process-wide generator
+/// when there is none.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlobalRng;
+
+/// The default generator, see `GlobalRng`
+pub fn global() -> GlobalRng {
+    GlobalRng
+}
+
+/// Run `f` with the default generator on this thread replaced by one seeded from `seed`.
+/// Scopes nest, and other threads keep using the shared generator.
+pub fn with_seed_scope<S, F, T>(seed: S, f: F) -> T
+where
+    S: Into<Seed>,
+    F: FnOnce() -> T,
+{
+    /// Pops the scope when dropped, so it also goes away if `f` panics
+    struct Guard;
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            SCOPES.with(|scopes| scopes.borrow_mut().pop());
+        }
+    }
+
+    let rng = TrackedRng::new(seed.into(), config().backend);
+    SCOPES.with(|scopes| scopes.borrow_mut().push(rng));
+    let _guard = Guard;
+    f()
+}
+
+/// The configuration from the environment. An invalid `RANDOLIB_*` variable panics rather
+/// than being ignored, since a CI run that asked for determinism shouldn't quietly get
+/// randomness instead.
+pub fn config() -> &'static Config {
+    CONFIG.get_or_init(|| Config::from_env().unwrap_or_else(|e| panic!("randolib: {}", e)))
+}
+
+fn new_global() -> TrackedRng {
+    let config = config();
+    let seed = config.resolve_seed();
+    if config.log_seed {
+        eprintln!(
+            "randolib: default generator seed {} (backend {}), rerun with \
+             RANDOLIB_SEED={} to reproduce",
+            seed, config.backend, seed
+        );
+    }
+    TrackedRng::new(seed, config.backend)
+}
+
+/// Run `draw` against whichever generator is current
+fn with_current<T>(draw: impl FnOnce(&mut TrackedRng) -> T) -> T {
+    SCOPES.with(|scopes| match scopes.borrow_mut().last_mut() {
+        Some(rng) => draw(rng),
+        None => {
+            let global = GLOBAL.get_or_init(|| Mutex::new(new_global()));
+            // A panic while drawing can't leave the generator in a broken state, so a
+            // poisoned lock is safe to keep using
+            let mut rng = global
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            draw(&mut rng)
+        }
+    })
+}
+
+/// Capture the current default generator (the innermost scope's, or the shared one) as
+/// a reproduction string, see `repro`
+pub fn capture_repro() -> String {
+    with_current(|rng| rng.repro().encode())
+}
+
+/// The state of every generator a draw on this thread could come from, innermost scope
+/// first and the shared generator (if it has been created) last.
+///
+/// Safe to call from a panic hook: generators that are busy at the time (the panic
+/// happened mid-draw) are skipped rather than waited on.
+pub fn active_repros() -> Vec<Repro> {
+    let mut repros = SCOPES.with(|scopes| match scopes.try_borrow() {
+        Ok(scopes) => scopes.iter().rev().map(TrackedRng::repro).collect(),
+        Err(_) => Vec::new(),
+    });
+    if let Some(Ok(rng)) = GLOBAL.get().map(Mutex::try_lock) {
+        repros.push(rng.repro());
+    }
+    repros
+}
+
+/// What `std::panic::set_hook` takes and `take_hook` gives back
+type PanicHook = Box<dyn Fn(&std::panic::PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+/// Install a panic hook that prints `active_repros` after the usual panic message, so
+/// every test failure comes with the seeds needed to reproduce it. The previous hook
+/// still runs first. Calling this more than once has no further effect.
+pub fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(chained_hook(previous, |report| eprint!("{}", report)));
+    });
+}
+
+/// A hook that runs `previous`, then hands the report on active generators to `write`.
+/// Split out of `install_panic_hook` so tests can see what it writes.
+fn chained_hook<W>(previous: PanicHook, write: W) -> PanicHook
+where
+    W: Fn(&str) + Sync + Send + 'static,
+{
+    Box::new(move |info| {
+        previous(info);
+        let repros = active_repros();
+        if repros.is_empty() {
+            return;
+        }
+        let mut report = String::from("randolib: active generators, innermost first:\n");
+        for repro in repros {
+            report += &format!(
+                "  seed {} backend {} after {} draws, repro {}\n",
+                repro.seed,
+                repro.backend,
+                repro.draws,
+                repro.encode()
+            );
+        }
+        write(&report);
+    })
+}
+
+impl RngCore for GlobalRng {
+    fn next_u32(&mut self) -> u32 {
+        with_current(|rng| rng.next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        with_current(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        with_current(|rng| rng.fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        with_current(|rng| rng.try_fill_bytes(dest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for code deep in a call tree that doesn't take an `rng`
+    fn roll() -> u8 {
+        global().gen_range(1..=6)
+    }
+
+    #[test]
+    fn it_scopes_seeds() {
+        let rolls = || (0..20).map(|_| roll()).collect::<Vec<_>>();
+        let first = with_seed_scope(Seed::from_label("dice"), rolls);
+        let second = with_seed_scope(Seed::from_label("dice"), rolls);
+        assert_eq!(first, second);
+
+        // An inner scope doesn't disturb the outer one's stream
+        let nested = with_seed_scope(Seed::from_label("dice"), || {
+            let mut out = vec![roll()];
+            with_seed_scope(1, roll);
+            out.extend((1..20).map(|_| roll()));
+            out
+        });
+        assert_eq!(nested, first);
+    }
+
+    #[test]
+    fn it_pops_scopes_on_panic() {
+        let result = std::panic::catch_unwind(|| with_seed_scope(3, || panic!("boom")));
+
+        assert!(result.is_err());
+        SCOPES.with(|scopes| assert!(scopes.borrow().is_empty()));
+    }
+
+    #[test]
+    fn it_lists_active_generators() {
+        with_seed_scope(5, || {
+            with_seed_scope(6, || {
+                roll();
+                let repros = active_repros();
+                assert_eq!(repros[0].seed, Seed::from_u64(6));
+                assert!(repros[0].draws > 0);
+                assert_eq!(repros[1].seed, Seed::from_u64(5));
+                assert_eq!(repros[1].draws, 0);
+            })
+        });
+    }
+
+    #[test]
+    fn it_chains_the_panic_hook_and_prints_repros() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        // The hook is process-wide, so only one test may swap it at a time. Other tests'
+        // panics can still pass through while ours is installed, hence `contains` below.
+        static HOOK_LOCK: Mutex<()> = Mutex::new(());
+        let _lock = HOOK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let original = std::panic::take_hook();
+
+        let previous_calls = Arc::new(AtomicUsize::new(0));
+        let written = Arc::new(Mutex::new(String::new()));
+        let counting = {
+            let calls = Arc::clone(&previous_calls);
+            Box::new(move |_: &std::panic::PanicHookInfo<'_>| {
+                calls.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+        let write = {
+            let written = Arc::clone(&written);
+            move |report: &str| written.lock().unwrap().push_str(report)
+        };
+        std::panic::set_hook(chained_hook(counting, write));
+
+        let expected = std::panic::catch_unwind(|| {
+            with_seed_scope(11, || {
+                roll();
+                let expected = capture_repro();
+                // The hook runs before unwinding, while the scope is still active
+                std::panic::panic_any(expected)
+            })
+        })
+        .unwrap_err()
+        .downcast::<String>()
+        .unwrap();
+
+        // Whatever `install_panic_hook` finds installed keeps running too
+        install_panic_hook();
+        let _ = std::panic::catch_unwind(|| panic!("again"));
+        std::panic::set_hook(original);
+
+        assert!(previous_calls.load(Ordering::SeqCst) >= 2);
+        let written = written.lock().unwrap();
+        assert!(
+            written.starts_with("randolib: active generators"),
+            "{}",
+            written
+        );
+        assert!(written.contains(&format!("after 1 draws, repro {}\n", expected)));
+    }
+}
@@ -0,0 +1,359 @@
+use rand::{
+    distributions::{Alphanumeric, WeightedIndex},
+    prelude::*,
+};
+use somelib::error::Error;
+use std::fmt::Write;
+
+/// Methods and how often they show up in typical API traffic, `(method, weight)`
+const METHODS: [(&str, f64); 5] = [
+    ("GET", 70.0),
+    ("POST", 15.0),
+    ("PUT", 7.0),
+    ("PATCH", 4.0),
+    ("DELETE", 4.0),
+];
+
+/// A randomly generated HTTP request. This is only a description, `HttpSender` sends it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub headers: Vec<(String, String)>,
+    /// A JSON object for methods that carry a body
+    pub body: Option<String>,
+}
+
+impl HttpRequest {
+    /// Path plus query string
+    pub fn uri(&self) -> String {
+        if self.query.is_empty() {
+            return self.path.clone();
+        }
+        let query = self
+            .query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+        format!("{}?{}", self.path, query)
+    }
+
+    /// Render as an HTTP/1.1 request, ready to be written to a socket
+    pub fn to_http1(&self, host: &str) -> String {
+        // `write!` into a `String` can't fail, so the `unwrap`s below are fine
+        let mut out = String::new();
+        write!(
+            out,
+            "{} {} HTTP/1.1\r\nHost: {}\r\n",
+            self.method,
+            self.uri(),
+            host
+        )
+        .unwrap();
+        for (name, value) in &self.headers {
+            write!(out, "{}: {}\r\n", name, value).unwrap();
+        }
+        if let Some(body) = &self.body {
+            write!(
+                out,
+                "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        } else {
+            out.push_str("\r\n");
+        }
+        out
+    }
+}
+
+/// Generates `HttpRequest`s from a set of path templates. Templates can contain `{id}`
+/// (a number) and `{slug}` (a short lowercase word) placeholders, e.g. `/users/{id}/posts`.
+pub struct HttpRequestGen {
+    paths: Vec<String>,
+    method_dist: WeightedIndex<f64>,
+    max_query_params: usize,
+    max_headers: usize,
+    max_body_fields: usize,
+}
+
+impl HttpRequestGen {
+    pub fn new(paths: &[&str]) -> Result<Self, Error> {
+        if paths.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one path template is needed".into(),
+            ));
+        }
+        Ok(HttpRequestGen {
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            method_dist: WeightedIndex::new(METHODS.iter().map(|(_, w)| *w)).unwrap(),
+            max_query_params: 3,
+            max_headers: 3,
+            max_body_fields: 5,
+        })
+    }
+
+    pub fn max_query_params(mut self, max: usize) -> Self {
+        self.max_query_params = max;
+        self
+    }
+
+    /// Headers beyond `Host` and, for requests with a body, the content headers
+    pub fn max_headers(mut self, max: usize) -> Self {
+        self.max_headers = max;
+        self
+    }
+
+    pub fn max_body_fields(mut self, max: usize) -> Self {
+        self.max_body_fields = max;
+        self
+    }
+
+    pub fn request<R>(&self, rng: &mut R) -> HttpRequest
+    where
+        R: Rng + ?Sized,
+    {
+        let method = METHODS[self.method_dist.sample(rng)].0;
+        let template = self.paths.choose(rng).unwrap();
+        let path = fill_template(template, rng);
+
+        let query = (0..rng.gen_range(0..=self.max_query_params))
+            .map(|_| (slug(rng), token(rng, 6)))
+            .collect();
+        let headers = (0..rng.gen_range(0..=self.max_headers))
+            .map(|_| (format!("X-{}", capitalize(&slug(rng))), token(rng, 12)))
+            .collect();
+        let body = match method {
+            "POST" | "PUT" | "PATCH" => Some(json_object(self.max_body_fields, rng)),
+            _ => None,
+        };
+
+        HttpRequest {
+            method: method.to_string(),
+            path,
+            query,
+            headers,
+            body,
+        }
+    }
+}
+
+/// Sends `HttpRequest`s to a server, with the `http-client` feature. `reqwest`'s blocking
+/// client keeps connections alive between requests, so make one sender and reuse it.
+#[cfg(feature = "http-client")]
+pub struct HttpSender {
+    client: reqwest::blocking::Client,
+    base_url: String,
+}
+
+/// What came back for a sent request
+#[cfg(feature = "http-client")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// Until the whole body was read
+    pub elapsed: std::time::Duration,
+    pub body_len: usize,
+}
+
+#[cfg(feature = "http-client")]
+impl HttpSender {
+    /// Requests go to `base_url` plus their path, e.g. `http://localhost:8080`
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        if !(base_url.starts_with("http://") || base_url.starts_with("https://")) {
+            return Err(Error::InvalidParameter(format!(
+                "base URL {:?} must start with http:// or https://",
+                base_url
+            )));
+        }
+        let client = reqwest::blocking::Client::builder()
+            .timeout(std::time::Duration::from_secs(30))
+            .build()
+            .map_err(client_error)?;
+        Ok(HttpSender {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Send `request` and read the whole response. Error statuses are still responses, only
+    /// failing to connect or a timeout is an `Err`.
+    pub fn send(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .map_err(|_| Error::InvalidParameter(format!("method {:?}", request.method)))?;
+        let mut builder = self
+            .client
+            .request(method, format!("{}{}", self.base_url, request.uri()));
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder
+                .header("Content-Type", "application/json")
+                .body(body.clone());
+        }
+        let start = std::time::Instant::now();
+        let response = builder.send().map_err(client_error)?;
+        let status = response.status().as_u16();
+        let body_len = response.bytes().map_err(client_error)?.len();
+        Ok(HttpResponse {
+            status,
+            elapsed: start.elapsed(),
+            body_len,
+        })
+    }
+}
+
+/// Connection problems are I/O problems as far as our `Error` is concerned
+#[cfg(feature = "http-client")]
+fn client_error(err: reqwest::Error) -> Error {
+    Error::Io(std::io::Error::other(err))
+}
+
+/// Replace every `{id}` and `{slug}` in `template`
+fn fill_template<R>(template: &str, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let mut path = template.to_string();
+    while let Some(at) = path.find("{id}") {
+        path.replace_range(at..at + 4, &rng.gen_range(1..100_000u32).to_string());
+    }
+    while let Some(at) = path.find("{slug}") {
+        path.replace_range(at..at + 6, &slug(rng));
+    }
+    path
+}
+
+/// A random flat JSON object with string, number and boolean values. The keys and strings
+/// are alphanumeric so nothing needs escaping.
+fn json_object<R>(max_fields: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let fields = (0..rng.gen_range(0..=max_fields))
+        .map(|i| {
+            let value = match rng.gen_range(0..3) {
+                0 => format!("\"{}\"", token(rng, 10)),
+                1 => rng.gen_range(-1000..1000).to_string(),
+                _ => rng.gen::<bool>().to_string(),
+            };
+            // Suffix with the index so keys are unique within the object
+            format!("\"{}{}\":{}", slug(rng), i, value)
+        })
+        .collect::<Vec<_>>();
+    format!("{{{}}}", fields.join(","))
+}
+
+fn token<R>(rng: &mut R, len: usize) -> String
+where
+    R: Rng + ?Sized,
+{
+    (0..len).map(|_| rng.sample(Alphanumeric) as char).collect()
+}
+
+fn slug<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    (0..rng.gen_range(3..8))
+        .map(|_| rng.gen_range(b'a'..=b'z') as char)
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_fills_path_templates() {
+        let mut rng = StdRng::seed_from_u64(31);
+        let gen = HttpRequestGen::new(&["/users/{id}/posts/{slug}"]).unwrap();
+
+        for _ in 0..50 {
+            let request = gen.request(&mut rng);
+            let segments = request.path.split('/').collect::<Vec<_>>();
+
+            assert_eq!(segments[1], "users");
+            assert!(segments[2].parse::<u32>().is_ok());
+            assert!(!request.path.contains('{'));
+            // Only methods with a body get one
+            assert_eq!(
+                request.body.is_some(),
+                matches!(request.method.as_str(), "POST" | "PUT" | "PATCH")
+            );
+        }
+    }
+
+    #[test]
+    fn it_renders_http1_with_content_length() {
+        let request = HttpRequest {
+            method: "POST".into(),
+            path: "/a".into(),
+            query: vec![("q".into(), "1".into())],
+            headers: vec![],
+            body: Some("{}".into()),
+        };
+
+        assert_eq!(
+            request.to_http1("example.com"),
+            "POST /a?q=1 HTTP/1.1\r\nHost: example.com\r\n\
+             Content-Type: application/json\r\nContent-Length: 2\r\n\r\n{}"
+        );
+    }
+
+    #[cfg(feature = "http-client")]
+    #[test]
+    fn it_sends_requests() {
+        use std::io::{Read, Write};
+
+        // A one-shot server on a free port that answers 204 and hands back what it read
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0; 1024];
+            while !received.ends_with(b"{}") {
+                let n = stream.read(&mut buf).unwrap();
+                received.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+            String::from_utf8(received).unwrap()
+        });
+
+        let request = HttpRequest {
+            method: "POST".into(),
+            path: "/a".into(),
+            query: vec![("q".into(), "1".into())],
+            headers: vec![("X-Test".into(), "yes".into())],
+            body: Some("{}".into()),
+        };
+        let sender = HttpSender::new(&format!("http://127.0.0.1:{}/", port)).unwrap();
+        let response = sender.send(&request).unwrap();
+        assert_eq!(response.status, 204);
+        assert_eq!(response.body_len, 0);
+
+        let received = server.join().unwrap();
+        assert!(
+            received.starts_with("POST /a?q=1 HTTP/1.1\r\n"),
+            "{}",
+            received
+        );
+        assert!(received.to_lowercase().contains("x-test: yes\r\n"));
+        assert!(HttpSender::new("localhost").is_err());
+    }
+}
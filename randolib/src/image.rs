@@ -0,0 +1,429 @@
+//! Random images for testing thumbnailers, upload handlers and vision pipelines: pure noise,
+//! gradients, and flat shapes on a background, at any size and in PNG, BMP or PPM.
+//!
+//! The encoders are deliberately minimal and dependency free. PNGs use "stored"
+//! (uncompressed) deflate blocks, which every decoder has to accept, so files are about as
+//! big as the raw pixels. That also makes sizes predictable, which helps when testing upload
+//! limits.
+
+use crate::{
+    checksum::{adler32, crc32},
+    probability,
+};
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{io::Write, path::Path, str::FromStr};
+
+/// What to draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// Every pixel an independent random color, the worst case for compression
+    Noise,
+    /// A linear blend between two random colors at a random angle
+    Gradient,
+    /// Rectangles and ellipses in flat colors on a flat background, with clear edges for
+    /// detectors to find
+    Shapes,
+}
+
+impl Pattern {
+    pub const ALL: [Pattern; 3] = [Pattern::Noise, Pattern::Gradient, Pattern::Shapes];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Noise => "noise",
+            Pattern::Gradient => "gradient",
+            Pattern::Shapes => "shapes",
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Pattern::ALL
+            .into_iter()
+            .find(|pattern| pattern.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown image pattern {:?}", s)))
+    }
+}
+
+/// How to encode an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    /// 24-bit Windows bitmap
+    Bmp,
+    /// Binary PPM (`P6`), the simplest format there is and handy for eyeballing in a hex dump
+    Ppm,
+}
+
+impl ImageFormat {
+    pub const ALL: [ImageFormat; 3] = [ImageFormat::Png, ImageFormat::Bmp, ImageFormat::Ppm];
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Ppm => "image/x-portable-pixmap",
+        }
+    }
+
+    /// The format a file name's extension asks for
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        extension.to_ascii_lowercase().parse()
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ImageFormat::ALL
+            .into_iter()
+            .find(|format| format.extension() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown image format {:?}", s)))
+    }
+}
+
+/// An 8-bit RGB image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row from the top left
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Image {
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        // Writing to a `Vec` can't fail
+        self.write_to(format, &mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn write_to<W>(&self, format: ImageFormat, out: &mut W) -> std::io::Result<()>
+    where
+        W: Write,
+    {
+        match format {
+            ImageFormat::Png => self.write_png(out),
+            ImageFormat::Bmp => self.write_bmp(out),
+            ImageFormat::Ppm => self.write_ppm(out),
+        }
+    }
+
+    /// Writes the image to `path` in the format its extension names
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let format = ImageFormat::from_path(path)?;
+        std::fs::write(path, self.encode(format))?;
+        Ok(())
+    }
+
+    fn write_ppm<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(self.pixels.as_flattened())
+    }
+
+    fn write_bmp<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        // Rows are padded to a multiple of four bytes
+        let row_len = (self.width as usize * 3).next_multiple_of(4);
+        let data_len = (row_len * self.height as usize) as u32;
+        // File header, then a `BITMAPINFOHEADER`, all little-endian
+        out.write_all(b"BM")?;
+        out.write_all(&(14 + 40 + data_len).to_le_bytes())?;
+        out.write_all(&[0; 4])?;
+        out.write_all(&(14u32 + 40).to_le_bytes())?;
+        out.write_all(&40u32.to_le_bytes())?;
+        out.write_all(&(self.width as i32).to_le_bytes())?;
+        out.write_all(&(self.height as i32).to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&24u16.to_le_bytes())?;
+        // No compression, then the data size, 72 DPI both ways and no palette
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(&data_len.to_le_bytes())?;
+        out.write_all(&2835u32.to_le_bytes())?;
+        out.write_all(&2835u32.to_le_bytes())?;
+        out.write_all(&[0; 8])?;
+        // Bottom row first, and blue, green, red
+        let mut row = vec![0; row_len];
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let [r, g, b] = self.pixel(x, y);
+                row[x as usize * 3..x as usize * 3 + 3].copy_from_slice(&[b, g, r]);
+            }
+            out.write_all(&row)?;
+        }
+        Ok(())
+    }
+
+    fn write_png<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, truecolor, then the only defined compression and filter methods
+        // and no interlacing
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        png_chunk(out, b"IHDR", &header)?;
+
+        // Each row starts with its filter type, 0 for none
+        let row_len = self.width as usize * 3;
+        let mut raw = Vec::with_capacity((row_len + 1) * self.height as usize);
+        for row in self.pixels.chunks(self.width.max(1) as usize) {
+            raw.push(0);
+            raw.extend_from_slice(row.as_flattened());
+        }
+        png_chunk(out, b"IDAT", &zlib_stored(&raw))?;
+        png_chunk(out, b"IEND", &[])
+    }
+}
+
+/// A PNG chunk: length, type, data and a CRC of the type and data
+fn png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(&[kind.as_slice(), data].concat());
+    out.write_all(&crc.to_be_bytes())
+}
+
+/// A zlib stream of uncompressed deflate blocks, each at most 65535 bytes
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32KiB window, no preset dictionary, and a check value making the header
+    // a multiple of 31
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        // Even nothing needs one final block
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Images bigger than this many pixels are almost certainly a typo
+const MAX_PIXELS: u64 = 1 << 26;
+
+/// Generates images of one size and pattern
+pub struct ImageGen {
+    width: u32,
+    height: u32,
+    pattern: Pattern,
+    noise: f64,
+}
+
+impl ImageGen {
+    /// Shapes, with no added noise
+    pub fn new(width: u32, height: u32) -> Result<Self, Error> {
+        if width == 0 || height == 0 || width as u64 * height as u64 > MAX_PIXELS {
+            return Err(Error::InvalidParameter(format!(
+                "image size {}x{} must be non-empty and at most {} pixels",
+                width, height, MAX_PIXELS
+            )));
+        }
+        Ok(ImageGen {
+            width,
+            height,
+            pattern: Pattern::Shapes,
+            noise: 0.0,
+        })
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// How far each channel can be pushed away from the pattern, as a share of the full
+    /// range, e.g. to make gradients look like sensor output
+    pub fn noise(mut self, amount: f64) -> Self {
+        self.noise = probability(amount);
+        self
+    }
+
+    pub fn image<R>(&self, rng: &mut R) -> Image
+    where
+        R: Rng + ?Sized,
+    {
+        let mut pixels = match self.pattern {
+            Pattern::Noise => (0..self.width as u64 * self.height as u64)
+                .map(|_| rng.gen())
+                .collect(),
+            Pattern::Gradient => self.gradient(rng),
+            Pattern::Shapes => self.shapes(rng),
+        };
+        let spread = (self.noise * 255.0).round() as i16;
+        if spread > 0 {
+            for channel in pixels.as_flattened_mut() {
+                let shifted = *channel as i16 + rng.gen_range(-spread..=spread);
+                *channel = shifted.clamp(0, 255) as u8;
+            }
+        }
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    fn gradient<R>(&self, rng: &mut R) -> Vec<[u8; 3]>
+    where
+        R: Rng + ?Sized,
+    {
+        let (from, to): ([u8; 3], [u8; 3]) = (rng.gen(), rng.gen());
+        let [dx, dy] = crate::geometry::unit_circle(rng);
+        // Project the corners onto the direction to scale positions to 0..=1
+        let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .map(|(x, y)| x * (self.width - 1) as f64 * dx + y * (self.height - 1) as f64 * dy);
+        let min = corners.into_iter().fold(f64::INFINITY, f64::min);
+        let max = corners.into_iter().fold(f64::NEG_INFINITY, f64::max);
+        let span = (max - min).max(f64::EPSILON);
+
+        let mut pixels = Vec::with_capacity((self.width * self.height) as usize);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let t = ((x as f64 * dx + y as f64 * dy) - min) / span;
+                pixels.push(std::array::from_fn(|c| {
+                    (from[c] as f64 + (to[c] as f64 - from[c] as f64) * t).round() as u8
+                }));
+            }
+        }
+        pixels
+    }
+
+    fn shapes<R>(&self, rng: &mut R) -> Vec<[u8; 3]>
+    where
+        R: Rng + ?Sized,
+    {
+        let (width, height) = (self.width as f64, self.height as f64);
+        let mut pixels = vec![rng.gen::<[u8; 3]>(); (self.width * self.height) as usize];
+        for _ in 0..rng.gen_range(1..=8) {
+            let color = rng.gen::<[u8; 3]>();
+            // A center anywhere in the image and half-sizes up to a third of it
+            let (cx, cy) = (rng.gen_range(0.0..width), rng.gen_range(0.0..height));
+            let rx = rng.gen_range(0.5..=(width / 3.0).max(0.5));
+            let ry = rng.gen_range(0.5..=(height / 3.0).max(0.5));
+            let ellipse = rng.gen_bool(0.5);
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    // Test the pixel's center
+                    let (ux, uy) = ((x as f64 + 0.5 - cx) / rx, (y as f64 + 0.5 - cy) / ry);
+                    let inside = if ellipse {
+                        ux * ux + uy * uy <= 1.0
+                    } else {
+                        ux.abs() <= 1.0 && uy.abs() <= 1.0
+                    };
+                    if inside {
+                        pixels[(y * self.width + x) as usize] = color;
+                    }
+                }
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mime::sniff;
+
+    #[test]
+    fn it_draws_each_pattern() {
+        let mut rng = StdRng::seed_from_u64(271);
+        for pattern in Pattern::ALL {
+            let gen = ImageGen::new(40, 30).unwrap().pattern(pattern);
+            let image = gen.image(&mut rng);
+            assert_eq!(image.pixels.len(), 40 * 30);
+            assert_eq!(pattern.name().parse::<Pattern>().unwrap(), pattern);
+        }
+        // A gradient with no noise changes slowly between neighbours
+        let gradient = ImageGen::new(64, 64)
+            .unwrap()
+            .pattern(Pattern::Gradient)
+            .image(&mut rng);
+        for pair in gradient.pixels.chunks(64).flat_map(|row| row.windows(2)) {
+            assert!(
+                (0..3).all(|c| pair[0][c].abs_diff(pair[1][c]) <= 5),
+                "{:?}",
+                pair
+            );
+        }
+        assert!(ImageGen::new(0, 10).is_err());
+        assert!(ImageGen::new(1 << 14, 1 << 14).is_err());
+    }
+
+    #[test]
+    fn it_encodes_each_format() {
+        let image = ImageGen::new(7, 5)
+            .unwrap()
+            .noise(0.2)
+            .image(&mut StdRng::seed_from_u64(272));
+
+        let png = image.encode(ImageFormat::Png);
+        assert_eq!(sniff(&png).unwrap().mime, ImageFormat::Png.mime());
+        // IHDR's CRC, and the zlib trailer checks the filtered rows
+        assert_eq!(&png[29..33], crc32(&png[12..29]).to_be_bytes());
+        let rows = image
+            .pixels
+            .chunks(7)
+            .flat_map(|row| [&[0][..], row.as_flattened()].concat())
+            .collect::<Vec<_>>();
+        let idat = &png[33 + 8..png.len() - 12 - 4];
+        assert_eq!(&idat[2 + 5..idat.len() - 4], rows);
+        assert_eq!(&idat[idat.len() - 4..], adler32(&rows).to_be_bytes());
+
+        let bmp = image.encode(ImageFormat::Bmp);
+        assert_eq!(sniff(&bmp).unwrap().mime, ImageFormat::Bmp.mime());
+        // 7 pixels take 21 bytes, padded to 24
+        assert_eq!(bmp.len(), 54 + 24 * 5);
+        let [r, g, b] = image.pixel(0, 4);
+        assert_eq!(bmp[54..57], [b, g, r]);
+
+        let ppm = image.encode(ImageFormat::Ppm);
+        let header = b"P6\n7 5\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(&ppm[header.len()..], image.pixels.as_flattened());
+    }
+
+    #[test]
+    fn it_picks_the_format_from_the_extension() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("a/b.PNG")).unwrap(),
+            ImageFormat::Png
+        );
+        assert!(ImageFormat::from_path(Path::new("a/b.gif")).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let mut rng = StdRng::seed_from_u64(2712);
+        let noisy = ImageGen::new(8, 8).unwrap().noise(f64::NAN);
+        assert_eq!(noisy.image(&mut rng).pixels.len(), 64);
+    }
+}
@@ -0,0 +1,396 @@
+//! Random interleavings of concurrent operations, for cheap model testing without a full
+//! model checker: describe each thread as a list of operations, draw schedules, and replay
+//! them against the code under test one operation at a time.
+//!
+//! Each thread's own order is always kept. On top of that, per-operation weights bias which
+//! thread runs next, and `before` constraints rule out orders that can't happen (e.g. a
+//! message can't be received before it's sent).
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use somelib::error::Error;
+use std::{
+    any::Any,
+    collections::HashSet,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+
+/// One entry of a schedule: run operation `index` of `thread`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Step {
+    pub thread: usize,
+    pub index: usize,
+}
+
+/// Threads of operations to interleave
+pub struct Interleaver<T> {
+    /// `(op, weight)` per thread, in program order
+    threads: Vec<Vec<(T, f64)>>,
+    /// `(first, then)`: `then` may only run after `first`
+    constraints: Vec<(Step, Step)>,
+}
+
+impl<T> Interleaver<T> {
+    pub fn new() -> Self {
+        Interleaver {
+            threads: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Add a thread whose operations all weigh the same
+    pub fn thread(mut self, ops: Vec<T>) -> Self {
+        self.threads
+            .push(ops.into_iter().map(|op| (op, 1.0)).collect());
+        self
+    }
+
+    /// Add a thread with a weight per operation. When several threads could run next, each
+    /// is picked in proportion to the weight of its next operation, so a heavy operation
+    /// tends to happen early and a light one late.
+    pub fn weighted_thread(mut self, ops: Vec<(T, f64)>) -> Result<Self, Error> {
+        if ops.iter().any(|(_, w)| !w.is_finite() || *w <= 0.0) {
+            return Err(Error::InvalidParameter(
+                "operation weights must be positive and finite".into(),
+            ));
+        }
+        self.threads.push(ops);
+        Ok(self)
+    }
+
+    /// Require `first` to run before `then`, e.g. across threads
+    pub fn before(mut self, first: Step, then: Step) -> Result<Self, Error> {
+        for step in [first, then] {
+            if self.get(step).is_none() {
+                return Err(Error::InvalidParameter(format!(
+                    "no operation {} in thread {}",
+                    step.index, step.thread
+                )));
+            }
+        }
+        self.constraints.push((first, then));
+        Ok(self)
+    }
+
+    /// The operation a step refers to
+    pub fn get(&self, step: Step) -> Option<&T> {
+        self.threads
+            .get(step.thread)?
+            .get(step.index)
+            .map(|(op, _)| op)
+    }
+
+    /// Total operations across all threads, i.e. the length of every schedule
+    pub fn len(&self) -> usize {
+        self.threads.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// A random schedule. Fails if the constraints contradict each other (or a thread's own
+    /// order), since then no schedule exists.
+    pub fn schedule<R>(&self, rng: &mut R) -> Result<Vec<Step>, Error>
+    where
+        R: Rng + ?Sized,
+    {
+        // `next[t]` is the index of thread `t`'s next operation
+        let mut next = vec![0; self.threads.len()];
+        let mut schedule = Vec::with_capacity(self.len());
+        while schedule.len() < self.len() {
+            let done = |step: Step| step.index < next[step.thread];
+            let ready = (0..self.threads.len())
+                .map(|thread| Step {
+                    thread,
+                    index: next[thread],
+                })
+                .filter(|step| step.index < self.threads[step.thread].len())
+                .filter(|step| {
+                    self.constraints
+                        .iter()
+                        .all(|(first, then)| then != step || done(*first))
+                })
+                .collect::<Vec<_>>();
+            let step = *ready
+                .choose_weighted(rng, |step| self.threads[step.thread][step.index].1)
+                .map_err(|_| {
+                    Error::InvalidParameter("the ordering constraints form a cycle".into())
+                })?;
+            next[step.thread] += 1;
+            schedule.push(step);
+        }
+        Ok(schedule)
+    }
+
+    /// The schedule for `seed`, the same on every run and platform, so a schedule that
+    /// exposed a bug can be replayed from its seed alone
+    pub fn schedule_from_seed(&self, seed: u64) -> Result<Vec<Step>, Error> {
+        self.schedule(&mut ChaCha20Rng::seed_from_u64(seed))
+    }
+
+    /// The operations of a schedule, in order
+    pub fn ops<'a>(&'a self, schedule: &'a [Step]) -> impl Iterator<Item = &'a T> + 'a {
+        schedule.iter().filter_map(|step| self.get(*step))
+    }
+}
+
+impl<T> Default for Interleaver<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs a check against many random schedules, a poor man's model checker: no guarantee
+/// of covering every order like loom, but any failure comes with a seed that replays it
+/// through `Interleaver::schedule_from_seed`.
+pub struct Explorer {
+    seed: u64,
+    budget: usize,
+    stop_at_first: bool,
+}
+
+/// A schedule the check rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// Replays with `Interleaver::schedule_from_seed`
+    pub seed: u64,
+    pub schedule: Vec<Step>,
+    pub message: String,
+}
+
+/// What an exploration covered and found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// Schedules checked, repeats included
+    pub runs: usize,
+    /// Distinct schedules among them. Far below `runs` means the space is small and
+    /// probably fully covered.
+    pub distinct: usize,
+    pub failures: Vec<Failure>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} schedules failed ({} distinct)",
+            self.failures.len(),
+            self.runs,
+            self.distinct
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  seed {}: {}", failure.seed, failure.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl Explorer {
+    /// 100 schedules derived from `seed`, stopping at the first failure
+    pub fn new(seed: u64) -> Self {
+        Explorer {
+            seed,
+            budget: 100,
+            stop_at_first: true,
+        }
+    }
+
+    /// How many schedules to try
+    pub fn budget(mut self, budget: usize) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Keep going after a failure, to collect every failing seed in the budget
+    pub fn all_failures(mut self) -> Self {
+        self.stop_at_first = false;
+        self
+    }
+
+    /// Draw schedules and run `check` on each. A check fails by returning `Err` or by
+    /// panicking, so plain `assert!`s work inside it.
+    pub fn run<T, F>(&self, interleaver: &Interleaver<T>, mut check: F) -> Result<Report, Error>
+    where
+        F: FnMut(&Interleaver<T>, &[Step]) -> Result<(), String>,
+    {
+        // Each schedule gets its own seed so it can be replayed without the others
+        let mut seeds = ChaCha20Rng::seed_from_u64(self.seed);
+        let mut seen = HashSet::new();
+        let mut report = Report {
+            runs: 0,
+            distinct: 0,
+            failures: Vec::new(),
+        };
+        for _ in 0..self.budget {
+            let seed = seeds.next_u64();
+            let schedule = interleaver.schedule_from_seed(seed)?;
+            report.runs += 1;
+            seen.insert(schedule.clone());
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| check(interleaver, &schedule)))
+                .unwrap_or_else(|payload| Err(panic_message(payload)));
+            if let Err(message) = outcome {
+                report.failures.push(Failure {
+                    seed,
+                    schedule,
+                    message,
+                });
+                if self.stop_at_first {
+                    break;
+                }
+            }
+        }
+        report.distinct = seen.len();
+        Ok(report)
+    }
+
+    /// `run` as a test assertion: panics with the failing seeds, if any
+    pub fn check<T, F>(&self, interleaver: &Interleaver<T>, check: F)
+    where
+        F: FnMut(&Interleaver<T>, &[Step]) -> Result<(), String>,
+    {
+        let report = self
+            .run(interleaver, check)
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert!(report.failures.is_empty(), "{}", report);
+    }
+}
+
+/// The text of a panic, which is a `&str` or `String` for `panic!` with a message
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "panicked".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(thread: usize, index: usize) -> Step {
+        Step { thread, index }
+    }
+
+    #[test]
+    fn it_keeps_program_order_and_constraints() {
+        let interleaver = Interleaver::new()
+            .thread(vec!["a0", "a1", "a2"])
+            .thread(vec!["b0", "b1"])
+            .thread(vec!["c0"])
+            // `b1` receives what `a1` sends
+            .before(step(0, 1), step(1, 1))
+            .unwrap();
+
+        let mut seen = HashSet::new();
+        for seed in 0..300 {
+            let schedule = interleaver.schedule_from_seed(seed).unwrap();
+            assert_eq!(schedule.len(), 6);
+            let position = |s: Step| schedule.iter().position(|x| *x == s).unwrap();
+            assert!(position(step(0, 0)) < position(step(0, 1)));
+            assert!(position(step(0, 1)) < position(step(0, 2)));
+            assert!(position(step(0, 1)) < position(step(1, 1)));
+            seen.insert(interleaver.ops(&schedule).copied().collect::<Vec<_>>());
+        }
+        // 6! / (3! 2! 1!) = 60 orders keep program order; the constraint rules out some
+        assert!(seen.len() > 30 && seen.len() < 60, "{}", seen.len());
+        assert_eq!(
+            interleaver.schedule_from_seed(7).unwrap(),
+            interleaver.schedule_from_seed(7).unwrap()
+        );
+    }
+
+    #[test]
+    fn it_weights_operations() {
+        let interleaver = Interleaver::new()
+            .weighted_thread(vec![("urgent", 50.0)])
+            .unwrap()
+            .thread(vec!["x"; 5]);
+        let mut rng = StdRng::seed_from_u64(255);
+
+        let first = (0..200)
+            .filter(|_| interleaver.schedule(&mut rng).unwrap()[0].thread == 0)
+            .count();
+        assert!(first > 180, "{}", first);
+    }
+
+    #[test]
+    fn it_rejects_impossible_orders() {
+        let cycle = Interleaver::new()
+            .thread(vec![1, 2])
+            .thread(vec![3, 4])
+            .before(step(0, 1), step(1, 0))
+            .unwrap()
+            .before(step(1, 1), step(0, 0))
+            .unwrap();
+        assert!(cycle.schedule_from_seed(0).is_err());
+        assert!(Interleaver::<u8>::new()
+            .before(step(0, 0), step(1, 0))
+            .is_err());
+        assert!(Interleaver::new()
+            .weighted_thread(vec![('a', 0.0)])
+            .is_err());
+    }
+
+    /// Two threads doing an unsynchronized read-modify-write on a counter
+    fn lost_update(interleaver: &Interleaver<&str>, schedule: &[Step]) -> Result<(), String> {
+        let mut counter = 0;
+        let mut local = [0; 2];
+        for step in schedule {
+            match *interleaver.get(*step).unwrap() {
+                "read" => local[step.thread] = counter,
+                _ => counter = local[step.thread] + 1,
+            }
+        }
+        if counter == 2 {
+            Ok(())
+        } else {
+            Err(format!("lost an update, counter is {}", counter))
+        }
+    }
+
+    #[test]
+    fn it_finds_and_replays_races() {
+        let racy = Interleaver::new()
+            .thread(vec!["read", "write"])
+            .thread(vec!["read", "write"]);
+
+        let report = Explorer::new(256)
+            .budget(50)
+            .all_failures()
+            .run(&racy, lost_update)
+            .unwrap();
+        assert_eq!(report.runs, 50);
+        // 4! / (2! 2!) = 6 orders, 4 of them racy
+        assert_eq!(report.distinct, 6);
+        assert!(report.failures.len() > 15);
+
+        let failure = &report.failures[0];
+        let replayed = racy.schedule_from_seed(failure.seed).unwrap();
+        assert_eq!(replayed, failure.schedule);
+        assert!(lost_update(&racy, &replayed).is_err());
+
+        // With a lock, modelled as "the other thread can't read until this one wrote"
+        let locked = racy.before(step(0, 1), step(1, 0)).unwrap();
+        Explorer::new(256).check(&locked, lost_update);
+    }
+
+    #[test]
+    fn it_reports_panics() {
+        let interleaver = Interleaver::new().thread(vec![1]).thread(vec![2]);
+        let report = Explorer::new(0)
+            .run(&interleaver, |_, schedule| {
+                assert_eq!(schedule[0].thread, 0, "thread 1 went first");
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(report.failures.len(), 1);
+        assert!(report.failures[0].message.contains("thread 1 went first"));
+        assert!(report.to_string().starts_with("1 of "));
+    }
+}
@@ -0,0 +1,390 @@
+//! Random JSON Web Tokens for testing auth middleware: varied claim sets, HS256 signatures
+//! with a key you provide, and tokens with one known defect (expired, wrong audience,
+//! `"alg": "none"`, a bad signature, ...) that a correct verifier has to reject.
+
+use crate::{fake, json_string, probability};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use rand::prelude::*;
+use sha2::Sha256;
+use somelib::error::Error;
+
+/// The subset of JSON values claims use here
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Bool(bool),
+    Strs(Vec<String>),
+}
+
+impl Value {
+    fn to_json(&self) -> String {
+        match self {
+            Value::Str(s) => json_string(s),
+            Value::Int(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            Value::Strs(items) => {
+                let items = items.iter().map(|s| json_string(s)).collect::<Vec<_>>();
+                format!("[{}]", items.join(","))
+            }
+        }
+    }
+}
+
+/// A claim set, in the order it's serialized
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Claims {
+    pub entries: Vec<(String, Value)>,
+}
+
+impl Claims {
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.entries.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+
+    fn set(&mut self, name: &str, value: Value) {
+        match self.entries.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.entries.push((name.to_string(), value)),
+        }
+    }
+
+    fn remove(&mut self, name: &str) {
+        self.entries.retain(|(n, _)| n != name);
+    }
+
+    pub fn to_json(&self) -> String {
+        let fields = self
+            .entries
+            .iter()
+            .map(|(name, value)| format!("{}:{}", json_string(name), value.to_json()))
+            .collect::<Vec<_>>();
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+/// What's wrong with a token that should be rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Defect {
+    /// `exp` is in the past
+    Expired,
+    /// `nbf` is in the future
+    NotYetValid,
+    /// No `exp` at all, which most policies require
+    MissingExpiry,
+    WrongIssuer,
+    WrongAudience,
+    /// `"alg": "none"` in some capitalization, with no signature: the classic bypass
+    AlgNone,
+    /// Signed with another key, or with a signature that was tampered with
+    BadSignature,
+}
+
+impl Defect {
+    pub const ALL: [Defect; 7] = [
+        Defect::Expired,
+        Defect::NotYetValid,
+        Defect::MissingExpiry,
+        Defect::WrongIssuer,
+        Defect::WrongAudience,
+        Defect::AlgNone,
+        Defect::BadSignature,
+    ];
+}
+
+/// A generated token and what went into it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Jwt {
+    /// `header.payload.signature`
+    pub token: String,
+    pub header: String,
+    pub claims: Claims,
+    /// `None` for a token a correct verifier accepts
+    pub defect: Option<Defect>,
+}
+
+/// The HS256 signature of `header.payload` (both already base64url encoded)
+pub fn hs256(signing_input: &str, key: &[u8]) -> String {
+    // HMAC accepts keys of any length, so this can't fail
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("any key length works");
+    mac.update(signing_input.as_bytes());
+    URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// Generates `Jwt`s for one issuer, audience and key, as a service would see them
+pub struct JwtGen {
+    key: Vec<u8>,
+    issuer: String,
+    audience: String,
+    now: i64,
+    lifetime: i64,
+    defect_rate: f64,
+    defects: Vec<Defect>,
+    max_extra_claims: usize,
+}
+
+impl JwtGen {
+    /// Tokens for `audience` from `issuer`, valid for an hour around `now` (Unix seconds),
+    /// with a 1 in 5 chance of a defect
+    pub fn new(key: &[u8], issuer: &str, audience: &str, now: i64) -> Self {
+        JwtGen {
+            key: key.to_vec(),
+            issuer: issuer.to_string(),
+            audience: audience.to_string(),
+            now,
+            lifetime: 3600,
+            defect_rate: 0.2,
+            defects: Defect::ALL.to_vec(),
+            max_extra_claims: 4,
+        }
+    }
+
+    /// How long tokens are valid, in seconds
+    pub fn lifetime(mut self, secs: i64) -> Result<Self, Error> {
+        if secs <= 0 {
+            return Err(Error::InvalidParameter("lifetime must be positive".into()));
+        }
+        self.lifetime = secs;
+        Ok(self)
+    }
+
+    pub fn defect_rate(mut self, rate: f64) -> Self {
+        self.defect_rate = probability(rate);
+        self
+    }
+
+    /// Only inject these defects
+    pub fn defects(mut self, defects: &[Defect]) -> Result<Self, Error> {
+        if defects.is_empty() {
+            return Err(Error::InvalidParameter("no defects to inject".into()));
+        }
+        self.defects = defects.to_vec();
+        Ok(self)
+    }
+
+    /// Up to `max` application claims (roles, scopes, email, ...) on top of the registered
+    /// ones
+    pub fn max_extra_claims(mut self, max: usize) -> Self {
+        self.max_extra_claims = max;
+        self
+    }
+
+    /// A claim set that passes every check
+    pub fn claims<R>(&self, rng: &mut R) -> Claims
+    where
+        R: Rng + ?Sized,
+    {
+        let mut claims = Claims::default();
+        claims.set("iss", Value::Str(self.issuer.clone()));
+        claims.set(
+            "sub",
+            Value::Str(format!("user-{}", rng.gen_range(1..100_000))),
+        );
+        // Both forms are valid: a string, or an array that contains the audience
+        let audience = if rng.gen() {
+            Value::Str(self.audience.clone())
+        } else {
+            let mut audiences = vec![self.audience.clone(), "other-service".into()];
+            audiences.shuffle(rng);
+            Value::Strs(audiences)
+        };
+        claims.set("aud", audience);
+        let issued = self.now - rng.gen_range(0..self.lifetime);
+        claims.set("iat", Value::Int(issued));
+        if rng.gen() {
+            claims.set("nbf", Value::Int(issued));
+        }
+        claims.set("exp", Value::Int(issued + self.lifetime));
+        if rng.gen() {
+            let jti = (0..16)
+                .map(|_| format!("{:02x}", rng.gen::<u8>()))
+                .collect();
+            claims.set("jti", Value::Str(jti));
+        }
+
+        let mut extras = [
+            ("scope", Value::Str("read write".into())),
+            ("roles", Value::Strs(vec!["admin".into(), "user".into()])),
+            ("email", Value::Str(fake::email(rng))),
+            ("email_verified", Value::Bool(rng.gen())),
+            ("name", Value::Str("Zoë \"Z\" O'Brien\n".into())),
+            ("tenant_id", Value::Int(rng.gen_range(1..=i64::MAX))),
+            ("groups", Value::Strs(Vec::new())),
+        ];
+        extras.shuffle(rng);
+        let count = rng.gen_range(0..=self.max_extra_claims.min(extras.len()));
+        for (name, value) in extras.into_iter().take(count) {
+            claims.set(name, value);
+        }
+        claims
+    }
+
+    pub fn token<R>(&self, rng: &mut R) -> Jwt
+    where
+        R: Rng + ?Sized,
+    {
+        let mut claims = self.claims(rng);
+        let defect = rng
+            .gen_bool(self.defect_rate)
+            .then(|| *self.defects.choose(rng).unwrap());
+        match defect {
+            Some(Defect::Expired) => {
+                let expired = self.now - rng.gen_range(1..86_400);
+                claims.set("iat", Value::Int(expired - self.lifetime));
+                claims.remove("nbf");
+                claims.set("exp", Value::Int(expired));
+            }
+            Some(Defect::NotYetValid) => {
+                claims.set("nbf", Value::Int(self.now + rng.gen_range(60..3600)))
+            }
+            Some(Defect::MissingExpiry) => claims.remove("exp"),
+            Some(Defect::WrongIssuer) => {
+                claims.set("iss", Value::Str(format!("{}.evil.example", self.issuer)))
+            }
+            Some(Defect::WrongAudience) => {
+                claims.set("aud", Value::Str(format!("not-{}", self.audience)))
+            }
+            _ => {}
+        }
+
+        let header = if defect == Some(Defect::AlgNone) {
+            let alg = ["none", "None", "NONE", "nOnE"].choose(rng).unwrap();
+            format!("{{\"alg\":\"{}\",\"typ\":\"JWT\"}}", alg)
+        } else if rng.gen() {
+            "{\"alg\":\"HS256\",\"typ\":\"JWT\"}".to_string()
+        } else {
+            // `kid` and no `typ` are both common
+            format!(
+                "{{\"alg\":\"HS256\",\"kid\":\"key-{}\"}}",
+                rng.gen_range(1..5)
+            )
+        };
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(&header),
+            URL_SAFE_NO_PAD.encode(claims.to_json())
+        );
+        let token = match defect {
+            // No signature but the trailing dot kept, as the spec's unsecured JWTs have it
+            Some(Defect::AlgNone) => format!("{}.", signing_input),
+            Some(Defect::BadSignature) => {
+                let signature = match rng.gen_range(0..3) {
+                    0 => hs256(&signing_input, b"not the key"),
+                    1 => {
+                        // Flip a bit in the middle of the decoded signature
+                        let good = hs256(&signing_input, &self.key);
+                        let mut bytes = URL_SAFE_NO_PAD.decode(good).unwrap();
+                        bytes[16] ^= 1 << rng.gen_range(0..8);
+                        URL_SAFE_NO_PAD.encode(bytes)
+                    }
+                    _ => hs256(&signing_input, &self.key)[..20].to_string(),
+                };
+                format!("{}.{}", signing_input, signature)
+            }
+            _ => format!("{}.{}", signing_input, hs256(&signing_input, &self.key)),
+        };
+        Jwt {
+            token,
+            header,
+            claims,
+            defect,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"secret key for tests";
+    const NOW: i64 = 1_700_000_000;
+
+    /// What a minimal verifier checks, in order
+    fn accepts(token: &str, claims: &Claims) -> bool {
+        let Some((signing_input, signature)) = token.rsplit_once('.') else {
+            return false;
+        };
+        let header = String::from_utf8(
+            URL_SAFE_NO_PAD
+                .decode(signing_input.split('.').next().unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        let int = |name| match claims.get(name) {
+            Some(Value::Int(n)) => Some(*n),
+            _ => None,
+        };
+        let audience_ok = match claims.get("aud") {
+            Some(Value::Str(aud)) => aud == "api",
+            Some(Value::Strs(auds)) => auds.iter().any(|a| a == "api"),
+            _ => false,
+        };
+        header.contains("\"alg\":\"HS256\"")
+            && hs256(signing_input, KEY) == signature
+            && int("exp").is_some_and(|exp| exp > NOW)
+            && int("nbf").is_none_or(|nbf| nbf <= NOW)
+            && claims.get("iss") == Some(&Value::Str("https://auth.example".into()))
+            && audience_ok
+    }
+
+    #[test]
+    fn it_generates_tokens_with_known_defects() {
+        let mut rng = StdRng::seed_from_u64(264);
+        let gen = JwtGen::new(KEY, "https://auth.example", "api", NOW).defect_rate(0.5);
+
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..500 {
+            let jwt = gen.token(&mut rng);
+            assert_eq!(
+                accepts(&jwt.token, &jwt.claims),
+                jwt.defect.is_none(),
+                "{:?}",
+                jwt
+            );
+            seen.insert(jwt.defect);
+
+            // The payload is the claims as JSON
+            let payload = jwt.token.split('.').nth(1).unwrap();
+            let json = String::from_utf8(URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap();
+            assert_eq!(json, jwt.claims.to_json());
+            if jwt.defect == Some(Defect::AlgNone) {
+                assert!(jwt.token.ends_with('.'));
+            }
+        }
+        assert_eq!(seen.len(), Defect::ALL.len() + 1);
+    }
+
+    #[test]
+    fn it_writes_json() {
+        let claims = Claims {
+            entries: vec![
+                ("name".into(), Value::Str("a \"b\"\\\n".into())),
+                ("n".into(), Value::Int(-1)),
+                ("ok".into(), Value::Bool(true)),
+                ("roles".into(), Value::Strs(vec!["x".into(), "y".into()])),
+            ],
+        };
+        assert_eq!(
+            claims.to_json(),
+            r#"{"name":"a \"b\"\\\u000a","n":-1,"ok":true,"roles":["x","y"]}"#
+        );
+
+        let gen = JwtGen::new(KEY, "iss", "aud", NOW)
+            .defects(&[Defect::Expired])
+            .unwrap();
+        let jwt = gen.defect_rate(1.0).token(&mut StdRng::seed_from_u64(264));
+        assert_eq!(jwt.defect, Some(Defect::Expired));
+        assert!(JwtGen::new(KEY, "iss", "aud", NOW).defects(&[]).is_err());
+        assert!(JwtGen::new(KEY, "iss", "aud", NOW).lifetime(0).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = JwtGen::new(KEY, "https://auth.example", "api", NOW).defect_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(265);
+        for _ in 0..20 {
+            assert!(gen.token(&mut rng).defect.is_none());
+        }
+    }
+}
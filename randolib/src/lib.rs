@@ -1,9 +1,139 @@
 /// Some libraries will expose a prelude module that's meant to be used with a wildcard.
 /// This is a convention to allow you to use the important bits easily. Generally you should
 /// not use wildcards in other cases. Favor explicit use.
-use rand::{distributions::Standard, prelude::*};
-use somelib::{error::Error, my_result::MyResult};
-use std::{cmp::PartialEq, fmt::Debug, marker::PhantomData};
+use rand::{
+    distributions::{uniform::SampleUniform, Standard, WeightedIndex},
+    prelude::*,
+};
+use rand_chacha::ChaCha20Rng;
+use rand_distr::{num_traits::Float, Normal, StandardNormal};
+use sha2::{Digest, Sha256};
+use somelib::{error::Error, my_option::MyOption, my_result::MyResult};
+use std::{
+    cmp::PartialEq,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{Debug, Write as _},
+    hash::Hash,
+    marker::PhantomData,
+    ops::{Range, RangeInclusive},
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// Export our child modules
+pub mod anonymize;
+pub mod archive;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "bdd")]
+pub mod bdd;
+pub mod cache;
+pub mod chaos;
+pub mod checksum;
+pub mod clickstream;
+pub mod clock;
+pub mod config;
+pub mod config_fuzz;
+pub mod copula;
+pub mod corrupt;
+pub mod csp;
+pub mod csv;
+pub mod dag;
+pub mod dice;
+pub mod distributions;
+pub mod expr;
+pub mod fake;
+pub mod file_tree;
+pub mod fleet;
+pub mod fuzzy;
+#[cfg(feature = "geo-data")]
+pub mod geo;
+pub mod geojson;
+pub mod geometry;
+pub mod global;
+pub mod http;
+#[cfg(feature = "image")]
+pub mod image;
+pub mod interleave;
+#[cfg(feature = "jwt")]
+pub mod jwt;
+pub mod locale;
+pub mod logs;
+pub mod markov;
+pub mod maze;
+pub mod mime;
+pub mod net;
+pub mod noise;
+pub mod order;
+pub mod packing;
+pub mod path_gen;
+pub mod picker;
+pub mod privacy;
+pub mod property;
+#[cfg(feature = "prost")]
+pub mod protobuf;
+pub mod quasi;
+pub mod rate_limit;
+pub mod regex_gen;
+pub mod repro;
+pub mod scenario;
+pub mod seed;
+pub mod semver;
+pub mod sink;
+pub mod sql;
+#[cfg(feature = "futures")]
+pub mod stream;
+pub mod strings;
+pub mod text;
+pub mod timeseries;
+pub mod traffic;
+pub mod unicode;
+pub mod url;
+pub mod variance;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(any(feature = "axum", feature = "actix"))]
+pub mod web;
+pub mod workload;
+
+/// Re-export the default generator so it's reachable as `randolib::global()`
+pub use global::{capture_repro, global, install_panic_hook, with_seed_scope, GlobalRng};
+/// Property tests, see `property`
+pub use randolib_macros::rando_test;
+pub use repro::from_repro;
+
+/// Lets `#[rando_test]`'s expansion, which names `::randolib`, work inside this crate too
+extern crate self as randolib;
+
+/// The generator `Rando*` types use unless they're given one. It holds nothing itself and
+/// draws from `thread_rng()` on whichever thread calls it, so unlike `ThreadRng` it is
+/// zero-sized, `Send` and `Sync`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRng;
+
+impl RngCore for DefaultRng {
+    fn next_u32(&mut self) -> u32 {
+        thread_rng().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        thread_rng().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        thread_rng().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        thread_rng().try_fill_bytes(dest)
+    }
+}
+
+/// How many draws `get_random_vec_where` makes per item before giving up. Random `char`s
+/// cover all of Unicode, so only about one in 12,000 is printable ASCII; this leaves room
+/// for predicates like that.
+pub const MAX_DRAWS_PER_ITEM: usize = 100_000;
 
 /// We want `get_random_vec` to be shared amongst all of our `Rando*` types
 pub trait GetRandoStuff<T>
@@ -12,32 +142,148 @@ where
     Standard: Distribution<T>,
     T: Debug,
 {
+    /// Run `f` with the generator to draw from. The default is a thread-local RNG, i.e., one
+    /// that is `!Send` and `!Sync`; types that carry their own generator override this.
+    ///
+    /// `dyn RngCore` rather than a generic parameter keeps this method simple to override,
+    /// and every `Rng` method still works on it.
+    fn with_rng<U>(&self, f: impl FnOnce(&mut dyn RngCore) -> U) -> U {
+        f(&mut thread_rng())
+    }
+
     /// This is a declaration and default implementation
     fn get_random_vec(&self, len: usize) -> Vec<T> {
-        // Create a thread-local RNG, i.e., one that is `!Send` and `!Sync`
-        let mut rng = thread_rng();
-        // Here is an example of Rust as a functional language
-        // Gen our (max) 32 elements of `T`
-        rng.gen::<[T; 32]>()
-            // `iter` returns an iterator of &T, `into_iter` returns (owned) T
-            .into_iter()
-            // take returns `len` or max items
-            .take(len)
-            // take an iterator and return a collection
-            .collect::<Vec<_>>()
+        self.with_rng(|rng| {
+            // Here is an example of Rust as a functional language
+            // Gen our (max) 32 elements of `T`
+            rng.gen::<[T; 32]>()
+                // `iter` returns an iterator of &T, `into_iter` returns (owned) T
+                .into_iter()
+                // take returns `len` or max items
+                .take(len)
+                // take an iterator and return a collection
+                .collect::<Vec<_>>()
+        })
+    }
+
+    /// `len` random `T`s that all satisfy `pred`, e.g. nonzero `u32`s with
+    /// `rando.get_random_vec_where(10, |n| *n != 0)`. Rejected draws are thrown away and
+    /// drawn again, with no 32 item limit.
+    ///
+    /// So an impossible or nearly impossible predicate can't loop forever, this gives up
+    /// with `Error::Exhausted` after `MAX_DRAWS_PER_ITEM` draws for each item wanted.
+    fn get_random_vec_where(
+        &self,
+        len: usize,
+        pred: impl Fn(&T) -> bool,
+    ) -> MyResult<Vec<T>, Error> {
+        self.with_rng(|rng| {
+            let mut items = Vec::with_capacity(len);
+            let mut draws = 0;
+            let max_draws = len.saturating_mul(MAX_DRAWS_PER_ITEM);
+            while items.len() < len {
+                if draws == max_draws {
+                    return MyResult::Err(Error::Exhausted);
+                }
+                draws += 1;
+                let item = rng.gen::<T>();
+                if pred(&item) {
+                    items.push(item);
+                }
+            }
+            MyResult::Ok(items)
+        })
+    }
+
+    /// A random `T` in `range`, e.g. `rando.get_random_in_range(1..101)` for 1 to 100.
+    /// The `where` clause here only applies to this method, so `Rando*`s of types without
+    /// a uniform range distribution (like `bool`) can still use the rest.
+    ///
+    /// Panics if `range` is empty, like `Rng::gen_range`.
+    fn get_random_in_range(&self, range: Range<T>) -> T
+    where
+        T: SampleUniform + PartialOrd,
+    {
+        self.with_rng(|rng| rng.gen_range(range))
+    }
+
+    /// `len` random `T`s in `range`. Unlike `get_random_vec` there's no 32 item limit, the
+    /// items are drawn one at a time rather than as an array.
+    fn get_random_vec_in_range(&self, range: Range<T>, len: usize) -> Vec<T>
+    where
+        T: SampleUniform + PartialOrd + Clone,
+    {
+        self.with_rng(|rng| {
+            // `gen_range` consumes its range, and `Range` isn't `Copy`, so clone it per item
+            (0..len).map(|_| rng.gen_range(range.clone())).collect()
+        })
+    }
+
+    /// Put `items` in a random order, in place. Every order is equally likely
+    /// (Fisher-Yates).
+    fn shuffle(&self, items: &mut [T]) {
+        self.with_rng(|rng| items.shuffle(rng))
+    }
+
+    /// `n` distinct items from `items`, in random order. "Distinct" is by position, so
+    /// equal items can both be picked. Asking for more than `items.len()` returns them all,
+    /// shuffled, like `take` in `get_random_vec`.
+    fn sample_without_replacement(&self, items: &[T], n: usize) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.with_rng(|rng| items.choose_multiple(rng, n).cloned().collect())
+    }
+}
+
+/// Lock a `Rando*`'s generator. A panic while drawing can't leave a generator in a broken
+/// state, so a poisoned lock is safe to keep using.
+fn lock<R>(rng: &Mutex<R>) -> MutexGuard<'_, R> {
+    rng.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A probability or share for a builder setter: clamped to `0..=1`, with NaN as 0.
+/// `clamp` alone passes NaN through, and `gen_bool` panics on it much later.
+pub(crate) fn probability(rate: f64) -> f64 {
+    if rate.is_nan() {
+        0.0
+    } else {
+        rate.clamp(0.0, 1.0)
+    }
+}
+
+/// `s` as a JSON string literal, for generators that build JSON by hand
+pub fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            // Control characters have to be escaped; everything else can go through as is
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
     }
+    out.push('"');
+    out
 }
 
 /// The struct is our main composite type. We can have structs with fields, unit structs
 /// and tuple structs `struct RandoX(RandoA)` often called `newtype`s
-pub struct RandoA<T>
+///
+/// `R = DefaultRng` is a default type parameter: plain `RandoA<char>` means
+/// `RandoA<char, DefaultRng>`, so code written before `R` existed still compiles.
+pub struct RandoA<T, R = DefaultRng>
 where
     Standard: Distribution<T>,
     T: Debug,
 {
-    // Since RandoA has no members, nothing takes the type `T`. PhantomData is the Rust
-    // workaround. It's a zero-sized item that 'carries' our generic param.
+    // Since RandoA has no members of type `T`, nothing takes the type `T`. PhantomData is
+    // the Rust workaround. It's a zero-sized item that 'carries' our generic param.
     phantom_data: PhantomData<T>,
+    /// Drawing needs `&mut R` but `get_random_item` only has `&self`. A `Mutex` gives us
+    /// that "interior mutability"; `RefCell` would too, but would make `RandoA` `!Sync`.
+    rng: Mutex<R>,
 }
 
 impl<T> RandoA<T>
@@ -54,40 +300,97 @@ where
     pub fn new() -> Self {
         // Implicit return. Note the lack of the `return` keyword and no `;` at the end of the line
         // You can also use `return RandoA { .. };`. Favor the former.
+        RandoA::with_rng(DefaultRng)
+    }
+}
+
+impl<T> RandoA<T, ChaCha20Rng>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    /// Deterministic: the same seed always gives the same sequence. `ChaCha20Rng` because
+    /// its output is stable across `rand` releases, unlike `StdRng`'s.
+    pub fn from_seed(seed: u64) -> Self {
+        RandoA::with_rng(ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T, R> RandoA<T, R>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+    R: RngCore,
+{
+    /// Use any generator, e.g. a seeded `StdRng` or one shared with other code
+    pub fn with_rng(rng: R) -> Self {
         RandoA {
             phantom_data: PhantomData,
+            rng: Mutex::new(rng),
         }
     }
 
     /// Get a single random `T`
     pub fn get_random_item(&self) -> T {
-        // Create a thread-local RNG, i.e., one that is `!Send` and `!Sync`
-        let mut rng = thread_rng();
-        rng.gen::<T>()
+        lock(&self.rng).gen::<T>()
+    }
+}
+
+/// Required by clippy's `new_without_default`: a type with an argument-free `new` should
+/// also work with `Default::default()` and `..Default::default()`
+impl<T> Default for RandoA<T>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-/// Since `GetRandoStuff` has a default impl for all of its members, we can use
-/// an empty impl here to make `Rando*` `GetRandoStuff`
-impl<T> GetRandoStuff<T> for RandoA<T>
+/// `GetRandoStuff` has a default impl for all of its members, but we override `with_rng`
+/// so `get_random_vec` draws from our own generator
+impl<T, R> GetRandoStuff<T> for RandoA<T, R>
 where
     Standard: Distribution<T>,
     T: Debug,
+    R: RngCore,
 {
+    fn with_rng<U>(&self, f: impl FnOnce(&mut dyn RngCore) -> U) -> U {
+        f(&mut *lock(&self.rng))
+    }
+}
+
+/// What `RandoB` does when a new item repeats a recent one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RepeatPolicy {
+    /// Return `Error::ConsecutiveRandom` or `Error::RecentRepeat`
+    #[default]
+    Error,
+    /// Quietly draw again, giving up with `Error::RetriesExhausted` after `max_attempts`
+    /// draws in all
+    Retry { max_attempts: usize },
+    /// Return repeats like any other item
+    Allow,
 }
 
 /// Here we're going to maintain state, storing the last random item produced
 /// so we can check for consecutive random values.
-pub struct RandoB<T>
+pub struct RandoB<T, R = DefaultRng>
 where
     Standard: Distribution<T>,
-    // We need `Clone` to copy `last_item`, `PartialEq` to compare it to our new item
-    // and `Debug` to satisfy the bound of `MyResult`
+    // We need `Clone` to keep a copy of each item, `PartialEq` to compare recent items to
+    // our new one and `Debug` to satisfy the bound of `MyResult`
     T: Clone + PartialEq + Debug,
 {
-    /// Since we won't have a last item until we run `get_random_item`, this
-    /// has to be an Option::None when we create our struct
-    last_item: Option<T>,
+    /// The last `lookback` items, newest at the back. A `VecDeque` is a ring buffer: pushing
+    /// at one end and popping at the other doesn't shift the items in between.
+    recent: VecDeque<T>,
+    /// How many past items a new one must differ from
+    lookback: usize,
+    policy: RepeatPolicy,
+    /// A `Mutex` for the same reason as `RandoA`'s: `get_random_vec` only has `&self`
+    rng: Mutex<R>,
 }
 
 impl<T> RandoB<T>
@@ -97,38 +400,977 @@ where
 {
     /// Our ctor
     pub fn new() -> Self {
-        // Start with None
-        RandoB { last_item: None }
+        RandoB::with_rng(DefaultRng)
+    }
+
+    /// Reject any item equal to one of the last `n`, not just the previous one
+    pub fn with_lookback(n: usize) -> Self {
+        RandoB::new().lookback(n)
+    }
+
+    /// Handle repeats with `policy`, e.g. `RepeatPolicy::Retry { max_attempts: 10 }` to
+    /// re-roll instead of erroring
+    pub fn with_policy(policy: RepeatPolicy) -> Self {
+        RandoB::new().policy(policy)
+    }
+}
+
+impl<T> RandoB<T, ChaCha20Rng>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+{
+    /// Deterministic, see `RandoA::from_seed`
+    pub fn from_seed(seed: u64) -> Self {
+        RandoB::with_rng(ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T, R> RandoB<T, R>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    pub fn with_rng(rng: R) -> Self {
+        // Start with nothing to compare against
+        RandoB {
+            recent: VecDeque::new(),
+            lookback: 1,
+            policy: RepeatPolicy::Error,
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// Compare each new item against the last `n` instead of only the previous one.
+    /// 0 turns the check off. The builder form works with any generator, e.g.
+    /// `RandoB::from_seed(7).lookback(3)`.
+    pub fn lookback(mut self, n: usize) -> Self {
+        self.lookback = n;
+        while self.recent.len() > n {
+            self.recent.pop_front();
+        }
+        self
+    }
+
+    /// The most recent item, `MyOption::None` before the first draw or with a lookback of 0
+    pub fn last_item(&self) -> MyOption<&T> {
+        self.recent.back().into()
+    }
+
+    /// What to do about repeats, `RepeatPolicy::Error` unless set
+    pub fn policy(mut self, policy: RepeatPolicy) -> Self {
+        self.policy = policy;
+        self
     }
 
-    /// Return a single random `T` or an error if `self.last_item` is the same as our new item
+    /// Return a single random `T`. With `RepeatPolicy::Error`, it's an error if the item
+    /// equals one of the last `lookback` items: `Error::ConsecutiveRandom` for the previous
+    /// item, carrying the item's `Debug` form, `Error::RecentRepeat` for one further back.
     /// Since we're mutating `self`, we need a mutable reference to it.
     pub fn get_random_item(&mut self) -> MyResult<T, Error> {
-        // Create a thread-local RNG, i.e., one that is `!Send` and `!Sync`
-        let mut rng = thread_rng();
-        let item = rng.gen::<T>();
-        if self.last_item.is_none() {
-            // This is an explicit return
-            return MyResult::Ok(item);
-        }
-        // Let's take the old item. We could then return it if necessary.
-        let last_item = self.last_item.take().unwrap();
-        self.last_item = Some(item.clone());
-        if last_item == item {
-            MyResult::Err(Error::ConsecutiveRandom)
-        } else {
-            MyResult::Ok(item)
+        // With `&mut self` we don't need the lock: `get_mut` proves no one else has it
+        let rng = self
+            .rng
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let attempts = match self.policy {
+            RepeatPolicy::Retry { max_attempts } => max_attempts.max(1),
+            RepeatPolicy::Error | RepeatPolicy::Allow => 1,
+        };
+        for _ in 0..attempts {
+            let item = rng.gen::<T>();
+            // Newest first, so the position is one less than how many draws back it was
+            let repeat = match self.policy {
+                RepeatPolicy::Allow => None,
+                _ => self.recent.iter().rev().position(|recent| *recent == item),
+            };
+            // A re-rolled draw was never handed out, so it doesn't count as history
+            if repeat.is_some() && matches!(self.policy, RepeatPolicy::Retry { .. }) {
+                continue;
+            }
+            // Otherwise every item counts as history, including rejected ones
+            if self.lookback > 0 {
+                if self.recent.len() == self.lookback {
+                    self.recent.pop_front();
+                }
+                self.recent.push_back(item.clone());
+            }
+            return match repeat {
+                None => MyResult::Ok(item),
+                Some(0) => MyResult::Err(Error::ConsecutiveRandom {
+                    value: format!("{:?}", item),
+                }),
+                Some(i) => MyResult::Err(Error::RecentRepeat { distance: i + 1 }),
+            };
         }
+        MyResult::Err(Error::RetriesExhausted { attempts })
+    }
+}
+
+impl<T> Default for RandoB<T>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+{
+    fn default() -> Self {
+        Self::new()
     }
 }
 
-/// Since GetRandoStuff has a default impl of all of its members, we can use
-/// an empty impl here to make `Rando*` `GetRandoStuff`
-impl<T> GetRandoStuff<T> for RandoB<T>
+/// Like `RandoA`, we override `with_rng` to use our own generator
+impl<T, R> GetRandoStuff<T> for RandoB<T, R>
 where
     Standard: Distribution<T>,
     T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    fn with_rng<U>(&self, f: impl FnOnce(&mut dyn RngCore) -> U) -> U {
+        f(&mut *lock(&self.rng))
+    }
+}
+
+/// An endless iterator over a `Rando*`'s items, so the usual adapters work:
+/// `rando.into_iter().take(10).collect::<Vec<_>>()`.
+///
+/// `S` is the source, owned or borrowed. Each source type gets its own `Iterator` impl,
+/// which is how `RandoA`'s iterator yields `T` while `RandoB`'s yields `MyResult<T, Error>`.
+pub struct RandoIter<S> {
+    source: S,
+}
+
+impl<T, R> RandoA<T, R>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+    R: RngCore,
+{
+    /// Iterate without giving up the `RandoA`
+    pub fn iter(&self) -> RandoIter<&Self> {
+        RandoIter { source: self }
+    }
+}
+
+/// `IntoIterator` is what `for item in rando` and `rando.into_iter()` use
+impl<T, R> IntoIterator for RandoA<T, R>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+    R: RngCore,
+{
+    type Item = T;
+    type IntoIter = RandoIter<Self>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        RandoIter { source: self }
+    }
+}
+
+impl<T, R> IntoIterator for &RandoA<T, R>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+    R: RngCore,
+{
+    type Item = T;
+    type IntoIter = RandoIter<Self>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, R> Iterator for RandoIter<RandoA<T, R>>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+    R: RngCore,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        Some(self.source.get_random_item())
+    }
+
+    /// Endless: at least `usize::MAX` items and no upper bound
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T, R> Iterator for RandoIter<&RandoA<T, R>>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+    R: RngCore,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        Some(self.source.get_random_item())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T, R> RandoB<T, R>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    /// Iterate without giving up the `RandoB`. `&mut` because each item updates the
+    /// last item seen.
+    pub fn iter(&mut self) -> RandoIter<&mut Self> {
+        RandoIter { source: self }
+    }
+}
+
+impl<T, R> IntoIterator for RandoB<T, R>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    type Item = MyResult<T, Error>;
+    type IntoIter = RandoIter<Self>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        RandoIter { source: self }
+    }
+}
+
+impl<T, R> IntoIterator for &mut RandoB<T, R>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    type Item = MyResult<T, Error>;
+    type IntoIter = RandoIter<Self>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Errors are items too, so the iterator keeps going after a repeat. Stop at the first
+/// one with `map_while` or `take_while`.
+impl<T, R> Iterator for RandoIter<RandoB<T, R>>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    type Item = MyResult<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.source.get_random_item())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+impl<T, R> Iterator for RandoIter<&mut RandoB<T, R>>
+where
+    Standard: Distribution<T>,
+    T: Clone + PartialEq + Debug,
+    R: RngCore,
+{
+    type Item = MyResult<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.source.get_random_item())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+/// Never repeats itself: every value is remembered, and `get_random_item` keeps drawing
+/// until it finds one it hasn't produced yet. Handy for unique ids in tests.
+///
+/// As the unseen values run out, finding one takes more and more draws, so after
+/// `max_attempts` draws in a row that were all repeats it gives up with `Error::Exhausted`.
+/// For a `u8` that's usually the 257th call, or with bad luck a few calls earlier.
+///
+/// There's no `GetRandoStuff` impl: its `get_random_vec` couldn't promise uniqueness.
+/// Use `get_unique_vec` instead.
+pub struct RandoC<T, R = DefaultRng>
+where
+    Standard: Distribution<T>,
+    // `Hash + Eq` so values can go in a `HashSet`
+    T: Clone + Hash + Eq + Debug,
+{
+    seen: HashSet<T>,
+    max_attempts: usize,
+    rng: R,
+}
+
+impl<T> RandoC<T>
+where
+    Standard: Distribution<T>,
+    T: Clone + Hash + Eq + Debug,
+{
+    pub fn new() -> Self {
+        RandoC::with_rng(DefaultRng)
+    }
+}
+
+impl<T> RandoC<T, ChaCha20Rng>
+where
+    Standard: Distribution<T>,
+    T: Clone + Hash + Eq + Debug,
+{
+    /// Deterministic, see `RandoA::from_seed`
+    pub fn from_seed(seed: u64) -> Self {
+        RandoC::with_rng(ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T, R> RandoC<T, R>
+where
+    Standard: Distribution<T>,
+    T: Clone + Hash + Eq + Debug,
+    R: RngCore,
+{
+    /// Every method here takes `&mut self`, so unlike `RandoA` the generator needs no
+    /// `Mutex`
+    pub fn with_rng(rng: R) -> Self {
+        RandoC {
+            seen: HashSet::new(),
+            max_attempts: 1000,
+            rng,
+        }
+    }
+
+    /// How many repeats in a row to tolerate before giving up, at least 1
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// A `T` this `RandoC` hasn't returned before
+    pub fn get_random_item(&mut self) -> MyResult<T, Error> {
+        for _ in 0..self.max_attempts {
+            let item = self.rng.gen::<T>();
+            // `insert` returns false if the value was already there
+            if self.seen.insert(item.clone()) {
+                return MyResult::Ok(item);
+            }
+        }
+        MyResult::Err(Error::Exhausted)
+    }
+
+    /// `len` values, all different from each other and from everything before
+    pub fn get_unique_vec(&mut self, len: usize) -> MyResult<Vec<T>, Error> {
+        let mut items = Vec::with_capacity(len);
+        for _ in 0..len {
+            match self.get_random_item() {
+                MyResult::Ok(item) => items.push(item),
+                MyResult::Err(e) => return MyResult::Err(e),
+            }
+        }
+        MyResult::Ok(items)
+    }
+
+    /// How many values have been handed out
+    pub fn seen_count(&self) -> usize {
+        self.seen.len()
+    }
+
+    /// Forget every value, so they can all come up again
+    pub fn reset(&mut self) {
+        self.seen.clear();
+    }
+}
+
+impl<T> Default for RandoC<T>
+where
+    Standard: Distribution<T>,
+    T: Clone + Hash + Eq + Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Picks items in proportion to their weights: with `[("common", 9.0), ("rare", 1.0)]`,
+/// "rare" comes up about one time in ten. Loot tables, A/B test buckets, ...
+pub struct RandoWeighted<T, R = DefaultRng>
+where
+    T: Clone,
+{
+    items: Vec<T>,
+    /// `rand`'s precomputed cumulative weights, so each pick is a binary search
+    index: WeightedIndex<f64>,
+    rng: Mutex<R>,
+}
+
+impl<T> RandoWeighted<T>
+where
+    T: Clone,
+{
+    /// Fails with `Error::NoItems` for an empty slice, or `Error::InvalidWeight` for a
+    /// weight that's zero, negative, infinite or NaN
+    pub fn new(choices: &[(T, f64)]) -> Result<Self, Error> {
+        RandoWeighted::with_rng(choices, DefaultRng)
+    }
+}
+
+impl<T> RandoWeighted<T, ChaCha20Rng>
+where
+    T: Clone,
+{
+    /// Deterministic, see `RandoA::from_seed`
+    pub fn from_seed(choices: &[(T, f64)], seed: u64) -> Result<Self, Error> {
+        RandoWeighted::with_rng(choices, ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T, R> RandoWeighted<T, R>
+where
+    T: Clone,
+    R: RngCore,
+{
+    pub fn with_rng(choices: &[(T, f64)], rng: R) -> Result<Self, Error> {
+        if choices.is_empty() {
+            return Err(Error::NoItems);
+        }
+        // `WeightedIndex` accepts zero weights, but an item that can never come up is
+        // almost certainly a mistake in the table
+        if let Some((_, weight)) = choices.iter().find(|(_, w)| !w.is_finite() || *w <= 0.0) {
+            return Err(Error::InvalidWeight(*weight));
+        }
+        // Weights are all finite and positive, so this can only fail if their sum
+        // overflows to infinity
+        let index = WeightedIndex::new(choices.iter().map(|(_, w)| *w))
+            .map_err(|_| Error::InvalidWeight(f64::INFINITY))?;
+        Ok(RandoWeighted {
+            items: choices.iter().map(|(item, _)| item.clone()).collect(),
+            index,
+            rng: Mutex::new(rng),
+        })
+    }
+
+    /// One item, chosen by weight
+    pub fn get_random_item(&self) -> T {
+        let i = self.index.sample(&mut *lock(&self.rng));
+        self.items[i].clone()
+    }
+
+    /// `len` items, each chosen independently
+    pub fn get_random_vec(&self, len: usize) -> Vec<T> {
+        let mut rng = lock(&self.rng);
+        (0..len)
+            .map(|_| self.items[self.index.sample(&mut *rng)].clone())
+            .collect()
+    }
+}
+
+/// Draws `f64`s or `f32`s from a normal (Gaussian) distribution: most values near `mean`,
+/// about 68% within one `std_dev` of it and 95% within two. Measurement noise, heights,
+/// response times around a typical value, ...
+pub struct RandoNormal<T, R = DefaultRng>
+where
+    T: Float,
+    StandardNormal: Distribution<T>,
+{
+    normal: Normal<T>,
+    rng: Mutex<R>,
+}
+
+impl<T> RandoNormal<T>
+where
+    T: Float,
+    StandardNormal: Distribution<T>,
+{
+    /// Fails if `mean` isn't finite or `std_dev` is negative or not finite
+    pub fn new(mean: T, std_dev: T) -> Result<Self, Error> {
+        RandoNormal::with_rng(mean, std_dev, DefaultRng)
+    }
+}
+
+impl<T> RandoNormal<T, ChaCha20Rng>
+where
+    T: Float,
+    StandardNormal: Distribution<T>,
+{
+    /// Deterministic, see `RandoA::from_seed`
+    pub fn from_seed(mean: T, std_dev: T, seed: u64) -> Result<Self, Error> {
+        RandoNormal::with_rng(mean, std_dev, ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T, R> RandoNormal<T, R>
+where
+    T: Float,
+    StandardNormal: Distribution<T>,
+    R: RngCore,
+{
+    pub fn with_rng(mean: T, std_dev: T, rng: R) -> Result<Self, Error> {
+        if !mean.is_finite() {
+            return Err(Error::InvalidParameter("mean must be finite".into()));
+        }
+        // `Normal::new` accepts a negative deviation (it mirrors the distribution)
+        if !std_dev.is_finite() || std_dev < T::zero() {
+            return Err(Error::InvalidParameter(
+                "standard deviation must be finite and non-negative".into(),
+            ));
+        }
+        Ok(RandoNormal {
+            normal: Normal::new(mean, std_dev).unwrap(),
+            rng: Mutex::new(rng),
+        })
+    }
+
+    pub fn mean(&self) -> T {
+        self.normal.mean()
+    }
+
+    pub fn std_dev(&self) -> T {
+        self.normal.std_dev()
+    }
+
+    pub fn get_random_item(&self) -> T {
+        self.normal.sample(&mut *lock(&self.rng))
+    }
+}
+
+/// `get_random_vec` draws from the normal distribution, without `RandoA`'s 32 item limit.
+/// The range methods keep their default, uniform behaviour: clamping a bell curve to a
+/// range is a different distribution (see `distributions::Truncated`).
+impl<T, R> GetRandoStuff<T> for RandoNormal<T, R>
+where
+    T: Float + Debug,
+    Standard: Distribution<T>,
+    StandardNormal: Distribution<T>,
+    R: RngCore,
+{
+    fn with_rng<U>(&self, f: impl FnOnce(&mut dyn RngCore) -> U) -> U {
+        f(&mut *lock(&self.rng))
+    }
+
+    fn get_random_vec(&self, len: usize) -> Vec<T> {
+        let mut rng = lock(&self.rng);
+        (0..len).map(|_| self.normal.sample(&mut *rng)).collect()
+    }
+}
+
+/// Draws `Duration`s from a range: timeouts, delays, ages of cache entries, ...
+///
+/// Uniform sampling suits narrow ranges. For one spanning several orders of magnitude, say
+/// 1ms to 1 day, almost every uniform sample lands in the last few hours and the short end
+/// is never tested; `log_uniform` gives each power of ten an equal share instead.
+pub struct RandoDuration<R = DefaultRng> {
+    min: Duration,
+    max: Duration,
+    log_uniform: bool,
+    rng: Mutex<R>,
+}
+
+impl RandoDuration {
+    /// Fails if the range is empty
+    pub fn new(range: RangeInclusive<Duration>) -> Result<Self, Error> {
+        RandoDuration::with_rng(range, DefaultRng)
+    }
+}
+
+impl RandoDuration<ChaCha20Rng> {
+    /// Deterministic, see `RandoA::from_seed`
+    pub fn from_seed(range: RangeInclusive<Duration>, seed: u64) -> Result<Self, Error> {
+        RandoDuration::with_rng(range, ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<R> RandoDuration<R>
+where
+    R: RngCore,
+{
+    pub fn with_rng(range: RangeInclusive<Duration>, rng: R) -> Result<Self, Error> {
+        let (min, max) = range.into_inner();
+        if min > max {
+            return Err(Error::InvalidParameter(format!(
+                "duration range {:?}..={:?} is empty",
+                min, max
+            )));
+        }
+        Ok(RandoDuration {
+            min,
+            max,
+            log_uniform: false,
+            rng: Mutex::new(rng),
+        })
+    }
+
+    /// Sample the logarithm of the duration uniformly. Fails if the range starts at zero,
+    /// which is infinitely many orders of magnitude away from anything.
+    pub fn log_uniform(mut self) -> Result<Self, Error> {
+        if self.min.is_zero() {
+            return Err(Error::InvalidParameter(
+                "a log-uniform range can't start at zero".into(),
+            ));
+        }
+        self.log_uniform = true;
+        Ok(self)
+    }
+
+    pub fn get_random_item(&self) -> Duration {
+        self.sample(&mut *lock(&self.rng))
+    }
+
+    pub fn get_random_vec(&self, len: usize) -> Vec<Duration> {
+        let mut rng = lock(&self.rng);
+        (0..len).map(|_| self.sample(&mut *rng)).collect()
+    }
+
+    /// A random duration rendered with `humanize`, e.g. `"3h 12m"`
+    pub fn get_random_humanized(&self) -> String {
+        RandoDuration::<R>::humanize(self.get_random_item())
+    }
+
+    fn sample(&self, rng: &mut R) -> Duration {
+        if self.log_uniform {
+            let (low, high) = (self.min.as_secs_f64().ln(), self.max.as_secs_f64().ln());
+            let secs = rng.gen_range(low..=high).exp();
+            // `exp(ln(x))` can land a rounding error outside the range
+            Duration::from_secs_f64(secs).clamp(self.min, self.max)
+        } else {
+            let nanos = rng.gen_range(self.min.as_nanos()..=self.max.as_nanos());
+            Duration::new(
+                (nanos / 1_000_000_000) as u64,
+                (nanos % 1_000_000_000) as u32,
+            )
+        }
+    }
+
+    /// The two largest units of `duration`, truncated: `"3h 12m"`, `"45s"`, `"1s 500ms"`.
+    /// The second unit is left out when it's zero, so a whole number of hours is `"3h"`.
+    pub fn humanize(duration: Duration) -> String {
+        const UNITS: [(&str, u128); 7] = [
+            ("d", 86_400_000_000_000),
+            ("h", 3_600_000_000_000),
+            ("m", 60_000_000_000),
+            ("s", 1_000_000_000),
+            ("ms", 1_000_000),
+            ("µs", 1_000),
+            ("ns", 1),
+        ];
+        let nanos = duration.as_nanos();
+        let Some(first) = UNITS.iter().position(|(_, size)| nanos >= *size) else {
+            return "0s".into();
+        };
+        let (unit, size) = UNITS[first];
+        let mut out = format!("{}{}", nanos / size, unit);
+        if let Some((next_unit, next_size)) = UNITS.get(first + 1) {
+            let next = nanos % size / next_size;
+            if next > 0 {
+                out.push_str(&format!(" {}{}", next, next_unit));
+            }
+        }
+        out
+    }
+}
+
+/// The kinds of identifier `RandoId` makes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdKind {
+    /// A random (version 4) UUID, e.g. `0b6cf2c6-6c31-4d5b-9a1e-35f3d0a0c1e2`
+    UuidV4,
+    /// A ULID: a millisecond timestamp then 80 random bits, in Crockford base32, so ids sort
+    /// by creation time, e.g. `01HZX3J4AKQ5WB9Q2V7S8T6M0R`
+    Ulid,
+    /// Nano ID style: `len` characters drawn uniformly from `alphabet`
+    NanoId { alphabet: Vec<char>, len: usize },
+}
+
+impl IdKind {
+    /// Nano ID's defaults: 21 characters of `A-Za-z0-9_-`, as much randomness as a UUID
+    pub fn nanoid() -> Self {
+        let alphabet = ('A'..='Z')
+            .chain('a'..='z')
+            .chain('0'..='9')
+            .chain(['_', '-']);
+        IdKind::NanoId {
+            alphabet: alphabet.collect(),
+            len: 21,
+        }
+    }
+
+    /// Fails if `alphabet` has fewer than two distinct characters, or `len` is zero
+    pub fn nanoid_with(alphabet: &str, len: usize) -> Result<Self, Error> {
+        let mut chars = Vec::new();
+        for c in alphabet.chars() {
+            if !chars.contains(&c) {
+                chars.push(c);
+            }
+        }
+        if chars.len() < 2 || len == 0 {
+            return Err(Error::InvalidParameter(format!(
+                "a nanoid needs 2 or more distinct characters and a length, got {:?} and {}",
+                alphabet, len
+            )));
+        }
+        Ok(IdKind::NanoId {
+            alphabet: chars,
+            len,
+        })
+    }
+}
+
+/// `uuid`, `ulid` or `nanoid` (with the default alphabet and length)
+impl FromStr for IdKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uuid" => Ok(IdKind::UuidV4),
+            "ulid" => Ok(IdKind::Ulid),
+            "nanoid" => Ok(IdKind::nanoid()),
+            _ => Err(Error::InvalidParameter(format!(
+                "unknown id kind {:?}, expected uuid, ulid or nanoid",
+                s
+            ))),
+        }
+    }
+}
+
+/// Generates identifiers of one kind: UUIDs, ULIDs or Nano IDs
+pub struct RandoId<R = DefaultRng> {
+    kind: IdKind,
+    rng: Mutex<R>,
+}
+
+impl RandoId {
+    pub fn new(kind: IdKind) -> Self {
+        RandoId::with_rng(kind, DefaultRng)
+    }
+}
+
+impl RandoId<ChaCha20Rng> {
+    /// Deterministic, see `RandoA::from_seed`. ULIDs still carry the current time, see
+    /// `get_ulid_at` for fully reproducible ones.
+    pub fn from_seed(kind: IdKind, seed: u64) -> Self {
+        RandoId::with_rng(kind, ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<R> RandoId<R>
+where
+    R: RngCore,
+{
+    pub fn with_rng(kind: IdKind, rng: R) -> Self {
+        RandoId {
+            kind,
+            rng: Mutex::new(rng),
+        }
+    }
+
+    pub fn kind(&self) -> &IdKind {
+        &self.kind
+    }
+
+    pub fn get_random_item(&self) -> String {
+        self.generate(&mut *lock(&self.rng))
+    }
+
+    pub fn get_random_vec(&self, len: usize) -> Vec<String> {
+        let mut rng = lock(&self.rng);
+        (0..len).map(|_| self.generate(&mut *rng)).collect()
+    }
+
+    /// A ULID for `unix_ms` milliseconds since the epoch, whatever this generator's kind
+    pub fn get_ulid_at(&self, unix_ms: u64) -> String {
+        ulid(unix_ms, &mut *lock(&self.rng))
+    }
+
+    fn generate(&self, rng: &mut R) -> String {
+        match &self.kind {
+            IdKind::UuidV4 => {
+                let mut bytes = rng.gen::<[u8; 16]>();
+                // The version in the high nibble of byte 6, the RFC 4122 variant (`10`) in
+                // the top bits of byte 8
+                bytes[6] = (bytes[6] & 0x0f) | 0x40;
+                bytes[8] = (bytes[8] & 0x3f) | 0x80;
+                let hex = bytes
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                )
+            }
+            IdKind::Ulid => {
+                // A clock before 1970 is broken enough that 0 will do
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap_or_default();
+                ulid(now.as_millis() as u64, rng)
+            }
+            IdKind::NanoId { alphabet, len } => (0..*len)
+                .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                .collect(),
+        }
+    }
+}
+
+/// 48 bits of timestamp and 80 of randomness, 5 bits per character from the top. 26
+/// characters hold 130 bits, so the first character only ever uses its low 3.
+fn ulid<R>(unix_ms: u64, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
 {
+    const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    let random = rng.gen::<u128>() & ((1 << 80) - 1);
+    let value = ((unix_ms as u128 & ((1 << 48) - 1)) << 80) | random;
+    (0..26)
+        .map(|i| CROCKFORD[((value >> (125 - 5 * i)) & 31) as usize] as char)
+        .collect()
+}
+
+/// A family of generators, one per key (a user id, a tenant, ..). Each key's generator is
+/// derived from the root seed and the key, so the same key always produces the same
+/// stream, even after a restart, without storing anything per key.
+///
+/// Child generators are memoized: asking for the same key again continues its stream
+/// rather than starting it over.
+pub struct KeyedRando<T>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    seed: u64,
+    children: HashMap<String, ChaCha20Rng>,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T> KeyedRando<T>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    pub fn new(seed: u64) -> Self {
+        KeyedRando {
+            seed,
+            children: HashMap::new(),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// The generator for `key`, created the first time it's needed
+    pub fn rng(&mut self, key: &str) -> &mut ChaCha20Rng {
+        // `entry` looks the key up once and lets us insert if it's missing. We can't use
+        // `or_insert_with(|| ..)` with `self.derive` because the closure would borrow
+        // `self` while `children` is already borrowed mutably, so copy the seed first.
+        let seed = self.seed;
+        self.children
+            .entry(key.to_string())
+            .or_insert_with(|| Self::derive(seed, key))
+    }
+
+    /// The derivation is part of the public contract: changing it changes every key's
+    /// stream. `ChaCha20Rng` is used because its output is stable across `rand` releases.
+    fn derive(seed: u64, key: &str) -> ChaCha20Rng {
+        let mut hash = Sha256::new();
+        hash.update(b"randolib/KeyedRando/v1");
+        hash.update(seed.to_le_bytes());
+        hash.update(key.as_bytes());
+        ChaCha20Rng::from_seed(hash.finalize().into())
+    }
+
+    /// The next random `T` from `key`'s stream
+    pub fn get_random_item(&mut self, key: &str) -> T {
+        self.rng(key).gen::<T>()
+    }
+
+    /// The next `len` random `T`s from `key`'s stream
+    pub fn get_random_vec(&mut self, key: &str, len: usize) -> Vec<T> {
+        let rng = self.rng(key);
+        (0..len).map(|_| rng.gen::<T>()).collect()
+    }
+
+    /// Drop `key`'s generator, so its stream starts over next time
+    pub fn reset(&mut self, key: &str) {
+        self.children.remove(key);
+    }
+}
+
+/// One seed for a whole pool of workers (rayon tasks, tokio tasks, threads) that still
+/// reproduces exactly.
+///
+/// Sharing one locked generator between workers is `Send` and `Sync`, but who draws next
+/// depends on scheduling, so the values each worker sees change from run to run. Keying
+/// generators by thread id has the same problem: ids and the work given to each thread
+/// aren't stable either. Instead each unit of work asks for `worker(i)` with an index that
+/// comes from the work itself (a chunk number, a task number, ...) and gets its own ChaCha
+/// stream: the same `i` always gets the same values, on whichever thread it runs.
+pub struct RandoPool<T>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    seed: u64,
+    /// Stream 0, for drawing through the pool itself
+    shared: Mutex<ChaCha20Rng>,
+    phantom_data: PhantomData<T>,
+}
+
+impl<T> RandoPool<T>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    pub fn new(seed: u64) -> Self {
+        RandoPool {
+            seed,
+            shared: Mutex::new(RandoPool::<T>::stream(seed, 0)),
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// ChaCha has 2^64 independent streams per seed, which makes deriving one per worker
+    /// free. Stream 0 is the pool's own, workers start at 1.
+    fn stream(seed: u64, stream: u64) -> ChaCha20Rng {
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        rng.set_stream(stream);
+        rng
+    }
+
+    /// The generator for unit of work `index`, independent of every other index's. There
+    /// are only 2^64 streams, so `u64::MAX` is an error: it would be stream 0, the pool's own.
+    pub fn worker(&self, index: u64) -> Result<RandoA<T, ChaCha20Rng>, Error> {
+        let stream = index.checked_add(1).ok_or_else(|| {
+            Error::InvalidParameter("worker index u64::MAX is the pool's own stream".into())
+        })?;
+        Ok(RandoA::with_rng(RandoPool::<T>::stream(self.seed, stream)))
+    }
+
+    /// A random `T` from the pool's shared stream. Callable from any thread, but only
+    /// reproducible when the calls happen in the same order; prefer `worker`.
+    pub fn get_random_item(&self) -> T {
+        lock(&self.shared).gen::<T>()
+    }
+}
+
+/// Like the other `Rando*`s, `get_random_vec` and friends draw from the shared stream
+impl<T> GetRandoStuff<T> for RandoPool<T>
+where
+    Standard: Distribution<T>,
+    T: Debug,
+{
+    fn with_rng<U>(&self, f: impl FnOnce(&mut dyn RngCore) -> U) -> U {
+        f(&mut *lock(&self.shared))
+    }
 }
 
 #[cfg(test)]
@@ -156,4 +1398,392 @@ mod tests {
         // It's impossible for this to error on the first call
         assert!(rand_item.is_ok());
     }
+
+    #[test]
+    fn it_rejects_recent_repeats_randob() {
+        // bools repeat all the time, so every kind of result shows up quickly
+        let mut rando = RandoB::<bool, _>::from_seed(260);
+        let mut items = Vec::new();
+        for _ in 0..50 {
+            let before = items.last().copied();
+            match rando.get_random_item() {
+                MyResult::Ok(item) => {
+                    assert_ne!(Some(item), before);
+                    items.push(item);
+                }
+                MyResult::Err(Error::ConsecutiveRandom { value }) => {
+                    assert_eq!(value, before.unwrap().to_string());
+                    items.push(before.unwrap())
+                }
+                MyResult::Err(e) => panic!("unexpected {:?}", e),
+            }
+        }
+        assert!(items.windows(2).any(|w| w[0] == w[1]));
+
+        // Rebuild what was drawn from the errors, which say how far back the repeat was
+        let mut rando = RandoB::<bool, _>::from_seed(260).lookback(2);
+        let mut draws = Vec::new();
+        let mut far_repeats = 0;
+        for _ in 0..50 {
+            let item = match rando.get_random_item() {
+                MyResult::Ok(item) => {
+                    let window = &draws[draws.len().saturating_sub(2)..];
+                    assert!(!window.contains(&item));
+                    item
+                }
+                MyResult::Err(Error::ConsecutiveRandom { .. }) => draws[draws.len() - 1],
+                MyResult::Err(Error::RecentRepeat { distance }) => {
+                    assert_eq!(distance, 2);
+                    far_repeats += 1;
+                    draws[draws.len() - distance]
+                }
+                MyResult::Err(e) => panic!("unexpected {:?}", e),
+            };
+            draws.push(item);
+        }
+        assert!(far_repeats > 0);
+
+        assert_eq!(rando.last_item(), MyOption::Some(draws.last().unwrap()));
+
+        let mut unchecked = RandoB::<bool>::with_lookback(0);
+        assert!((0..20).all(|_| unchecked.get_random_item().is_ok()));
+    }
+
+    #[test]
+    fn it_filters_random_vecs() {
+        let rando = RandoA::<char, _>::from_seed(274);
+        let printable = rando
+            .get_random_vec_where(20, |c| c.is_ascii_graphic())
+            .unwrap();
+        assert_eq!(printable.len(), 20);
+        assert!(printable.iter().all(char::is_ascii_graphic));
+
+        let nonzero = RandoB::<u8>::new().get_random_vec_where(50, |n| *n != 0);
+        assert!(nonzero.unwrap().iter().all(|n| *n != 0));
+        let never = RandoA::<u8>::new().get_random_vec_where(3, |_| false);
+        assert!(matches!(never, MyResult::Err(Error::Exhausted)));
+        assert!(rando.get_random_vec_where(0, |_| false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_follows_its_repeat_policy_randob() {
+        // Re-rolling a bool against the previous one can only alternate
+        let retry = RepeatPolicy::Retry { max_attempts: 64 };
+        let mut rando = RandoB::<bool, _>::from_seed(273).policy(retry);
+        let items = (0..50)
+            .map(|_| rando.get_random_item().unwrap())
+            .collect::<Vec<_>>();
+        assert!(items.windows(2).all(|w| w[0] != w[1]));
+
+        // With both bools in the last two, every draw is a repeat
+        let retry = RepeatPolicy::Retry { max_attempts: 5 };
+        let mut rando = RandoB::<bool, _>::from_seed(274).lookback(2).policy(retry);
+        assert!(rando.get_random_item().is_ok());
+        assert!(rando.get_random_item().is_ok());
+        assert!(matches!(
+            rando.get_random_item(),
+            MyResult::Err(Error::RetriesExhausted { attempts: 5 })
+        ));
+
+        let mut allow = RandoB::<bool>::with_policy(RepeatPolicy::Allow);
+        let items = (0..50)
+            .map(|_| allow.get_random_item().unwrap())
+            .collect::<Vec<_>>();
+        assert!(items.windows(2).any(|w| w[0] == w[1]));
+    }
+
+    #[test]
+    fn it_repeats_with_a_seed() {
+        let a = RandoA::<u32, _>::from_seed(251);
+        let again = RandoA::<u32, _>::from_seed(251);
+        assert_eq!(a.get_random_vec(5), again.get_random_vec(5));
+        assert_eq!(a.get_random_item(), again.get_random_item());
+
+        let mut b = RandoB::<u64, _>::from_seed(251);
+        let mut again = RandoB::<u64, _>::with_rng(ChaCha20Rng::seed_from_u64(251));
+        assert_eq!(b.get_random_vec(3), again.get_random_vec(3));
+        assert_eq!(
+            b.get_random_item().unwrap(),
+            again.get_random_item().unwrap()
+        );
+
+        // Any generator works, and the default one still isn't seeded
+        let c = RandoA::<u8, _>::with_rng(StdRng::seed_from_u64(1));
+        assert_eq!(c.get_random_vec(40).len(), 32);
+        assert_ne!(RandoA::<u128>::new().get_random_item(), 0);
+    }
+
+    #[test]
+    fn it_gens_within_a_range() {
+        let rando = RandoA::<u32, _>::from_seed(2552);
+        for _ in 0..100 {
+            assert!((1..101).contains(&rando.get_random_in_range(1..101)));
+        }
+
+        let floats = RandoB::<f64>::new().get_random_vec_in_range(-1.0..1.0, 100);
+        assert_eq!(floats.len(), 100);
+        assert!(floats.iter().all(|f| (-1.0..1.0).contains(f)));
+    }
+
+    #[test]
+    fn it_shuffles_and_samples() {
+        let rando = RandoA::<u8, _>::from_seed(258);
+        let mut items = (0..50).collect::<Vec<u8>>();
+        rando.shuffle(&mut items);
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+
+        let sample = rando.sample_without_replacement(&items, 10);
+        assert_eq!(sample.len(), 10);
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 10);
+        assert!(sample.iter().all(|i| items.contains(i)));
+        assert_eq!(rando.sample_without_replacement(&items[..3], 5).len(), 3);
+
+        // The default generator works too
+        let mut chars = vec!['a', 'b', 'c'];
+        RandoB::<char>::new().shuffle(&mut chars);
+        assert_eq!(chars.len(), 3);
+    }
+
+    #[test]
+    fn it_iterates_rando_types() {
+        let rando = RandoA::<u16, _>::from_seed(254);
+        let borrowed = rando.iter().take(10).collect::<Vec<_>>();
+        let owned = RandoA::<u16, _>::from_seed(254)
+            .into_iter()
+            .take(10)
+            .collect::<Vec<_>>();
+        assert_eq!(borrowed, owned);
+        // The borrowed iterator advanced the original
+        assert_ne!(rando.get_random_item(), owned[0]);
+
+        let mut rando = RandoB::<u32, _>::from_seed(254);
+        let mut again = RandoB::<u32, _>::from_seed(254);
+        for item in (&mut rando).into_iter().take(5) {
+            assert_eq!(item.unwrap(), again.get_random_item().unwrap());
+        }
+        assert_eq!(rando.iter().take(5).count(), 5);
+        assert_eq!(RandoB::<u8>::new().into_iter().take(3).count(), 3);
+    }
+
+    #[test]
+    fn it_never_repeats_randoc() {
+        let mut rando = RandoC::<u8, _>::from_seed(253);
+
+        // Every `u8` once, in some order
+        let mut all = Vec::new();
+        while let MyResult::Ok(item) = rando.get_random_item() {
+            all.push(item);
+        }
+        let unique = all.iter().collect::<HashSet<_>>();
+        assert_eq!(unique.len(), all.len());
+        // The last few take many draws to hit, but most are found
+        assert!(all.len() > 250, "{}", all.len());
+        assert!(matches!(
+            rando.get_random_item(),
+            MyResult::Err(Error::Exhausted)
+        ));
+
+        rando.reset();
+        assert_eq!(rando.get_unique_vec(10).unwrap().len(), 10);
+        assert_eq!(rando.seen_count(), 10);
+
+        let mut bools = RandoC::<bool>::new().max_attempts(50);
+        assert!(bools.get_unique_vec(3).is_err());
+    }
+
+    #[test]
+    fn it_picks_by_weight_randoweighted() {
+        let loot = RandoWeighted::from_seed(&[("common", 90.0), ("rare", 9.0), ("epic", 1.0)], 256)
+            .unwrap();
+        let drops = loot.get_random_vec(10_000);
+        let count = |name| drops.iter().filter(|d| **d == name).count();
+
+        assert!((8800..9200).contains(&count("common")));
+        assert!((750..1050).contains(&count("rare")));
+        assert!((50..150).contains(&count("epic")));
+
+        assert!(matches!(RandoWeighted::<u8>::new(&[]), Err(Error::NoItems)));
+        assert!(matches!(
+            RandoWeighted::new(&[('a', 1.0), ('b', 0.0)]),
+            Err(Error::InvalidWeight(w)) if w == 0.0
+        ));
+        assert!(RandoWeighted::new(&[('a', f64::NAN)]).is_err());
+        assert_eq!(
+            RandoWeighted::new(&[(7, 0.5)]).unwrap().get_random_item(),
+            7
+        );
+    }
+
+    #[test]
+    fn it_gens_a_bell_curve_randonormal() {
+        let rando = RandoNormal::from_seed(100.0, 15.0, 259).unwrap();
+        let samples = rando.get_random_vec(10_000);
+        assert_eq!(samples.len(), 10_000);
+
+        let mean = samples.iter().sum::<f64>() / 10_000.0;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / 9_999.0;
+        assert!((mean - 100.0).abs() < 0.5, "mean {}", mean);
+        assert!((var.sqrt() - 15.0).abs() < 0.5, "sd {}", var.sqrt());
+        let within = samples.iter().filter(|x| (*x - 100.0).abs() < 15.0).count();
+        assert!((6600..7000).contains(&within), "{}", within);
+
+        let single = RandoNormal::<f32>::new(0.0, 0.0).unwrap();
+        assert_eq!(single.get_random_item(), 0.0);
+        assert_eq!(single.std_dev(), 0.0);
+        assert!(RandoNormal::new(0.0, -1.0).is_err());
+        assert!(RandoNormal::new(f64::NAN, 1.0).is_err());
+    }
+
+    #[test]
+    fn it_gens_stable_streams_per_key_keyedrando() {
+        let mut rando = KeyedRando::<u64>::new(7);
+        let mut restarted = KeyedRando::<u64>::new(7);
+
+        let alice = rando.get_random_vec("alice", 3);
+        // Interleaving other keys doesn't disturb alice's stream
+        restarted.get_random_item("bob");
+        assert_eq!(restarted.get_random_vec("alice", 3), alice);
+        assert_ne!(rando.get_random_vec("bob", 3), alice);
+
+        // Memoized: the stream continues, until it's reset
+        assert_ne!(rando.get_random_item("alice"), alice[0]);
+        rando.reset("alice");
+        assert_eq!(rando.get_random_item("alice"), alice[0]);
+
+        // Lock the derivation, changing it would change every key's stream
+        assert_eq!(alice[0], 1788207897141200516);
+    }
+
+    #[test]
+    fn it_gens_durations_randoduration() {
+        let second = Duration::from_secs(1);
+        let rando = RandoDuration::from_seed(second..=second * 10, 268).unwrap();
+        let samples = rando.get_random_vec(1000);
+        assert!(samples.iter().all(|d| (second..=second * 10).contains(d)));
+        let mean = samples.iter().sum::<Duration>() / 1000;
+        assert!((mean.as_secs_f64() - 5.5).abs() < 0.3, "{:?}", mean);
+
+        // From 1ms to 1 day, a uniform sample is almost never under a minute, while a
+        // log-uniform one is 60% of the time: 11 of the range's 18.3 natural log units
+        let range = Duration::from_millis(1)..=Duration::from_secs(60 * 60 * 24);
+        let uniform = RandoDuration::from_seed(range.clone(), 268).unwrap();
+        let log = RandoDuration::from_seed(range, 268)
+            .unwrap()
+            .log_uniform()
+            .unwrap();
+        let minute = Duration::from_secs(60);
+        let short = |rando: &RandoDuration<ChaCha20Rng>| {
+            rando
+                .get_random_vec(1000)
+                .iter()
+                .filter(|d| **d < minute)
+                .count()
+        };
+        assert!(short(&uniform) < 10);
+        let log_short = short(&log);
+        assert!((540..660).contains(&log_short), "{}", log_short);
+
+        assert!(RandoDuration::new(second..=Duration::ZERO).is_err());
+        assert!(RandoDuration::new(Duration::ZERO..=second)
+            .unwrap()
+            .log_uniform()
+            .is_err());
+    }
+
+    #[test]
+    fn it_humanizes_durations_randoduration() {
+        let humanize = RandoDuration::<DefaultRng>::humanize;
+        assert_eq!(
+            humanize(Duration::from_secs(3 * 3600 + 12 * 60 + 5)),
+            "3h 12m"
+        );
+        assert_eq!(humanize(Duration::from_secs(2 * 86_400 + 3600)), "2d 1h");
+        assert_eq!(humanize(Duration::from_secs(7200)), "2h");
+        assert_eq!(humanize(Duration::from_millis(1500)), "1s 500ms");
+        assert_eq!(humanize(Duration::from_micros(250)), "250µs");
+        assert_eq!(humanize(Duration::ZERO), "0s");
+    }
+
+    #[test]
+    fn it_gens_uuids_randoid() {
+        let rando = RandoId::from_seed(IdKind::UuidV4, 269);
+        let ids = rando.get_random_vec(100);
+        for id in &ids {
+            let groups = id.split('-').map(str::len).collect::<Vec<_>>();
+            assert_eq!(groups, [8, 4, 4, 4, 12]);
+            assert_eq!(&id[14..15], "4");
+            assert!("89ab".contains(&id[19..20]), "{}", id);
+        }
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 100);
+    }
+
+    #[test]
+    fn it_gens_sortable_ulids_randoid() {
+        let rando = RandoId::from_seed(IdKind::Ulid, 269);
+        let earlier = rando.get_ulid_at(1_700_000_000_000);
+        let later = rando.get_ulid_at(1_700_000_000_001);
+        assert_eq!(earlier.len(), 26);
+        assert!(earlier < later);
+        // The timestamp is the first 10 characters
+        assert_eq!(&rando.get_ulid_at(0)[..10], "0000000000");
+        assert_eq!(&rando.get_ulid_at((1 << 48) - 1)[..10], "7ZZZZZZZZZ");
+        assert!(rando.get_random_item() > earlier);
+    }
+
+    #[test]
+    fn it_gens_nanoids_randoid() {
+        let default = RandoId::from_seed(IdKind::nanoid(), 269).get_random_item();
+        assert_eq!(default.len(), 21);
+
+        let kind = IdKind::nanoid_with("abcabc", 40).unwrap();
+        let id = RandoId::from_seed(kind, 269).get_random_item();
+        assert_eq!(id.len(), 40);
+        assert!(id.chars().all(|c| "abc".contains(c)));
+        assert!(IdKind::nanoid_with("aaa", 4).is_err());
+        assert!(IdKind::nanoid_with("ab", 0).is_err());
+        assert!("guid".parse::<IdKind>().is_err());
+    }
+
+    #[test]
+    fn it_reproduces_across_threads_randopool() {
+        let pool = RandoPool::<u64>::new(270);
+        // Four workers on four threads, then the same again
+        let run = || {
+            std::thread::scope(|scope| {
+                let handles = (0..4)
+                    .map(|i| {
+                        let pool = &pool;
+                        scope.spawn(move || pool.worker(i).unwrap().get_random_vec(8))
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let first = run();
+        assert_eq!(first, run());
+        // Each worker's stream is its own, and doesn't depend on the thread it ran on
+        assert_ne!(first[0], first[1]);
+        assert_eq!(first[2], pool.worker(2).unwrap().get_random_vec(8));
+        assert!(pool.worker(u64::MAX - 1).is_ok());
+        assert!(pool.worker(u64::MAX).is_err());
+
+        // The shared stream works from any thread too
+        std::thread::scope(|scope| {
+            scope.spawn(|| pool.get_random_item());
+        });
+        assert_ne!(pool.get_random_vec(8), first[0]);
+    }
+
+    #[test]
+    fn it_turns_any_rate_into_a_probability() {
+        assert_eq!(probability(f64::NAN), 0.0);
+        assert_eq!(probability(-f64::INFINITY), 0.0);
+        assert_eq!(probability(f64::INFINITY), 1.0);
+        assert_eq!(probability(0.25), 0.25);
+    }
 }
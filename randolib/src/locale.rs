@@ -0,0 +1,339 @@
+//! Countries, their languages and currencies, weighted by population so internationalized
+//! fixtures look like real users instead of a uniform pick from a list of codes
+use rand::{distributions::WeightedIndex, prelude::*};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Currency {
+    /// ISO 4217
+    pub code: &'static str,
+    pub name: &'static str,
+    pub symbol: &'static str,
+    /// Digits after the decimal point, 0 for yen and won, 3 for dinars
+    pub minor_units: u8,
+}
+
+impl Currency {
+    /// An amount given in minor units (cents) as a decimal string, e.g. `-1234` USD is `-12.34`
+    pub fn format(&self, minor: i64) -> String {
+        if self.minor_units == 0 {
+            return minor.to_string();
+        }
+        let scale = 10u64.pow(self.minor_units as u32);
+        let sign = if minor < 0 { "-" } else { "" };
+        let abs = minor.unsigned_abs();
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            abs / scale,
+            abs % scale,
+            width = self.minor_units as usize
+        )
+    }
+}
+
+/// `const fn` so the tables below can be built at compile time
+const fn currency(
+    code: &'static str,
+    name: &'static str,
+    symbol: &'static str,
+    minor_units: u8,
+) -> Currency {
+    Currency {
+        code,
+        name,
+        symbol,
+        minor_units,
+    }
+}
+
+pub const ARS: Currency = currency("ARS", "Argentine Peso", "$", 2);
+pub const AUD: Currency = currency("AUD", "Australian Dollar", "$", 2);
+pub const BDT: Currency = currency("BDT", "Bangladeshi Taka", "৳", 2);
+pub const BRL: Currency = currency("BRL", "Brazilian Real", "R$", 2);
+pub const CAD: Currency = currency("CAD", "Canadian Dollar", "$", 2);
+pub const CHF: Currency = currency("CHF", "Swiss Franc", "CHF", 2);
+pub const CNY: Currency = currency("CNY", "Chinese Yuan", "¥", 2);
+pub const COP: Currency = currency("COP", "Colombian Peso", "$", 2);
+pub const EGP: Currency = currency("EGP", "Egyptian Pound", "E£", 2);
+pub const EUR: Currency = currency("EUR", "Euro", "€", 2);
+pub const GBP: Currency = currency("GBP", "Pound Sterling", "£", 2);
+pub const IDR: Currency = currency("IDR", "Indonesian Rupiah", "Rp", 2);
+pub const INR: Currency = currency("INR", "Indian Rupee", "₹", 2);
+pub const JPY: Currency = currency("JPY", "Japanese Yen", "¥", 0);
+pub const KRW: Currency = currency("KRW", "South Korean Won", "₩", 0);
+pub const KWD: Currency = currency("KWD", "Kuwaiti Dinar", "KD", 3);
+pub const MXN: Currency = currency("MXN", "Mexican Peso", "$", 2);
+pub const NGN: Currency = currency("NGN", "Nigerian Naira", "₦", 2);
+pub const NZD: Currency = currency("NZD", "New Zealand Dollar", "$", 2);
+pub const PHP: Currency = currency("PHP", "Philippine Peso", "₱", 2);
+pub const PKR: Currency = currency("PKR", "Pakistani Rupee", "₨", 2);
+pub const PLN: Currency = currency("PLN", "Polish Zloty", "zł", 2);
+pub const RUB: Currency = currency("RUB", "Russian Ruble", "₽", 2);
+pub const SAR: Currency = currency("SAR", "Saudi Riyal", "﷼", 2);
+pub const SEK: Currency = currency("SEK", "Swedish Krona", "kr", 2);
+pub const THB: Currency = currency("THB", "Thai Baht", "฿", 2);
+pub const TRY: Currency = currency("TRY", "Turkish Lira", "₺", 2);
+pub const USD: Currency = currency("USD", "US Dollar", "$", 2);
+pub const VND: Currency = currency("VND", "Vietnamese Dong", "₫", 0);
+pub const ZAR: Currency = currency("ZAR", "South African Rand", "R", 2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Country {
+    /// ISO 3166-1 alpha-2
+    pub code: &'static str,
+    pub name: &'static str,
+    /// Approximate, in thousands, which is all we need to weight samples
+    pub population: u64,
+    pub currency: Currency,
+    /// ISO 639-1 codes with the rough percentage of people using each as their main language
+    pub languages: &'static [(&'static str, u32)],
+}
+
+const fn country(
+    code: &'static str,
+    name: &'static str,
+    population: u64,
+    currency: Currency,
+    languages: &'static [(&'static str, u32)],
+) -> Country {
+    Country {
+        code,
+        name,
+        population,
+        currency,
+        languages,
+    }
+}
+
+/// The most populous countries plus a few smaller ones with interesting currencies or several
+/// official languages
+pub const COUNTRIES: &[Country] = &[
+    country(
+        "IN",
+        "India",
+        1_430_000,
+        INR,
+        &[
+            ("hi", 57),
+            ("bn", 8),
+            ("te", 7),
+            ("mr", 7),
+            ("ta", 6),
+            ("en", 15),
+        ],
+    ),
+    country("CN", "China", 1_410_000, CNY, &[("zh", 100)]),
+    country(
+        "US",
+        "United States",
+        335_000,
+        USD,
+        &[("en", 85), ("es", 15)],
+    ),
+    country("ID", "Indonesia", 277_000, IDR, &[("id", 100)]),
+    country("PK", "Pakistan", 240_000, PKR, &[("ur", 75), ("en", 25)]),
+    country("NG", "Nigeria", 224_000, NGN, &[("en", 100)]),
+    country("BR", "Brazil", 216_000, BRL, &[("pt", 100)]),
+    country("BD", "Bangladesh", 173_000, BDT, &[("bn", 100)]),
+    country("RU", "Russia", 144_000, RUB, &[("ru", 100)]),
+    country("MX", "Mexico", 128_000, MXN, &[("es", 100)]),
+    country("JP", "Japan", 124_000, JPY, &[("ja", 100)]),
+    country(
+        "PH",
+        "Philippines",
+        117_000,
+        PHP,
+        &[("fil", 60), ("en", 40)],
+    ),
+    country("EG", "Egypt", 113_000, EGP, &[("ar", 100)]),
+    country("VN", "Vietnam", 99_000, VND, &[("vi", 100)]),
+    country("TR", "Turkey", 85_000, TRY, &[("tr", 100)]),
+    country("DE", "Germany", 84_000, EUR, &[("de", 100)]),
+    country("TH", "Thailand", 72_000, THB, &[("th", 100)]),
+    country("FR", "France", 68_000, EUR, &[("fr", 100)]),
+    country("GB", "United Kingdom", 68_000, GBP, &[("en", 100)]),
+    country(
+        "ZA",
+        "South Africa",
+        60_000,
+        ZAR,
+        &[("zu", 30), ("xh", 20), ("af", 15), ("en", 35)],
+    ),
+    country("IT", "Italy", 59_000, EUR, &[("it", 100)]),
+    country("KR", "South Korea", 52_000, KRW, &[("ko", 100)]),
+    country("CO", "Colombia", 52_000, COP, &[("es", 100)]),
+    country("ES", "Spain", 48_000, EUR, &[("es", 85), ("ca", 15)]),
+    country("AR", "Argentina", 46_000, ARS, &[("es", 100)]),
+    country("CA", "Canada", 39_000, CAD, &[("en", 78), ("fr", 22)]),
+    country("PL", "Poland", 37_000, PLN, &[("pl", 100)]),
+    country("SA", "Saudi Arabia", 37_000, SAR, &[("ar", 100)]),
+    country("AU", "Australia", 27_000, AUD, &[("en", 100)]),
+    country("NL", "Netherlands", 18_000, EUR, &[("nl", 100)]),
+    country("BE", "Belgium", 11_700, EUR, &[("nl", 60), ("fr", 40)]),
+    country("SE", "Sweden", 10_500, SEK, &[("sv", 100)]),
+    country(
+        "CH",
+        "Switzerland",
+        8_800,
+        CHF,
+        &[("de", 65), ("fr", 25), ("it", 10)],
+    ),
+    country("NZ", "New Zealand", 5_200, NZD, &[("en", 100)]),
+    country("KW", "Kuwait", 4_300, KWD, &[("ar", 100)]),
+];
+
+/// A language as spoken in a country. The currency always comes from the country, so
+/// fixtures never pair `ja-JP` with euros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Locale {
+    pub country: &'static Country,
+    pub language: &'static str,
+}
+
+impl Locale {
+    /// The BCP 47 tag, e.g. `pt-BR`
+    pub fn tag(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn currency(&self) -> &'static Currency {
+        &self.country.currency
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.language, self.country.code)
+    }
+}
+
+/// Samples countries by population, then a language by its share within the country
+pub struct LocaleSampler {
+    countries: Vec<&'static Country>,
+    dist: WeightedIndex<u64>,
+}
+
+impl LocaleSampler {
+    /// A sampler over the whole embedded dataset
+    pub fn new() -> Self {
+        Self::filter(|_| true).expect("the embedded dataset has positive populations")
+    }
+
+    /// A sampler over the countries `keep` accepts, e.g. the eurozone with
+    /// `|c| c.currency == EUR`. `None` if none are left.
+    pub fn filter<F>(keep: F) -> Option<Self>
+    where
+        F: Fn(&Country) -> bool,
+    {
+        let countries = COUNTRIES.iter().filter(|c| keep(c)).collect::<Vec<_>>();
+        let dist = WeightedIndex::new(countries.iter().map(|c| c.population)).ok()?;
+        Some(LocaleSampler { countries, dist })
+    }
+
+    pub fn country<R>(&self, rng: &mut R) -> &'static Country
+    where
+        R: Rng + ?Sized,
+    {
+        self.countries[self.dist.sample(rng)]
+    }
+
+    pub fn locale<R>(&self, rng: &mut R) -> Locale
+    where
+        R: Rng + ?Sized,
+    {
+        let country = self.country(rng);
+        // Shares are small tables, a linear walk beats building a `WeightedIndex` per country
+        let total = country
+            .languages
+            .iter()
+            .map(|&(_, share)| share)
+            .sum::<u32>();
+        let mut pick = rng.gen_range(0..total);
+        for &(language, share) in country.languages {
+            if pick < share {
+                return Locale { country, language };
+            }
+            pick -= share;
+        }
+        unreachable!("`pick` is below the sum of the shares")
+    }
+
+    /// The currency of a population-weighted country, so USD and CNY come up far more than KWD
+    pub fn currency<R>(&self, rng: &mut R) -> &'static Currency
+    where
+        R: Rng + ?Sized,
+    {
+        &self.country(rng).currency
+    }
+}
+
+impl Default for LocaleSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_has_a_consistent_dataset() {
+        for country in COUNTRIES {
+            assert_eq!(country.code.len(), 2);
+            assert!(country.code.chars().all(|c| c.is_ascii_uppercase()));
+            assert_eq!(country.currency.code.len(), 3);
+            assert_eq!(
+                country.languages.iter().map(|&(_, s)| s).sum::<u32>(),
+                100,
+                "{}",
+                country.code
+            );
+        }
+    }
+
+    #[test]
+    fn it_favors_bigger_countries_and_languages() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let sampler = LocaleSampler::new();
+
+        let mut tags = HashMap::new();
+        for _ in 0..20_000 {
+            let locale = sampler.locale(&mut rng);
+            assert!(locale
+                .country
+                .languages
+                .iter()
+                .any(|&(l, _)| l == locale.language));
+            *tags.entry(locale.tag()).or_insert(0) += 1;
+        }
+
+        // India is ~30x Canada, and most Canadians are sampled as English speakers
+        assert!(tags["hi-IN"] > tags["en-CA"] * 10);
+        assert!(tags["en-CA"] > tags["fr-CA"]);
+        assert!(!tags.contains_key("ja-BR"));
+    }
+
+    #[test]
+    fn it_keeps_currencies_with_their_countries() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let euro = LocaleSampler::filter(|c| c.currency == EUR).unwrap();
+
+        for _ in 0..1000 {
+            let locale = euro.locale(&mut rng);
+            assert_eq!(locale.currency().code, "EUR");
+            assert!(["DE", "FR", "IT", "ES", "NL", "BE"].contains(&locale.country.code));
+        }
+        assert!(LocaleSampler::filter(|c| c.code == "XX").is_none());
+    }
+
+    #[test]
+    fn it_formats_minor_units() {
+        assert_eq!(USD.format(123_456), "1234.56");
+        assert_eq!(USD.format(-5), "-0.05");
+        assert_eq!(JPY.format(1200), "1200");
+        assert_eq!(KWD.format(1_001), "1.001");
+    }
+}
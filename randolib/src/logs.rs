@@ -0,0 +1,460 @@
+//! Random web server access logs for testing log parsers and SIEM rules. Each request
+//! becomes a `LogEntry`, which can be written as an Apache/nginx combined log line, a JSON
+//! object, or an RFC 5424 syslog message. Status codes are weighted like real traffic,
+//! latencies are log-normal with errors running slower, and clients come from `net`.
+//!
+//! Timestamps are written in UTC.
+
+use crate::{fake::DeviceProfileGen, json_string, net, probability};
+use rand::{distributions::WeightedIndex, prelude::*};
+use rand_distr::{Exp1, LogNormal};
+use somelib::error::Error;
+use std::{
+    net::IpAddr,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// How a `LogEntry` is written out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogFormat {
+    /// The combined format Apache and nginx both default to
+    Combined,
+    /// One JSON object per line, as structured loggers write
+    Json,
+    /// RFC 5424 syslog, with the request in the message
+    Syslog,
+}
+
+impl LogFormat {
+    pub const ALL: [LogFormat; 3] = [LogFormat::Combined, LogFormat::Json, LogFormat::Syslog];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogFormat::Combined => "combined",
+            LogFormat::Json => "json",
+            LogFormat::Syslog => "syslog",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LogFormat::ALL
+            .into_iter()
+            .find(|format| format.name() == s)
+            .ok_or_else(|| Error::InvalidParameter(format!("unknown log format {:?}", s)))
+    }
+}
+
+/// One request as the server saw it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub at: SystemTime,
+    pub client: IpAddr,
+    pub method: &'static str,
+    pub path: String,
+    pub status: u16,
+    /// Response body size
+    pub bytes: u64,
+    pub latency: Duration,
+    pub referrer: Option<String>,
+    pub user_agent: String,
+    /// The server's name, for syslog
+    pub host: String,
+}
+
+impl LogEntry {
+    pub fn format(&self, format: LogFormat) -> String {
+        match format {
+            // `%h %l %u %t "%r" %>s %b "%{Referer}i" "%{User-Agent}i"`, `-` for unknowns
+            LogFormat::Combined => format!(
+                "{} - - [{}] \"{} {} HTTP/1.1\" {} {} \"{}\" \"{}\"",
+                self.client,
+                clf_time(self.at),
+                self.method,
+                self.path,
+                self.status,
+                self.bytes,
+                self.referrer.as_deref().unwrap_or("-"),
+                self.user_agent
+            ),
+            LogFormat::Json => format!(
+                "{{\"ts\":\"{}\",\"client\":\"{}\",\"method\":\"{}\",\"path\":{},\"status\":{},\"bytes\":{},\"latency_ms\":{:.3},\"referrer\":{},\"user_agent\":{}}}",
+                rfc3339(self.at),
+                self.client,
+                self.method,
+                json_string(&self.path),
+                self.status,
+                self.bytes,
+                self.latency.as_secs_f64() * 1000.0,
+                self.referrer.as_deref().map_or("null".into(), json_string),
+                json_string(&self.user_agent)
+            ),
+            // `<PRI>VERSION TIMESTAMP HOST APP PROCID MSGID SD MSG`, PRI being facility
+            // local0 (16) times 8 plus the severity
+            LogFormat::Syslog => format!(
+                "<{}>1 {} {} nginx - access - {} {} {} {} {}B {}ms",
+                16 * 8 + self.severity(),
+                rfc3339(self.at),
+                self.host,
+                self.client,
+                self.method,
+                self.path,
+                self.status,
+                self.bytes,
+                self.latency.as_millis()
+            ),
+        }
+    }
+
+    /// Syslog severity: error for 5xx, warning for 4xx, informational otherwise
+    pub fn severity(&self) -> u8 {
+        match self.status {
+            500.. => 3,
+            400..=499 => 4,
+            _ => 6,
+        }
+    }
+}
+
+/// `10/Oct/2000:13:55:36 +0000`
+fn clf_time(t: SystemTime) -> String {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (year, month, day, secs) = civil(t);
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// `2000-10-10T13:55:36.123Z`
+fn rfc3339(t: SystemTime) -> String {
+    let (year, month, day, secs) = civil(t);
+    let millis = t
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_millis();
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        millis
+    )
+}
+
+/// Year, month, day and seconds into the day of a UTC time. Howard Hinnant's
+/// `civil_from_days`: count from 1 March 0000, so the leap day falls at the end of each
+/// year, in 400-year eras of exactly 146,097 days.
+fn civil(t: SystemTime) -> (i64, u32, u32, u64) {
+    let secs = t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u32;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day, secs % 86_400)
+}
+
+/// Status codes and how often a typical site serves them, `(status, weight)`
+const STATUSES: [(u16, f64); 12] = [
+    (200, 800.0),
+    (201, 10.0),
+    (204, 10.0),
+    (301, 15.0),
+    (302, 20.0),
+    (304, 60.0),
+    (400, 8.0),
+    (401, 10.0),
+    (403, 7.0),
+    (404, 40.0),
+    (500, 6.0),
+    (503, 4.0),
+];
+
+/// `(method, weight)`, mostly reads like a site rather than an API
+const METHODS: [(&str, f64); 4] = [("GET", 90.0), ("POST", 7.0), ("HEAD", 2.0), ("PUT", 1.0)];
+
+const PATHS: [&str; 10] = [
+    "/",
+    "/index.html",
+    "/about",
+    "/login",
+    "/search?q=shoes",
+    "/products/1042",
+    "/api/v1/items",
+    "/static/app.js",
+    "/static/style.css",
+    "/favicon.ico",
+];
+
+/// Generates `LogEntry`s
+pub struct LogGen {
+    paths: Vec<String>,
+    statuses: Vec<u16>,
+    status_dist: WeightedIndex<f64>,
+    methods: WeightedIndex<f64>,
+    latency: LogNormal<f64>,
+    ipv6_rate: f64,
+    host: String,
+    agents: DeviceProfileGen,
+}
+
+impl LogGen {
+    /// A small website: mostly 200s and 304s, a median latency of 50ms, 10% IPv6 clients
+    pub fn new() -> Self {
+        // `unwrap` is fine, the tables have positive weights and the sigma is finite
+        LogGen {
+            paths: PATHS.iter().map(|p| p.to_string()).collect(),
+            statuses: STATUSES.iter().map(|(status, _)| *status).collect(),
+            status_dist: WeightedIndex::new(STATUSES.iter().map(|(_, w)| *w)).unwrap(),
+            methods: WeightedIndex::new(METHODS.iter().map(|(_, w)| *w)).unwrap(),
+            latency: LogNormal::new(0.05f64.ln(), 0.8).unwrap(),
+            ipv6_rate: 0.1,
+            host: "web-01".into(),
+            agents: DeviceProfileGen::new(),
+        }
+    }
+
+    /// The paths requested, picked evenly
+    pub fn paths(mut self, paths: &[&str]) -> Result<Self, Error> {
+        if paths.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one path is needed".into(),
+            ));
+        }
+        self.paths = paths.iter().map(|p| p.to_string()).collect();
+        Ok(self)
+    }
+
+    /// Status codes and how often each is served, `(status, weight)`. Weights only have to
+    /// be proportional.
+    pub fn statuses(mut self, statuses: &[(u16, f64)]) -> Result<Self, Error> {
+        if let Some((status, _)) = statuses.iter().find(|(s, _)| !(100..600).contains(s)) {
+            return Err(Error::InvalidParameter(format!(
+                "{} isn't an HTTP status code",
+                status
+            )));
+        }
+        self.status_dist = WeightedIndex::new(statuses.iter().map(|(_, w)| *w))
+            .map_err(|err| Error::InvalidParameter(format!("status weights: {}", err)))?;
+        self.statuses = statuses.iter().map(|(status, _)| *status).collect();
+        Ok(self)
+    }
+
+    /// Half of all successful requests are faster than `median`; `sigma` is the log-normal's
+    /// spread. Server errors take about three times as long.
+    pub fn latency(mut self, median: Duration, sigma: f64) -> Result<Self, Error> {
+        if median.is_zero() {
+            return Err(Error::InvalidParameter(
+                "the median latency can't be zero".into(),
+            ));
+        }
+        self.latency = LogNormal::new(median.as_secs_f64().ln(), sigma)
+            .map_err(|err| Error::InvalidParameter(format!("latency sigma: {}", err)))?;
+        Ok(self)
+    }
+
+    /// The share of clients on IPv6
+    pub fn ipv6_rate(mut self, rate: f64) -> Self {
+        self.ipv6_rate = probability(rate);
+        self
+    }
+
+    /// The server's name in syslog lines
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = host.to_string();
+        self
+    }
+
+    /// One request served at `at`
+    pub fn entry<R>(&self, at: SystemTime, rng: &mut R) -> LogEntry
+    where
+        R: Rng + ?Sized,
+    {
+        let status = self.statuses[self.status_dist.sample(rng)];
+        let method = METHODS[self.methods.sample(rng)].0;
+        let path = self.paths.choose(rng).unwrap().clone();
+        let mut latency = self.latency.sample(rng);
+        if status >= 500 {
+            latency *= 3.0;
+        }
+        // Bodies that carry a page: redirects, 204s, 304s and `HEAD`s don't
+        let bytes = match status {
+            200 | 201 | 400..=599 if method != "HEAD" => rng.gen_range(200..50_000),
+            _ => 0,
+        };
+        // Most browsers send a referrer for pages they followed a link to
+        let referrer = rng
+            .gen_bool(0.4)
+            .then(|| format!("https://example.com{}", self.paths.choose(rng).unwrap()));
+        LogEntry {
+            at,
+            client: net::ip(self.ipv6_rate, rng),
+            method,
+            path,
+            status,
+            bytes,
+            latency: Duration::from_secs_f64(latency.min(3600.0)),
+            referrer,
+            user_agent: self.agents.user_agent(rng),
+            host: self.host.clone(),
+        }
+    }
+
+    /// An endless iterator of requests from `start`, arriving `per_second` on average with
+    /// exponential gaps between them, as independent visitors do
+    pub fn entries<R>(
+        &self,
+        start: SystemTime,
+        per_second: f64,
+        rng: R,
+    ) -> Result<Entries<'_, R>, Error>
+    where
+        R: Rng,
+    {
+        if !(per_second.is_finite() && per_second > 0.0) {
+            return Err(Error::InvalidParameter(format!(
+                "request rate {} must be positive",
+                per_second
+            )));
+        }
+        Ok(Entries {
+            gen: self,
+            rng,
+            mean_gap: 1.0 / per_second,
+            next: start,
+        })
+    }
+}
+
+impl Default for LogGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Iterator over a `LogGen`'s entries, in time order
+pub struct Entries<'a, R> {
+    gen: &'a LogGen,
+    rng: R,
+    /// In seconds
+    mean_gap: f64,
+    next: SystemTime,
+}
+
+impl<R: Rng> Iterator for Entries<'_, R> {
+    type Item = LogEntry;
+
+    fn next(&mut self) -> Option<LogEntry> {
+        let entry = self.gen.entry(self.next, &mut self.rng);
+        let gap = self.rng.sample::<f64, _>(Exp1) * self.mean_gap;
+        self.next += Duration::from_secs_f64(gap);
+        Some(entry)
+    }
+
+    /// Endless
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    #[test]
+    fn it_formats_times() {
+        let t = UNIX_EPOCH + Duration::from_millis(971_185_736_123);
+        assert_eq!(clf_time(t), "10/Oct/2000:13:48:56 +0000");
+        assert_eq!(rfc3339(t), "2000-10-10T13:48:56.123Z");
+        // A leap day, and the day after the last one of a year
+        let leap = UNIX_EPOCH + Duration::from_secs(1_709_164_800);
+        assert_eq!(rfc3339(leap), "2024-02-29T00:00:00.000Z");
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let new_year = UNIX_EPOCH + Duration::from_secs(1_704_067_199);
+        assert_eq!(clf_time(new_year), "31/Dec/2023:23:59:59 +0000");
+    }
+
+    #[test]
+    fn it_writes_parseable_lines() {
+        let combined = Regex::new(
+            r#"^\S+ - - \[\d{2}/\w{3}/\d{4}:\d{2}:\d{2}:\d{2} \+0000\] "[A-Z]+ \S+ HTTP/1\.1" \d{3} \d+ "[^"]*" "[^"]+"$"#,
+        )
+        .unwrap();
+        let syslog = Regex::new(r"^<(\d+)>1 \S+Z web-01 nginx - access - .+ \d+ms$").unwrap();
+        let gen = LogGen::new();
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let entries = gen
+            .entries(start, 10.0, StdRng::seed_from_u64(277))
+            .unwrap()
+            .take(500)
+            .collect::<Vec<_>>();
+        assert!(entries.windows(2).all(|pair| pair[0].at <= pair[1].at));
+        for entry in &entries {
+            let line = entry.format(LogFormat::Combined);
+            assert!(combined.is_match(&line), "{}", line);
+            let line = entry.format(LogFormat::Syslog);
+            let pri = &syslog.captures(&line).unwrap()[1];
+            assert_eq!(pri.parse::<u8>().unwrap(), 128 + entry.severity());
+            let json = entry.format(LogFormat::Json);
+            let value = serde_json::from_str::<serde_json::Value>(&json).unwrap();
+            assert_eq!(value["status"], entry.status);
+        }
+        let ok = entries.iter().filter(|e| e.status == 200).count();
+        assert!((300..450).contains(&ok), "{}", ok);
+        assert!(entries.iter().any(|e| e.client.is_ipv6()));
+    }
+
+    #[test]
+    fn it_uses_the_given_statuses() {
+        let gen = LogGen::new()
+            .statuses(&[(418, 1.0), (503, 1.0)])
+            .unwrap()
+            .latency(Duration::from_millis(10), 0.0)
+            .unwrap();
+        let mut rng = StdRng::seed_from_u64(278);
+        for _ in 0..100 {
+            let entry = gen.entry(UNIX_EPOCH, &mut rng);
+            let expected = if entry.status == 503 { 30 } else { 10 };
+            assert!([418, 503].contains(&entry.status));
+            assert!(entry.latency.as_micros().abs_diff(expected * 1000) <= 1);
+        }
+        assert!(LogGen::new().statuses(&[(42, 1.0)]).is_err());
+        assert!(LogGen::new().statuses(&[]).is_err());
+        assert!(LogGen::new().paths(&[]).is_err());
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = LogGen::new().ipv6_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(277);
+        for _ in 0..20 {
+            assert!(gen.entry(SystemTime::now(), &mut rng).client.is_ipv4());
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use rand::{distributions::WeightedIndex, prelude::*};
+use somelib::error::Error;
+
+/// A Markov chain over user-defined states. Row `i` of the transition matrix gives the
+/// probabilities of moving from `states[i]` to each state. Rows only have to be
+/// proportional, e.g. `[1.0, 3.0]` is the same as `[0.25, 0.75]`.
+///
+/// Cloning copies the current state too, so one configured chain can start many walks.
+#[derive(Clone)]
+pub struct MarkovChain<S> {
+    states: Vec<S>,
+    /// One weighted distribution per row, built once up front so `step` is cheap
+    rows: Vec<WeightedIndex<f64>>,
+    current: usize,
+}
+
+impl<S> MarkovChain<S> {
+    /// Build a chain starting in `states[0]`. The matrix has to be square with one row and
+    /// column per state, and each row needs a positive total.
+    pub fn new(states: Vec<S>, matrix: Vec<Vec<f64>>) -> Result<Self, Error> {
+        if states.is_empty() {
+            return Err(Error::InvalidParameter(
+                "a chain needs at least one state".into(),
+            ));
+        }
+        if matrix.len() != states.len() {
+            return Err(Error::InvalidParameter(format!(
+                "expected {} rows, got {}",
+                states.len(),
+                matrix.len()
+            )));
+        }
+        let rows = matrix
+            .iter()
+            .enumerate()
+            .map(|(i, row)| {
+                if row.len() != states.len() {
+                    return Err(Error::InvalidParameter(format!(
+                        "row {} has {} columns, expected {}",
+                        i,
+                        row.len(),
+                        states.len()
+                    )));
+                }
+                // `map_err` converts rand's error into ours so `?`-style handling still works
+                WeightedIndex::new(row)
+                    .map_err(|err| Error::InvalidParameter(format!("row {}: {}", i, err)))
+            })
+            // Collecting an iterator of `Result`s into a `Result<Vec<_>, _>` stops at the first `Err`
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(MarkovChain {
+            states,
+            rows,
+            current: 0,
+        })
+    }
+
+    /// The state the chain is currently in
+    pub fn state(&self) -> &S {
+        &self.states[self.current]
+    }
+
+    /// All states, in matrix order
+    pub fn states(&self) -> &[S] {
+        &self.states
+    }
+
+    /// Move the chain to `state`, e.g. to restart a walk
+    pub fn set_state(&mut self, state: &S) -> Result<(), Error>
+    where
+        S: PartialEq,
+    {
+        self.current = self
+            .states
+            .iter()
+            .position(|s| s == state)
+            .ok_or_else(|| Error::InvalidParameter("unknown state".into()))?;
+        Ok(())
+    }
+
+    /// Take one transition and return the new state
+    pub fn step<R>(&mut self, rng: &mut R) -> &S
+    where
+        R: Rng + ?Sized,
+    {
+        self.current = self.rows[self.current].sample(rng);
+        self.state()
+    }
+
+    /// Take `n` transitions, returning the visited states (not including the starting state)
+    pub fn walk<R>(&mut self, n: usize, rng: &mut R) -> Vec<S>
+    where
+        R: Rng + ?Sized,
+        S: Clone,
+    {
+        (0..n).map(|_| self.step(rng).clone()).collect()
+    }
+
+    /// Estimate the stationary distribution by simulation: walk `steps` transitions from the
+    /// current state and return the fraction of time spent in each state, in matrix order.
+    /// The chain itself is left untouched.
+    pub fn estimate_stationary<R>(&self, steps: usize, rng: &mut R) -> Vec<f64>
+    where
+        R: Rng + ?Sized,
+    {
+        let mut counts = vec![0usize; self.states.len()];
+        let mut current = self.current;
+        for _ in 0..steps {
+            current = self.rows[current].sample(rng);
+            counts[current] += 1;
+        }
+        counts
+            .into_iter()
+            .map(|count| count as f64 / steps.max(1) as f64)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Conn {
+        Idle,
+        Busy,
+    }
+
+    #[test]
+    fn it_estimates_the_stationary_distribution() {
+        let mut rng = StdRng::seed_from_u64(3);
+        // Idle -> Busy with 0.1, Busy -> Idle with 0.3 gives a stationary Idle share of 0.75
+        let chain = MarkovChain::new(
+            vec![Conn::Idle, Conn::Busy],
+            vec![vec![0.9, 0.1], vec![0.3, 0.7]],
+        )
+        .unwrap();
+
+        let pi = chain.estimate_stationary(100_000, &mut rng);
+
+        assert!((pi[0] - 0.75).abs() < 0.02);
+        assert!((pi[0] + pi[1] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn it_only_takes_allowed_transitions() {
+        let mut rng = StdRng::seed_from_u64(5);
+        // A strict cycle: Idle always goes to Busy and back
+        let mut chain = MarkovChain::new(
+            vec![Conn::Idle, Conn::Busy],
+            vec![vec![0.0, 1.0], vec![1.0, 0.0]],
+        )
+        .unwrap();
+
+        assert_eq!(
+            chain.walk(4, &mut rng),
+            vec![Conn::Busy, Conn::Idle, Conn::Busy, Conn::Idle]
+        );
+    }
+
+    #[test]
+    fn it_rejects_bad_matrices() {
+        assert!(MarkovChain::new(vec![1, 2], vec![vec![1.0, 0.0]]).is_err());
+        assert!(MarkovChain::new(vec![1, 2], vec![vec![1.0], vec![1.0]]).is_err());
+        assert!(MarkovChain::new(vec![1, 2], vec![vec![0.0, 0.0], vec![1.0, 0.0]]).is_err());
+    }
+}
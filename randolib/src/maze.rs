@@ -0,0 +1,242 @@
+//! Random mazes and grid maps for exercising pathfinding code. Everything is a `Grid` of
+//! open and blocked tiles, so the same search code can run on either.
+
+use rand::prelude::*;
+use somelib::error::Error;
+use std::fmt;
+
+/// A rectangular map of tiles, each either open or a wall
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    /// Row-major, `true` is a wall
+    walls: Vec<bool>,
+}
+
+/// The four neighbors of a tile, `(dx, dy)`
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+impl Grid {
+    fn filled(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            walls: vec![true; width * height],
+        }
+    }
+
+    /// A grid with each tile blocked with probability `density`
+    pub fn random<R>(width: usize, height: usize, density: f64, rng: &mut R) -> Result<Self, Error>
+    where
+        R: Rng + ?Sized,
+    {
+        if !(0.0..=1.0).contains(&density) {
+            return Err(Error::InvalidParameter(format!(
+                "obstacle density {} is outside [0, 1]",
+                density
+            )));
+        }
+        Ok(Grid {
+            width,
+            height,
+            walls: (0..width * height).map(|_| rng.gen_bool(density)).collect(),
+        })
+    }
+
+    /// A perfect maze (exactly one path between any two open tiles) of `width` x `height`
+    /// rooms, carved by a randomized depth-first search. Backtracker mazes have long,
+    /// winding corridors and few dead ends.
+    ///
+    /// Rooms sit on odd coordinates with walls between them, so the grid is
+    /// `2 * width + 1` by `2 * height + 1` tiles.
+    pub fn backtracker<R>(width: usize, height: usize, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let mut grid = Self::maze_frame(width, height);
+        if width == 0 || height == 0 {
+            return grid;
+        }
+        let mut visited = vec![false; width * height];
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        grid.open_room(0, 0);
+
+        while let Some(&(x, y)) = stack.last() {
+            let unvisited = rooms_next_to(x, y, width, height)
+                .filter(|&(nx, ny)| !visited[ny * width + nx])
+                .collect::<Vec<_>>();
+            match unvisited.choose(rng) {
+                Some(&(nx, ny)) => {
+                    visited[ny * width + nx] = true;
+                    grid.connect(x, y, nx, ny);
+                    stack.push((nx, ny));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+        grid
+    }
+
+    /// A perfect maze grown with randomized Prim's algorithm: repeatedly connect a random
+    /// frontier room to the maze. Prim mazes branch a lot and have many short dead ends.
+    /// Same layout as `backtracker`.
+    pub fn prim<R>(width: usize, height: usize, rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let mut grid = Self::maze_frame(width, height);
+        if width == 0 || height == 0 {
+            return grid;
+        }
+        let mut in_maze = vec![false; width * height];
+        // `(room outside the maze, room inside it)`
+        let mut frontier = Vec::new();
+        let add = |x: usize, y: usize, in_maze: &mut Vec<bool>, frontier: &mut Vec<_>| {
+            in_maze[y * width + x] = true;
+            for (nx, ny) in rooms_next_to(x, y, width, height) {
+                if !in_maze[ny * width + nx] {
+                    frontier.push(((nx, ny), (x, y)));
+                }
+            }
+        };
+        grid.open_room(0, 0);
+        add(0, 0, &mut in_maze, &mut frontier);
+
+        while !frontier.is_empty() {
+            let ((x, y), (px, py)) = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+            // A room can be on the frontier more than once
+            if in_maze[y * width + x] {
+                continue;
+            }
+            grid.connect(px, py, x, y);
+            add(x, y, &mut in_maze, &mut frontier);
+        }
+        grid
+    }
+
+    fn maze_frame(width: usize, height: usize) -> Self {
+        Self::filled(2 * width + 1, 2 * height + 1)
+    }
+
+    fn open_room(&mut self, x: usize, y: usize) {
+        self.set_wall(2 * x + 1, 2 * y + 1, false);
+    }
+
+    /// Open room `(x2, y2)` and the wall between it and its neighbor `(x1, y1)`
+    fn connect(&mut self, x1: usize, y1: usize, x2: usize, y2: usize) {
+        self.open_room(x2, y2);
+        self.set_wall(x1 + x2 + 1, y1 + y2 + 1, false);
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Tiles outside the grid count as walls
+    pub fn is_wall(&self, x: usize, y: usize) -> bool {
+        x >= self.width || y >= self.height || self.walls[y * self.width + x]
+    }
+
+    pub fn set_wall(&mut self, x: usize, y: usize, wall: bool) {
+        self.walls[y * self.width + x] = wall;
+    }
+
+    /// Open tiles next to `(x, y)`
+    pub fn neighbors(&self, x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        DIRECTIONS.iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+            (!self.is_wall(nx, ny)).then_some((nx, ny))
+        })
+    }
+
+    pub fn open_tiles(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| !self.is_wall(x, y))
+    }
+}
+
+/// Rooms adjacent to room `(x, y)` in a `width` x `height` maze
+fn rooms_next_to(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    DIRECTIONS.iter().filter_map(move |&(dx, dy)| {
+        let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+        (nx < width && ny < height).then_some((nx, ny))
+    })
+}
+
+/// ASCII rendering, `#` for walls and a space for open tiles
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            let row = (0..self.width)
+                .map(|x| if self.is_wall(x, y) { '#' } else { ' ' })
+                .collect::<String>();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Open tiles reachable from the first open tile
+    fn reachable(grid: &Grid) -> usize {
+        let Some(start) = grid.open_tiles().next() else {
+            return 0;
+        };
+        let mut seen = HashSet::from([start]);
+        let mut todo = vec![start];
+        while let Some((x, y)) = todo.pop() {
+            for next in grid.neighbors(x, y) {
+                if seen.insert(next) {
+                    todo.push(next);
+                }
+            }
+        }
+        seen.len()
+    }
+
+    #[test]
+    fn it_carves_perfect_mazes() {
+        let mut rng = StdRng::seed_from_u64(34);
+
+        for grid in [
+            Grid::backtracker(12, 7, &mut rng),
+            Grid::prim(12, 7, &mut rng),
+        ] {
+            assert_eq!((grid.width(), grid.height()), (25, 15));
+            // A spanning tree of 84 rooms has 83 passages, so every open tile is a room
+            // or a passage and all of them are connected
+            let open = grid.open_tiles().count();
+            assert_eq!(open, 84 + 83);
+            assert_eq!(reachable(&grid), open);
+        }
+    }
+
+    #[test]
+    fn it_fills_grids_to_a_density() {
+        let mut rng = StdRng::seed_from_u64(35);
+        let grid = Grid::random(100, 100, 0.3, &mut rng).unwrap();
+
+        let walls = 100 * 100 - grid.open_tiles().count();
+        assert!((2700..3300).contains(&walls), "{}", walls);
+        assert_eq!(grid.to_string().lines().count(), 100);
+        assert!(Grid::random(2, 2, 1.5, &mut rng).is_err());
+    }
+}
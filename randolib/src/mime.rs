@@ -0,0 +1,328 @@
+//! Payloads for content-sniffing and upload-validation code: bytes that start with a file
+//! format's signature ("magic bytes") together with a declared MIME type and file name, and
+//! negative cases where the declaration and the content disagree.
+
+use crate::probability;
+use rand::{distributions::Alphanumeric, prelude::*};
+use somelib::error::Error;
+use std::ops::RangeInclusive;
+
+/// A file format and how to recognize it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileType {
+    pub mime: &'static str,
+    pub extension: &'static str,
+    /// Byte strings at fixed offsets which all have to match. Most formats have one at 0;
+    /// RIFF-based ones have a size field between two.
+    pub signature: &'static [(usize, &'static [u8])],
+}
+
+impl FileType {
+    /// Whether `bytes` carry this type's signature
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        self.signature
+            .iter()
+            .all(|(offset, magic)| bytes.get(*offset..offset + magic.len()) == Some(*magic))
+    }
+
+    /// Bytes needed to hold the whole signature
+    pub fn signature_len(&self) -> usize {
+        self.signature
+            .iter()
+            .map(|(offset, magic)| offset + magic.len())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+const fn file_type(
+    mime: &'static str,
+    extension: &'static str,
+    signature: &'static [(usize, &'static [u8])],
+) -> FileType {
+    FileType {
+        mime,
+        extension,
+        signature,
+    }
+}
+
+/// Common formats with unambiguous signatures. ZIP-based formats like `.docx` are left out:
+/// their signature is ZIP's and only the archive's contents tell them apart.
+pub const FILE_TYPES: &[FileType] = &[
+    file_type("image/png", "png", &[(0, b"\x89PNG\r\n\x1a\n")]),
+    file_type("image/jpeg", "jpg", &[(0, b"\xff\xd8\xff")]),
+    file_type("image/gif", "gif", &[(0, b"GIF89a")]),
+    file_type("image/webp", "webp", &[(0, b"RIFF"), (8, b"WEBP")]),
+    file_type("image/bmp", "bmp", &[(0, b"BM")]),
+    file_type("image/tiff", "tif", &[(0, b"II*\0")]),
+    file_type("application/pdf", "pdf", &[(0, b"%PDF-")]),
+    file_type("application/zip", "zip", &[(0, b"PK\x03\x04")]),
+    file_type("application/gzip", "gz", &[(0, b"\x1f\x8b\x08")]),
+    file_type("application/wasm", "wasm", &[(0, b"\0asm")]),
+    file_type("audio/mpeg", "mp3", &[(0, b"ID3")]),
+    file_type("audio/wav", "wav", &[(0, b"RIFF"), (8, b"WAVE")]),
+    file_type("video/mp4", "mp4", &[(4, b"ftyp")]),
+];
+
+/// The type in `FILE_TYPES` whose signature `bytes` carry, a reference sniffer to compare
+/// the code under test against. If several match, e.g. an MP4 whose first bytes happen to
+/// be BMP's `BM`, the one with the longest signature wins.
+pub fn sniff(bytes: &[u8]) -> Option<&'static FileType> {
+    FILE_TYPES
+        .iter()
+        .filter(|file_type| file_type.matches(bytes))
+        .max_by_key(|file_type| {
+            file_type
+                .signature
+                .iter()
+                .map(|(_, m)| m.len())
+                .sum::<usize>()
+        })
+}
+
+/// How a negative case lies about its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mismatch {
+    /// The content is one type, the MIME type and extension claim another
+    Label,
+    /// The MIME type is right but the file name's extension belongs to another type
+    Extension,
+    /// The signature is cut short, as in an interrupted upload
+    Truncated,
+    /// No signature at all, just bytes labelled as a type
+    Missing,
+}
+
+impl Mismatch {
+    pub const ALL: [Mismatch; 4] = [
+        Mismatch::Label,
+        Mismatch::Extension,
+        Mismatch::Truncated,
+        Mismatch::Missing,
+    ];
+}
+
+/// A generated upload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Payload {
+    pub bytes: Vec<u8>,
+    pub declared_mime: &'static str,
+    pub file_name: String,
+    /// What the bytes really are, `None` if they carry no known signature
+    pub actual: Option<&'static FileType>,
+    /// `None` for an honest payload
+    pub mismatch: Option<Mismatch>,
+}
+
+impl Payload {
+    pub fn is_consistent(&self) -> bool {
+        self.mismatch.is_none()
+    }
+}
+
+/// Generates payloads, a share of them mismatched
+pub struct MimeGen {
+    size: RangeInclusive<usize>,
+    mismatch_rate: f64,
+    mismatches: Vec<Mismatch>,
+}
+
+impl MimeGen {
+    /// Payloads of up to 4KiB, a quarter of them mismatched
+    pub fn new() -> Self {
+        MimeGen {
+            size: 0..=4096,
+            mismatch_rate: 0.25,
+            mismatches: Mismatch::ALL.to_vec(),
+        }
+    }
+
+    /// Total payload sizes in bytes. Payloads are never shorter than their signature, so
+    /// small sizes are rounded up.
+    pub fn size(mut self, size: RangeInclusive<usize>) -> Result<Self, Error> {
+        if size.is_empty() {
+            return Err(Error::InvalidParameter("empty payload size range".into()));
+        }
+        self.size = size;
+        Ok(self)
+    }
+
+    pub fn mismatch_rate(mut self, rate: f64) -> Self {
+        self.mismatch_rate = probability(rate);
+        self
+    }
+
+    /// Which kinds of mismatch to use, at least one
+    pub fn mismatches(mut self, mismatches: &[Mismatch]) -> Result<Self, Error> {
+        if mismatches.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one mismatch is needed".into(),
+            ));
+        }
+        self.mismatches = mismatches.to_vec();
+        Ok(self)
+    }
+
+    pub fn payload<R>(&self, rng: &mut R) -> Payload
+    where
+        R: Rng + ?Sized,
+    {
+        let declared = FILE_TYPES.choose(rng).unwrap();
+        let mismatch = if rng.gen_bool(self.mismatch_rate) {
+            self.mismatches.choose(rng).copied()
+        } else {
+            None
+        };
+        let size = rng.gen_range(self.size.clone());
+        let stem = rng
+            .sample_iter(Alphanumeric)
+            .take(8)
+            .map(char::from)
+            .collect::<String>();
+        let name = |file_type: &FileType| format!("{}.{}", stem, file_type.extension);
+
+        let (bytes, actual, file_name) = match mismatch {
+            None => (content(declared, size, rng), Some(declared), name(declared)),
+            Some(Mismatch::Label) => {
+                let actual = other_than(declared, rng);
+                (content(actual, size, rng), Some(actual), name(declared))
+            }
+            Some(Mismatch::Extension) => {
+                let other = other_than(declared, rng);
+                (content(declared, size, rng), Some(declared), name(other))
+            }
+            Some(Mismatch::Truncated) => {
+                let mut bytes = content(declared, size, rng);
+                // Cut inside the last signature piece, so it's started but unfinished
+                let (offset, magic) = declared.signature.last().unwrap();
+                bytes.truncate(offset + rng.gen_range(0..magic.len()));
+                // Whatever is left may still be a shorter signature, RIFF's for one
+                let actual = sniff(&bytes);
+                (bytes, actual, name(declared))
+            }
+            Some(Mismatch::Missing) => {
+                let mut bytes = random_bytes(size.max(16), rng);
+                // Random bytes matching a two-byte signature like BMP's isn't rare enough
+                // to ignore
+                while sniff(&bytes).is_some() {
+                    bytes[0] = rng.gen();
+                    bytes[4] = rng.gen();
+                }
+                (bytes, None, name(declared))
+            }
+        };
+        Payload {
+            bytes,
+            declared_mime: declared.mime,
+            file_name,
+            actual,
+            mismatch,
+        }
+    }
+}
+
+impl Default for MimeGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn other_than<R>(file_type: &FileType, rng: &mut R) -> &'static FileType
+where
+    R: Rng + ?Sized,
+{
+    loop {
+        let other = FILE_TYPES.choose(rng).unwrap();
+        if other.mime != file_type.mime {
+            return other;
+        }
+    }
+}
+
+fn random_bytes<R>(len: usize, rng: &mut R) -> Vec<u8>
+where
+    R: Rng + ?Sized,
+{
+    let mut bytes = vec![0; len];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// `size` bytes (or the signature's length if longer) carrying `file_type`'s signature
+fn content<R>(file_type: &FileType, size: usize, rng: &mut R) -> Vec<u8>
+where
+    R: Rng + ?Sized,
+{
+    let mut bytes = random_bytes(size.max(file_type.signature_len()), rng);
+    for (offset, magic) in file_type.signature {
+        bytes[*offset..offset + magic.len()].copy_from_slice(magic);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_has_distinct_signatures() {
+        for (i, a) in FILE_TYPES.iter().enumerate() {
+            let bytes = content(a, 64, &mut StdRng::seed_from_u64(i as u64));
+            assert_eq!(sniff(&bytes).unwrap().mime, a.mime);
+        }
+    }
+
+    #[test]
+    fn it_makes_consistent_payloads() {
+        let mut rng = StdRng::seed_from_u64(55);
+        let gen = MimeGen::new().mismatch_rate(0.0).size(0..=64).unwrap();
+        for _ in 0..500 {
+            let payload = gen.payload(&mut rng);
+            assert!(payload.is_consistent());
+            let actual = sniff(&payload.bytes).unwrap();
+            assert_eq!(actual.mime, payload.declared_mime);
+            assert!(payload
+                .file_name
+                .ends_with(&format!(".{}", actual.extension)));
+        }
+    }
+
+    #[test]
+    fn it_makes_each_kind_of_mismatch() {
+        let mut rng = StdRng::seed_from_u64(56);
+        for mismatch in Mismatch::ALL {
+            let gen = MimeGen::new()
+                .mismatch_rate(1.0)
+                .mismatches(&[mismatch])
+                .unwrap();
+            for _ in 0..200 {
+                let payload = gen.payload(&mut rng);
+                assert_eq!(payload.mismatch, Some(mismatch));
+                let sniffed = sniff(&payload.bytes);
+                assert_eq!(sniffed.map(|t| t.mime), payload.actual.map(|t| t.mime));
+                match mismatch {
+                    Mismatch::Label => assert_ne!(sniffed.unwrap().mime, payload.declared_mime),
+                    Mismatch::Extension => {
+                        assert_eq!(sniffed.unwrap().mime, payload.declared_mime);
+                        let ext = payload.file_name.rsplit('.').next().unwrap();
+                        assert_ne!(ext, sniffed.unwrap().extension);
+                    }
+                    Mismatch::Truncated | Mismatch::Missing => {
+                        assert!(sniffed.is_none_or(|t| t.mime != payload.declared_mime))
+                    }
+                }
+            }
+        }
+        assert!(MimeGen::new().mismatches(&[]).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = MimeGen::new().mismatch_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(270);
+        for _ in 0..20 {
+            assert!(gen.payload(&mut rng).mismatch.is_none());
+        }
+    }
+}
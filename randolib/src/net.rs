@@ -0,0 +1,111 @@
+//! Random IP addresses, kept to the ranges real traffic comes from. A uniformly random
+//! `u32` is often loopback, multicast or reserved, which a log parser or geo lookup might
+//! rightly treat as garbage.
+
+use crate::probability;
+use rand::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// Blocks a public address is never in, `(first octets, prefix length)`
+const NOT_PUBLIC: [([u8; 2], u32); 11] = [
+    ([0, 0], 8),
+    ([10, 0], 8),
+    ([100, 64], 10),
+    ([127, 0], 8),
+    ([169, 254], 16),
+    ([172, 16], 12),
+    ([192, 0], 16),
+    ([192, 168], 16),
+    ([198, 18], 15),
+    ([203, 0], 16),
+    // Multicast and everything above it
+    ([224, 0], 3),
+];
+
+/// Whether `ip` could be a host on the internet: not private, loopback, link-local,
+/// shared (CGNAT), documentation, benchmarking, multicast or reserved
+pub fn is_public(ip: Ipv4Addr) -> bool {
+    let ip = u32::from(ip);
+    !NOT_PUBLIC.iter().any(|(octets, prefix)| {
+        let block = u32::from(Ipv4Addr::new(octets[0], octets[1], 0, 0));
+        let mask = u32::MAX << (32 - prefix);
+        ip & mask == block & mask
+    })
+}
+
+/// A public IPv4 address. `192.0.0.0/16` and `203.0.0.0/16` are a little wider than the
+/// reserved blocks inside them, which doesn't matter for test data.
+pub fn ipv4<R>(rng: &mut R) -> Ipv4Addr
+where
+    R: Rng + ?Sized,
+{
+    // About 86% of addresses are public, so this rarely loops
+    loop {
+        let ip = Ipv4Addr::from(rng.gen::<u32>());
+        if is_public(ip) {
+            return ip;
+        }
+    }
+}
+
+/// An address in one of the private (RFC 1918) blocks, like clients behind a proxy
+pub fn private_ipv4<R>(rng: &mut R) -> Ipv4Addr
+where
+    R: Rng + ?Sized,
+{
+    match rng.gen_range(0..3) {
+        0 => Ipv4Addr::new(10, rng.gen(), rng.gen(), rng.gen_range(1..255)),
+        1 => Ipv4Addr::new(172, rng.gen_range(16..32), rng.gen(), rng.gen_range(1..255)),
+        _ => Ipv4Addr::new(192, 168, rng.gen(), rng.gen_range(1..255)),
+    }
+}
+
+/// A global unicast IPv6 address, in `2000::/3` like every address handed out so far
+pub fn ipv6<R>(rng: &mut R) -> Ipv6Addr
+where
+    R: Rng + ?Sized,
+{
+    let bits = rng.gen::<u128>() >> 3 | 1 << 125;
+    Ipv6Addr::from(bits)
+}
+
+/// A public IPv4 address, or an IPv6 one with probability `v6_rate`
+pub fn ip<R>(v6_rate: f64, rng: &mut R) -> IpAddr
+where
+    R: Rng + ?Sized,
+{
+    if rng.gen_bool(probability(v6_rate)) {
+        ipv6(rng).into()
+    } else {
+        ipv4(rng).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_stays_out_of_special_ranges() {
+        let mut rng = StdRng::seed_from_u64(277);
+        for _ in 0..10_000 {
+            let ip = ipv4(&mut rng);
+            assert!(!ip.is_private() && !ip.is_loopback() && !ip.is_link_local());
+            assert!(!ip.is_multicast() && !ip.is_broadcast() && !ip.is_unspecified());
+            assert!(!ip.is_documentation());
+            assert!(private_ipv4(&mut rng).is_private());
+            let v6 = ipv6(&mut rng);
+            assert_eq!(v6.segments()[0] >> 13, 1);
+        }
+        assert!(is_public(Ipv4Addr::new(8, 8, 8, 8)));
+        assert!(!is_public(Ipv4Addr::new(100, 100, 1, 1)));
+        assert!(!is_public(Ipv4Addr::new(172, 31, 255, 255)));
+        assert!(is_public(Ipv4Addr::new(172, 32, 0, 0)));
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let mut rng = StdRng::seed_from_u64(278);
+        assert!(ip(f64::NAN, &mut rng).is_ipv4());
+    }
+}
@@ -0,0 +1,129 @@
+//! Colored noise series for DSP and audio-style test signals. The "color" describes how
+//! power is spread over frequencies: white noise is flat, pink noise falls off as `1/f`
+//! and brown (Brownian) noise as `1/f^2`, so it wanders slowly like a random walk.
+//!
+//! Every generator returns `len` samples scaled so the largest magnitude is `amplitude`.
+
+use rand::prelude::*;
+
+/// Rows in the Voss-McCartney pink noise generator. Each row covers one octave, so 16 rows
+/// keep the `1/f` slope over the lowest 16 octaves of the series.
+const PINK_ROWS: usize = 16;
+
+/// Independent uniform samples
+pub fn white<R>(len: usize, amplitude: f64, rng: &mut R) -> Vec<f64>
+where
+    R: Rng + ?Sized,
+{
+    let series = (0..len).map(|_| rng.gen_range(-1.0..1.0)).collect();
+    normalize(series, amplitude)
+}
+
+/// Voss-McCartney pink noise: the sum of several white noise sources, where source `k` is
+/// only redrawn every `2^(k+1)` samples. Slow sources add low frequencies, fast ones high
+/// frequencies, and together they approximate a `1/f` spectrum.
+pub fn pink<R>(len: usize, amplitude: f64, rng: &mut R) -> Vec<f64>
+where
+    R: Rng + ?Sized,
+{
+    let mut rows = [0.0; PINK_ROWS];
+    for row in rows.iter_mut() {
+        *row = rng.gen_range(-1.0..1.0);
+    }
+    let mut sum = rows.iter().sum::<f64>();
+
+    let series = (1..=len)
+        .map(|i| {
+            // The number of trailing zeros cycles through 0, 1, 0, 2, 0, 1, 0, 3, ..
+            let k = i.trailing_zeros() as usize;
+            if k < PINK_ROWS {
+                let new = rng.gen_range(-1.0..1.0);
+                sum += new - rows[k];
+                rows[k] = new;
+            }
+            // A fresh white sample fills in the highest octave
+            sum + rng.gen_range(-1.0..1.0)
+        })
+        .collect();
+    normalize(series, amplitude)
+}
+
+/// Brown noise: a random walk of white noise steps, re-centered on zero
+pub fn brown<R>(len: usize, amplitude: f64, rng: &mut R) -> Vec<f64>
+where
+    R: Rng + ?Sized,
+{
+    let mut position = 0.0;
+    let mut series = (0..len)
+        .map(|_| {
+            position += rng.gen_range(-1.0..1.0);
+            position
+        })
+        .collect::<Vec<f64>>();
+    let mean = series.iter().sum::<f64>() / len.max(1) as f64;
+    for x in series.iter_mut() {
+        *x -= mean;
+    }
+    normalize(series, amplitude)
+}
+
+/// Scale so the peak magnitude is `amplitude`
+fn normalize(mut series: Vec<f64>, amplitude: f64) -> Vec<f64> {
+    let peak = series.iter().fold(0.0f64, |peak, x| peak.max(x.abs()));
+    if peak > 0.0 {
+        for x in series.iter_mut() {
+            *x *= amplitude / peak;
+        }
+    }
+    series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Correlation between neighboring samples, which grows as power shifts to lower
+    /// frequencies
+    fn lag1_autocorrelation(series: &[f64]) -> f64 {
+        let mean = series.iter().sum::<f64>() / series.len() as f64;
+        let var = series.iter().map(|x| (x - mean).powi(2)).sum::<f64>();
+        let cov = series
+            .windows(2)
+            .map(|w| (w[0] - mean) * (w[1] - mean))
+            .sum::<f64>();
+        cov / var
+    }
+
+    #[test]
+    fn it_colors_noise() {
+        let mut rng = StdRng::seed_from_u64(32);
+        let white = white(8192, 1.0, &mut rng);
+        let pink = pink(8192, 1.0, &mut rng);
+        let brown = brown(8192, 1.0, &mut rng);
+
+        let (w, p, b) = (
+            lag1_autocorrelation(&white),
+            lag1_autocorrelation(&pink),
+            lag1_autocorrelation(&brown),
+        );
+        assert!(w.abs() < 0.05, "{}", w);
+        assert!((0.5..0.95).contains(&p), "{}", p);
+        assert!(b > 0.98, "{}", b);
+    }
+
+    #[test]
+    fn it_scales_to_the_amplitude() {
+        let mut rng = StdRng::seed_from_u64(33);
+
+        for series in [
+            white(100, 0.5, &mut rng),
+            pink(100, 0.5, &mut rng),
+            brown(100, 0.5, &mut rng),
+        ] {
+            assert_eq!(series.len(), 100);
+            let peak = series.iter().fold(0.0f64, |peak, x| peak.max(x.abs()));
+            assert!((peak - 0.5).abs() < 1e-12);
+        }
+        assert!(brown(0, 1.0, &mut rng).is_empty());
+    }
+}
@@ -0,0 +1,406 @@
+//! Random e-commerce orders that add up: line totals, discount, tax, shipping and the grand
+//! total are all derived from each other the way a shop would compute them, so a pipeline
+//! under test can be checked against `Order::is_consistent` instead of trusting random
+//! fields that don't agree.
+//!
+//! Amounts are integers in the currency's minor units (cents), like real payment systems use,
+//! so there's no floating-point rounding to argue about.
+
+use crate::{
+    fake,
+    locale::{self, Currency},
+    probability,
+};
+use rand::prelude::*;
+use somelib::error::Error;
+use std::ops::RangeInclusive;
+
+/// What a coupon takes off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Discount {
+    /// A whole-number percentage of the subtotal
+    Percent(u32),
+    /// A fixed amount in minor units, capped at the subtotal
+    Fixed(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Coupon {
+    pub code: String,
+    pub discount: Discount,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LineItem {
+    pub sku: String,
+    pub name: String,
+    pub quantity: u32,
+    pub unit_price: i64,
+    /// `quantity * unit_price`
+    pub line_total: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    pub id: String,
+    pub customer_email: String,
+    pub currency: Currency,
+    /// No two with the same SKU
+    pub items: Vec<LineItem>,
+    pub coupon: Option<Coupon>,
+    /// The sum of the line totals
+    pub subtotal: i64,
+    pub discount: i64,
+    /// In basis points, hundredths of a percent: 825 is 8.25%
+    pub tax_rate: u32,
+    /// On the discounted subtotal, rounded half up. Shipping isn't taxed.
+    pub tax: i64,
+    pub shipping: i64,
+    pub total: i64,
+}
+
+impl Order {
+    /// Whether every derived amount matches the amounts it's derived from
+    pub fn is_consistent(&self) -> bool {
+        let lines_add_up = self
+            .items
+            .iter()
+            .all(|item| item.line_total == item.quantity as i64 * item.unit_price);
+        let subtotal = self.items.iter().map(|item| item.line_total).sum::<i64>();
+        let discount = self
+            .coupon
+            .as_ref()
+            .map_or(0, |coupon| discount(coupon.discount, subtotal));
+        let taxable = subtotal - discount;
+        lines_add_up
+            && self.subtotal == subtotal
+            && self.discount == discount
+            && self.tax == tax(taxable, self.tax_rate)
+            && self.total == taxable + self.tax + self.shipping
+    }
+
+    /// An amount of this order's currency for display, e.g. `"$12.34"`
+    pub fn format(&self, minor: i64) -> String {
+        format!("{}{}", self.currency.symbol, self.currency.format(minor))
+    }
+}
+
+fn discount(discount: Discount, subtotal: i64) -> i64 {
+    match discount {
+        Discount::Percent(percent) => subtotal * percent as i64 / 100,
+        Discount::Fixed(amount) => amount.min(subtotal),
+    }
+}
+
+fn tax(taxable: i64, rate: u32) -> i64 {
+    (taxable * rate as i64 + 5_000) / 10_000
+}
+
+const ADJECTIVES: [&str; 12] = [
+    "Classic",
+    "Compact",
+    "Deluxe",
+    "Eco",
+    "Ergonomic",
+    "Heavy-Duty",
+    "Mini",
+    "Organic",
+    "Portable",
+    "Premium",
+    "Smart",
+    "Vintage",
+];
+
+const PRODUCTS: [&str; 12] = [
+    "Backpack",
+    "Blender",
+    "Desk Lamp",
+    "Headphones",
+    "Kettle",
+    "Mug",
+    "Notebook",
+    "Sneakers",
+    "Sunglasses",
+    "Tent",
+    "Water Bottle",
+    "Yoga Mat",
+];
+
+/// Generates orders
+pub struct OrderGen {
+    currency: Currency,
+    items: RangeInclusive<usize>,
+    quantity: RangeInclusive<u32>,
+    price: RangeInclusive<i64>,
+    tax_rate: u32,
+    coupon_rate: f64,
+    shipping: i64,
+    free_shipping_over: i64,
+}
+
+impl OrderGen {
+    /// US dollars: 1 to 5 items of 1 to 3 each at $1 to $200, 8.25% tax, a coupon on one
+    /// order in five, and $4.99 shipping that's free from $50
+    pub fn new() -> Self {
+        OrderGen {
+            currency: locale::USD,
+            items: 1..=5,
+            quantity: 1..=3,
+            price: 100..=20_000,
+            tax_rate: 825,
+            coupon_rate: 0.2,
+            shipping: 499,
+            free_shipping_over: 5_000,
+        }
+    }
+
+    /// Amounts set afterwards are in this currency's minor units
+    pub fn currency(mut self, currency: Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// How many distinct products an order has, at least one
+    pub fn items(mut self, items: RangeInclusive<usize>) -> Result<Self, Error> {
+        if items.is_empty()
+            || *items.start() == 0
+            || *items.end() > ADJECTIVES.len() * PRODUCTS.len()
+        {
+            return Err(Error::InvalidParameter(format!(
+                "item count range {:?} must be non-empty, from 1 and at most {}",
+                items,
+                ADJECTIVES.len() * PRODUCTS.len()
+            )));
+        }
+        self.items = items;
+        Ok(self)
+    }
+
+    pub fn quantity(mut self, quantity: RangeInclusive<u32>) -> Result<Self, Error> {
+        if quantity.is_empty() || *quantity.start() == 0 {
+            return Err(Error::InvalidParameter(format!(
+                "quantity range {:?} must be non-empty and from 1",
+                quantity
+            )));
+        }
+        self.quantity = quantity;
+        Ok(self)
+    }
+
+    /// Unit prices in minor units
+    pub fn price(mut self, price: RangeInclusive<i64>) -> Result<Self, Error> {
+        if price.is_empty() || *price.start() < 1 {
+            return Err(Error::InvalidParameter(format!(
+                "price range {:?} must be non-empty and positive",
+                price
+            )));
+        }
+        self.price = price;
+        Ok(self)
+    }
+
+    /// Tax as a fraction, e.g. `0.2` for 20% VAT
+    pub fn tax_rate(mut self, rate: f64) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(Error::InvalidParameter(format!(
+                "tax rate {} must be between 0 and 1",
+                rate
+            )));
+        }
+        self.tax_rate = (rate * 10_000.0).round() as u32;
+        Ok(self)
+    }
+
+    /// The chance an order uses a coupon
+    pub fn coupon_rate(mut self, rate: f64) -> Self {
+        self.coupon_rate = probability(rate);
+        self
+    }
+
+    /// A flat shipping fee, waived once the discounted subtotal reaches `free_over`
+    pub fn shipping(mut self, fee: i64, free_over: i64) -> Result<Self, Error> {
+        if fee < 0 || free_over < 0 {
+            return Err(Error::InvalidParameter(
+                "shipping amounts can't be negative".into(),
+            ));
+        }
+        self.shipping = fee;
+        self.free_shipping_over = free_over;
+        Ok(self)
+    }
+
+    pub fn order<R>(&self, rng: &mut R) -> Order
+    where
+        R: Rng + ?Sized,
+    {
+        // Distinct products, so no SKU shows up on two lines
+        let mut names = ADJECTIVES
+            .iter()
+            .flat_map(|adjective| PRODUCTS.iter().map(move |product| (*adjective, *product)))
+            .collect::<Vec<_>>();
+        let count = rng.gen_range(self.items.clone());
+        let (names, _) = names.partial_shuffle(rng, count);
+        let mut items = names
+            .iter()
+            .map(|(adjective, product)| {
+                let quantity = rng.gen_range(self.quantity.clone());
+                let unit_price = self.unit_price(rng);
+                LineItem {
+                    sku: format!("SKU-{:06}", rng.gen_range(0..1_000_000)),
+                    name: format!("{} {}", adjective, product),
+                    quantity,
+                    unit_price,
+                    line_total: quantity as i64 * unit_price,
+                }
+            })
+            .collect::<Vec<_>>();
+        // A collision is unlikely, but the promise is no repeated SKUs
+        for i in 1..items.len() {
+            while items[..i].iter().any(|other| other.sku == items[i].sku) {
+                items[i].sku = format!("SKU-{:06}", rng.gen_range(0..1_000_000));
+            }
+        }
+
+        let subtotal = items.iter().map(|item| item.line_total).sum::<i64>();
+        let coupon = rng
+            .gen_bool(self.coupon_rate)
+            .then(|| self.coupon(subtotal, rng));
+        let discount = coupon
+            .as_ref()
+            .map_or(0, |coupon| discount(coupon.discount, subtotal));
+        let taxable = subtotal - discount;
+        let tax = tax(taxable, self.tax_rate);
+        let shipping = if taxable >= self.free_shipping_over {
+            0
+        } else {
+            self.shipping
+        };
+        Order {
+            id: format!("ORD-{:08}", rng.gen_range(0..100_000_000)),
+            customer_email: fake::email(rng),
+            currency: self.currency,
+            items,
+            coupon,
+            subtotal,
+            discount,
+            tax_rate: self.tax_rate,
+            tax,
+            shipping,
+            total: taxable + tax + shipping,
+        }
+    }
+
+    /// Shops like prices ending in 99 where the currency has cents
+    fn unit_price<R>(&self, rng: &mut R) -> i64
+    where
+        R: Rng + ?Sized,
+    {
+        let price = rng.gen_range(self.price.clone());
+        let rounded = price - price % 100 + 99;
+        if self.currency.minor_units == 2 && self.price.contains(&rounded) {
+            rounded
+        } else {
+            price
+        }
+    }
+
+    fn coupon<R>(&self, subtotal: i64, rng: &mut R) -> Coupon
+    where
+        R: Rng + ?Sized,
+    {
+        let percent = rng.gen_range(1..=6) * 5;
+        let discount = if rng.gen_bool(0.5) {
+            Discount::Percent(percent)
+        } else {
+            // Around the same size as the percentage would be, in whole units
+            let unit = 10i64.pow(self.currency.minor_units as u32);
+            let amount = (subtotal * percent as i64 / 100 / unit).max(1) * unit;
+            Discount::Fixed(amount)
+        };
+        let code = match discount {
+            Discount::Percent(p) => format!("SAVE{}", p),
+            Discount::Fixed(_) => format!("TAKE{}", percent),
+        };
+        Coupon { code, discount }
+    }
+}
+
+impl Default for OrderGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn it_makes_consistent_orders() {
+        let mut rng = StdRng::seed_from_u64(275);
+        let gen = OrderGen::new().coupon_rate(0.5);
+        let mut coupons = 0;
+        for _ in 0..1000 {
+            let order = gen.order(&mut rng);
+            assert!(order.is_consistent(), "{:?}", order);
+            assert!((1..=5).contains(&order.items.len()));
+            let skus = order.items.iter().map(|i| &i.sku).collect::<HashSet<_>>();
+            assert_eq!(skus.len(), order.items.len());
+            assert!(order.discount <= order.subtotal && order.total > 0);
+            assert_eq!(
+                order.shipping == 0,
+                order.subtotal - order.discount >= 5_000
+            );
+            if order.coupon.is_some() {
+                coupons += 1;
+            }
+        }
+        assert!((400..600).contains(&coupons), "{}", coupons);
+    }
+
+    #[test]
+    fn it_detects_tampering() {
+        let mut order = OrderGen::new().order(&mut StdRng::seed_from_u64(276));
+        assert!(order.is_consistent());
+        order.items[0].quantity += 1;
+        assert!(!order.is_consistent());
+
+        let mut order = OrderGen::new().order(&mut StdRng::seed_from_u64(276));
+        order.tax += 1;
+        assert!(!order.is_consistent());
+    }
+
+    #[test]
+    fn it_respects_the_currency_and_rates() {
+        let mut rng = StdRng::seed_from_u64(277);
+        let gen = OrderGen::new()
+            .currency(locale::JPY)
+            .price(100..=10_000)
+            .unwrap()
+            .tax_rate(0.1)
+            .unwrap()
+            .shipping(0, 0)
+            .unwrap();
+        let order = gen.order(&mut rng);
+        assert!(order.is_consistent());
+        assert_eq!((order.tax_rate, order.shipping), (1000, 0));
+        assert_eq!(order.format(1234), "¥1234");
+
+        // 8.25% of $10.00 is 82.5 cents, rounded up
+        assert_eq!(tax(1000, 825), 83);
+        assert_eq!(discount(Discount::Fixed(500), 300), 300);
+        assert!(OrderGen::new().tax_rate(1.5).is_err());
+        assert!(OrderGen::new().items(0..=2).is_err());
+        assert!(OrderGen::new().price(0..=10).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = OrderGen::new().coupon_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(275);
+        for _ in 0..20 {
+            assert!(gen.order(&mut rng).coupon.is_none());
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Items with random sizes and packers that group them into bins, for testing chunking,
+//! batching and storage allocation code.
+
+use rand::{distributions::WeightedIndex, prelude::*};
+use somelib::error::Error;
+
+/// An item of a given size, e.g. a file, a message or a blob
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizedItem {
+    /// Position in the order the items were generated
+    pub id: usize,
+    pub size: u64,
+}
+
+/// A group of items whose sizes add up to `total`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bin {
+    pub items: Vec<SizedItem>,
+    pub total: u64,
+}
+
+impl Bin {
+    fn push(&mut self, item: SizedItem) {
+        self.total += item.size;
+        self.items.push(item);
+    }
+}
+
+/// Generates items whose sizes follow any distribution over `f64`, e.g. `rand_distr`'s
+/// `LogNormal` for file sizes. Samples are rounded and clamped to `[min_size, max_size]`.
+pub struct SizedItemGen<D> {
+    dist: D,
+    min_size: u64,
+    max_size: u64,
+}
+
+impl<D> SizedItemGen<D>
+where
+    D: Distribution<f64>,
+{
+    pub fn new(dist: D) -> Self {
+        SizedItemGen {
+            dist,
+            min_size: 0,
+            max_size: u64::MAX,
+        }
+    }
+
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// `n` items with ids `0..n`
+    pub fn items<R>(&self, n: usize, rng: &mut R) -> Vec<SizedItem>
+    where
+        R: Rng + ?Sized,
+    {
+        (0..n)
+            .map(|id| {
+                // `as` saturates floats, so negative samples become 0 and huge ones u64::MAX
+                let size =
+                    (self.dist.sample(rng).round() as u64).clamp(self.min_size, self.max_size);
+                SizedItem { id, size }
+            })
+            .collect()
+    }
+}
+
+/// Pick one item with probability proportional to its size, the way a random byte of a
+/// dataset lands in bigger files more often. Errors if every item is empty.
+pub fn pick_by_size<'a, R>(items: &'a [SizedItem], rng: &mut R) -> Result<&'a SizedItem, Error>
+where
+    R: Rng + ?Sized,
+{
+    let dist = WeightedIndex::new(items.iter().map(|item| item.size)).map_err(|_| {
+        Error::InvalidParameter("size-weighted picks need an item with a nonzero size".into())
+    })?;
+    Ok(&items[dist.sample(rng)])
+}
+
+/// Next fit: fill bins in order, starting a new one whenever the next item would push the
+/// current bin past `target`. Keeps the items' order, like batching a stream.
+///
+/// Items bigger than `target` get a bin of their own.
+pub fn pack_in_order(items: &[SizedItem], target: u64) -> Vec<Bin> {
+    let mut bins = Vec::new();
+    let mut current = Bin::default();
+    for item in items {
+        if !current.items.is_empty() && current.total + item.size > target {
+            bins.push(std::mem::take(&mut current));
+        }
+        current.push(*item);
+    }
+    if !current.items.is_empty() {
+        bins.push(current);
+    }
+    bins
+}
+
+/// First fit decreasing: place the biggest items first, each into the first bin with room.
+/// Reorders items but uses at most about 11/9 of the optimal number of bins.
+///
+/// Items bigger than `target` get a bin of their own.
+pub fn pack_tight(items: &[SizedItem], target: u64) -> Vec<Bin> {
+    let mut sorted = items.to_vec();
+    sorted.sort_by_key(|item| std::cmp::Reverse(item.size));
+
+    let mut bins: Vec<Bin> = Vec::new();
+    for item in sorted {
+        match bins.iter_mut().find(|bin| bin.total + item.size <= target) {
+            Some(bin) => bin.push(item),
+            None => {
+                let mut bin = Bin::default();
+                bin.push(item);
+                bins.push(bin);
+            }
+        }
+    }
+    bins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_distr::LogNormal;
+
+    #[test]
+    fn it_packs_items_near_the_target() {
+        let mut rng = StdRng::seed_from_u64(37);
+        let gen = SizedItemGen::new(LogNormal::new(8.0, 1.0).unwrap())
+            .min_size(1)
+            .max_size(20_000);
+        let items = gen.items(500, &mut rng);
+        let total = items.iter().map(|item| item.size).sum::<u64>();
+
+        for bins in [pack_in_order(&items, 50_000), pack_tight(&items, 50_000)] {
+            assert_eq!(bins.iter().map(|bin| bin.total).sum::<u64>(), total);
+            assert_eq!(bins.iter().map(|bin| bin.items.len()).sum::<usize>(), 500);
+            assert!(bins.iter().all(|bin| bin.total <= 50_000));
+        }
+
+        let in_order = pack_in_order(&items, 50_000);
+        let ids = in_order
+            .iter()
+            .flat_map(|bin| &bin.items)
+            .map(|item| item.id);
+        assert!(ids.eq(0..500));
+        assert!(pack_tight(&items, 50_000).len() <= in_order.len());
+    }
+
+    #[test]
+    fn it_picks_bigger_items_more_often() {
+        let mut rng = StdRng::seed_from_u64(38);
+        let items = [
+            SizedItem { id: 0, size: 1 },
+            SizedItem { id: 1, size: 9 },
+            SizedItem { id: 2, size: 0 },
+        ];
+
+        let big = (0..1000)
+            .filter(|_| pick_by_size(&items, &mut rng).unwrap().id == 1)
+            .count();
+        assert!((850..950).contains(&big), "{}", big);
+        assert!(pick_by_size(&items[2..], &mut rng).is_err());
+    }
+}
@@ -0,0 +1,462 @@
+//! File system paths for a target OS, with the names that trip up file-handling code mixed
+//! in: Windows device names, components and paths over the OS limits, the same name in two
+//! normalization forms, trailing dots and spaces, `..` and other link-like segments.
+//!
+//! Paths are generated as text for the *target*, not the OS running the tests, so a Linux CI
+//! box can produce Windows paths for a parser without touching its own file system.
+
+use crate::probability;
+use rand::{distributions::Alphanumeric, prelude::*};
+use somelib::error::Error;
+use std::{
+    fmt::{Display, Formatter},
+    ops::RangeInclusive,
+};
+
+/// The OS whose rules a path follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Target {
+    Windows,
+    Linux,
+    /// Like Linux, but with a shorter `PATH_MAX`, and HFS+ stores names decomposed (NFD)
+    MacOs,
+}
+
+impl Target {
+    pub fn separator(&self) -> char {
+        match self {
+            Target::Windows => '\\',
+            Target::Linux | Target::MacOs => '/',
+        }
+    }
+
+    /// Longest whole path: `MAX_PATH` in UTF-16 units on Windows (without the `\\?\` prefix),
+    /// `PATH_MAX` in bytes elsewhere
+    pub fn max_path(&self) -> usize {
+        match self {
+            Target::Windows => 260,
+            Target::Linux => 4096,
+            Target::MacOs => 1024,
+        }
+    }
+
+    /// Longest single component, `NAME_MAX`. Windows counts UTF-16 units, the others bytes.
+    pub fn max_name(&self) -> usize {
+        255
+    }
+
+    /// The length of `s` in the unit this target's limits are measured in
+    pub fn length_of(&self, s: &str) -> usize {
+        match self {
+            Target::Windows => s.encode_utf16().count(),
+            Target::Linux | Target::MacOs => s.len(),
+        }
+    }
+}
+
+/// A kind of awkward path component
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathQuirk {
+    /// `CON`, `NUL`, `COM1` and friends, in any case and even with an extension
+    ReservedName,
+    /// The whole path is longer than the target's `max_path`
+    LongPath,
+    /// One component is longer than `max_name`, sometimes only when counted in bytes
+    LongName,
+    /// An accented name, precomposed (NFC) or decomposed (NFD); both look the same
+    Normalization,
+    /// A name ending in `.` or a space, which Windows silently strips
+    TrailingDotSpace,
+    /// `.`, `..`, `~` or `name -> target`, segments that look like they point elsewhere
+    LinkLike,
+    /// A name starting with `-`, which command line tools read as an option
+    LeadingDash,
+    /// A character the target can't store in a name: `<>:"|?*` and controls on Windows, NUL
+    /// everywhere
+    IllegalChar,
+}
+
+impl PathQuirk {
+    pub const ALL: [PathQuirk; 8] = [
+        PathQuirk::ReservedName,
+        PathQuirk::LongPath,
+        PathQuirk::LongName,
+        PathQuirk::Normalization,
+        PathQuirk::TrailingDotSpace,
+        PathQuirk::LinkLike,
+        PathQuirk::LeadingDash,
+        PathQuirk::IllegalChar,
+    ];
+
+    /// Whether a path with this quirk can be created as written on `target`. The others are
+    /// legal, just surprising.
+    pub fn is_valid_on(&self, target: Target) -> bool {
+        match self {
+            PathQuirk::LongPath | PathQuirk::LongName | PathQuirk::IllegalChar => false,
+            PathQuirk::ReservedName | PathQuirk::TrailingDotSpace => target != Target::Windows,
+            PathQuirk::Normalization | PathQuirk::LinkLike | PathQuirk::LeadingDash => true,
+        }
+    }
+}
+
+const RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The same names precomposed and decomposed
+const NORMALIZATION: &[(&str, &str)] = &[
+    ("caf\u{e9}", "cafe\u{301}"),
+    ("\u{c5}ngstr\u{f6}m", "A\u{30a}ngstro\u{308}m"),
+    ("ma\u{f1}ana", "man\u{303}ana"),
+    (
+        "\u{d55c}\u{ae00}",
+        "\u{1112}\u{1161}\u{11ab}\u{1100}\u{1173}\u{11af}",
+    ),
+];
+
+const LINK_LIKE: &[&str] = &[".", "..", "~", "latest -> v2.1.0"];
+
+const WINDOWS_ILLEGAL: &[char] = &['<', '>', ':', '"', '|', '?', '*', '\u{1}', '\u{1f}'];
+
+/// A generated path and the quirks it contains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenPath {
+    pub text: String,
+    pub target: Target,
+    pub quirks: Vec<PathQuirk>,
+}
+
+impl GenPath {
+    /// The names between separators, without the root
+    pub fn components(&self) -> impl Iterator<Item = &str> {
+        let body = match self.target {
+            Target::Windows => self.text.strip_prefix("C:\\"),
+            _ => None,
+        };
+        body.unwrap_or(&self.text)
+            .split(self.target.separator())
+            .filter(|c| !c.is_empty())
+    }
+
+    /// Whether the path could be created on its target as written
+    pub fn is_valid(&self) -> bool {
+        self.quirks.iter().all(|q| q.is_valid_on(self.target))
+    }
+}
+
+impl Display for GenPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Generates paths for one target, replacing components with quirky ones at `quirk_rate`
+pub struct PathGen {
+    target: Target,
+    depth: RangeInclusive<usize>,
+    absolute: bool,
+    quirk_rate: f64,
+    quirks: Vec<PathQuirk>,
+}
+
+impl PathGen {
+    /// Relative paths of 1-4 components, a quarter of them quirky
+    pub fn new(target: Target) -> Self {
+        PathGen {
+            target,
+            depth: 1..=4,
+            absolute: false,
+            quirk_rate: 0.25,
+            quirks: PathQuirk::ALL.to_vec(),
+        }
+    }
+
+    /// How many components, at least one
+    pub fn depth(mut self, depth: RangeInclusive<usize>) -> Result<Self, Error> {
+        if *depth.start() == 0 || depth.is_empty() {
+            return Err(Error::InvalidParameter(format!(
+                "depth {:?} must be a non-empty range starting at 1 or more",
+                depth
+            )));
+        }
+        self.depth = depth;
+        Ok(self)
+    }
+
+    /// Start at the root: `C:\` on Windows, `/` elsewhere
+    pub fn absolute(mut self, absolute: bool) -> Self {
+        self.absolute = absolute;
+        self
+    }
+
+    /// Chance of each component being quirky, and separately of the path being too long
+    pub fn quirk_rate(mut self, rate: f64) -> Self {
+        self.quirk_rate = probability(rate);
+        self
+    }
+
+    /// Which kinds to use, at least one
+    pub fn quirks(mut self, quirks: &[PathQuirk]) -> Result<Self, Error> {
+        if quirks.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one quirk is needed".into(),
+            ));
+        }
+        self.quirks = quirks.to_vec();
+        Ok(self)
+    }
+
+    pub fn path<R>(&self, rng: &mut R) -> GenPath
+    where
+        R: Rng + ?Sized,
+    {
+        let mut quirks = Vec::new();
+        // `LongPath` is about the whole path, so it's rolled once rather than per component
+        let per_component = self
+            .quirks
+            .iter()
+            .copied()
+            .filter(|q| *q != PathQuirk::LongPath)
+            .collect::<Vec<_>>();
+
+        let depth = rng.gen_range(self.depth.clone());
+        let mut components = Vec::with_capacity(depth);
+        for i in 0..depth {
+            let last = i + 1 == depth;
+            let quirk = match per_component.choose(rng) {
+                Some(&quirk) if rng.gen_bool(self.quirk_rate) => quirk,
+                _ => {
+                    components.push(plain_name(last, rng));
+                    continue;
+                }
+            };
+            components.push(self.quirky_name(quirk, rng));
+            if !quirks.contains(&quirk) {
+                quirks.push(quirk);
+            }
+        }
+
+        let separator = self.target.separator().to_string();
+        let root = match (self.absolute, self.target) {
+            (false, _) => "",
+            (true, Target::Windows) => "C:\\",
+            (true, _) => "/",
+        };
+        let mut text = format!("{}{}", root, components.join(&separator));
+
+        if self.quirks.contains(&PathQuirk::LongPath) && rng.gen_bool(self.quirk_rate) {
+            // Pad with ordinary directories under the root until it no longer fits
+            while self.target.length_of(&text) <= self.target.max_path() {
+                let name = token(rng.gen_range(16..=64), rng);
+                text.insert_str(root.len(), &format!("{}{}", name, separator));
+            }
+            quirks.push(PathQuirk::LongPath);
+        }
+
+        GenPath {
+            text,
+            target: self.target,
+            quirks,
+        }
+    }
+
+    fn quirky_name<R>(&self, quirk: PathQuirk, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        match quirk {
+            PathQuirk::ReservedName => {
+                let name = RESERVED.choose(rng).unwrap();
+                let name = match rng.gen_range(0..3) {
+                    0 => name.to_string(),
+                    1 => name.to_lowercase(),
+                    _ => format!("{}{}", &name[..1], name[1..].to_lowercase()),
+                };
+                // `CON.txt` is just as much the console as `CON`
+                if rng.gen_bool(0.5) {
+                    format!("{}.txt", name)
+                } else {
+                    name
+                }
+            }
+            PathQuirk::LongName => {
+                let max = self.target.max_name();
+                if rng.gen_bool(0.5) {
+                    token(rng.gen_range(max + 1..=max + 64), rng)
+                } else {
+                    // Two bytes per character: too long in bytes, but on Windows, which
+                    // counts UTF-16 units, twice the limit is needed
+                    let chars = match self.target {
+                        Target::Windows => max + 1,
+                        _ => max / 2 + 1,
+                    };
+                    "\u{e9}".repeat(chars)
+                }
+            }
+            PathQuirk::Normalization => {
+                let (nfc, nfd) = NORMALIZATION.choose(rng).unwrap();
+                // Decomposed is what HFS+ hands back, whatever was written
+                if self.target == Target::MacOs || rng.gen_bool(0.5) {
+                    nfd.to_string()
+                } else {
+                    nfc.to_string()
+                }
+            }
+            PathQuirk::TrailingDotSpace => {
+                let tail = [".", " ", ". ", " ."].choose(rng).unwrap();
+                format!("{}{}", token(rng.gen_range(1..=8), rng), tail)
+            }
+            PathQuirk::LinkLike => LINK_LIKE.choose(rng).unwrap().to_string(),
+            PathQuirk::LeadingDash => match rng.gen_range(0..3) {
+                0 => "-rf".into(),
+                1 => "--help".into(),
+                _ => format!("-{}", token(rng.gen_range(1..=8), rng)),
+            },
+            PathQuirk::IllegalChar => {
+                let mut name = token(rng.gen_range(2..=8), rng);
+                let c = match self.target {
+                    Target::Windows => *WINDOWS_ILLEGAL.choose(rng).unwrap(),
+                    _ => '\0',
+                };
+                name.insert(rng.gen_range(1..name.len()), c);
+                name
+            }
+            PathQuirk::LongPath => unreachable!("`LongPath` is applied to the whole path"),
+        }
+    }
+}
+
+fn token<R>(len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    rng.sample_iter(Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// A boring name, with an extension when it's the last component
+fn plain_name<R>(last: bool, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let name = token(rng.gen_range(1..=12), rng);
+    if last {
+        let ext = ["txt", "log", "json", "tar.gz", "rs"].choose(rng).unwrap();
+        format!("{}.{}", name, ext)
+    } else {
+        name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGETS: [Target; 3] = [Target::Windows, Target::Linux, Target::MacOs];
+
+    #[test]
+    fn it_makes_plain_paths_without_quirks() {
+        let mut rng = StdRng::seed_from_u64(45);
+        for target in TARGETS {
+            let gen = PathGen::new(target)
+                .quirk_rate(0.0)
+                .absolute(true)
+                .depth(2..=3)
+                .unwrap();
+            for _ in 0..100 {
+                let path = gen.path(&mut rng);
+                assert!(path.quirks.is_empty() && path.is_valid());
+                assert!((2..=3).contains(&path.components().count()), "{}", path);
+                assert!(path.text.starts_with(if target == Target::Windows {
+                    "C:\\"
+                } else {
+                    "/"
+                }));
+            }
+        }
+        assert!(PathGen::new(Target::Linux).depth(0..=2).is_err());
+        assert!(PathGen::new(Target::Linux).quirks(&[]).is_err());
+    }
+
+    #[test]
+    fn it_makes_paths_longer_than_the_target_allows() {
+        let mut rng = StdRng::seed_from_u64(46);
+        for target in TARGETS {
+            let gen = PathGen::new(target)
+                .quirks(&[PathQuirk::LongPath])
+                .unwrap()
+                .quirk_rate(1.0)
+                .absolute(true);
+            let path = gen.path(&mut rng);
+            assert_eq!(path.quirks, vec![PathQuirk::LongPath]);
+            assert!(target.length_of(&path.text) > target.max_path());
+            assert!(path.text.starts_with(if target == Target::Windows {
+                "C:\\"
+            } else {
+                "/"
+            }));
+            assert!(!path.is_valid());
+        }
+    }
+
+    #[test]
+    fn it_makes_each_kind_of_quirk() {
+        let mut rng = StdRng::seed_from_u64(47);
+        for quirk in PathQuirk::ALL {
+            for target in TARGETS {
+                let gen = PathGen::new(target)
+                    .quirks(&[quirk])
+                    .unwrap()
+                    .quirk_rate(1.0)
+                    .depth(1..=1)
+                    .unwrap();
+                let path = gen.path(&mut rng);
+                assert_eq!(path.quirks, vec![quirk]);
+                assert_eq!(path.is_valid(), quirk.is_valid_on(target));
+
+                let name = path.components().last().unwrap();
+                match quirk {
+                    PathQuirk::ReservedName => {
+                        let stem = name.split('.').next().unwrap().to_uppercase();
+                        assert!(RESERVED.contains(&stem.as_str()), "{}", name);
+                    }
+                    PathQuirk::LongName => assert!(target.length_of(name) > target.max_name()),
+                    PathQuirk::TrailingDotSpace => assert!(name.ends_with(['.', ' '])),
+                    PathQuirk::LeadingDash => assert!(name.starts_with('-')),
+                    PathQuirk::IllegalChar if target == Target::Windows => {
+                        assert!(name.contains(WINDOWS_ILLEGAL))
+                    }
+                    PathQuirk::IllegalChar => assert!(name.contains('\0')),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_decomposes_names_for_macos() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let gen = PathGen::new(Target::MacOs)
+            .quirks(&[PathQuirk::Normalization])
+            .unwrap()
+            .quirk_rate(1.0);
+        for _ in 0..20 {
+            let path = gen.path(&mut rng);
+            assert!(path
+                .components()
+                .all(|name| !NORMALIZATION.iter().any(|(nfc, _)| name == *nfc)));
+        }
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = PathGen::new(Target::Linux).quirk_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(266);
+        for _ in 0..20 {
+            assert!(gen.path(&mut rng).quirks.is_empty());
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Load-balancer style selection over a changing set of backends. Picks only take a read
+//! lock and bump reference counts, so the hot path never allocates.
+
+use rand::prelude::*;
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicI64, AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Each pick is independent, with probability proportional to weight
+    Weighted,
+    /// Sample two backends and take the one with fewer picks in flight relative to its
+    /// weight. Nearly as good as checking every backend, at the cost of checking two.
+    PowerOfTwoChoices,
+    /// Nginx's smooth weighted round robin: deterministic, proportional to weight, and
+    /// interleaved (weights 5, 1, 1 give `a a b a c a a`, not `a a a a a b c`)
+    SmoothRoundRobin,
+}
+
+struct Backend<T> {
+    item: T,
+    weight: AtomicU32,
+    /// Picks whose `Picked` guard is still alive
+    in_flight: AtomicUsize,
+    /// Smooth round robin's running score
+    current: AtomicI64,
+}
+
+/// A thread-safe picker, usually shared behind an `Arc`
+pub struct Picker<T> {
+    strategy: Strategy,
+    backends: RwLock<Vec<Arc<Backend<T>>>>,
+    /// Smooth round robin updates every backend's score in one pick, which has to happen
+    /// atomically as a whole
+    round_robin: Mutex<()>,
+}
+
+/// A picked backend. Dereferences to the item; dropping it marks the pick as finished,
+/// which is what `PowerOfTwoChoices` balances on, so hold it for the whole request.
+pub struct Picked<T> {
+    backend: Arc<Backend<T>>,
+}
+
+impl<T> Deref for Picked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.backend.item
+    }
+}
+
+impl<T> Drop for Picked<T> {
+    fn drop(&mut self) {
+        self.backend.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<T> Picker<T> {
+    pub fn new(strategy: Strategy) -> Self {
+        Picker {
+            strategy,
+            backends: RwLock::new(Vec::new()),
+            round_robin: Mutex::new(()),
+        }
+    }
+
+    /// Add a backend. A weight of 0 keeps it in the set without picking it, e.g. to drain
+    /// it before removal.
+    pub fn add(&self, item: T, weight: u32) {
+        self.write().push(Arc::new(Backend {
+            item,
+            weight: AtomicU32::new(weight),
+            in_flight: AtomicUsize::new(0),
+            current: AtomicI64::new(0),
+        }));
+    }
+
+    /// Remove every backend matching `pred`, returning how many were removed. Picks that
+    /// are still in flight stay valid.
+    pub fn remove_where<F>(&self, mut pred: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        let mut backends = self.write();
+        let before = backends.len();
+        backends.retain(|backend| !pred(&backend.item));
+        before - backends.len()
+    }
+
+    /// Change the weight of every backend matching `pred`
+    pub fn set_weight<F>(&self, mut pred: F, weight: u32)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        for backend in self.read().iter().filter(|backend| pred(&backend.item)) {
+            backend.weight.store(weight, Ordering::Relaxed);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pick a backend, or `None` if there are none with a nonzero weight.
+    /// `SmoothRoundRobin` doesn't use `rng`.
+    pub fn pick<R>(&self, rng: &mut R) -> Option<Picked<T>>
+    where
+        R: Rng + ?Sized,
+    {
+        let backends = self.read();
+        let backend = match self.strategy {
+            Strategy::Weighted => weighted(&backends, rng).map(|(backend, _)| backend),
+            Strategy::PowerOfTwoChoices => power_of_two(&backends, rng),
+            Strategy::SmoothRoundRobin => {
+                let _lock = self.round_robin.lock().unwrap_or_else(|e| e.into_inner());
+                smooth_round_robin(&backends)
+            }
+        }?;
+        backend.in_flight.fetch_add(1, Ordering::Relaxed);
+        Some(Picked {
+            backend: Arc::clone(backend),
+        })
+    }
+
+    // A panic while holding the lock can't leave the `Vec` half-updated, so poisoning is
+    // safe to ignore
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Vec<Arc<Backend<T>>>> {
+        self.backends.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Vec<Arc<Backend<T>>>> {
+        self.backends.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn weight<T>(backend: &Backend<T>) -> u64 {
+    u64::from(backend.weight.load(Ordering::Relaxed))
+}
+
+/// Pick a backend with probability proportional to its weight, returning the weight it was
+/// picked with. `set_weight` can run while we scan, so each weight is loaded exactly once:
+/// keep a running total and replace the pick with the current backend with probability
+/// `weight / total`, which leaves every backend picked with probability `weight / sum`.
+/// Summing first and scanning again would read weights twice and could overshoot the end.
+fn weighted<'a, T, R>(
+    backends: &'a [Arc<Backend<T>>],
+    rng: &mut R,
+) -> Option<(&'a Arc<Backend<T>>, u64)>
+where
+    R: Rng + ?Sized,
+{
+    let mut total = 0;
+    let mut picked = None;
+    for backend in backends {
+        let w = weight(backend);
+        if w == 0 {
+            continue;
+        }
+        total += w;
+        if rng.gen_range(0..total) < w {
+            picked = Some((backend, w));
+        }
+    }
+    picked
+}
+
+fn power_of_two<'a, T, R>(
+    backends: &'a [Arc<Backend<T>>],
+    rng: &mut R,
+) -> Option<&'a Arc<Backend<T>>>
+where
+    R: Rng + ?Sized,
+{
+    let (a, weight_a) = weighted(backends, rng)?;
+    let (b, weight_b) = weighted(backends, rng)?;
+    // Compare `in_flight / weight` without dividing, using the weights the candidates were
+    // drawn with, which are nonzero
+    let load = |backend: &Backend<T>| backend.in_flight.load(Ordering::Relaxed) as u64;
+    Some(if load(a) * weight_b <= load(b) * weight_a {
+        a
+    } else {
+        b
+    })
+}
+
+/// Raise every score by its weight, pick the highest, and lower it by the total
+fn smooth_round_robin<T>(backends: &[Arc<Backend<T>>]) -> Option<&Arc<Backend<T>>> {
+    let mut total = 0;
+    let mut best: Option<(&Arc<Backend<T>>, i64)> = None;
+    for backend in backends {
+        let w = weight(backend) as i64;
+        if w == 0 {
+            continue;
+        }
+        total += w;
+        let current = backend.current.fetch_add(w, Ordering::Relaxed) + w;
+        if best.is_none_or(|(_, score)| current > score) {
+            best = Some((backend, current));
+        }
+    }
+    let (backend, _) = best?;
+    backend.current.fetch_sub(total, Ordering::Relaxed);
+    Some(backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_interleaves_smooth_round_robin() {
+        let picker = Picker::new(Strategy::SmoothRoundRobin);
+        picker.add('a', 5);
+        picker.add('b', 1);
+        picker.add('c', 1);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let order = (0..7)
+            .map(|_| *picker.pick(&mut rng).unwrap())
+            .collect::<String>();
+        assert_eq!(order, "aabacaa");
+    }
+
+    #[test]
+    fn it_picks_by_weight() {
+        let mut rng = StdRng::seed_from_u64(43);
+        let picker = Picker::new(Strategy::Weighted);
+        picker.add("big", 3);
+        picker.add("small", 1);
+        picker.add("drained", 0);
+
+        let big = (0..4000)
+            .filter(|_| *picker.pick(&mut rng).unwrap() == "big")
+            .count();
+        assert!((2850..3150).contains(&big), "{}", big);
+
+        assert_eq!(picker.remove_where(|name| *name != "drained"), 2);
+        assert!(picker.pick(&mut rng).is_none());
+    }
+
+    #[test]
+    fn it_balances_in_flight_picks() {
+        let mut rng = StdRng::seed_from_u64(44);
+        let picker = Picker::new(Strategy::PowerOfTwoChoices);
+        for i in 0..4 {
+            picker.add(i, 1);
+        }
+
+        // Hold every pick so load builds up, P2C should keep it close to even
+        let held = (0..400)
+            .map(|_| picker.pick(&mut rng).unwrap())
+            .collect::<Vec<_>>();
+        let mut counts = [0; 4];
+        for pick in &held {
+            counts[**pick] += 1;
+        }
+        assert!(
+            counts.iter().all(|c| (85..=115).contains(c)),
+            "{:?}",
+            counts
+        );
+    }
+
+    #[test]
+    fn it_always_picks_while_weights_change() {
+        // Weights flip between 1 and 1000 under our feet but never reach zero, so every
+        // pick must find a backend
+        for strategy in [Strategy::Weighted, Strategy::PowerOfTwoChoices] {
+            let picker = Arc::new(Picker::new(strategy));
+            for i in 0..8 {
+                picker.add(i, 1000);
+            }
+            let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let flipper = {
+                let (picker, done) = (Arc::clone(&picker), Arc::clone(&done));
+                std::thread::spawn(move || {
+                    let mut heavy = false;
+                    while !done.load(Ordering::Relaxed) {
+                        picker.set_weight(|_| true, if heavy { 1000 } else { 1 });
+                        heavy = !heavy;
+                    }
+                })
+            };
+
+            let mut rng = StdRng::seed_from_u64(243);
+            for _ in 0..20_000 {
+                assert!(picker.pick(&mut rng).is_some(), "{:?}", strategy);
+            }
+            done.store(true, Ordering::Relaxed);
+            flipper.join().unwrap();
+        }
+    }
+}
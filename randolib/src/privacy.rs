@@ -0,0 +1,157 @@
+//! Differential privacy noise. The `*_noise` functions draw from `OsRng`, the operating
+//! system's secure generator, because noise from a predictable generator can be subtracted
+//! back out. The `*_with` variants take any generator, which is useful for tests but
+//! should not be used for real releases.
+//!
+//! These are textbook mechanisms on `f64`. Floating point sampling leaks a little
+//! information in theory (see Mironov, "On Significance of the Least Significant Bits for
+//! Differential Privacy"), so use a dedicated library when the privacy guarantee is a
+//! hard requirement.
+use rand::{prelude::*, rngs::OsRng};
+use rand_distr::Normal;
+use somelib::error::Error;
+
+fn check_positive(name: &str, value: f64) -> Result<(), Error> {
+    if !value.is_finite() || value <= 0.0 {
+        return Err(Error::InvalidParameter(format!(
+            "{} must be positive and finite, got {}",
+            name, value
+        )));
+    }
+    Ok(())
+}
+
+/// Scale `b = sensitivity / epsilon` of the Laplace mechanism
+pub fn laplace_scale(sensitivity: f64, epsilon: f64) -> Result<f64, Error> {
+    check_positive("sensitivity", sensitivity)?;
+    check_positive("epsilon", epsilon)?;
+    Ok(sensitivity / epsilon)
+}
+
+/// Noise for an `epsilon`-DP release of a query with the given L1 `sensitivity`
+pub fn laplace_noise(sensitivity: f64, epsilon: f64) -> Result<f64, Error> {
+    laplace_noise_with(sensitivity, epsilon, &mut OsRng)
+}
+
+pub fn laplace_noise_with<R>(sensitivity: f64, epsilon: f64, rng: &mut R) -> Result<f64, Error>
+where
+    R: Rng + ?Sized,
+{
+    let b = laplace_scale(sensitivity, epsilon)?;
+    // Inverse CDF: for `u` uniform in (-0.5, 0.5), `-b * sign(u) * ln(1 - 2|u|)` is Laplace.
+    // `gen_range` excludes the upper bound and we reject the lower one so `ln` never sees 0.
+    let u = loop {
+        let u = rng.gen_range(-0.5f64..0.5);
+        if u != -0.5 {
+            break u;
+        }
+    };
+    Ok(-b * u.signum() * (1.0 - 2.0 * u.abs()).ln())
+}
+
+/// Standard deviation of the Gaussian mechanism for `(epsilon, delta)`-DP with the given L2
+/// `sensitivity`: `sigma = sensitivity * sqrt(2 ln(1.25 / delta)) / epsilon`. The classic
+/// analysis only holds for `epsilon < 1`, so larger values are rejected.
+pub fn gaussian_sigma(sensitivity: f64, epsilon: f64, delta: f64) -> Result<f64, Error> {
+    check_positive("sensitivity", sensitivity)?;
+    check_positive("epsilon", epsilon)?;
+    if epsilon >= 1.0 {
+        return Err(Error::InvalidParameter(format!(
+            "the Gaussian mechanism needs epsilon < 1, got {}",
+            epsilon
+        )));
+    }
+    if !(delta > 0.0 && delta < 1.0) {
+        return Err(Error::InvalidParameter(format!(
+            "delta must be within (0, 1), got {}",
+            delta
+        )));
+    }
+    Ok(sensitivity * (2.0 * (1.25 / delta).ln()).sqrt() / epsilon)
+}
+
+pub fn gaussian_noise(sensitivity: f64, epsilon: f64, delta: f64) -> Result<f64, Error> {
+    gaussian_noise_with(sensitivity, epsilon, delta, &mut OsRng)
+}
+
+pub fn gaussian_noise_with<R>(
+    sensitivity: f64,
+    epsilon: f64,
+    delta: f64,
+    rng: &mut R,
+) -> Result<f64, Error>
+where
+    R: Rng + ?Sized,
+{
+    let sigma = gaussian_sigma(sensitivity, epsilon, delta)?;
+    // `sigma` was validated above so this can't fail
+    Ok(Normal::new(0.0, sigma).unwrap().sample(rng))
+}
+
+/// `value` plus Laplace noise, e.g. a count with sensitivity 1
+pub fn laplace_mechanism(value: f64, sensitivity: f64, epsilon: f64) -> Result<f64, Error> {
+    Ok(value + laplace_noise(sensitivity, epsilon)?)
+}
+
+/// `value` plus Gaussian noise
+pub fn gaussian_mechanism(
+    value: f64,
+    sensitivity: f64,
+    epsilon: f64,
+    delta: f64,
+) -> Result<f64, Error> {
+    Ok(value + gaussian_noise(sensitivity, epsilon, delta)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sample mean and variance
+    fn moments(samples: &[f64]) -> (f64, f64) {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let var = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, var)
+    }
+
+    #[test]
+    fn laplace_noise_has_the_expected_moments() {
+        let mut rng = StdRng::seed_from_u64(61);
+        let samples = (0..200_000)
+            .map(|_| laplace_noise_with(1.0, 0.5, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+
+        let (mean, var) = moments(&samples);
+        // b = 2, so the variance is 2b^2 = 8
+        assert!(mean.abs() < 0.05, "mean {}", mean);
+        assert!((var - 8.0).abs() < 0.2, "variance {}", var);
+
+        // P(|X| > t) = exp(-t / b), so about 37% of samples are beyond b
+        let tail = samples.iter().filter(|x| x.abs() > 2.0).count() as f64 / samples.len() as f64;
+        assert!((tail - (-1.0f64).exp()).abs() < 0.01, "tail {}", tail);
+    }
+
+    #[test]
+    fn gaussian_noise_has_the_expected_moments() {
+        let mut rng = StdRng::seed_from_u64(62);
+        let sigma = gaussian_sigma(1.0, 0.5, 1e-5).unwrap();
+        let samples = (0..200_000)
+            .map(|_| gaussian_noise_with(1.0, 0.5, 1e-5, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+
+        let (mean, var) = moments(&samples);
+        assert!(mean.abs() < 0.1, "mean {}", mean);
+        assert!((var.sqrt() / sigma - 1.0).abs() < 0.01, "sd {}", var.sqrt());
+    }
+
+    #[test]
+    fn it_rejects_invalid_privacy_parameters() {
+        assert!(laplace_noise(1.0, 0.0).is_err());
+        assert!(laplace_noise(-1.0, 1.0).is_err());
+        assert!(gaussian_noise(1.0, 1.5, 1e-5).is_err());
+        assert!(gaussian_noise(1.0, 0.5, 0.0).is_err());
+        // The secure generator works out of the box
+        assert!(laplace_mechanism(10.0, 1.0, 1.0).unwrap().is_finite());
+    }
+}
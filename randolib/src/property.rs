@@ -0,0 +1,359 @@
+//! The runtime behind `#[rando_test]`, a small property-testing harness: each test runs
+//! its body for many cases, every case drawing fresh arguments from its own seed.
+//!
+//! When a case fails its seed and arguments are printed. Two environment variables help
+//! narrow things down:
+//!
+//! - `RANDOLIB_CASE`: a case seed as printed on failure. Only that case runs, so pair it
+//!   with a test name filter: `RANDOLIB_CASE=<seed> cargo test my_property`
+//! - `RANDOLIB_CASES`: run this many cases instead of the number in the attribute
+//!
+//! Case seeds derive from the default generator's seed, so `RANDOLIB_SEED` (see `config`)
+//! makes a whole run repeat exactly.
+
+use crate::{global, seed::Seed, unicode::AdversarialGen};
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{
+    fmt::{Debug, Write},
+    panic::{self, AssertUnwindSafe},
+};
+
+/// Longest `Vec` or `String` `Arbitrary` generates
+pub const MAX_LEN: usize = 32;
+
+/// Types a property test can take as arguments
+pub trait Arbitrary: Sized {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized;
+}
+
+/// Integers favor the values bugs cluster around now and then, rather than relying on a
+/// uniform draw to ever hit them
+macro_rules! arbitrary_int {
+    ($($t:ty),*) => {
+        $(impl Arbitrary for $t {
+            fn arbitrary<R>(rng: &mut R) -> Self
+            where
+                R: Rng + ?Sized,
+            {
+                if rng.gen_ratio(1, 8) {
+                    *[0, 1, <$t>::MIN, <$t>::MAX].choose(rng).unwrap()
+                } else {
+                    rng.gen()
+                }
+            }
+        })*
+    };
+}
+
+arbitrary_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// Always finite: NaN and infinities break too many reasonable properties (`x == x`) to be
+/// a useful default. Magnitudes spread over many orders, with the edge values mixed in.
+impl Arbitrary for f64 {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_ratio(1, 8) {
+            return *[
+                0.0,
+                -0.0,
+                1.0,
+                f64::EPSILON,
+                f64::MIN_POSITIVE,
+                f64::MIN,
+                f64::MAX,
+            ]
+            .choose(rng)
+            .unwrap();
+        }
+        let sign = if rng.gen() { 1.0 } else { -1.0 };
+        sign * rng.gen::<f64>() * 10f64.powi(rng.gen_range(-6..=6))
+    }
+}
+
+impl Arbitrary for bool {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        rng.gen()
+    }
+}
+
+/// Mostly printable ASCII, sometimes any code point
+impl Arbitrary for char {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_ratio(3, 4) {
+            rng.gen_range(' '..='~')
+        } else {
+            rng.gen()
+        }
+    }
+}
+
+/// Up to `MAX_LEN` characters, with an adversarial string (see `unicode`) now and then
+impl Arbitrary for String {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_ratio(1, 10) {
+            return AdversarialGen::new().string(rng.gen_range(1..=4), rng);
+        }
+        let len = rng.gen_range(0..=MAX_LEN);
+        (0..len).map(|_| char::arbitrary(rng)).collect()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        let len = rng.gen_range(0..=MAX_LEN);
+        (0..len).map(|_| T::arbitrary(rng)).collect()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    fn arbitrary<R>(rng: &mut R) -> Self
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_ratio(1, 4) {
+            None
+        } else {
+            Some(T::arbitrary(rng))
+        }
+    }
+}
+
+macro_rules! arbitrary_tuple {
+    ($($t:ident),*) => {
+        impl<$($t: Arbitrary),*> Arbitrary for ($($t,)*) {
+            fn arbitrary<R>(rng: &mut R) -> Self
+            where
+                R: Rng + ?Sized,
+            {
+                ($($t::arbitrary(rng),)*)
+            }
+        }
+    };
+}
+
+arbitrary_tuple!(A);
+arbitrary_tuple!(A, B);
+arbitrary_tuple!(A, B, C);
+arbitrary_tuple!(A, B, C, D);
+
+/// One run of a property: its generator plus the arguments drawn, for the failure report
+pub struct Case {
+    index: u32,
+    seed: Seed,
+    rng: Box<dyn RngCore + Send>,
+    inputs: String,
+}
+
+impl Case {
+    fn new(index: u32, seed: Seed) -> Self {
+        Case {
+            index,
+            seed,
+            rng: global::config().backend.rng(seed),
+            inputs: String::new(),
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn seed(&self) -> Seed {
+        self.seed
+    }
+
+    /// The case's generator, for drawing more values in the body
+    pub fn rng(&mut self) -> &mut (dyn RngCore + Send) {
+        &mut *self.rng
+    }
+
+    pub fn arbitrary<T: Arbitrary>(&mut self) -> T {
+        T::arbitrary(&mut self.rng)
+    }
+
+    /// Remember the arguments, formatted now since the body may consume them
+    pub fn record(&mut self, inputs: &[(&str, &dyn Debug)]) {
+        self.inputs.clear();
+        for (name, value) in inputs {
+            // Writing to a `String` can't fail
+            let _ = write!(self.inputs, "\n  {} = {:?}", name, value);
+        }
+    }
+}
+
+/// What the environment asks of a property run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Overrides {
+    pub cases: Option<u32>,
+    pub case: Option<Seed>,
+}
+
+impl Overrides {
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like `from_env` with a custom lookup
+    pub fn from_vars<F>(var: F) -> Result<Self, Error>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let cases = var("RANDOLIB_CASES")
+            .map(|n| {
+                n.parse().map_err(|_| {
+                    Error::InvalidParameter(format!("RANDOLIB_CASES must be a number, got {:?}", n))
+                })
+            })
+            .transpose()?;
+        let case = var("RANDOLIB_CASE").map(|s| s.parse()).transpose()?;
+        Ok(Overrides { cases, case })
+    }
+}
+
+/// Run a property `cases` times, see the module docs. This is what `#[rando_test]`
+/// expands to. Code inside the body that uses the default generator (`randolib::global()`)
+/// gets a per-case seed too, so it repeats along with the case.
+pub fn run<F>(name: &str, cases: u32, f: F)
+where
+    F: Fn(&mut Case),
+{
+    let overrides = Overrides::from_env().unwrap_or_else(|e| panic!("randolib: {}", e));
+    run_with(name, cases, overrides, global::config().resolve_seed(), f)
+}
+
+fn run_with<F>(name: &str, cases: u32, overrides: Overrides, base: Seed, f: F)
+where
+    F: Fn(&mut Case),
+{
+    if let Some(seed) = overrides.case {
+        return run_case(name, Case::new(0, seed), 1, &f);
+    }
+    let cases = overrides.cases.unwrap_or(cases);
+    for index in 0..cases {
+        let seed = Seed::from_label(&format!("{}/{}/{}", base, name, index));
+        run_case(name, Case::new(index, seed), cases, &f);
+    }
+}
+
+fn run_case<F>(name: &str, mut case: Case, cases: u32, f: &F)
+where
+    F: Fn(&mut Case),
+{
+    let global_seed = Seed::from_label(&format!("{}/global", case.seed));
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        global::with_seed_scope(global_seed, || f(&mut case))
+    }));
+    if let Err(payload) = result {
+        eprintln!(
+            "rando_test {}: case {} of {} failed with seed {}{}\n\
+             rerun just this case with RANDOLIB_CASE={}",
+            name,
+            case.index + 1,
+            cases,
+            case.seed,
+            case.inputs,
+            case.seed
+        );
+        panic::resume_unwind(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rando_test;
+    use std::cell::RefCell;
+
+    #[rando_test(cases = 200)]
+    fn it_runs_properties(v: Vec<u8>, pair: (Option<i32>, bool), s: String) {
+        let mut w = v.clone();
+        w.reverse();
+        w.reverse();
+        assert_eq!(v, w);
+        assert!(v.len() <= MAX_LEN);
+        if let (Some(n), _) = pair {
+            assert_eq!(n.wrapping_add(1).wrapping_sub(1), n);
+        }
+        assert_eq!(
+            s.chars().rev().collect::<String>().chars().count(),
+            s.chars().count()
+        );
+    }
+
+    #[rando_test]
+    fn it_keeps_floats_finite(x: f64) {
+        assert!(x.is_finite());
+    }
+
+    #[test]
+    fn it_reports_and_replays_failures() {
+        let failed = RefCell::new(None);
+        let property = |case: &mut Case| {
+            let n = case.arbitrary::<u32>();
+            case.record(&[("n", &n)]);
+            if n.is_multiple_of(3) {
+                *failed.borrow_mut() = Some((case.seed(), n));
+                panic!("divisible by three");
+            }
+        };
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_with(
+                "demo",
+                100,
+                Overrides::default(),
+                Seed::from_u64(49),
+                property,
+            )
+        }));
+        assert!(result.is_err());
+        let (seed, n) = failed.borrow_mut().take().unwrap();
+
+        // The printed seed reproduces the same arguments
+        let replay = Overrides {
+            case: Some(seed),
+            ..Overrides::default()
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_with("demo", 100, replay, Seed::from_u64(0), property)
+        }));
+        assert!(result.is_err());
+        assert_eq!(failed.borrow_mut().take(), Some((seed, n)));
+    }
+
+    #[test]
+    fn it_reads_overrides() {
+        let seed = Seed::from_label("case");
+        let hex = seed.to_string();
+        let overrides = Overrides::from_vars(|name| match name {
+            "RANDOLIB_CASES" => Some("7".into()),
+            "RANDOLIB_CASE" => Some(hex.clone()),
+            _ => None,
+        });
+        assert_eq!(
+            overrides.unwrap(),
+            Overrides {
+                cases: Some(7),
+                case: Some(seed)
+            }
+        );
+        assert!(Overrides::from_vars(|_| Some("many".into())).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use crate::probability;
+use prost_reflect::{
+    bytes::Bytes, Cardinality, DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor,
+    Value,
+};
+use rand::{distributions::Alphanumeric, prelude::*};
+use std::collections::HashMap;
+
+/// Generates random protobuf messages from descriptors at runtime, using `prost-reflect`'s
+/// `DynamicMessage`. Every field gets a value of the right type, enums only use declared
+/// values, at most one field of each `oneof` is set and proto2 `required` fields always are,
+/// so the output is always a structurally valid message.
+#[derive(Debug, Clone)]
+pub struct MessageGen {
+    max_repeated: usize,
+    max_string_len: usize,
+    max_depth: usize,
+    /// Chance of leaving an optional field unset
+    skip_rate: f64,
+}
+
+impl MessageGen {
+    pub fn new() -> Self {
+        MessageGen {
+            max_repeated: 4,
+            max_string_len: 16,
+            max_depth: 4,
+            skip_rate: 0.1,
+        }
+    }
+
+    /// Max elements in repeated and map fields
+    pub fn max_repeated(mut self, max: usize) -> Self {
+        self.max_repeated = max;
+        self
+    }
+
+    pub fn max_string_len(mut self, max: usize) -> Self {
+        self.max_string_len = max;
+        self
+    }
+
+    /// How deep nested messages go. Recursive message types would otherwise never end,
+    /// so below this depth message fields are left unset, unless they're `required`. Those
+    /// are still followed, which only loops forever for a cycle of required fields, a
+    /// schema no finite message satisfies.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Probability of leaving a field that has presence (`optional`, message or oneof
+    /// fields) unset. `required` fields are always set.
+    pub fn skip_rate(mut self, rate: f64) -> Self {
+        self.skip_rate = probability(rate);
+        self
+    }
+
+    pub fn message<R>(&self, desc: &MessageDescriptor, rng: &mut R) -> DynamicMessage
+    where
+        R: Rng + ?Sized,
+    {
+        self.message_at(desc, 0, rng)
+    }
+
+    fn message_at<R>(&self, desc: &MessageDescriptor, depth: usize, rng: &mut R) -> DynamicMessage
+    where
+        R: Rng + ?Sized,
+    {
+        let mut message = DynamicMessage::new(desc.clone());
+
+        // Pick one member of each real (non-synthetic) oneof up front
+        let chosen = desc
+            .oneofs()
+            .filter(|oneof| !oneof.is_synthetic())
+            .filter_map(|oneof| oneof.fields().collect::<Vec<_>>().choose(rng).cloned())
+            .map(|field| field.number())
+            .collect::<Vec<_>>();
+
+        for field in desc.fields() {
+            let in_oneof = field
+                .containing_oneof()
+                .is_some_and(|oneof| !oneof.is_synthetic());
+            if in_oneof && !chosen.contains(&field.number()) {
+                continue;
+            }
+            if field.cardinality() == Cardinality::Required {
+                // Past `max_depth` too, `field_value` would give up on a message there
+                let value = match field.kind() {
+                    Kind::Message(desc) => {
+                        Some(Value::Message(self.message_at(&desc, depth + 1, rng)))
+                    }
+                    kind => self.scalar_or_message(&kind, depth, rng),
+                };
+                if let Some(value) = value {
+                    message.set_field(&field, value);
+                }
+                continue;
+            }
+            let is_message = matches!(field.kind(), Kind::Message(_)) && !field.is_list();
+            if is_message && depth >= self.max_depth {
+                continue;
+            }
+            if field.supports_presence() && rng.gen_bool(self.skip_rate) {
+                continue;
+            }
+            if let Some(value) = self.field_value(&field, depth, rng) {
+                message.set_field(&field, value);
+            }
+        }
+        message
+    }
+
+    /// `None` when nesting is too deep to produce a value
+    fn field_value<R>(&self, field: &FieldDescriptor, depth: usize, rng: &mut R) -> Option<Value>
+    where
+        R: Rng + ?Sized,
+    {
+        if field.is_map() {
+            let entry = match field.kind() {
+                Kind::Message(entry) => entry,
+                _ => unreachable!("map fields are always backed by an entry message"),
+            };
+            let key_kind = entry.map_entry_key_field().kind();
+            let value_kind = entry.map_entry_value_field().kind();
+            let mut map = HashMap::new();
+            for _ in 0..rng.gen_range(0..=self.max_repeated) {
+                let value = self.scalar_or_message(&value_kind, depth, rng)?;
+                map.insert(self.map_key(&key_kind, rng), value);
+            }
+            return Some(Value::Map(map));
+        }
+        if field.is_list() {
+            let values = (0..rng.gen_range(0..=self.max_repeated))
+                .map(|_| self.scalar_or_message(&field.kind(), depth, rng))
+                .collect::<Option<Vec<_>>>()?;
+            return Some(Value::List(values));
+        }
+        self.scalar_or_message(&field.kind(), depth, rng)
+    }
+
+    fn scalar_or_message<R>(&self, kind: &Kind, depth: usize, rng: &mut R) -> Option<Value>
+    where
+        R: Rng + ?Sized,
+    {
+        Some(match kind {
+            Kind::Double => Value::F64(rng.gen_range(-1e6..1e6)),
+            Kind::Float => Value::F32(rng.gen_range(-1e6..1e6)),
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Value::I32(rng.gen()),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(rng.gen()),
+            Kind::Uint32 | Kind::Fixed32 => Value::U32(rng.gen()),
+            Kind::Uint64 | Kind::Fixed64 => Value::U64(rng.gen()),
+            Kind::Bool => Value::Bool(rng.gen()),
+            Kind::String => Value::String(self.string(rng)),
+            Kind::Bytes => {
+                let mut bytes = vec![0u8; rng.gen_range(0..=self.max_string_len)];
+                rng.fill_bytes(&mut bytes);
+                Value::Bytes(Bytes::from(bytes))
+            }
+            Kind::Enum(desc) => {
+                Value::EnumNumber(desc.values().collect::<Vec<_>>().choose(rng)?.number())
+            }
+            Kind::Message(desc) => {
+                if depth >= self.max_depth {
+                    return None;
+                }
+                Value::Message(self.message_at(desc, depth + 1, rng))
+            }
+        })
+    }
+
+    fn map_key<R>(&self, kind: &Kind, rng: &mut R) -> MapKey
+    where
+        R: Rng + ?Sized,
+    {
+        match kind {
+            Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => MapKey::I32(rng.gen()),
+            Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => MapKey::I64(rng.gen()),
+            Kind::Uint32 | Kind::Fixed32 => MapKey::U32(rng.gen()),
+            Kind::Uint64 | Kind::Fixed64 => MapKey::U64(rng.gen()),
+            Kind::Bool => MapKey::Bool(rng.gen()),
+            // Protobuf only allows integral, bool and string map keys
+            _ => MapKey::String(self.string(rng)),
+        }
+    }
+
+    fn string<R>(&self, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        (0..rng.gen_range(0..=self.max_string_len))
+            .map(|_| rng.sample(Alphanumeric) as char)
+            .collect()
+    }
+}
+
+impl Default for MessageGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::{
+        prost::Message,
+        prost_types::{
+            field_descriptor_proto::{Label, Type},
+            DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+            FileDescriptorProto,
+        },
+        DescriptorPool,
+    };
+
+    fn field(name: &str, number: i32, ty: Type, label: Label) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.into()),
+            number: Some(number),
+            r#type: Some(ty as i32),
+            label: Some(label as i32),
+            ..Default::default()
+        }
+    }
+
+    /// A descriptor for a recursive `Node` message with an enum and a repeated field,
+    /// built by hand so the tests don't need `protoc`
+    fn node_descriptor() -> MessageDescriptor {
+        let mut children = field("children", 3, Type::Message, Label::Repeated);
+        children.type_name = Some(".test.Node".into());
+        let mut color = field("color", 2, Type::Enum, Label::Optional);
+        color.type_name = Some(".test.Color".into());
+
+        let file = FileDescriptorProto {
+            name: Some("test.proto".into()),
+            package: Some("test".into()),
+            syntax: Some("proto3".into()),
+            message_type: vec![DescriptorProto {
+                name: Some("Node".into()),
+                field: vec![
+                    field("name", 1, Type::String, Label::Optional),
+                    color,
+                    children,
+                    field("weights", 4, Type::Double, Label::Repeated),
+                ],
+                ..Default::default()
+            }],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Color".into()),
+                value: [("RED", 0), ("GREEN", 5), ("BLUE", 9)]
+                    .iter()
+                    .map(|(name, number)| EnumValueDescriptorProto {
+                        name: Some(name.to_string()),
+                        number: Some(*number),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut pool = DescriptorPool::new();
+        pool.add_file_descriptor_proto(file).unwrap();
+        pool.get_message_by_name("test.Node").unwrap()
+    }
+
+    /// Check enum values and nesting depth, returning the deepest level seen
+    fn check(message: &DynamicMessage, depth: usize) -> usize {
+        let color = message.get_field_by_name("color").unwrap();
+        assert!([0, 5, 9].contains(&color.as_enum_number().unwrap()));
+
+        let children = message.get_field_by_name("children").unwrap();
+        children
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|child| check(child.as_message().unwrap(), depth + 1))
+            .max()
+            .unwrap_or(depth)
+    }
+
+    #[test]
+    fn it_generates_valid_messages_that_round_trip() {
+        let mut rng = StdRng::seed_from_u64(17);
+        let desc = node_descriptor();
+        let gen = MessageGen::new().max_depth(2);
+
+        for _ in 0..20 {
+            let message = gen.message(&desc, &mut rng);
+
+            assert!(check(&message, 0) <= 2);
+            // Proto3 drops default values on the wire, so compare encodings rather than
+            // the messages themselves
+            let bytes = message.encode_to_vec();
+            let decoded = DynamicMessage::decode(desc.clone(), bytes.as_slice()).unwrap();
+            assert_eq!(decoded.encode_to_vec(), bytes);
+        }
+    }
+
+    #[test]
+    fn it_always_sets_required_fields() {
+        // proto2: `Outer { required int32 id; required Inner inner; optional string note }`
+        // and `Inner { required string name; optional Inner next }`
+        let mut inner = field("inner", 2, Type::Message, Label::Required);
+        inner.type_name = Some(".test2.Inner".into());
+        let mut next = field("next", 2, Type::Message, Label::Optional);
+        next.type_name = Some(".test2.Inner".into());
+        let file = FileDescriptorProto {
+            name: Some("test2.proto".into()),
+            package: Some("test2".into()),
+            syntax: Some("proto2".into()),
+            message_type: vec![
+                DescriptorProto {
+                    name: Some("Outer".into()),
+                    field: vec![
+                        field("id", 1, Type::Int32, Label::Required),
+                        inner,
+                        field("note", 3, Type::String, Label::Optional),
+                    ],
+                    ..Default::default()
+                },
+                DescriptorProto {
+                    name: Some("Inner".into()),
+                    field: vec![field("name", 1, Type::String, Label::Required), next],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut pool = DescriptorPool::new();
+        pool.add_file_descriptor_proto(file).unwrap();
+        let desc = pool.get_message_by_name("test2.Outer").unwrap();
+
+        // Skip everything that can be skipped, and don't nest at all
+        let gen = MessageGen::new().skip_rate(1.0).max_depth(0);
+        let mut rng = StdRng::seed_from_u64(217);
+        for _ in 0..20 {
+            let message = gen.message(&desc, &mut rng);
+            assert!(message.has_field_by_name("id"));
+            assert!(!message.has_field_by_name("note"));
+            let inner = message.get_field_by_name("inner").unwrap();
+            let inner = inner.as_message().unwrap();
+            assert!(inner.has_field_by_name("name"));
+            assert!(!inner.has_field_by_name("next"));
+        }
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        // Each call to `node_descriptor` makes a new pool, and messages from different
+        // pools never compare equal, so compare encodings
+        let desc = node_descriptor();
+        let nan = MessageGen::new().skip_rate(f64::NAN);
+        let zero = MessageGen::new().skip_rate(0.0);
+        let bytes = |gen: MessageGen| {
+            gen.message(&desc, &mut StdRng::seed_from_u64(218))
+                .encode_to_vec()
+        };
+        assert_eq!(bytes(nan), bytes(zero));
+    }
+}
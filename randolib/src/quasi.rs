@@ -0,0 +1,352 @@
+//! Low-discrepancy ("quasi-random") sequences. Their points fill the unit cube much more
+//! evenly than pseudo-random ones, so Monte Carlo estimates converge at close to `1/n`
+//! instead of `1/sqrt(n)`. They are deterministic, so don't use them where you need
+//! unpredictability.
+//!
+//! Latin hypercube sampling sits in between: it is random, but stratified so that every
+//! coordinate covers its whole range evenly.
+
+use rand::prelude::*;
+use somelib::error::Error;
+use std::ops::Range;
+
+/// Anything that produces points in `[0, 1)^d`, so code can switch between quasi-random
+/// sequences and a pseudo-random backend. The method names mirror `GetRandoStuff`.
+pub trait PointSource {
+    fn dimensions(&self) -> usize;
+
+    /// The next point, with `dimensions()` coordinates
+    fn get_random_item(&mut self) -> Vec<f64>;
+
+    fn get_random_vec(&mut self, len: usize) -> Vec<Vec<f64>> {
+        (0..len).map(|_| self.get_random_item()).collect()
+    }
+}
+
+/// Halton sequence: coordinate `k` is the radical inverse of the point's index in the
+/// `k`th prime base. Simple and works in any dimension, though coordinates in high, close
+/// prime bases start out correlated, so prefer `Sobol` beyond ten or so dimensions.
+#[derive(Debug, Clone)]
+pub struct Halton {
+    bases: Vec<u64>,
+    index: u64,
+}
+
+impl Halton {
+    pub fn new(dimensions: usize) -> Result<Self, Error> {
+        if dimensions == 0 {
+            return Err(Error::InvalidParameter(
+                "a sequence needs at least one dimension".into(),
+            ));
+        }
+        let mut bases = Vec::with_capacity(dimensions);
+        let mut candidate = 2;
+        while bases.len() < dimensions {
+            if bases.iter().all(|p| candidate % p != 0) {
+                bases.push(candidate);
+            }
+            candidate += 1;
+        }
+        // Index 0 would be the origin in every base, so start at 1
+        Ok(Halton { bases, index: 1 })
+    }
+}
+
+/// Mirror the digits of `index` in `base` around the radix point, e.g. 6 = 110b -> 0.011b
+fn radical_inverse(mut index: u64, base: u64) -> f64 {
+    let mut result = 0.0;
+    let mut scale = 1.0 / base as f64;
+    while index > 0 {
+        result += (index % base) as f64 * scale;
+        index /= base;
+        scale /= base as f64;
+    }
+    result
+}
+
+impl PointSource for Halton {
+    fn dimensions(&self) -> usize {
+        self.bases.len()
+    }
+
+    fn get_random_item(&mut self) -> Vec<f64> {
+        let point = self
+            .bases
+            .iter()
+            .map(|&base| radical_inverse(self.index, base))
+            .collect();
+        self.index += 1;
+        point
+    }
+}
+
+/// Joe and Kuo's direction numbers for dimensions 2 and up, `(s, a, m)`: the degree and
+/// coefficients of a primitive polynomial and its initial direction numbers. Dimension 1
+/// is the van der Corput sequence and needs none.
+const SOBOL_PARAMS: [(u32, u32, &[u32]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+];
+
+/// Bits of precision per coordinate, which also caps the sequence at `2^32` points
+const SOBOL_BITS: usize = 32;
+
+/// Sobol sequence, generated in Gray code order so each point costs one XOR per
+/// coordinate. Supports up to 16 dimensions and starts at the origin.
+#[derive(Debug, Clone)]
+pub struct Sobol {
+    /// `directions[d][k]` is direction number `k + 1` of dimension `d`, left aligned
+    directions: Vec<[u32; SOBOL_BITS]>,
+    state: Vec<u32>,
+    index: u64,
+}
+
+impl Sobol {
+    pub const MAX_DIMENSIONS: usize = SOBOL_PARAMS.len() + 1;
+
+    pub fn new(dimensions: usize) -> Result<Self, Error> {
+        if dimensions == 0 || dimensions > Self::MAX_DIMENSIONS {
+            return Err(Error::InvalidParameter(format!(
+                "Sobol sequences support 1 to {} dimensions",
+                Self::MAX_DIMENSIONS
+            )));
+        }
+
+        let mut directions = Vec::with_capacity(dimensions);
+        let mut first = [0u32; SOBOL_BITS];
+        for (k, v) in first.iter_mut().enumerate() {
+            *v = 1 << (SOBOL_BITS - 1 - k);
+        }
+        directions.push(first);
+
+        for &(s, a, m) in SOBOL_PARAMS.iter().take(dimensions - 1) {
+            let s = s as usize;
+            let mut v = [0u32; SOBOL_BITS];
+            for k in 0..SOBOL_BITS {
+                v[k] = if k < s {
+                    m[k] << (SOBOL_BITS - 1 - k)
+                } else {
+                    // The recurrence defined by the primitive polynomial's coefficients
+                    let mut next = v[k - s] ^ (v[k - s] >> s);
+                    for i in 1..s {
+                        if (a >> (s - 1 - i)) & 1 == 1 {
+                            next ^= v[k - i];
+                        }
+                    }
+                    next
+                };
+            }
+            directions.push(v);
+        }
+
+        Ok(Sobol {
+            directions,
+            state: vec![0; dimensions],
+            index: 0,
+        })
+    }
+}
+
+impl PointSource for Sobol {
+    fn dimensions(&self) -> usize {
+        self.directions.len()
+    }
+
+    fn get_random_item(&mut self) -> Vec<f64> {
+        assert!(self.index < 1 << SOBOL_BITS, "Sobol sequence exhausted");
+        let point = self
+            .state
+            .iter()
+            .map(|&x| x as f64 / (1u64 << SOBOL_BITS) as f64)
+            .collect();
+        // Successive Gray codes differ in the bit at the lowest zero bit of the index
+        let bit = self.index.trailing_ones() as usize;
+        for (x, v) in self.state.iter_mut().zip(&self.directions) {
+            *x ^= v[bit];
+        }
+        self.index += 1;
+        point
+    }
+}
+
+/// The pseudo-random baseline: independent uniform coordinates from any `Rng`
+#[derive(Debug, Clone)]
+pub struct PseudoRandom<R> {
+    rng: R,
+    dimensions: usize,
+}
+
+impl<R: Rng> PseudoRandom<R> {
+    pub fn new(rng: R, dimensions: usize) -> Self {
+        PseudoRandom { rng, dimensions }
+    }
+}
+
+impl<R: Rng> PointSource for PseudoRandom<R> {
+    fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    fn get_random_item(&mut self) -> Vec<f64> {
+        (0..self.dimensions).map(|_| self.rng.gen()).collect()
+    }
+}
+
+/// `n_samples` points in `[0, 1)^dims` such that, in every dimension, each of the
+/// `n_samples` equal-width strata holds exactly one point. Strata are paired up across
+/// dimensions at random and each point is placed uniformly within its cell.
+///
+/// This is the usual design-of-experiments sampler: with 10 samples of 5 parameters every
+/// parameter still gets tried across 10 distinct slices of its range.
+pub fn latin_hypercube<R>(n_samples: usize, dims: usize, rng: &mut R) -> Vec<Vec<f64>>
+where
+    R: Rng + ?Sized,
+{
+    let mut points = vec![Vec::with_capacity(dims); n_samples];
+    let mut strata = (0..n_samples).collect::<Vec<_>>();
+    for _ in 0..dims {
+        strata.shuffle(rng);
+        for (point, &stratum) in points.iter_mut().zip(&strata) {
+            point.push((stratum as f64 + rng.gen::<f64>()) / n_samples as f64);
+        }
+    }
+    points
+}
+
+/// Map a point from the unit cube onto parameter ranges, one range per coordinate
+pub fn map_to_ranges(point: &[f64], ranges: &[Range<f64>]) -> Result<Vec<f64>, Error> {
+    check_ranges(point, ranges)?;
+    Ok(point
+        .iter()
+        .zip(ranges)
+        .map(|(u, range)| range.start + u * (range.end - range.start))
+        .collect())
+}
+
+/// Like `map_to_ranges` but uniform in the logarithm, for parameters that span orders of
+/// magnitude such as learning rates or timeouts. Ranges must be positive.
+pub fn map_to_log_ranges(point: &[f64], ranges: &[Range<f64>]) -> Result<Vec<f64>, Error> {
+    if ranges
+        .iter()
+        .any(|range| range.start <= 0.0 || range.end <= 0.0)
+    {
+        return Err(Error::InvalidParameter(
+            "log-scaled ranges must be positive".into(),
+        ));
+    }
+    let logs = ranges
+        .iter()
+        .map(|range| range.start.ln()..range.end.ln())
+        .collect::<Vec<_>>();
+    Ok(map_to_ranges(point, &logs)?
+        .into_iter()
+        .map(f64::exp)
+        .collect())
+}
+
+fn check_ranges(point: &[f64], ranges: &[Range<f64>]) -> Result<(), Error> {
+    if point.len() != ranges.len() {
+        return Err(Error::InvalidParameter(format!(
+            "{} coordinates but {} ranges",
+            point.len(),
+            ranges.len()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_matches_known_sequence_prefixes() {
+        let mut sobol = Sobol::new(2).unwrap();
+        assert_eq!(
+            sobol.get_random_vec(5),
+            vec![
+                vec![0.0, 0.0],
+                vec![0.5, 0.5],
+                vec![0.75, 0.25],
+                vec![0.25, 0.75],
+                vec![0.375, 0.375],
+            ]
+        );
+
+        let mut halton = Halton::new(2).unwrap();
+        let points = halton.get_random_vec(3);
+        assert_eq!(points[0], vec![0.5, 1.0 / 3.0]);
+        assert_eq!(points[2], vec![0.75, 1.0 / 9.0]);
+    }
+
+    #[test]
+    fn it_integrates_better_than_pseudo_random() {
+        // The integral of x * y * z over the unit cube is 1/8
+        let error = |source: &mut dyn PointSource| {
+            let n = 4096;
+            let sum = source
+                .get_random_vec(n)
+                .iter()
+                .map(|p| p.iter().product::<f64>())
+                .sum::<f64>();
+            (sum / n as f64 - 0.125).abs()
+        };
+
+        let pseudo = error(&mut PseudoRandom::new(StdRng::seed_from_u64(3), 3));
+        let sobol = error(&mut Sobol::new(3).unwrap());
+        let halton = error(&mut Halton::new(3).unwrap());
+
+        assert!(sobol < 1e-3 && sobol < pseudo, "{} {}", sobol, pseudo);
+        assert!(halton < 1e-3 && halton < pseudo, "{} {}", halton, pseudo);
+    }
+
+    #[test]
+    fn it_rejects_unsupported_dimensions() {
+        assert!(Halton::new(0).is_err());
+        assert!(Sobol::new(Sobol::MAX_DIMENSIONS + 1).is_err());
+        assert_eq!(Sobol::new(Sobol::MAX_DIMENSIONS).unwrap().dimensions(), 16);
+    }
+
+    #[test]
+    fn it_stratifies_latin_hypercubes() {
+        let mut rng = StdRng::seed_from_u64(27);
+        let points = latin_hypercube(10, 3, &mut rng);
+
+        assert_eq!(points.len(), 10);
+        for d in 0..3 {
+            let mut strata = points
+                .iter()
+                .map(|p| (p[d] * 10.0) as usize)
+                .collect::<Vec<_>>();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..10).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn it_maps_points_onto_ranges() {
+        let point = [0.5, 0.5];
+
+        assert_eq!(
+            map_to_ranges(&point, &[0.0..10.0, -1.0..1.0]).unwrap(),
+            vec![5.0, 0.0]
+        );
+        let lr = map_to_log_ranges(&point, &[1e-4..1e-2, 1.0..100.0]).unwrap();
+        assert!((lr[0] - 1e-3).abs() < 1e-12 && (lr[1] - 10.0).abs() < 1e-9);
+        assert!(map_to_ranges(&point, &[0.0..1.0]).is_err());
+        assert!(map_to_log_ranges(&point, &[0.0..1.0, 1.0..2.0]).is_err());
+    }
+}
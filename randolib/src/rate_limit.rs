@@ -0,0 +1,246 @@
+use rand::prelude::*;
+use somelib::error::Error;
+use std::time::{Duration, Instant};
+
+/// A token bucket. Tokens are refilled at `rate` per second up to `capacity`, and each
+/// emission spends one token. An optional `jitter` stretches or shrinks every wait by a
+/// random fraction so the output doesn't tick like a metronome.
+pub struct RateLimiter {
+    rate: f64,
+    capacity: f64,
+    /// This can go negative: a negative balance is time we've promised to wait for
+    tokens: f64,
+    last_refill: Instant,
+    jitter: f64,
+}
+
+impl RateLimiter {
+    /// `per_second` is the sustained rate, `burst` is how many items can be emitted back to
+    /// back before the limiter starts making us wait. The bucket starts full.
+    pub fn new(per_second: f64, burst: u32) -> Result<Self, Error> {
+        // `NaN` is not finite, so this rejects it as well
+        if !per_second.is_finite() || per_second <= 0.0 {
+            return Err(Error::InvalidParameter(format!(
+                "rate must be positive and finite, got {}",
+                per_second
+            )));
+        }
+        if burst == 0 {
+            return Err(Error::InvalidParameter("burst must be at least 1".into()));
+        }
+        Ok(RateLimiter {
+            rate: per_second,
+            capacity: burst as f64,
+            tokens: burst as f64,
+            last_refill: Instant::now(),
+            jitter: 0.0,
+        })
+    }
+
+    /// Builder-style setter. Each wait is scaled by a uniform factor in `[1 - jitter, 1 + jitter]`
+    /// so `jitter` must be within `[0, 1]`. The average rate is unchanged.
+    pub fn with_jitter(mut self, jitter: f64) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&jitter) {
+            return Err(Error::InvalidParameter(format!(
+                "jitter must be within [0, 1], got {}",
+                jitter
+            )));
+        }
+        self.jitter = jitter;
+        Ok(self)
+    }
+
+    /// Add the tokens earned since the last refill
+    fn refill(&mut self, now: Instant) {
+        // `saturating_duration_since` gives zero rather than panicking if `now` is in the past
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last_refill = self.last_refill.max(now);
+    }
+
+    /// Take a token if one is available at `now`, never waits
+    pub fn try_acquire_at(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Take a token if one is available right now
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Reserve a token at `now` and return how long the caller has to wait before using it.
+    /// Taking `now` and the RNG as parameters keeps this deterministic, which is what the
+    /// tests use.
+    pub fn reserve_at<R>(&mut self, now: Instant, rng: &mut R) -> Duration
+    where
+        // `?Sized` lets us pass trait objects like `&mut dyn RngCore` as well
+        R: Rng + ?Sized,
+    {
+        self.refill(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        let wait = -self.tokens / self.rate;
+        let scale = if self.jitter > 0.0 {
+            rng.gen_range(1.0 - self.jitter..=1.0 + self.jitter)
+        } else {
+            1.0
+        };
+        Duration::from_secs_f64(wait * scale)
+    }
+
+    /// Block the current thread until a token is available
+    pub fn acquire(&mut self) {
+        self.acquire_with(&mut thread_rng());
+    }
+
+    /// `acquire` with the jitter drawn from `rng`, so a seeded run waits the same way
+    /// every time
+    pub fn acquire_with<R>(&mut self, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let wait = self.reserve_at(Instant::now(), rng);
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+/// Tames noisy log sites: lets records through at up to a fixed rate, then only a random
+/// fraction of the overflow, e.g. "at most 10 per second, and 1% beyond that". Records
+/// that are dropped are counted so the next one logged can say how many it stands for.
+///
+/// Methods take `&mut self`; put the sampler in a `Mutex` to share it between threads.
+pub struct LogSampler {
+    limiter: RateLimiter,
+    overflow_rate: f64,
+    suppressed: u64,
+}
+
+impl LogSampler {
+    /// `per_second` and `burst` as for `RateLimiter`, `overflow_rate` is the chance of
+    /// logging a record once the rate is used up
+    pub fn new(per_second: f64, burst: u32, overflow_rate: f64) -> Result<Self, Error> {
+        if !(0.0..=1.0).contains(&overflow_rate) {
+            return Err(Error::InvalidParameter(format!(
+                "overflow rate must be within [0, 1], got {}",
+                overflow_rate
+            )));
+        }
+        Ok(LogSampler {
+            limiter: RateLimiter::new(per_second, burst)?,
+            overflow_rate,
+            suppressed: 0,
+        })
+    }
+
+    pub fn should_log_at<R>(&mut self, now: Instant, rng: &mut R) -> bool
+    where
+        R: Rng + ?Sized,
+    {
+        let log = self.limiter.try_acquire_at(now) || rng.gen_bool(self.overflow_rate);
+        if !log {
+            self.suppressed += 1;
+        }
+        log
+    }
+
+    /// Whether to log the current record
+    pub fn should_log(&mut self) -> bool {
+        self.should_log_at(Instant::now(), &mut thread_rng())
+    }
+
+    /// How many records were dropped since the last call, for a "(N suppressed)" suffix
+    pub fn take_suppressed(&mut self) -> u64 {
+        std::mem::take(&mut self.suppressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_allows_a_burst_then_limits() {
+        let mut limiter = RateLimiter::new(10.0, 3).unwrap();
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at(now));
+        assert!(limiter.try_acquire_at(now));
+        assert!(limiter.try_acquire_at(now));
+        assert!(!limiter.try_acquire_at(now));
+
+        // 10 per second means one new token every 100ms
+        assert!(limiter.try_acquire_at(now + Duration::from_millis(100)));
+    }
+
+    #[test]
+    fn it_jitters_waits_around_the_rate() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut limiter = RateLimiter::new(10.0, 1).unwrap().with_jitter(0.5).unwrap();
+        let now = Instant::now();
+
+        assert_eq!(limiter.reserve_at(now, &mut rng), Duration::ZERO);
+        // The next token is 100ms away, give or take 50%
+        let wait = limiter.reserve_at(now, &mut rng);
+        assert!(wait >= Duration::from_millis(50) && wait <= Duration::from_millis(150));
+    }
+
+    #[test]
+    fn it_draws_jitter_from_the_given_rng() {
+        // All at the same instant, so nothing refills. The first token is free, the other
+        // two are jittered.
+        let mut limiter = RateLimiter::new(1000.0, 1)
+            .unwrap()
+            .with_jitter(0.5)
+            .unwrap();
+        let now = Instant::now();
+        let mut rng = StdRng::seed_from_u64(210);
+        for _ in 0..3 {
+            limiter.reserve_at(now, &mut rng);
+        }
+        // Only the jitter draws from `rng`, so a copy that drew the same twice agrees
+        let mut expected = StdRng::seed_from_u64(210);
+        for _ in 0..2 {
+            expected.gen_range(0.5..=1.5);
+        }
+        assert_eq!(rng.gen::<u64>(), expected.gen::<u64>());
+    }
+
+    #[test]
+    fn it_rejects_bad_parameters() {
+        assert!(RateLimiter::new(0.0, 1).is_err());
+        assert!(RateLimiter::new(f64::NAN, 1).is_err());
+        assert!(RateLimiter::new(1.0, 0).is_err());
+        assert!(RateLimiter::new(1.0, 1).unwrap().with_jitter(1.5).is_err());
+    }
+
+    #[test]
+    fn it_samples_logs_beyond_the_rate() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut sampler = LogSampler::new(10.0, 10, 0.01).unwrap();
+        let now = Instant::now();
+
+        // A burst of 10_000 records in one instant: the first 10, then about 1%
+        let logged = (0..10_000)
+            .filter(|_| sampler.should_log_at(now, &mut rng))
+            .count();
+        assert!((90..=130).contains(&logged), "{}", logged);
+        assert_eq!(sampler.take_suppressed(), 10_000 - logged as u64);
+        assert_eq!(sampler.take_suppressed(), 0);
+
+        // A second later the rate allows a fresh batch
+        assert!(sampler.should_log_at(now + Duration::from_secs(1), &mut rng));
+        assert!(LogSampler::new(10.0, 10, 2.0).is_err());
+    }
+}
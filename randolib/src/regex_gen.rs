@@ -0,0 +1,431 @@
+//! Random regular expressions with known answers: every generated pattern comes with its
+//! own matcher, so tests can produce strings that must match or must not match and check
+//! a regex engine or validator against them.
+//!
+//! Patterns use the syntax common to most engines (literals, `.`, `[a-c]`, `[^a-c]`,
+//! groups, `|`, `*`, `+`, `?` and `{m,n}`). Matching is whole-string, so wrap the pattern
+//! with `anchored` before handing it to an engine that searches.
+
+use rand::prelude::*;
+use std::{collections::BTreeSet, fmt};
+
+/// Characters that need a backslash outside a class
+const META: &str = r".^$|?*+()[]{}\";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Regex {
+    Literal(char),
+    /// `.`, anything but a newline
+    Any,
+    /// `[a-cx]` as inclusive ranges, or `[^..]` when negated
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Concat(Vec<Regex>),
+    Alt(Vec<Regex>),
+    Repeat {
+        inner: Box<Regex>,
+        min: u32,
+        /// `None` is unbounded
+        max: Option<u32>,
+    },
+}
+
+impl Regex {
+    /// The pattern wrapped in `^(..)$`, for engines that find matches anywhere
+    pub fn anchored(&self) -> String {
+        format!("^({})$", self)
+    }
+
+    /// Whether the whole of `s` matches
+    pub fn is_match(&self, s: &str) -> bool {
+        let chars = s.chars().collect::<Vec<_>>();
+        self.ends(&chars, 0).contains(&chars.len())
+    }
+
+    /// Every position a match starting at `start` can end at. Sets of positions keep
+    /// this polynomial, unlike backtracking.
+    fn ends(&self, s: &[char], start: usize) -> BTreeSet<usize> {
+        let one = |ok: bool| {
+            if ok {
+                BTreeSet::from([start + 1])
+            } else {
+                BTreeSet::new()
+            }
+        };
+        match self {
+            Regex::Literal(c) => one(s.get(start) == Some(c)),
+            Regex::Any => one(s.get(start).is_some_and(|c| *c != '\n')),
+            Regex::Class { ranges, negated } => one(s
+                .get(start)
+                .is_some_and(|c| in_ranges(ranges, *c) != *negated)),
+            Regex::Concat(parts) => parts.iter().fold(BTreeSet::from([start]), |starts, part| {
+                starts.iter().flat_map(|&p| part.ends(s, p)).collect()
+            }),
+            Regex::Alt(options) => options.iter().flat_map(|o| o.ends(s, start)).collect(),
+            Regex::Repeat { inner, min, max } => {
+                let mut result = BTreeSet::new();
+                let mut frontier = BTreeSet::from([start]);
+                let mut count = 0;
+                // Stop once no new positions appear, which also ends unbounded repeats
+                // of patterns that can match the empty string
+                let mut seen = BTreeSet::new();
+                loop {
+                    if count >= *min {
+                        result.extend(frontier.iter().copied());
+                    }
+                    if max.is_some_and(|max| count >= max) || frontier.is_empty() {
+                        break;
+                    }
+                    let next = frontier
+                        .iter()
+                        .flat_map(|&p| inner.ends(s, p))
+                        .collect::<BTreeSet<_>>();
+                    count += 1;
+                    if count > *min && next.iter().all(|p| seen.contains(p)) {
+                        break;
+                    }
+                    seen.extend(next.iter().copied());
+                    frontier = next;
+                }
+                result
+            }
+        }
+    }
+
+    /// A random string that matches. Unbounded repeats add up to `max_repeat` extra
+    /// copies.
+    pub fn sample_match<R>(&self, alphabet: &[char], max_repeat: u32, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        let mut out = String::new();
+        self.write_match(alphabet, max_repeat, rng, &mut out);
+        out
+    }
+
+    fn write_match<R>(&self, alphabet: &[char], max_repeat: u32, rng: &mut R, out: &mut String)
+    where
+        R: Rng + ?Sized,
+    {
+        match self {
+            Regex::Literal(c) => out.push(*c),
+            Regex::Any => out.push(*alphabet.choose(rng).unwrap_or(&'a')),
+            Regex::Class { ranges, negated } => {
+                let c = if *negated {
+                    // Try the alphabet first so strings stay readable, then anything
+                    let outside = alphabet
+                        .iter()
+                        .filter(|c| !in_ranges(ranges, **c))
+                        .collect::<Vec<_>>();
+                    match outside.choose(rng) {
+                        Some(c) => **c,
+                        None => (' '..='~')
+                            .find(|c| !in_ranges(ranges, *c))
+                            .unwrap_or('\u{e000}'),
+                    }
+                } else {
+                    let (lo, hi) = *ranges.choose(rng).unwrap();
+                    rng.gen_range(lo..=hi)
+                };
+                out.push(c);
+            }
+            Regex::Concat(parts) => {
+                for part in parts {
+                    part.write_match(alphabet, max_repeat, rng, out);
+                }
+            }
+            Regex::Alt(options) => options
+                .choose(rng)
+                .unwrap()
+                .write_match(alphabet, max_repeat, rng, out),
+            Regex::Repeat { inner, min, max } => {
+                let max = max.unwrap_or(min + max_repeat);
+                for _ in 0..rng.gen_range(*min..=max) {
+                    inner.write_match(alphabet, max_repeat, rng, out);
+                }
+            }
+        }
+    }
+
+    /// A random string that doesn't match, made by mutating matching strings, or `None`
+    /// if none turned up (`.*` matches everything, for example)
+    pub fn sample_non_match<R>(
+        &self,
+        alphabet: &[char],
+        max_repeat: u32,
+        rng: &mut R,
+    ) -> Option<String>
+    where
+        R: Rng + ?Sized,
+    {
+        for _ in 0..100 {
+            let mut chars = self
+                .sample_match(alphabet, max_repeat, rng)
+                .chars()
+                .collect::<Vec<_>>();
+            for _ in 0..rng.gen_range(1..=2) {
+                let at = rng.gen_range(0..=chars.len());
+                match rng.gen_range(0..3) {
+                    0 if at < chars.len() => {
+                        chars.remove(at);
+                    }
+                    1 if at < chars.len() => chars[at] = *alphabet.choose(rng)?,
+                    _ => chars.insert(at, *alphabet.choose(rng)?),
+                }
+            }
+            let candidate = chars.into_iter().collect::<String>();
+            if !self.is_match(&candidate) {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+
+    /// Binding strength when printed: alternation < concatenation < repetition < atoms
+    fn precedence(&self) -> u8 {
+        match self {
+            Regex::Alt(options) if options.len() > 1 => 0,
+            Regex::Concat(parts) if parts.len() != 1 => 1,
+            Regex::Repeat { .. } => 2,
+            _ => 3,
+        }
+    }
+}
+
+fn in_ranges(ranges: &[(char, char)], c: char) -> bool {
+    ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c))
+}
+
+fn write_group(f: &mut fmt::Formatter<'_>, r: &Regex, group: bool) -> fmt::Result {
+    if group {
+        write!(f, "({})", r)
+    } else {
+        write!(f, "{}", r)
+    }
+}
+
+/// Inside a class only `]`, `\`, `^` and `-` are special
+fn write_class_char(f: &mut fmt::Formatter<'_>, c: char) -> fmt::Result {
+    if "]\\^-[".contains(c) {
+        write!(f, "\\{}", c)
+    } else {
+        write!(f, "{}", c)
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Regex::Literal(c) if META.contains(*c) => write!(f, "\\{}", c),
+            Regex::Literal(c) => write!(f, "{}", c),
+            Regex::Any => f.write_str("."),
+            Regex::Class { ranges, negated } => {
+                f.write_str(if *negated { "[^" } else { "[" })?;
+                for (lo, hi) in ranges {
+                    write_class_char(f, *lo)?;
+                    if lo != hi {
+                        f.write_str("-")?;
+                        write_class_char(f, *hi)?;
+                    }
+                }
+                f.write_str("]")
+            }
+            Regex::Concat(parts) if parts.is_empty() => f.write_str("()"),
+            Regex::Concat(parts) => parts
+                .iter()
+                .try_for_each(|p| write_group(f, p, p.precedence() < 1)),
+            Regex::Alt(options) => {
+                for (i, option) in options.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("|")?;
+                    }
+                    write_group(f, option, option.precedence() < 1)?;
+                }
+                Ok(())
+            }
+            Regex::Repeat { inner, min, max } => {
+                write_group(f, inner, inner.precedence() < 3)?;
+                match (min, max) {
+                    (0, None) => f.write_str("*"),
+                    (1, None) => f.write_str("+"),
+                    (0, Some(1)) => f.write_str("?"),
+                    (m, None) => write!(f, "{{{},}}", m),
+                    (m, Some(n)) if m == n => write!(f, "{{{}}}", m),
+                    (m, Some(n)) => write!(f, "{{{},{}}}", m, n),
+                }
+            }
+        }
+    }
+}
+
+/// Generates random `Regex`es of bounded size
+pub struct RegexGen {
+    alphabet: Vec<char>,
+    max_depth: usize,
+    max_repeat: u32,
+}
+
+impl RegexGen {
+    pub fn new() -> Self {
+        RegexGen {
+            alphabet: "abcxyz.-".chars().collect(),
+            max_depth: 3,
+            max_repeat: 3,
+        }
+    }
+
+    /// Characters for literals, classes and sample strings. The default includes a few
+    /// that need escaping.
+    pub fn alphabet(mut self, alphabet: &str) -> Self {
+        self.alphabet = alphabet.chars().collect();
+        self
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Upper bound for `{m,n}` and for how often unbounded repeats repeat in samples
+    pub fn max_repeat(mut self, max: u32) -> Self {
+        self.max_repeat = max.max(1);
+        self
+    }
+
+    pub fn regex<R>(&self, rng: &mut R) -> Regex
+    where
+        R: Rng + ?Sized,
+    {
+        self.regex_at(self.max_depth, rng)
+    }
+
+    /// A string matching `regex`, using this generator's alphabet
+    pub fn matching<R>(&self, regex: &Regex, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        regex.sample_match(&self.alphabet, self.max_repeat, rng)
+    }
+
+    /// A string not matching `regex`, if one can be found
+    pub fn non_matching<R>(&self, regex: &Regex, rng: &mut R) -> Option<String>
+    where
+        R: Rng + ?Sized,
+    {
+        regex.sample_non_match(&self.alphabet, self.max_repeat, rng)
+    }
+
+    fn regex_at<R>(&self, depth: usize, rng: &mut R) -> Regex
+    where
+        R: Rng + ?Sized,
+    {
+        if depth == 0 || rng.gen_bool(0.25) {
+            return self.atom(rng);
+        }
+        match rng.gen_range(0..3) {
+            0 => Regex::Concat(
+                (0..rng.gen_range(2..=4))
+                    .map(|_| self.regex_at(depth - 1, rng))
+                    .collect(),
+            ),
+            1 => Regex::Alt(
+                (0..rng.gen_range(2..=3))
+                    .map(|_| self.regex_at(depth - 1, rng))
+                    .collect(),
+            ),
+            _ => {
+                let (min, max) = match rng.gen_range(0..4) {
+                    0 => (0, None),
+                    1 => (1, None),
+                    2 => (0, Some(1)),
+                    _ => {
+                        let min = rng.gen_range(0..=self.max_repeat);
+                        (min, Some(rng.gen_range(min..=self.max_repeat)))
+                    }
+                };
+                Regex::Repeat {
+                    inner: Box::new(self.regex_at(depth - 1, rng)),
+                    min,
+                    max,
+                }
+            }
+        }
+    }
+
+    fn atom<R>(&self, rng: &mut R) -> Regex
+    where
+        R: Rng + ?Sized,
+    {
+        match rng.gen_range(0..10) {
+            0 => Regex::Any,
+            1 | 2 => {
+                let ranges = (0..rng.gen_range(1..=2))
+                    .map(|_| {
+                        let (a, b) = (
+                            *self.alphabet.choose(rng).unwrap(),
+                            *self.alphabet.choose(rng).unwrap(),
+                        );
+                        (a.min(b), a.max(b))
+                    })
+                    .collect();
+                Regex::Class {
+                    ranges,
+                    negated: rng.gen_bool(0.3),
+                }
+            }
+            _ => Regex::Literal(*self.alphabet.choose(rng).unwrap()),
+        }
+    }
+}
+
+impl Default for RegexGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_prints_patterns() {
+        let r = Regex::Concat(vec![
+            Regex::Literal('.'),
+            Regex::Repeat {
+                inner: Box::new(Regex::Alt(vec![Regex::Literal('a'), Regex::Literal('b')])),
+                min: 2,
+                max: Some(3),
+            },
+            Regex::Class {
+                ranges: vec![('a', 'c'), ('-', '-')],
+                negated: true,
+            },
+        ]);
+
+        assert_eq!(r.to_string(), r"\.(a|b){2,3}[^a-c\-]");
+        assert!(r.is_match(".aba!"));
+        assert!(!r.is_match(".ab-"));
+        assert!(!r.is_match(".abababx"));
+    }
+
+    #[test]
+    fn it_agrees_with_the_regex_crate() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let gen = RegexGen::new();
+
+        for _ in 0..300 {
+            let regex = gen.regex(&mut rng);
+            let engine = ::regex::Regex::new(&regex.anchored()).unwrap();
+
+            let yes = gen.matching(&regex, &mut rng);
+            assert!(regex.is_match(&yes), "{} {:?}", regex, yes);
+            assert!(engine.is_match(&yes), "{} {:?}", regex, yes);
+            if let Some(no) = gen.non_matching(&regex, &mut rng) {
+                assert!(!engine.is_match(&no), "{} {:?}", regex, no);
+            }
+        }
+    }
+}
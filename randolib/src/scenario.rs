@@ -0,0 +1,235 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use somelib::error::Error;
+use std::collections::HashMap;
+
+/// A boxed closure that produces a step's payload. `dyn RngCore` lets every step share one
+/// generator without the scenario itself being generic over the RNG type.
+type PayloadFn<P> = Box<dyn Fn(&mut dyn RngCore) -> P>;
+
+struct Step<P> {
+    name: String,
+    payload: PayloadFn<P>,
+    /// `(target step index, probability)`
+    transitions: Vec<(usize, f64)>,
+}
+
+/// A random state machine describing a multi-step journey. Each step produces a payload when
+/// it's entered, then moves to one of its successors with the given probabilities. Whatever
+/// probability is left over (or a step with no transitions at all) ends the journey.
+pub struct Scenario<P> {
+    steps: Vec<Step<P>>,
+    start: usize,
+}
+
+/// Collects steps and transitions by name; names are resolved and checked in `build`
+pub struct ScenarioBuilder<P> {
+    start: String,
+    steps: Vec<(String, PayloadFn<P>)>,
+    transitions: Vec<(String, String, f64)>,
+}
+
+/// One step of a journey as produced by `ScenarioRunner`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event<P> {
+    /// How many steps came before this one
+    pub index: usize,
+    pub step: String,
+    pub payload: P,
+}
+
+impl<P> Scenario<P> {
+    /// Start describing a scenario whose journeys begin at the step named `start`
+    pub fn builder(start: &str) -> ScenarioBuilder<P> {
+        ScenarioBuilder {
+            start: start.to_string(),
+            steps: Vec::new(),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Run one journey with its own seeded generator. Deterministic across `rand` releases
+    /// too, see `RandoA::from_seed`.
+    pub fn run(&self, seed: u64) -> ScenarioRunner<'_, P, ChaCha20Rng> {
+        ScenarioRunner::new(self, ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<P> ScenarioBuilder<P> {
+    /// Add a step and the generator for the payload it emits
+    pub fn step<F>(mut self, name: &str, payload: F) -> Self
+    where
+        // `'static` because the closure is boxed and stored in the scenario
+        F: Fn(&mut dyn RngCore) -> P + 'static,
+    {
+        self.steps.push((name.to_string(), Box::new(payload)));
+        self
+    }
+
+    /// Move from `from` to `to` with the given `probability`
+    pub fn transition(mut self, from: &str, to: &str, probability: f64) -> Self {
+        self.transitions
+            .push((from.to_string(), to.to_string(), probability));
+        self
+    }
+
+    /// Check the description and turn it into a `Scenario`
+    pub fn build(self) -> Result<Scenario<P>, Error> {
+        let mut index = HashMap::new();
+        for (i, (name, _)) in self.steps.iter().enumerate() {
+            if index.insert(name.clone(), i).is_some() {
+                return Err(Error::InvalidParameter(format!(
+                    "duplicate step {:?}",
+                    name
+                )));
+            }
+        }
+        // A closure that borrows `index` so we can reuse the lookup and its error
+        let lookup = |name: &str| {
+            index
+                .get(name)
+                .copied()
+                .ok_or_else(|| Error::InvalidParameter(format!("unknown step {:?}", name)))
+        };
+
+        let start = lookup(&self.start)?;
+        let mut steps = self
+            .steps
+            .into_iter()
+            .map(|(name, payload)| Step {
+                name,
+                payload,
+                transitions: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        for (from, to, probability) in &self.transitions {
+            if !(0.0..=1.0).contains(probability) {
+                return Err(Error::InvalidParameter(format!(
+                    "transition {:?} -> {:?} has probability {} outside [0, 1]",
+                    from, to, probability
+                )));
+            }
+            let (from_idx, to_idx) = (lookup(from)?, lookup(to)?);
+            steps[from_idx].transitions.push((to_idx, *probability));
+        }
+
+        for step in &steps {
+            let total = step.transitions.iter().map(|(_, p)| p).sum::<f64>();
+            // Allow a little floating point slop, e.g. 0.1 + 0.2 + 0.7
+            if total > 1.0 + 1e-9 {
+                return Err(Error::InvalidParameter(format!(
+                    "transitions out of {:?} sum to {}",
+                    step.name, total
+                )));
+            }
+        }
+
+        Ok(Scenario { steps, start })
+    }
+}
+
+/// Walks a `Scenario`, yielding one `Event` per step until the journey ends
+pub struct ScenarioRunner<'a, P, R> {
+    scenario: &'a Scenario<P>,
+    rng: R,
+    /// `None` once the journey has ended
+    current: Option<usize>,
+    index: usize,
+}
+
+impl<'a, P, R> ScenarioRunner<'a, P, R>
+where
+    R: Rng,
+{
+    pub fn new(scenario: &'a Scenario<P>, rng: R) -> Self {
+        ScenarioRunner {
+            scenario,
+            rng,
+            current: Some(scenario.start),
+            index: 0,
+        }
+    }
+
+    /// Pick the successor of `step`, or `None` if the journey ends here
+    fn next_step(&mut self, step: usize) -> Option<usize> {
+        let mut roll = self.rng.gen::<f64>();
+        for (target, probability) in &self.scenario.steps[step].transitions {
+            if roll < *probability {
+                return Some(*target);
+            }
+            roll -= probability;
+        }
+        None
+    }
+}
+
+impl<'a, P, R> Iterator for ScenarioRunner<'a, P, R>
+where
+    R: Rng,
+{
+    type Item = Event<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current?;
+        let step = &self.scenario.steps[current];
+        let event = Event {
+            index: self.index,
+            step: step.name.clone(),
+            payload: (step.payload)(&mut self.rng),
+        };
+        self.index += 1;
+        self.current = self.next_step(current);
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkout_flow() -> Scenario<u32> {
+        Scenario::builder("landing")
+            .step("landing", |rng| rng.gen_range(0..100))
+            .step("cart", |rng| rng.gen_range(1..5))
+            .step("paid", |_| 0)
+            .transition("landing", "landing", 0.3)
+            .transition("landing", "cart", 0.6)
+            .transition("cart", "paid", 1.0)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn it_replays_the_same_journey_from_a_seed() {
+        let scenario = checkout_flow();
+
+        let first = scenario.run(1234).collect::<Vec<_>>();
+        let second = scenario.run(1234).collect::<Vec<_>>();
+
+        assert_eq!(first, second);
+        assert_eq!(first[0].step, "landing");
+        // "paid" is terminal and can only be reached from "cart"
+        if let Some(paid) = first.iter().position(|e| e.step == "paid") {
+            assert_eq!(first[paid - 1].step, "cart");
+            assert_eq!(paid, first.len() - 1);
+        }
+    }
+
+    #[test]
+    fn it_rejects_bad_descriptions() {
+        let unknown = Scenario::<()>::builder("a")
+            .step("a", |_| ())
+            .transition("a", "b", 0.5)
+            .build();
+        assert!(unknown.is_err());
+
+        let over = Scenario::<()>::builder("a")
+            .step("a", |_| ())
+            .step("b", |_| ())
+            .transition("a", "b", 0.7)
+            .transition("a", "a", 0.7)
+            .build();
+        assert!(over.is_err());
+    }
+}
@@ -0,0 +1,707 @@
+//! Semantic versions and version requirements for testing dependency resolvers.
+//!
+//! `Version` follows SemVer 2.0.0, including its precedence rules for pre-release
+//! identifiers. `VersionReq` follows Cargo's requirement syntax (`^1.2`, `~1.2.3`, `>=1, <2`,
+//! `1.*`, ...), including the rule that a pre-release version only matches a requirement
+//! that mentions a pre-release of the same `major.minor.patch`. `SemverGen` generates both,
+//! with the edge cases resolvers get wrong, and pairs that are known to match or not.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+/// A dot-separated part of a pre-release, e.g. `alpha` and `1` in `1.0.0-alpha.1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    Numeric(u64),
+    Alpha(String),
+}
+
+impl Identifier {
+    /// Numeric identifiers compare as numbers and sort before alphanumeric ones, so
+    /// `rc.9 < rc.10` but `rc.10 < rc.a`
+    fn precedence(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alpha(_)) => Ordering::Less,
+            (Identifier::Alpha(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alpha(a), Identifier::Alpha(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::Alpha(s) => f.write_str(s),
+        }
+    }
+}
+
+fn invalid(what: &str, s: &str) -> Error {
+    Error::InvalidParameter(format!("invalid {} {:?}", what, s))
+}
+
+/// A number without leading zeros, as SemVer requires for version numbers
+fn parse_number(s: &str) -> Result<u64, Error> {
+    if s.is_empty() || (s.len() > 1 && s.starts_with('0')) || !s.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid("version number", s));
+    }
+    s.parse().map_err(|_| invalid("version number", s))
+}
+
+fn parse_pre(s: &str) -> Result<Vec<Identifier>, Error> {
+    s.split('.')
+        .map(|part| {
+            if part.bytes().all(|b| b.is_ascii_digit()) {
+                parse_number(part).map(Identifier::Numeric)
+            } else if part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                Ok(Identifier::Alpha(part.to_string()))
+            } else {
+                Err(invalid("pre-release identifier", part))
+            }
+        })
+        .collect()
+}
+
+/// Build metadata may have leading zeros, it's never compared
+fn parse_build(s: &str) -> Result<Vec<String>, Error> {
+    s.split('.')
+        .map(|part| {
+            if !part.is_empty() && part.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                Ok(part.to_string())
+            } else {
+                Err(invalid("build identifier", part))
+            }
+        })
+        .collect()
+}
+
+fn join<T: fmt::Display>(parts: &[T]) -> String {
+    parts
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// `major.minor.patch[-pre][+build]`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre: Vec<Identifier>,
+    pub build: Vec<String>,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Version {
+            major,
+            minor,
+            patch,
+            pre: Vec::new(),
+            build: Vec::new(),
+        }
+    }
+
+    /// SemVer precedence. This is not `Ord` because build metadata is ignored: `1.0.0+a`
+    /// and `1.0.0+b` have the same precedence but aren't equal.
+    pub fn precedence(&self, other: &Self) -> Ordering {
+        let triple = |v: &Version| (v.major, v.minor, v.patch);
+        triple(self).cmp(&triple(other)).then_with(|| {
+            match (self.pre.is_empty(), other.pre.is_empty()) {
+                // A pre-release comes before its release
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => {
+                    for (a, b) in self.pre.iter().zip(&other.pre) {
+                        let order = a.precedence(b);
+                        if order != Ordering::Equal {
+                            return order;
+                        }
+                    }
+                    // More identifiers win when all the shared ones are equal
+                    self.pre.len().cmp(&other.pre.len())
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", join(&self.pre))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", join(&self.build))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rest, build) = match s.split_once('+') {
+            Some((rest, build)) => (rest, parse_build(build)?),
+            None => (s, Vec::new()),
+        };
+        let (rest, pre) = match rest.split_once('-') {
+            Some((rest, pre)) => (rest, parse_pre(pre)?),
+            None => (rest, Vec::new()),
+        };
+        let numbers = rest
+            .split('.')
+            .map(parse_number)
+            .collect::<Result<Vec<_>, _>>()?;
+        let [major, minor, patch] = numbers[..] else {
+            return Err(invalid("version", s));
+        };
+        Ok(Version {
+            major,
+            minor,
+            patch,
+            pre,
+            build,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+    /// `1.*` or `1.2.*`. A bare `*` is a `VersionReq` with no comparators.
+    Wildcard,
+}
+
+impl Op {
+    const ALL: [Op; 8] = [
+        Op::Exact,
+        Op::Greater,
+        Op::GreaterEq,
+        Op::Less,
+        Op::LessEq,
+        Op::Tilde,
+        Op::Caret,
+        Op::Wildcard,
+    ];
+
+    fn symbol(&self) -> &'static str {
+        match self {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+            Op::Wildcard => "",
+        }
+    }
+}
+
+/// One condition of a requirement. `minor` and `patch` can be left out, as in `^1` or
+/// `>=1.2`; a pre-release needs all three numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Comparator {
+    pub op: Op,
+    pub major: u64,
+    pub minor: Option<u64>,
+    pub patch: Option<u64>,
+    pub pre: Vec<Identifier>,
+}
+
+impl Comparator {
+    /// The comparator's version with missing parts as 0
+    fn floor(&self) -> Version {
+        Version {
+            pre: self.pre.clone(),
+            ..Version::new(self.major, self.minor.unwrap_or(0), self.patch.unwrap_or(0))
+        }
+    }
+
+    /// The first release past the parts that were given: `1.2` becomes `1.3.0`
+    fn next(&self) -> Version {
+        match (self.minor, self.patch) {
+            (None, _) => Version::new(self.major + 1, 0, 0),
+            (Some(minor), None) => Version::new(self.major, minor + 1, 0),
+            (Some(minor), Some(patch)) => Version::new(self.major, minor, patch + 1),
+        }
+    }
+
+    /// Whether `version` satisfies this comparator, ignoring the pre-release opt-in rule
+    /// (see `VersionReq::matches`)
+    fn matches(&self, version: &Version) -> bool {
+        let cmp = |v: &Version| version.precedence(v);
+        let floor = self.floor();
+        match self.op {
+            Op::Exact | Op::Wildcard if self.patch.is_some() => cmp(&floor).is_eq(),
+            Op::Exact | Op::Wildcard => cmp(&floor).is_ge() && cmp(&self.next()).is_lt(),
+            Op::Greater if self.patch.is_some() => cmp(&floor).is_gt(),
+            Op::Greater => cmp(&self.next()).is_ge(),
+            Op::GreaterEq => cmp(&floor).is_ge(),
+            Op::Less => cmp(&floor).is_lt(),
+            Op::LessEq if self.patch.is_some() => cmp(&floor).is_le(),
+            Op::LessEq => cmp(&self.next()).is_lt(),
+            // `~1.2.3` and `~1.2` allow patch updates, `~1` minor ones
+            Op::Tilde => {
+                let upper = match self.minor {
+                    Some(minor) => Version::new(self.major, minor + 1, 0),
+                    None => Version::new(self.major + 1, 0, 0),
+                };
+                cmp(&floor).is_ge() && cmp(&upper).is_lt()
+            }
+            // Everything up to the next change of the leftmost non-zero part
+            Op::Caret => {
+                let upper = match (self.major, self.minor, self.patch) {
+                    (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                    (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                    (major, _, _) => Version::new(major + 1, 0, 0),
+                };
+                cmp(&floor).is_ge() && cmp(&upper).is_lt()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.op == Op::Wildcard {
+            return match self.minor {
+                None => write!(f, "{}.*", self.major),
+                Some(minor) => write!(f, "{}.{}.*", self.major, minor),
+            };
+        }
+        write!(f, "{}{}", self.op.symbol(), self.major)?;
+        if let Some(minor) = self.minor {
+            write!(f, ".{}", minor)?;
+        }
+        if let Some(patch) = self.patch {
+            write!(f, ".{}", patch)?;
+        }
+        if !self.pre.is_empty() {
+            write!(f, "-{}", join(&self.pre))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Comparator {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // Longest symbols first so `>=` isn't read as `>`
+        let (op, rest) = match [">=", "<=", "=", ">", "<", "~", "^"]
+            .iter()
+            .find_map(|symbol| s.strip_prefix(symbol).map(|rest| (*symbol, rest)))
+        {
+            Some((symbol, rest)) => {
+                let op = Op::ALL.into_iter().find(|op| op.symbol() == symbol);
+                (op, rest.trim_start())
+            }
+            None => (None, s),
+        };
+        let (rest, pre) = match rest.split_once('-') {
+            Some((rest, pre)) => (rest, parse_pre(pre)?),
+            None => (rest, Vec::new()),
+        };
+        let parts = rest.split('.').collect::<Vec<_>>();
+        if parts.len() > 3 {
+            return Err(invalid("requirement", s));
+        }
+        let wildcard = parts.last() == Some(&"*");
+        if wildcard && (op.is_some() || !pre.is_empty() || parts.len() == 1) {
+            return Err(invalid("requirement", s));
+        }
+        let numbers = parts[..parts.len() - wildcard as usize]
+            .iter()
+            .map(|p| parse_number(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        if !pre.is_empty() && numbers.len() < 3 {
+            return Err(invalid("requirement", s));
+        }
+        Ok(Comparator {
+            // Cargo reads a bare version as a caret requirement
+            op: if wildcard {
+                Op::Wildcard
+            } else {
+                op.unwrap_or(Op::Caret)
+            },
+            major: numbers[0],
+            minor: numbers.get(1).copied(),
+            patch: numbers.get(2).copied(),
+            pre,
+        })
+    }
+}
+
+/// Comma-separated comparators, all of which must match. `*` is no comparators at all.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VersionReq {
+    pub comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    pub fn matches(&self, version: &Version) -> bool {
+        if !self.comparators.iter().all(|c| c.matches(version)) {
+            return false;
+        }
+        // Pre-releases are opt-in: `>=1.0.0` doesn't match `2.0.0-beta`, only a comparator
+        // on a pre-release of the same `major.minor.patch` lets them in
+        version.pre.is_empty()
+            || self.comparators.iter().any(|c| {
+                !c.pre.is_empty()
+                    && (c.major, c.minor, c.patch)
+                        == (version.major, Some(version.minor), Some(version.patch))
+            })
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.comparators.is_empty() {
+            return f.write_str("*");
+        }
+        let comparators = self.comparators.iter().map(|c| c.to_string());
+        f.write_str(&comparators.collect::<Vec<_>>().join(", "))
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim() == "*" {
+            return Ok(VersionReq {
+                comparators: Vec::new(),
+            });
+        }
+        let comparators = s
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(VersionReq { comparators })
+    }
+}
+
+/// Generates versions, requirements and pairs of the two
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemverGen {
+    max_number: u64,
+    pre_rate: f64,
+    build_rate: f64,
+}
+
+impl SemverGen {
+    /// Small numbers, so requirements and versions collide often, and a 1 in 4 chance each
+    /// of pre-release and build metadata
+    pub fn new() -> Self {
+        SemverGen {
+            max_number: 3,
+            pre_rate: 0.25,
+            build_rate: 0.25,
+        }
+    }
+
+    /// Version numbers go up to `max`; now and then one is much larger regardless
+    pub fn max_number(mut self, max: u64) -> Self {
+        self.max_number = max;
+        self
+    }
+
+    pub fn pre_rate(mut self, rate: f64) -> Self {
+        self.pre_rate = probability(rate);
+        self
+    }
+
+    pub fn build_rate(mut self, rate: f64) -> Self {
+        self.build_rate = probability(rate);
+        self
+    }
+
+    fn number<R>(&self, rng: &mut R) -> u64
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_bool(0.02) {
+            // Past `u32`, where some resolvers store version parts
+            *[u32::MAX as u64 + 1, u64::MAX / 2].choose(rng).unwrap()
+        } else {
+            rng.gen_range(0..=self.max_number)
+        }
+    }
+
+    /// Pre-release identifiers that sort in surprising ways: numeric against
+    /// alphanumeric, `rc.9` against `rc.10`, longer against shorter
+    fn pre<R>(&self, rng: &mut R) -> Vec<Identifier>
+    where
+        R: Rng + ?Sized,
+    {
+        let pres = [
+            "alpha",
+            "alpha.1",
+            "alpha.beta",
+            "beta",
+            "beta.2",
+            "beta.11",
+            "rc.1",
+            "rc.9",
+            "rc.10",
+            "0",
+            "0.3.7",
+            "x-y-z.--",
+            "a.b.c.d",
+            "RC.1",
+        ];
+        parse_pre(pres.choose(rng).unwrap()).unwrap()
+    }
+
+    pub fn version<R>(&self, rng: &mut R) -> Version
+    where
+        R: Rng + ?Sized,
+    {
+        let mut version = Version::new(self.number(rng), self.number(rng), self.number(rng));
+        if rng.gen_bool(self.pre_rate) {
+            version.pre = self.pre(rng);
+        }
+        if rng.gen_bool(self.build_rate) {
+            // Leading zeros are legal here, unlike in version numbers and pre-releases
+            let builds = [
+                "001",
+                "build.5",
+                "sha.5114f85",
+                "20240101",
+                "exp-sha.a1b2c3",
+            ];
+            version.build = parse_build(builds.choose(rng).unwrap()).unwrap();
+        }
+        version
+    }
+
+    pub fn comparator<R>(&self, rng: &mut R) -> Comparator
+    where
+        R: Rng + ?Sized,
+    {
+        let op = *Op::ALL.choose(rng).unwrap();
+        let parts = if op == Op::Wildcard {
+            rng.gen_range(1..=2)
+        } else {
+            rng.gen_range(1..=3)
+        };
+        let mut comparator = Comparator {
+            op,
+            major: self.number(rng),
+            minor: (parts > 1).then(|| self.number(rng)),
+            patch: (parts > 2).then(|| self.number(rng)),
+            pre: Vec::new(),
+        };
+        if parts == 3 && rng.gen_bool(self.pre_rate) {
+            comparator.pre = self.pre(rng);
+        }
+        comparator
+    }
+
+    /// One comparator, sometimes two (`>=1.2, <1.5`), and now and then `*`
+    pub fn req<R>(&self, rng: &mut R) -> VersionReq
+    where
+        R: Rng + ?Sized,
+    {
+        if rng.gen_bool(0.05) {
+            return "*".parse().unwrap();
+        }
+        let count = if rng.gen_bool(0.3) { 2 } else { 1 };
+        VersionReq {
+            comparators: (0..count).map(|_| self.comparator(rng)).collect(),
+        }
+    }
+
+    /// A version close to one of `req`'s comparators, where the boundaries are
+    fn near<R>(&self, req: &VersionReq, rng: &mut R) -> Version
+    where
+        R: Rng + ?Sized,
+    {
+        let Some(c) = req.comparators.choose(rng) else {
+            return self.version(rng);
+        };
+        let nudge = |n: u64, rng: &mut R| match rng.gen_range(0..4) {
+            0 => n.saturating_sub(1),
+            1 => n.saturating_add(1),
+            _ => n,
+        };
+        let mut version = c.floor();
+        version.major = nudge(version.major, rng);
+        version.minor = nudge(version.minor, rng);
+        version.patch = nudge(version.patch, rng);
+        if rng.gen_bool(self.pre_rate) {
+            version.pre = self.pre(rng);
+        }
+        version
+    }
+
+    /// A requirement and a version that it matches (`matches` is true) or doesn't
+    pub fn pair<R>(&self, matches: bool, rng: &mut R) -> (VersionReq, Version)
+    where
+        R: Rng + ?Sized,
+    {
+        for _ in 0..1000 {
+            let req = self.req(rng);
+            let version = self.near(&req, rng);
+            if req.matches(&version) == matches {
+                return (req, version);
+            }
+        }
+        // Practically unreachable, but an exact requirement always settles it
+        let version = self.version(rng);
+        let patch = if matches {
+            version.patch
+        } else {
+            version.patch.wrapping_add(1)
+        };
+        let req = VersionReq {
+            comparators: vec![Comparator {
+                op: Op::Exact,
+                major: version.major,
+                minor: Some(version.minor),
+                patch: Some(patch),
+                pre: version.pre.clone(),
+            }],
+        };
+        (req, version)
+    }
+}
+
+impl Default for SemverGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    fn req(s: &str) -> VersionReq {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn it_orders_by_precedence() {
+        // The example from the SemVer spec
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.10.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert_eq!(
+                v(pair[0]).precedence(&v(pair[1])),
+                Ordering::Less,
+                "{:?}",
+                pair
+            );
+        }
+        assert_eq!(v("1.0.0+a").precedence(&v("1.0.0+b")), Ordering::Equal);
+        assert_ne!(v("1.0.0+a"), v("1.0.0+b"));
+
+        for bad in ["1.2", "01.2.3", "1.2.3-01", "1.2.3-", "1.2.3+a..b", "1.2.x"] {
+            assert!(bad.parse::<Version>().is_err(), "{}", bad);
+        }
+        assert_eq!(v("1.0.0-x-y.7+001.b").to_string(), "1.0.0-x-y.7+001.b");
+    }
+
+    #[test]
+    fn it_matches_like_cargo() {
+        let cases = [
+            ("^1.2.3", "1.9.0", true),
+            ("^1.2.3", "2.0.0", false),
+            ("^0.2.3", "0.2.9", true),
+            ("^0.2.3", "0.3.0", false),
+            ("^0.0.3", "0.0.4", false),
+            ("^0.0", "0.0.9", true),
+            ("1.2", "1.3.0", true),
+            ("~1.2.3", "1.2.9", true),
+            ("~1.2.3", "1.3.0", false),
+            ("~1", "1.9.9", true),
+            ("=1.2", "1.2.7", true),
+            ("=1.2", "1.3.0", false),
+            (">1.2", "1.2.9", false),
+            (">1.2", "1.3.0", true),
+            ("<=1.2", "1.2.9", true),
+            ("<1.2", "1.1.9", true),
+            ("<1.2", "1.2.0", false),
+            ("1.*", "1.7.0", true),
+            ("1.2.*", "1.3.0", false),
+            ("*", "42.0.0", true),
+            (">=1.2, <1.5", "1.4.9", true),
+            (">=1.2, <1.5", "1.5.0", false),
+            // Pre-releases need an opt-in on the same version
+            (">=1.0.0", "2.0.0-beta", false),
+            ("*", "1.0.0-alpha", false),
+            (">=1.0.0-alpha", "1.0.0-beta", true),
+            (">=1.0.0-alpha", "1.0.1-beta", false),
+            ("^1.2.3-rc.9", "1.2.3-rc.10", true),
+        ];
+        for (r, version, expected) in cases {
+            assert_eq!(req(r).matches(&v(version)), expected, "{} {}", r, version);
+        }
+        for bad in ["", "1.2.3.4", "^1.*", "*.1", ">=1.2-alpha", "~x"] {
+            assert!(bad.parse::<VersionReq>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn it_generates_matching_and_non_matching_pairs() {
+        let mut rng = StdRng::seed_from_u64(263);
+        let gen = SemverGen::new();
+
+        for _ in 0..500 {
+            let (r, version) = gen.pair(true, &mut rng);
+            assert!(r.matches(&version), "{} {}", r, version);
+            let (r, version) = gen.pair(false, &mut rng);
+            assert!(!r.matches(&version), "{} {}", r, version);
+
+            // Everything generated survives a round trip through text
+            let version = gen.version(&mut rng);
+            assert_eq!(v(&version.to_string()), version);
+            let generated = gen.req(&mut rng);
+            assert_eq!(req(&generated.to_string()), generated, "{}", generated);
+        }
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = SemverGen::new().pre_rate(f64::NAN).build_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(263);
+        for _ in 0..20 {
+            let version = gen.version(&mut rng);
+            assert!(version.pre.is_empty() && version.build.is_empty());
+        }
+    }
+}
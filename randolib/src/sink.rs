@@ -0,0 +1,276 @@
+//! Where generated events go. A `Sink` takes a batch of events and only returns once the
+//! destination has them, so a slow destination slows the producer down instead of events
+//! piling up in memory. `Batcher` groups events into batches for it.
+//!
+//! `WriteSink` writes lines to any `Write`, e.g. stdout. With the `redis` feature,
+//! `RedisSink` pushes onto a Redis list, which removes the shell pipe between a generator
+//! and a load test that reads from Redis.
+
+use somelib::error::Error;
+use std::io::Write;
+
+/// A destination for batches of events
+pub trait Sink {
+    /// Deliver `events` in order, blocking while the destination can't take them
+    fn send(&mut self, events: &[String]) -> Result<(), Error>;
+}
+
+/// So the sink can be picked at runtime, e.g. `Batcher<Box<dyn Sink>>`
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    fn send(&mut self, events: &[String]) -> Result<(), Error> {
+        (**self).send(events)
+    }
+}
+
+/// Collects events into batches of `size` for a `Sink`
+///
+/// A `Drop` impl can't report errors, so call `flush` when done or the last partial batch
+/// is lost.
+pub struct Batcher<S> {
+    sink: S,
+    size: usize,
+    pending: Vec<String>,
+}
+
+impl<S: Sink> Batcher<S> {
+    pub fn new(sink: S, size: usize) -> Result<Self, Error> {
+        if size == 0 {
+            return Err(Error::InvalidParameter(
+                "batches need room for at least one event".into(),
+            ));
+        }
+        Ok(Batcher {
+            sink,
+            size,
+            pending: Vec::with_capacity(size),
+        })
+    }
+
+    /// Queue `event`, sending the batch once it's full
+    pub fn push(&mut self, event: String) -> Result<(), Error> {
+        self.pending.push(event);
+        if self.pending.len() >= self.size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Send whatever is queued, even if the batch isn't full
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if !self.pending.is_empty() {
+            self.sink.send(&self.pending)?;
+            self.pending.clear();
+        }
+        Ok(())
+    }
+
+    /// The sink, with anything still queued dropped
+    pub fn into_inner(self) -> S {
+        self.sink
+    }
+}
+
+/// Writes each event as a line and flushes after every batch
+pub struct WriteSink<W>(pub W);
+
+impl<W: Write> Sink for WriteSink<W> {
+    fn send(&mut self, events: &[String]) -> Result<(), Error> {
+        for event in events {
+            writeln!(self.0, "{}", event)?;
+        }
+        Ok(self.0.flush()?)
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use self::redis_sink::RedisSink;
+
+#[cfg(feature = "redis")]
+mod redis_sink {
+    use super::Sink;
+    use somelib::error::Error;
+    use std::time::Duration;
+
+    /// Pushes events onto the end of a Redis list with `RPUSH`, one round trip per batch.
+    /// Consumers pop from the front.
+    ///
+    /// With `max_len` set, a batch waits while the list is too long to take it, for
+    /// backpressure from consumers that fall behind.
+    pub struct RedisSink {
+        connection: redis::Connection,
+        key: String,
+        max_len: Option<usize>,
+        poll: Duration,
+    }
+
+    impl RedisSink {
+        /// `url` like `redis://localhost:6379/0`
+        pub fn new(url: &str, key: &str) -> Result<Self, Error> {
+            let client = redis::Client::open(url).map_err(redis_error)?;
+            Ok(RedisSink {
+                connection: client.get_connection().map_err(redis_error)?,
+                key: key.to_string(),
+                max_len: None,
+                poll: Duration::from_millis(50),
+            })
+        }
+
+        /// Wait for the list to drain below `max_len` before pushing more
+        pub fn max_len(mut self, max_len: usize) -> Self {
+            self.max_len = Some(max_len);
+            self
+        }
+
+        /// How often to check the list length while waiting
+        pub fn poll(mut self, poll: Duration) -> Self {
+            self.poll = poll;
+            self
+        }
+    }
+
+    impl Sink for RedisSink {
+        fn send(&mut self, events: &[String]) -> Result<(), Error> {
+            if let Some(max_len) = self.max_len {
+                loop {
+                    let len = redis::cmd("LLEN")
+                        .arg(&self.key)
+                        .query::<usize>(&mut self.connection)
+                        .map_err(redis_error)?;
+                    // An empty list always takes a batch, even one bigger than `max_len`
+                    if len == 0 || len + events.len() <= max_len {
+                        break;
+                    }
+                    std::thread::sleep(self.poll);
+                }
+            }
+            redis::cmd("RPUSH")
+                .arg(&self.key)
+                .arg(events)
+                .query::<usize>(&mut self.connection)
+                .map_err(redis_error)?;
+            Ok(())
+        }
+    }
+
+    /// Connection problems are I/O problems as far as our `Error` is concerned
+    fn redis_error(err: redis::RedisError) -> Error {
+        Error::Io(std::io::Error::other(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Remembers every batch it's sent
+    #[derive(Default)]
+    struct Recorder(Vec<Vec<String>>);
+
+    impl Sink for Recorder {
+        fn send(&mut self, events: &[String]) -> Result<(), Error> {
+            self.0.push(events.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_sends_full_batches_then_the_rest_on_flush() {
+        let mut batcher = Batcher::new(Recorder::default(), 2).unwrap();
+        for i in 0..5 {
+            batcher.push(i.to_string()).unwrap();
+        }
+        assert_eq!(batcher.sink.0.len(), 2);
+        batcher.flush().unwrap();
+        batcher.flush().unwrap();
+        let batches = batcher.into_inner().0;
+        assert_eq!(batches, [vec!["0", "1"], vec!["2", "3"], vec!["4"]]);
+        assert!(Batcher::new(Recorder::default(), 0).is_err());
+
+        let mut lines = WriteSink(Vec::new());
+        lines.send(&["a".into(), "b".into()]).unwrap();
+        assert_eq!(lines.0, b"a\nb\n");
+    }
+
+    #[cfg(feature = "redis")]
+    #[test]
+    fn it_pushes_to_redis_with_backpressure() {
+        use std::{
+            io::{BufRead, BufReader, Read},
+            net::TcpListener,
+            time::Duration,
+        };
+
+        // Just enough of a Redis server: reads RESP commands, reports a list that drains
+        // by one on every `LLEN`, and hands back the commands it saw
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut commands = Vec::new();
+            let mut len = 3;
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 {
+                    return commands;
+                }
+                // `*N` then `$LEN` and the bytes for each of N arguments
+                let argc = line.trim()[1..].parse::<usize>().unwrap();
+                let mut args = Vec::new();
+                for _ in 0..argc {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    let n = line.trim()[1..].parse::<usize>().unwrap();
+                    let mut arg = vec![0; n + 2];
+                    reader.read_exact(&mut arg).unwrap();
+                    args.push(String::from_utf8_lossy(&arg[..n]).into_owned());
+                }
+                let reply = match args[0].as_str() {
+                    "LLEN" => {
+                        len -= 1;
+                        format!(":{}\r\n", len)
+                    }
+                    "RPUSH" => {
+                        len += args.len() - 2;
+                        format!(":{}\r\n", len)
+                    }
+                    _ => "+OK\r\n".to_string(),
+                };
+                std::io::Write::write_all(reader.get_mut(), reply.as_bytes()).unwrap();
+                commands.push(args);
+            }
+        });
+
+        let url = format!("redis://127.0.0.1:{}", port);
+        let sink = RedisSink::new(&url, "events")
+            .unwrap()
+            .max_len(2)
+            .poll(Duration::ZERO);
+        let mut batcher = Batcher::new(sink, 2).unwrap();
+        for event in ["a", "b", "c"] {
+            batcher.push(event.to_string()).unwrap();
+        }
+        batcher.flush().unwrap();
+        drop(batcher);
+
+        let commands = server
+            .join()
+            .unwrap()
+            .into_iter()
+            .filter(|args| args[0] == "LLEN" || args[0] == "RPUSH")
+            .map(|args| args.join(" "))
+            .collect::<Vec<_>>();
+        // 3 waiting: wait for 2, then 1, then 0 before the first batch
+        assert_eq!(
+            commands,
+            [
+                "LLEN events",
+                "LLEN events",
+                "LLEN events",
+                "RPUSH events a b",
+                "LLEN events",
+                "RPUSH events c",
+            ]
+        );
+    }
+}
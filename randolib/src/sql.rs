@@ -0,0 +1,572 @@
+//! Random `SELECT` queries over a schema you describe, for fuzzing query engines. Queries
+//! are generated as an AST (`Select`) and rendered by `Display`, so tests get both the
+//! text to run and the structure to check the results against.
+//!
+//! Queries are always valid: columns exist, joins compare columns of the same type,
+//! `SUM`/`AVG` only see numbers, and grouped queries only select grouped columns and
+//! aggregates.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    Float,
+    Text,
+    Bool,
+}
+
+impl ColumnType {
+    fn is_numeric(&self) -> bool {
+        matches!(self, ColumnType::Int | ColumnType::Float)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub name: String,
+    pub columns: Vec<(String, ColumnType)>,
+}
+
+impl Table {
+    pub fn new(name: &str) -> Self {
+        Table {
+            name: name.to_string(),
+            columns: Vec::new(),
+        }
+    }
+
+    pub fn column(mut self, name: &str, ty: ColumnType) -> Self {
+        self.columns.push((name.to_string(), ty));
+        self
+    }
+}
+
+/// A column of one of the query's tables, by the table's alias
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnRef {
+    pub alias: String,
+    pub column: String,
+    pub ty: ColumnType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Float(f64),
+    Text(String),
+    Bool(bool),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    Compare(ColumnRef, CmpOp, Literal),
+    IsNull(ColumnRef),
+    Not(Box<Predicate>),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    Column(ColumnRef),
+    /// `None` is `COUNT(*)`
+    Aggregate(Aggregate, Option<ColumnRef>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: String,
+    pub alias: String,
+    /// `left = right`, where `right` belongs to the joined table
+    pub on: (ColumnRef, ColumnRef),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub items: Vec<SelectItem>,
+    pub from: String,
+    pub from_alias: String,
+    pub joins: Vec<Join>,
+    pub filter: Option<Predicate>,
+    pub group_by: Vec<ColumnRef>,
+    /// `(column, descending)`
+    pub order_by: Vec<(ColumnRef, bool)>,
+    pub limit: Option<u64>,
+}
+
+/// Double quoted, so reserved words like `order` work as names
+fn ident(f: &mut fmt::Formatter<'_>, name: &str) -> fmt::Result {
+    write!(f, "\"{}\"", name.replace('"', "\"\""))
+}
+
+impl fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.", self.alias)?;
+        ident(f, &self.column)
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Int(n) => write!(f, "{}", n),
+            // `{:?}` always includes a decimal point, so it stays a float literal
+            Literal::Float(x) => write!(f, "{:?}", x),
+            Literal::Text(s) => write!(f, "'{}'", s.replace('\'', "''")),
+            Literal::Bool(b) => f.write_str(if *b { "TRUE" } else { "FALSE" }),
+        }
+    }
+}
+
+impl fmt::Display for CmpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "<>",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+        })
+    }
+}
+
+/// Compound predicates are fully parenthesized so precedence never matters
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::Compare(c, op, lit) => write!(f, "{} {} {}", c, op, lit),
+            Predicate::IsNull(c) => write!(f, "{} IS NULL", c),
+            Predicate::Not(p) => write!(f, "NOT ({})", p),
+            Predicate::And(l, r) => write!(f, "({}) AND ({})", l, r),
+            Predicate::Or(l, r) => write!(f, "({}) OR ({})", l, r),
+        }
+    }
+}
+
+impl fmt::Display for SelectItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectItem::Column(c) => write!(f, "{}", c),
+            SelectItem::Aggregate(agg, column) => {
+                let name = match agg {
+                    Aggregate::Count => "COUNT",
+                    Aggregate::Sum => "SUM",
+                    Aggregate::Avg => "AVG",
+                    Aggregate::Min => "MIN",
+                    Aggregate::Max => "MAX",
+                };
+                match column {
+                    Some(c) => write!(f, "{}({})", name, c),
+                    None => write!(f, "{}(*)", name),
+                }
+            }
+        }
+    }
+}
+
+/// Writes `items` separated by commas
+fn list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SELECT ")?;
+        list(f, &self.items)?;
+        f.write_str(" FROM ")?;
+        ident(f, &self.from)?;
+        write!(f, " AS {}", self.from_alias)?;
+        for join in &self.joins {
+            let kind = match join.kind {
+                JoinKind::Inner => "JOIN",
+                JoinKind::Left => "LEFT JOIN",
+            };
+            write!(f, " {} ", kind)?;
+            ident(f, &join.table)?;
+            write!(f, " AS {} ON {} = {}", join.alias, join.on.0, join.on.1)?;
+        }
+        if let Some(filter) = &self.filter {
+            write!(f, " WHERE {}", filter)?;
+        }
+        if !self.group_by.is_empty() {
+            f.write_str(" GROUP BY ")?;
+            list(f, &self.group_by)?;
+        }
+        for (i, (column, desc)) in self.order_by.iter().enumerate() {
+            f.write_str(if i == 0 { " ORDER BY " } else { ", " })?;
+            write!(f, "{}{}", column, if *desc { " DESC" } else { "" })?;
+        }
+        if let Some(limit) = self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text literals, including some that need escaping or are empty
+const TEXT_VALUES: [&str; 6] = ["a", "alice", "O'Brien", "", "100%", "ünïcödé"];
+
+/// Generates `Select`s over a fixed set of tables
+pub struct SqlGen {
+    tables: Vec<Table>,
+    max_joins: usize,
+    max_predicate_depth: usize,
+    aggregate_rate: f64,
+    limit_rate: f64,
+}
+
+impl SqlGen {
+    pub fn new(tables: Vec<Table>) -> Result<Self, Error> {
+        if tables.is_empty() || tables.iter().any(|t| t.columns.is_empty()) {
+            return Err(Error::InvalidParameter(
+                "the schema needs at least one table, and every table a column".into(),
+            ));
+        }
+        Ok(SqlGen {
+            tables,
+            max_joins: 2,
+            max_predicate_depth: 3,
+            aggregate_rate: 0.3,
+            limit_rate: 0.3,
+        })
+    }
+
+    pub fn max_joins(mut self, max: usize) -> Self {
+        self.max_joins = max;
+        self
+    }
+
+    pub fn max_predicate_depth(mut self, depth: usize) -> Self {
+        self.max_predicate_depth = depth;
+        self
+    }
+
+    /// Chance of a grouped query with aggregates
+    pub fn aggregate_rate(mut self, rate: f64) -> Self {
+        self.aggregate_rate = probability(rate);
+        self
+    }
+
+    pub fn limit_rate(mut self, rate: f64) -> Self {
+        self.limit_rate = probability(rate);
+        self
+    }
+
+    pub fn select<R>(&self, rng: &mut R) -> Select
+    where
+        R: Rng + ?Sized,
+    {
+        let from = self.tables.choose(rng).unwrap();
+        let mut columns = columns_of(from, "t0");
+
+        let mut joins = Vec::new();
+        for i in 1..=rng.gen_range(0..=self.max_joins) {
+            let alias = format!("t{}", i);
+            let table = self.tables.choose(rng).unwrap();
+            let candidates = columns_of(table, &alias);
+            // Join on any pair of columns with matching types, if there is one
+            let pairs = columns
+                .iter()
+                .flat_map(|l| {
+                    candidates
+                        .iter()
+                        .filter(|r| r.ty == l.ty)
+                        .map(move |r| (l, r))
+                })
+                .collect::<Vec<_>>();
+            let Some(&(left, right)) = pairs.choose(rng) else {
+                continue;
+            };
+            joins.push(Join {
+                kind: if rng.gen_bool(0.7) {
+                    JoinKind::Inner
+                } else {
+                    JoinKind::Left
+                },
+                table: table.name.clone(),
+                alias,
+                on: (left.clone(), right.clone()),
+            });
+            columns.extend(candidates);
+        }
+
+        let filter = rng
+            .gen_bool(0.7)
+            .then(|| self.predicate(&columns, self.max_predicate_depth, rng));
+
+        let (items, group_by) = if rng.gen_bool(self.aggregate_rate) {
+            let group_by = sample(&columns, 0..=2, rng);
+            let mut items = group_by
+                .iter()
+                .cloned()
+                .map(SelectItem::Column)
+                .collect::<Vec<_>>();
+            for _ in 0..rng.gen_range(1..=3) {
+                items.push(aggregate(&columns, rng));
+            }
+            (items, group_by)
+        } else {
+            let picked = sample(&columns, 1..=4, rng);
+            (
+                picked.into_iter().map(SelectItem::Column).collect(),
+                Vec::new(),
+            )
+        };
+
+        // Grouped queries can only order by what's grouped
+        let orderable = if items.iter().any(|i| matches!(i, SelectItem::Aggregate(..))) {
+            &group_by
+        } else {
+            &columns
+        };
+        let order_by = sample(orderable, 0..=2, rng)
+            .into_iter()
+            .map(|c| (c, rng.gen()))
+            .collect();
+
+        Select {
+            items,
+            from: from.name.clone(),
+            from_alias: "t0".to_string(),
+            joins,
+            filter,
+            group_by,
+            order_by,
+            limit: rng.gen_bool(self.limit_rate).then(|| rng.gen_range(0..100)),
+        }
+    }
+
+    fn predicate<R>(&self, columns: &[ColumnRef], depth: usize, rng: &mut R) -> Predicate
+    where
+        R: Rng + ?Sized,
+    {
+        let column = columns.choose(rng).unwrap().clone();
+        if depth == 0 || rng.gen_bool(0.4) {
+            if rng.gen_bool(0.1) {
+                return Predicate::IsNull(column);
+            }
+            let op = match column.ty {
+                // Ordering booleans is allowed in some engines but not others
+                ColumnType::Bool => *[CmpOp::Eq, CmpOp::Ne].choose(rng).unwrap(),
+                _ => *[
+                    CmpOp::Eq,
+                    CmpOp::Ne,
+                    CmpOp::Lt,
+                    CmpOp::Le,
+                    CmpOp::Gt,
+                    CmpOp::Ge,
+                ]
+                .choose(rng)
+                .unwrap(),
+            };
+            let literal = literal(column.ty, rng);
+            return Predicate::Compare(column, op, literal);
+        }
+        let kind = rng.gen_range(0..5);
+        let mut sub = || Box::new(self.predicate(columns, depth - 1, rng));
+        match kind {
+            0 => Predicate::Not(sub()),
+            1 | 2 => Predicate::And(sub(), sub()),
+            _ => Predicate::Or(sub(), sub()),
+        }
+    }
+}
+
+fn columns_of(table: &Table, alias: &str) -> Vec<ColumnRef> {
+    table
+        .columns
+        .iter()
+        .map(|(name, ty)| ColumnRef {
+            alias: alias.to_string(),
+            column: name.clone(),
+            ty: *ty,
+        })
+        .collect()
+}
+
+/// Between `count` distinct columns, fewer if there aren't enough
+fn sample<R>(
+    columns: &[ColumnRef],
+    count: std::ops::RangeInclusive<usize>,
+    rng: &mut R,
+) -> Vec<ColumnRef>
+where
+    R: Rng + ?Sized,
+{
+    let n = rng.gen_range(count).min(columns.len());
+    columns.choose_multiple(rng, n).cloned().collect()
+}
+
+fn aggregate<R>(columns: &[ColumnRef], rng: &mut R) -> SelectItem
+where
+    R: Rng + ?Sized,
+{
+    let column = columns.choose(rng).unwrap().clone();
+    match rng.gen_range(0..5) {
+        0 => SelectItem::Aggregate(Aggregate::Count, None),
+        1 => SelectItem::Aggregate(Aggregate::Count, Some(column)),
+        2 if column.ty.is_numeric() => SelectItem::Aggregate(Aggregate::Sum, Some(column)),
+        3 if column.ty.is_numeric() => SelectItem::Aggregate(Aggregate::Avg, Some(column)),
+        _ => {
+            let agg = if rng.gen() {
+                Aggregate::Min
+            } else {
+                Aggregate::Max
+            };
+            SelectItem::Aggregate(agg, Some(column))
+        }
+    }
+}
+
+fn literal<R>(ty: ColumnType, rng: &mut R) -> Literal
+where
+    R: Rng + ?Sized,
+{
+    match ty {
+        ColumnType::Int => Literal::Int(rng.gen_range(-100..=100)),
+        ColumnType::Float => {
+            Literal::Float((rng.gen_range(-100.0..100.0f64) * 100.0).round() / 100.0)
+        }
+        ColumnType::Text => Literal::Text(TEXT_VALUES.choose(rng).unwrap().to_string()),
+        ColumnType::Bool => Literal::Bool(rng.gen()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Vec<Table> {
+        vec![
+            Table::new("users")
+                .column("id", ColumnType::Int)
+                .column("name", ColumnType::Text)
+                .column("active", ColumnType::Bool),
+            Table::new("order")
+                .column("id", ColumnType::Int)
+                .column("user_id", ColumnType::Int)
+                .column("total", ColumnType::Float),
+        ]
+    }
+
+    #[test]
+    fn it_renders_sql() {
+        let id = ColumnRef {
+            alias: "t0".into(),
+            column: "id".into(),
+            ty: ColumnType::Int,
+        };
+        let name = ColumnRef {
+            column: "name".into(),
+            ty: ColumnType::Text,
+            ..id.clone()
+        };
+        let select = Select {
+            items: vec![
+                SelectItem::Column(name.clone()),
+                SelectItem::Aggregate(Aggregate::Count, None),
+            ],
+            from: "users".into(),
+            from_alias: "t0".into(),
+            joins: vec![],
+            filter: Some(Predicate::Or(
+                Box::new(Predicate::Compare(id, CmpOp::Gt, Literal::Int(3))),
+                Box::new(Predicate::Compare(
+                    name.clone(),
+                    CmpOp::Eq,
+                    Literal::Text("O'Brien".into()),
+                )),
+            )),
+            group_by: vec![name.clone()],
+            order_by: vec![(name, true)],
+            limit: Some(5),
+        };
+
+        assert_eq!(
+            select.to_string(),
+            "SELECT t0.\"name\", COUNT(*) FROM \"users\" AS t0 \
+             WHERE (t0.\"id\" > 3) OR (t0.\"name\" = 'O''Brien') \
+             GROUP BY t0.\"name\" ORDER BY t0.\"name\" DESC LIMIT 5"
+        );
+    }
+
+    #[test]
+    fn it_generates_consistent_queries() {
+        let mut rng = StdRng::seed_from_u64(46);
+        let gen = SqlGen::new(schema()).unwrap().aggregate_rate(0.5);
+
+        for _ in 0..200 {
+            let select = gen.select(&mut rng);
+            let grouped = select
+                .items
+                .iter()
+                .any(|item| matches!(item, SelectItem::Aggregate(..)));
+
+            for item in &select.items {
+                match item {
+                    SelectItem::Column(c) if grouped => assert!(select.group_by.contains(c)),
+                    SelectItem::Aggregate(Aggregate::Sum | Aggregate::Avg, Some(c)) => {
+                        assert!(c.ty.is_numeric())
+                    }
+                    _ => {}
+                }
+            }
+            for join in &select.joins {
+                assert_eq!(join.on.0.ty, join.on.1.ty);
+                assert_eq!(join.on.1.alias, join.alias);
+            }
+            assert!(select.to_string().starts_with("SELECT "));
+        }
+        assert!(SqlGen::new(vec![Table::new("empty")]).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = SqlGen::new(schema())
+            .unwrap()
+            .aggregate_rate(f64::NAN)
+            .limit_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(246);
+        for _ in 0..20 {
+            let select = gen.select(&mut rng);
+            assert!(select.group_by.is_empty() && select.limit.is_none());
+        }
+    }
+}
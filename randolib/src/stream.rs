@@ -0,0 +1,157 @@
+//! Random values as an async `Stream`, so async code can consume them with backpressure:
+//! nothing is generated until the consumer polls, and with `items_per_second` values come
+//! no faster than a fixed rate, e.g. to drive a load test at a steady pace.
+
+use crate::DefaultRng;
+use futures_core::Stream;
+use rand::{distributions::Standard, prelude::*};
+use rand_chacha::ChaCha20Rng;
+use somelib::error::Error;
+use std::{
+    marker::PhantomData,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// An endless stream of random `T`s
+pub struct RandoStream<T, R = DefaultRng> {
+    rng: R,
+    period: Option<Duration>,
+    /// Created on the first poll, since a tokio timer needs to be made inside a runtime
+    interval: Option<Interval>,
+    // `fn() -> T` rather than `T` so the stream is `Send` and `Unpin` whatever `T` is; it
+    // only ever hands `T`s out
+    phantom_data: PhantomData<fn() -> T>,
+}
+
+impl<T> RandoStream<T> {
+    pub fn new() -> Self {
+        RandoStream::with_rng(DefaultRng)
+    }
+}
+
+impl<T> RandoStream<T, ChaCha20Rng> {
+    /// Deterministic, see `RandoA::from_seed`
+    pub fn from_seed(seed: u64) -> Self {
+        RandoStream::with_rng(ChaCha20Rng::seed_from_u64(seed))
+    }
+}
+
+impl<T> Default for RandoStream<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, R> RandoStream<T, R>
+where
+    R: RngCore,
+{
+    pub fn with_rng(rng: R) -> Self {
+        RandoStream {
+            rng,
+            period: None,
+            interval: None,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// Yield at most `rate` values a second. A consumer that falls behind doesn't get a
+    /// burst to catch up: the schedule restarts from its next poll.
+    pub fn items_per_second(mut self, rate: f64) -> Result<Self, Error> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return Err(Error::InvalidParameter(format!(
+                "items per second {} must be finite and positive",
+                rate
+            )));
+        }
+        // Tiny rates overflow a `Duration`, and above a billion a second the period rounds
+        // to zero, which `tokio::time::interval` refuses
+        let period = Duration::try_from_secs_f64(1.0 / rate)
+            .ok()
+            .filter(|period| !period.is_zero())
+            .ok_or_else(|| {
+                Error::InvalidParameter(format!(
+                    "items per second {} gives no usable period between items",
+                    rate
+                ))
+            })?;
+        self.period = Some(period);
+        self.interval = None;
+        Ok(self)
+    }
+}
+
+impl<T, R> Stream for RandoStream<T, R>
+where
+    Standard: Distribution<T>,
+    R: RngCore + Unpin,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // Every field is `Unpin`, so we can work with a plain `&mut Self`
+        let this = self.get_mut();
+        if let Some(period) = this.period {
+            let interval = this.interval.get_or_insert_with(|| {
+                let mut interval = tokio::time::interval(period);
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                interval
+            });
+            // `ready!` returns `Poll::Pending` for us until the next tick
+            ready!(interval.poll_tick(cx));
+        }
+        Poll::Ready(Some(this.rng.gen()))
+    }
+
+    /// Endless, so at least `usize::MAX` items and no upper bound
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (usize::MAX, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::Instant;
+
+    /// `StreamExt::next` without depending on `futures-util`
+    async fn next<S>(stream: &mut S) -> Option<S::Item>
+    where
+        S: Stream + Unpin,
+    {
+        std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn it_streams_reproducible_values() {
+        let mut a = RandoStream::<u32, _>::from_seed(271);
+        let mut b = RandoStream::<u32, _>::from_seed(271);
+        for _ in 0..10 {
+            assert_eq!(next(&mut a).await, next(&mut b).await);
+        }
+        assert!(next(&mut RandoStream::<bool>::new()).await.is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn it_limits_the_rate() {
+        let mut stream = RandoStream::<u8>::new().items_per_second(10.0).unwrap();
+        let start = Instant::now();
+        for _ in 0..5 {
+            next(&mut stream).await.unwrap();
+        }
+        // The first value is immediate, then one every 100ms
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+        assert!(RandoStream::<u8>::new().items_per_second(0.0).is_err());
+    }
+
+    #[test]
+    fn it_rejects_rates_without_a_period() {
+        assert!(RandoStream::<u8>::new().items_per_second(1e-300).is_err());
+        assert!(RandoStream::<u8>::new().items_per_second(1e10).is_err());
+        assert!(RandoStream::<u8>::new().items_per_second(1e-9).is_ok());
+        assert!(RandoStream::<u8>::new().items_per_second(1e9).is_ok());
+    }
+}
@@ -0,0 +1,207 @@
+//! Random strings from a character set: passwords, tokens, ids, fixtures.
+//!
+//! Like `privacy`, the plain functions draw from `OsRng`, the operating system's secure
+//! generator, since their output often ends up as a secret. The `*_with` variants and
+//! `StringGen` take any generator, for reproducible fixtures.
+
+use rand::{prelude::*, rngs::OsRng};
+use somelib::error::Error;
+use std::ops::RangeInclusive;
+
+pub const ALPHANUMERIC: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+pub const HEX: &str = "0123456789abcdef";
+/// `!` to `~`: every visible ASCII character. No space, so values survive trimming.
+pub const ASCII_PRINTABLE: &str =
+    "!\"#$%&'()*+,-./0123456789:;<=>?@ABCDEFGHIJKLMNOPQRSTUVWXYZ[\\]^_`abcdefghijklmnopqrstuvwxyz{|}~";
+/// Characters easily mistaken for one another in many fonts
+pub const AMBIGUOUS: &str = "0O1lI|";
+
+/// `len` letters and digits, from the OS generator
+pub fn alphanumeric(len: usize) -> String {
+    alphanumeric_with(len, &mut OsRng)
+}
+
+pub fn alphanumeric_with<R>(len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    from_charset(ALPHANUMERIC, len, rng)
+}
+
+/// `len` lowercase hex digits, from the OS generator
+pub fn hex(len: usize) -> String {
+    hex_with(len, &mut OsRng)
+}
+
+pub fn hex_with<R>(len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    from_charset(HEX, len, rng)
+}
+
+/// `len` visible ASCII characters, from the OS generator
+pub fn ascii_printable(len: usize) -> String {
+    ascii_printable_with(len, &mut OsRng)
+}
+
+pub fn ascii_printable_with<R>(len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    from_charset(ASCII_PRINTABLE, len, rng)
+}
+
+/// `len` raw bytes, from the OS generator, like reading `/dev/urandom`
+pub fn bytes(len: usize) -> Vec<u8> {
+    bytes_with(len, &mut OsRng)
+}
+
+pub fn bytes_with<R>(len: usize, rng: &mut R) -> Vec<u8>
+where
+    R: Rng + ?Sized,
+{
+    let mut bytes = vec![0; len];
+    rng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Only for the built-in charsets, which are ASCII and not empty
+fn from_charset<R>(charset: &str, len: usize, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let bytes = charset.as_bytes();
+    (0..len)
+        .map(|_| *bytes.choose(rng).unwrap() as char)
+        .collect()
+}
+
+/// Strings of a configurable charset and length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StringGen {
+    charset: Vec<char>,
+    length: RangeInclusive<usize>,
+}
+
+impl StringGen {
+    /// 16 alphanumeric characters
+    pub fn new() -> Self {
+        StringGen {
+            charset: ALPHANUMERIC.chars().collect(),
+            length: 16..=16,
+        }
+    }
+
+    /// Draw from these characters, each equally likely. Repeats are dropped so they don't
+    /// skew the odds. Any Unicode works, e.g. `"αβγ"`.
+    pub fn charset(mut self, charset: &str) -> Result<Self, Error> {
+        let mut chars = Vec::new();
+        for c in charset.chars() {
+            if !chars.contains(&c) {
+                chars.push(c);
+            }
+        }
+        if chars.is_empty() {
+            return Err(Error::InvalidParameter("charset is empty".into()));
+        }
+        self.charset = chars;
+        Ok(self)
+    }
+
+    /// Leave out look-alike characters (see `AMBIGUOUS`), for strings people have to
+    /// read or type
+    pub fn exclude_ambiguous(mut self) -> Result<Self, Error> {
+        self.charset.retain(|c| !AMBIGUOUS.contains(*c));
+        if self.charset.is_empty() {
+            return Err(Error::InvalidParameter(
+                "charset has only ambiguous characters".into(),
+            ));
+        }
+        Ok(self)
+    }
+
+    /// Exactly `len` characters
+    pub fn length(mut self, len: usize) -> Self {
+        self.length = len..=len;
+        self
+    }
+
+    /// A length picked uniformly from `range` for each string
+    pub fn length_between(mut self, range: RangeInclusive<usize>) -> Result<Self, Error> {
+        if range.is_empty() {
+            return Err(Error::InvalidParameter("empty length range".into()));
+        }
+        self.length = range;
+        Ok(self)
+    }
+
+    pub fn string<R>(&self, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        let len = rng.gen_range(self.length.clone());
+        (0..len)
+            .map(|_| *self.charset.choose(rng).unwrap())
+            .collect()
+    }
+
+    /// Bits of entropy in a string of `len` characters, to size tokens and passwords:
+    /// 128 bits is a common target for secrets
+    pub fn entropy_bits(&self, len: usize) -> f64 {
+        len as f64 * (self.charset.len() as f64).log2()
+    }
+}
+
+impl Default for StringGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_uses_the_charset() {
+        let mut rng = StdRng::seed_from_u64(2572);
+
+        let token = hex_with(32, &mut rng);
+        assert_eq!(token.len(), 32);
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() && !c.is_uppercase()));
+        assert!(alphanumeric(20).chars().all(|c| c.is_ascii_alphanumeric()));
+        assert!(ascii_printable(50).chars().all(|c| c.is_ascii_graphic()));
+        assert_eq!(ASCII_PRINTABLE.len(), 94);
+        assert_ne!(alphanumeric(20), alphanumeric(20));
+        assert_eq!(bytes_with(100, &mut rng).len(), 100);
+        assert_ne!(bytes(16), bytes(16));
+    }
+
+    #[test]
+    fn it_builds_custom_strings() {
+        let mut rng = StdRng::seed_from_u64(2572);
+        let gen = StringGen::new()
+            .charset("aαb1l0")
+            .unwrap()
+            .exclude_ambiguous()
+            .unwrap()
+            .length_between(3..=5)
+            .unwrap();
+
+        for _ in 0..100 {
+            let s = gen.string(&mut rng);
+            assert!((3..=5).contains(&s.chars().count()));
+            assert!(s.chars().all(|c| "aαb".contains(c)), "{}", s);
+        }
+        assert_eq!(StringGen::new().entropy_bits(22).floor(), 130.0);
+        assert!(StringGen::new().charset("").is_err());
+        assert!(StringGen::new()
+            .charset("0O")
+            .unwrap()
+            .exclude_ambiguous()
+            .is_err());
+    }
+}
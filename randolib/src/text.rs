@@ -0,0 +1,285 @@
+use rand::{distributions::WeightedIndex, prelude::*};
+use somelib::error::Error;
+use std::collections::HashSet;
+
+/// Relative frequency of `a..=z` in English text, so made-up words have a believable mix of
+/// letters (lots of `e`s and `t`s, very few `q`s)
+const LETTER_WEIGHTS: [f64; 26] = [
+    8.2, 1.5, 2.8, 4.3, 12.7, 2.2, 2.0, 6.1, 7.0, 0.15, 0.77, 4.0, 2.4, 6.7, 7.5, 1.9, 0.095, 6.0,
+    6.3, 9.1, 2.8, 0.98, 2.4, 0.15, 2.0, 0.074,
+];
+
+/// Rough distribution of English word lengths, `(length, weight)`
+const WORD_LENGTHS: [(usize, f64); 12] = [
+    (1, 3.0),
+    (2, 17.0),
+    (3, 20.0),
+    (4, 16.0),
+    (5, 11.0),
+    (6, 9.0),
+    (7, 8.0),
+    (8, 6.0),
+    (9, 4.0),
+    (10, 3.0),
+    (11, 2.0),
+    (12, 1.0),
+];
+
+/// Sentences mostly between 8 and 25 words, `(length, weight)`
+const SENTENCE_LENGTHS: [(usize, f64); 6] = [
+    (4, 1.0),
+    (8, 3.0),
+    (12, 4.0),
+    (17, 4.0),
+    (25, 2.0),
+    (35, 1.0),
+];
+
+/// Generates text that looks statistically like prose without any real language behind it.
+/// Words come from a made-up vocabulary drawn with Zipfian frequencies, so a handful of
+/// short words dominate just like "the" and "of" do in English.
+pub struct TextGen {
+    vocabulary: Vec<String>,
+    word_dist: WeightedIndex<f64>,
+    sentence_lengths: Vec<usize>,
+    sentence_dist: WeightedIndex<f64>,
+    comma_rate: f64,
+    question_rate: f64,
+}
+
+/// Configuration for `TextGen`, see the setters for what each knob does
+pub struct TextGenBuilder {
+    vocabulary_size: usize,
+    zipf_exponent: f64,
+    word_lengths: Vec<(usize, f64)>,
+    sentence_lengths: Vec<(usize, f64)>,
+    comma_rate: f64,
+    question_rate: f64,
+}
+
+impl TextGen {
+    pub fn builder() -> TextGenBuilder {
+        TextGenBuilder {
+            vocabulary_size: 2000,
+            zipf_exponent: 1.0,
+            word_lengths: WORD_LENGTHS.to_vec(),
+            sentence_lengths: SENTENCE_LENGTHS.to_vec(),
+            comma_rate: 0.06,
+            question_rate: 0.1,
+        }
+    }
+
+    /// The made-up vocabulary, most frequent word first
+    pub fn vocabulary(&self) -> &[String] {
+        &self.vocabulary
+    }
+
+    /// `n` words with no punctuation, handy for search index tests
+    pub fn words<R>(&self, n: usize, rng: &mut R) -> Vec<&str>
+    where
+        R: Rng + ?Sized,
+    {
+        (0..n)
+            .map(|_| self.vocabulary[self.word_dist.sample(rng)].as_str())
+            .collect()
+    }
+
+    /// One capitalized sentence with occasional commas, ending in `.` or `?`
+    pub fn sentence<R>(&self, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        let len = self.sentence_lengths[self.sentence_dist.sample(rng)];
+        let mut sentence = String::new();
+        for (i, word) in self.words(len, rng).into_iter().enumerate() {
+            if i == 0 {
+                // Uppercase the first letter; our words are ASCII so byte slicing is safe
+                sentence.push_str(&word[..1].to_uppercase());
+                sentence.push_str(&word[1..]);
+                continue;
+            }
+            // Never put a comma before the first or after the last word
+            if i < len - 1 && rng.gen_bool(self.comma_rate) {
+                sentence.push(',');
+            }
+            sentence.push(' ');
+            sentence.push_str(word);
+        }
+        sentence.push(if rng.gen_bool(self.question_rate) {
+            '?'
+        } else {
+            '.'
+        });
+        sentence
+    }
+
+    /// `sentences` sentences joined by spaces
+    pub fn paragraph<R>(&self, sentences: usize, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        (0..sentences)
+            .map(|_| self.sentence(rng))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl TextGenBuilder {
+    /// How many distinct words the text draws from
+    pub fn vocabulary_size(mut self, size: usize) -> Self {
+        self.vocabulary_size = size;
+        self
+    }
+
+    /// Word `k` (by rank) is drawn with weight `1 / k^exponent`. `1.0` is classic Zipf,
+    /// larger values make the text more repetitive, `0.0` makes every word equally likely.
+    pub fn zipf_exponent(mut self, exponent: f64) -> Self {
+        self.zipf_exponent = exponent;
+        self
+    }
+
+    /// `(length, weight)` pairs for the lengths of vocabulary words
+    pub fn word_lengths(mut self, lengths: &[(usize, f64)]) -> Self {
+        self.word_lengths = lengths.to_vec();
+        self
+    }
+
+    /// `(words, weight)` pairs for sentence lengths
+    pub fn sentence_lengths(mut self, lengths: &[(usize, f64)]) -> Self {
+        self.sentence_lengths = lengths.to_vec();
+        self
+    }
+
+    /// Probability of a comma after each word that isn't first or last in its sentence
+    pub fn comma_rate(mut self, rate: f64) -> Self {
+        self.comma_rate = rate;
+        self
+    }
+
+    /// Probability of a sentence being a question
+    pub fn question_rate(mut self, rate: f64) -> Self {
+        self.question_rate = rate;
+        self
+    }
+
+    /// Make up the vocabulary using `rng`, so the same seed always gives the same words
+    pub fn build<R>(self, rng: &mut R) -> Result<TextGen, Error>
+    where
+        R: Rng + ?Sized,
+    {
+        let invalid = |msg: &str| Error::InvalidParameter(msg.to_string());
+        if self.vocabulary_size == 0 {
+            return Err(invalid("vocabulary needs at least one word"));
+        }
+        if !(0.0..=1.0).contains(&self.comma_rate) || !(0.0..=1.0).contains(&self.question_rate) {
+            return Err(invalid("rates must be within [0, 1]"));
+        }
+        if !self.zipf_exponent.is_finite() || self.zipf_exponent < 0.0 {
+            return Err(invalid("zipf exponent must be finite and non-negative"));
+        }
+        if self.word_lengths.iter().any(|(len, _)| *len == 0)
+            || self.sentence_lengths.iter().any(|(len, _)| *len == 0)
+        {
+            return Err(invalid("lengths must be at least 1"));
+        }
+
+        let weights = |pairs: &[(usize, f64)]| {
+            WeightedIndex::new(pairs.iter().map(|(_, w)| *w))
+                .map_err(|err| Error::InvalidParameter(err.to_string()))
+        };
+        let length_dist = weights(&self.word_lengths)?;
+        let sentence_dist = weights(&self.sentence_lengths)?;
+        let letter_dist = WeightedIndex::new(LETTER_WEIGHTS).unwrap();
+
+        // Keep drawing until we have enough distinct words. Short words run out quickly
+        // (there are only 26 one letter words) so cap the attempts.
+        let mut seen = HashSet::new();
+        let mut vocabulary = Vec::with_capacity(self.vocabulary_size);
+        let max_attempts = self.vocabulary_size * 100;
+        for _ in 0..max_attempts {
+            if vocabulary.len() == self.vocabulary_size {
+                break;
+            }
+            let len = self.word_lengths[length_dist.sample(rng)].0;
+            let word = (0..len)
+                .map(|_| (b'a' + letter_dist.sample(rng) as u8) as char)
+                .collect::<String>();
+            if seen.insert(word.clone()) {
+                vocabulary.push(word);
+            }
+        }
+        if vocabulary.len() < self.vocabulary_size {
+            return Err(invalid(
+                "word lengths are too short for the vocabulary size",
+            ));
+        }
+        // Shorter words get the lower ranks and so the higher frequencies
+        vocabulary.sort_by_key(String::len);
+
+        let word_dist = WeightedIndex::new(
+            (1..=vocabulary.len()).map(|rank| 1.0 / (rank as f64).powf(self.zipf_exponent)),
+        )
+        .map_err(|err| Error::InvalidParameter(err.to_string()))?;
+
+        Ok(TextGen {
+            vocabulary,
+            word_dist,
+            sentence_lengths: self.sentence_lengths.iter().map(|(len, _)| *len).collect(),
+            sentence_dist,
+            comma_rate: self.comma_rate,
+            question_rate: self.question_rate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_builds_sentences_from_the_vocabulary() {
+        let mut rng = StdRng::seed_from_u64(21);
+        let gen = TextGen::builder()
+            .vocabulary_size(500)
+            .sentence_lengths(&[(6, 1.0)])
+            .build(&mut rng)
+            .unwrap();
+
+        let sentence = gen.sentence(&mut rng);
+
+        assert!(sentence.ends_with('.') || sentence.ends_with('?'));
+        assert!(sentence.chars().next().unwrap().is_uppercase());
+        let words = sentence
+            .trim_end_matches(['.', '?'])
+            .split(' ')
+            .map(|w| w.trim_end_matches(',').to_lowercase())
+            .collect::<Vec<_>>();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| gen.vocabulary().contains(w)));
+    }
+
+    #[test]
+    fn it_favors_top_ranked_words() {
+        let mut rng = StdRng::seed_from_u64(22);
+        let gen = TextGen::builder().build(&mut rng).unwrap();
+
+        let top = gen.vocabulary()[0].as_str();
+        let words = gen.words(10_000, &mut rng);
+        let top_count = words.iter().filter(|w| **w == top).count();
+
+        // With 2000 words and Zipf exponent 1 the top word is roughly 12% of the text
+        assert!(top_count > 800, "top word seen {} times", top_count);
+    }
+
+    #[test]
+    fn it_rejects_an_impossible_vocabulary() {
+        let mut rng = StdRng::seed_from_u64(23);
+        let result = TextGen::builder()
+            .vocabulary_size(100)
+            .word_lengths(&[(1, 1.0)])
+            .build(&mut rng);
+
+        assert!(result.is_err());
+    }
+}
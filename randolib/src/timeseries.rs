@@ -0,0 +1,273 @@
+//! Synthetic time series with ground truth: trend plus seasonality plus noise, with
+//! anomalies injected at known points so an anomaly detector's output can be scored.
+
+use crate::probability;
+use rand::prelude::*;
+use rand_distr::Normal;
+use somelib::error::Error;
+use std::f64::consts::TAU;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// One point far off the curve
+    Spike,
+    /// Everything from this point on moves up or down
+    LevelShift,
+}
+
+/// An injected anomaly, `delta` being how far it moved the series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Anomaly {
+    pub index: usize,
+    pub kind: AnomalyKind,
+    pub delta: f64,
+}
+
+/// A generated series. `labels[i]` is true where an anomaly was injected: the spike itself,
+/// or the first point of a level shift (the points after it are the new normal).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub values: Vec<f64>,
+    pub labels: Vec<bool>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// A repeating sine component, e.g. period 24 for a daily cycle in hourly data
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Season {
+    period: f64,
+    amplitude: f64,
+}
+
+/// Generates `Series`. Anomaly sizes are `magnitude` to twice `magnitude` in either
+/// direction, so set them relative to the noise to make detection easy or hard.
+pub struct SeriesGen {
+    level: f64,
+    trend: f64,
+    seasons: Vec<Season>,
+    noise: Normal<f64>,
+    spike_rate: f64,
+    spike_magnitude: f64,
+    shift_rate: f64,
+    shift_magnitude: f64,
+}
+
+impl SeriesGen {
+    /// A flat series at 0 with unit noise and no anomalies
+    pub fn new() -> Self {
+        SeriesGen {
+            level: 0.0,
+            trend: 0.0,
+            seasons: Vec::new(),
+            noise: Normal::new(0.0, 1.0).unwrap(),
+            spike_rate: 0.0,
+            spike_magnitude: 0.0,
+            shift_rate: 0.0,
+            shift_magnitude: 0.0,
+        }
+    }
+
+    /// The value at the first point
+    pub fn level(mut self, level: f64) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Change per step
+    pub fn trend(mut self, slope: f64) -> Self {
+        self.trend = slope;
+        self
+    }
+
+    /// Add a seasonal cycle of `period` steps (at least 2); can be repeated, e.g. daily
+    /// and weekly
+    pub fn season(mut self, period: f64, amplitude: f64) -> Result<Self, Error> {
+        if !period.is_finite() || period < 2.0 {
+            return Err(Error::InvalidParameter(
+                "a season needs a period of at least 2 steps".into(),
+            ));
+        }
+        self.seasons.push(Season { period, amplitude });
+        Ok(self)
+    }
+
+    /// Standard deviation of the Gaussian noise
+    pub fn noise(mut self, sd: f64) -> Result<Self, Error> {
+        // `Normal::new` accepts a negative deviation (it mirrors the distribution)
+        if !sd.is_finite() || sd < 0.0 {
+            return Err(Error::InvalidParameter(
+                "noise must be finite and non-negative".into(),
+            ));
+        }
+        self.noise = Normal::new(0.0, sd).unwrap();
+        Ok(self)
+    }
+
+    /// Chance of each point being a spike
+    pub fn spikes(mut self, rate: f64, magnitude: f64) -> Self {
+        self.spike_rate = probability(rate);
+        self.spike_magnitude = magnitude;
+        self
+    }
+
+    /// Chance of the level shifting at each point
+    pub fn level_shifts(mut self, rate: f64, magnitude: f64) -> Self {
+        self.shift_rate = probability(rate);
+        self.shift_magnitude = magnitude;
+        self
+    }
+
+    /// The value at step `t` without noise or anomalies
+    pub fn expected(&self, t: usize) -> f64 {
+        let t = t as f64;
+        self.level
+            + self.trend * t
+            + self
+                .seasons
+                .iter()
+                .map(|s| s.amplitude * (TAU * t / s.period).sin())
+                .sum::<f64>()
+    }
+
+    pub fn series<R>(&self, len: usize, rng: &mut R) -> Series
+    where
+        R: Rng + ?Sized,
+    {
+        let mut values = Vec::with_capacity(len);
+        let mut labels = vec![false; len];
+        let mut anomalies = Vec::new();
+        let mut shift = 0.0;
+        for (t, label) in labels.iter_mut().enumerate() {
+            let mut value = self.expected(t) + self.noise.sample(rng);
+            // Never a shift at the first point, there's nothing to shift from
+            if t > 0 && rng.gen_bool(self.shift_rate) {
+                let delta = anomaly_delta(self.shift_magnitude, rng);
+                shift += delta;
+                anomalies.push(Anomaly {
+                    index: t,
+                    kind: AnomalyKind::LevelShift,
+                    delta,
+                });
+                *label = true;
+            }
+            value += shift;
+            if rng.gen_bool(self.spike_rate) {
+                let delta = anomaly_delta(self.spike_magnitude, rng);
+                value += delta;
+                anomalies.push(Anomaly {
+                    index: t,
+                    kind: AnomalyKind::Spike,
+                    delta,
+                });
+                *label = true;
+            }
+            values.push(value);
+        }
+        Series {
+            values,
+            labels,
+            anomalies,
+        }
+    }
+}
+
+impl Default for SeriesGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn anomaly_delta<R>(magnitude: f64, rng: &mut R) -> f64
+where
+    R: Rng + ?Sized,
+{
+    let size = magnitude * rng.gen_range(1.0..=2.0);
+    if rng.gen() {
+        size
+    } else {
+        -size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_combines_components() {
+        let mut rng = StdRng::seed_from_u64(52);
+        let gen = SeriesGen::new()
+            .level(10.0)
+            .trend(0.5)
+            .season(4.0, 2.0)
+            .unwrap()
+            .noise(0.0)
+            .unwrap();
+
+        let series = gen.series(8, &mut rng);
+        // sin at quarter periods is 0, 1, 0, -1
+        let expected = [10.0, 12.5, 11.0, 9.5, 12.0, 14.5, 13.0, 11.5];
+        for (value, expected) in series.values.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-9, "{:?}", series.values);
+        }
+        assert!(series.anomalies.is_empty());
+        assert!(SeriesGen::new().season(1.0, 1.0).is_err());
+        assert!(SeriesGen::new().noise(-1.0).is_err());
+    }
+
+    #[test]
+    fn it_labels_anomalies() {
+        let mut rng = StdRng::seed_from_u64(52);
+        let gen = SeriesGen::new()
+            .season(24.0, 5.0)
+            .unwrap()
+            .spikes(0.02, 10.0)
+            .level_shifts(0.005, 20.0);
+
+        let series = gen.series(2000, &mut rng);
+        assert!(series
+            .anomalies
+            .iter()
+            .any(|a| a.kind == AnomalyKind::Spike));
+        assert!(series
+            .anomalies
+            .iter()
+            .any(|a| a.kind == AnomalyKind::LevelShift));
+        for a in &series.anomalies {
+            assert!(series.labels[a.index]);
+        }
+        assert_eq!(
+            series.labels.iter().filter(|l| **l).count(),
+            series
+                .anomalies
+                .iter()
+                .map(|a| a.index)
+                .collect::<std::collections::BTreeSet<_>>()
+                .len()
+        );
+
+        // Spikes stand out from the curve after undoing the shifts, other points don't
+        let mut shift = 0.0;
+        for (t, value) in series.values.iter().enumerate() {
+            let here = series.anomalies.iter().filter(|a| a.index == t);
+            let mut spike = 0.0;
+            for a in here {
+                match a.kind {
+                    AnomalyKind::LevelShift => shift += a.delta,
+                    AnomalyKind::Spike => spike = a.delta,
+                }
+            }
+            let residual = value - shift - spike - gen.expected(t);
+            assert!(residual.abs() < 6.0, "{} at {}", residual, t);
+        }
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = SeriesGen::new()
+            .spikes(f64::NAN, 5.0)
+            .level_shifts(f64::NAN, 5.0);
+        let mut rng = StdRng::seed_from_u64(252);
+        assert!(gen.series(100, &mut rng).anomalies.is_empty());
+    }
+}
@@ -0,0 +1,407 @@
+//! Synthetic traffic: when events happen, shaped like the real thing rather than spread
+//! evenly over time.
+
+use rand::prelude::*;
+use rand_distr::{Exp1, Pareto};
+use somelib::error::Error;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const HOUR: u64 = 3600;
+const DAY: u64 = 24 * HOUR;
+
+/// How busy each hour of the day (UTC) is, and optionally each day of the week. Weights are
+/// relative: an hour weighted 2 sees twice the events of an hour weighted 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityProfile {
+    hours: [f64; 24],
+    /// Monday first
+    weekdays: [f64; 7],
+}
+
+impl ActivityProfile {
+    /// Weights for hours 0..24, non-negative and not all zero
+    pub fn new(hours: [f64; 24]) -> Result<Self, Error> {
+        check_weights(&hours, "hour")?;
+        Ok(ActivityProfile {
+            hours,
+            weekdays: [1.0; 7],
+        })
+    }
+
+    /// Every hour alike
+    pub fn flat() -> Self {
+        Self::new([1.0; 24]).unwrap()
+    }
+
+    /// A consumer-facing service: quiet before dawn, climbing through the morning, a
+    /// lunchtime bump and the peak in the evening
+    pub fn diurnal() -> Self {
+        Self::new([
+            0.35, 0.25, 0.18, 0.14, 0.13, 0.16, 0.28, 0.5, 0.72, 0.85, 0.9, 0.95, //
+            1.0, 0.95, 0.9, 0.88, 0.9, 0.95, 1.05, 1.15, 1.2, 1.1, 0.85, 0.55,
+        ])
+        .unwrap()
+    }
+
+    /// An internal tool: busy 9 to 5 on weekdays, a trickle otherwise
+    pub fn business_hours() -> Self {
+        let mut hours = [0.05; 24];
+        hours[9..17].fill(1.0);
+        hours[8] = 0.4;
+        hours[17] = 0.4;
+        Self::new(hours)
+            .unwrap()
+            .weekdays([1.0, 1.0, 1.0, 1.0, 1.0, 0.1, 0.1])
+            .unwrap()
+    }
+
+    /// Scale each day of the week, Monday first
+    pub fn weekdays(mut self, weekdays: [f64; 7]) -> Result<Self, Error> {
+        check_weights(&weekdays, "weekday")?;
+        self.weekdays = weekdays;
+        Ok(self)
+    }
+
+    /// The relative rate at `t`
+    pub fn weight_at(&self, t: SystemTime) -> f64 {
+        self.weight_at_secs(secs(t))
+    }
+
+    fn weight_at_secs(&self, secs: u64) -> f64 {
+        let days = secs / DAY;
+        // 1970-01-01 was a Thursday, index 3 counting from Monday
+        let weekday = ((days + 3) % 7) as usize;
+        self.hours[((secs % DAY) / HOUR) as usize] * self.weekdays[weekday]
+    }
+
+    /// `n` timestamps in `[start, end)`, sorted, with density following the profile.
+    /// An empty range, or one that only covers zero-weight hours, gives none.
+    pub fn timestamps<R>(
+        &self,
+        start: SystemTime,
+        end: SystemTime,
+        n: usize,
+        rng: &mut R,
+    ) -> Vec<SystemTime>
+    where
+        R: Rng + ?Sized,
+    {
+        // Split the range at hour boundaries into segments of constant weight, keeping
+        // the running total of weight × length to draw against
+        let (start, end) = (secs_f64(start), secs_f64(end));
+        let mut segments = Vec::new();
+        let mut total = 0.0;
+        let mut at = start;
+        while at < end {
+            let next = ((at.floor() as u64 / HOUR + 1) * HOUR) as f64;
+            let next = next.min(end);
+            total += self.weight_at_secs(at as u64) * (next - at);
+            segments.push((at, next, total));
+            at = next;
+        }
+        if total <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut out = (0..n)
+            .map(|_| {
+                let target = rng.gen_range(0.0..total);
+                let i = segments.partition_point(|(_, _, cumulative)| *cumulative <= target);
+                let (from, to, _) = segments[i.min(segments.len() - 1)];
+                UNIX_EPOCH + Duration::from_secs_f64(rng.gen_range(from..to))
+            })
+            .collect::<Vec<_>>();
+        out.sort();
+        out
+    }
+}
+
+/// A steady baseline with flash crowds on top. Bursts arrive at exponentially
+/// distributed intervals and their sizes follow a Pareto distribution, so most are modest
+/// and a few are enormous, much like real traffic spikes.
+#[derive(Debug, Clone)]
+pub struct BurstPattern {
+    /// Baseline events per second
+    base_rate: f64,
+    /// Mean seconds between burst starts
+    burst_gap: f64,
+    burst_size: Pareto<f64>,
+    /// Seconds each burst's extra events are spread over
+    burst_duration: f64,
+}
+
+impl BurstPattern {
+    /// `base_rate` events per second between bursts, which may be 0. By default a burst
+    /// starts every minute on average and adds at least 50 events over 5 seconds.
+    pub fn new(base_rate: f64) -> Result<Self, Error> {
+        if !base_rate.is_finite() || base_rate < 0.0 {
+            return Err(Error::InvalidParameter(
+                "base rate must be finite and non-negative".into(),
+            ));
+        }
+        Ok(BurstPattern {
+            base_rate,
+            burst_gap: 60.0,
+            burst_size: Pareto::new(50.0, 1.5).unwrap(),
+            burst_duration: 5.0,
+        })
+    }
+
+    /// Mean time between burst starts
+    pub fn burst_gap(mut self, mean: Duration) -> Result<Self, Error> {
+        self.burst_gap = positive_secs(mean, "burst gap")?;
+        Ok(self)
+    }
+
+    /// Burst sizes in events: at least `min`, with a tail that gets heavier as `shape`
+    /// shrinks (below 2 the variance is infinite, below 1 so is the mean)
+    pub fn burst_size(mut self, min: f64, shape: f64) -> Result<Self, Error> {
+        self.burst_size = Pareto::new(min, shape)
+            .map_err(|e| Error::InvalidParameter(format!("burst size: {}", e)))?;
+        Ok(self)
+    }
+
+    pub fn burst_duration(mut self, duration: Duration) -> Result<Self, Error> {
+        self.burst_duration = positive_secs(duration, "burst duration")?;
+        Ok(self)
+    }
+
+    /// Event times as offsets from the start, forever
+    pub fn events<R: Rng>(&self, mut rng: R) -> BurstEvents<R> {
+        let next_burst = rng.sample::<f64, _>(Exp1) * self.burst_gap;
+        BurstEvents {
+            pattern: self.clone(),
+            rng,
+            now: 0.0,
+            bursts: Vec::new(),
+            next_burst,
+        }
+    }
+}
+
+fn positive_secs(d: Duration, what: &str) -> Result<f64, Error> {
+    if d.is_zero() {
+        return Err(Error::InvalidParameter(format!(
+            "{} must be positive",
+            what
+        )));
+    }
+    Ok(d.as_secs_f64())
+}
+
+/// Iterator over a `BurstPattern`'s event times
+pub struct BurstEvents<R> {
+    pattern: BurstPattern,
+    rng: R,
+    /// Seconds since the start
+    now: f64,
+    /// Bursts in progress, `(end, extra rate)`. Bursts can overlap.
+    bursts: Vec<(f64, f64)>,
+    next_burst: f64,
+}
+
+impl<R: Rng> BurstEvents<R> {
+    /// Events per second right now
+    pub fn rate(&self) -> f64 {
+        self.pattern.base_rate + self.bursts.iter().map(|(_, rate)| rate).sum::<f64>()
+    }
+
+    /// Whether a burst is in progress
+    pub fn in_burst(&self) -> bool {
+        !self.bursts.is_empty()
+    }
+}
+
+impl<R: Rng> Iterator for BurstEvents<R> {
+    type Item = Duration;
+
+    /// The rate is constant between burst starts and ends, so draw an exponential gap at
+    /// the current rate; if it would cross the next change, move to the change and draw
+    /// again, which the exponential's lack of memory makes exact
+    fn next(&mut self) -> Option<Duration> {
+        loop {
+            let change = self
+                .bursts
+                .iter()
+                .map(|(end, _)| *end)
+                .fold(self.next_burst, f64::min);
+            let rate = self.rate();
+            if rate > 0.0 {
+                let at = self.now + self.rng.sample::<f64, _>(Exp1) / rate;
+                if at < change {
+                    self.now = at;
+                    return Some(Duration::from_secs_f64(at));
+                }
+            }
+            self.now = change;
+            self.bursts.retain(|(end, _)| *end > change);
+            if change >= self.next_burst {
+                let size = self.rng.sample(self.pattern.burst_size);
+                let duration = self.pattern.burst_duration;
+                self.bursts.push((change + duration, size / duration));
+                self.next_burst = change + self.rng.sample::<f64, _>(Exp1) * self.pattern.burst_gap;
+            }
+        }
+    }
+}
+
+/// Paces a live stream to a `BurstPattern`: `wait` sleeps until the next event is due
+pub struct RateController<R> {
+    events: BurstEvents<R>,
+    start: Instant,
+}
+
+impl<R: Rng> RateController<R> {
+    /// Starts the clock now
+    pub fn new(events: BurstEvents<R>) -> Self {
+        RateController {
+            events,
+            start: Instant::now(),
+        }
+    }
+
+    /// The next event's offset and how long to wait for it, `elapsed` since the start.
+    /// Zero when running behind, so a slow consumer catches up rather than drifting.
+    pub fn next_delay(&mut self, elapsed: Duration) -> Option<(Duration, Duration)> {
+        let at = self.events.next()?;
+        Some((at, at.saturating_sub(elapsed)))
+    }
+
+    /// Sleep until the next event and return its offset
+    pub fn wait(&mut self) -> Option<Duration> {
+        let (at, delay) = self.next_delay(self.start.elapsed())?;
+        std::thread::sleep(delay);
+        Some(at)
+    }
+
+    /// Events per second at the latest event
+    pub fn rate(&self) -> f64 {
+        self.events.rate()
+    }
+
+    pub fn in_burst(&self) -> bool {
+        self.events.in_burst()
+    }
+}
+
+fn check_weights(weights: &[f64], what: &str) -> Result<(), Error> {
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return Err(Error::InvalidParameter(format!(
+            "{} weights must be finite and non-negative",
+            what
+        )));
+    }
+    if weights.iter().all(|w| *w == 0.0) {
+        return Err(Error::InvalidParameter(format!(
+            "at least one {} weight must be positive",
+            what
+        )));
+    }
+    Ok(())
+}
+
+/// Whole seconds since the epoch, times before it count as the epoch
+fn secs(t: SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn secs_f64(t: SystemTime) -> f64 {
+    t.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn it_follows_the_profile() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let mut hours = [0.0; 24];
+        hours[3] = 1.0;
+        hours[15] = 3.0;
+        let profile = ActivityProfile::new(hours).unwrap();
+
+        // A week starting on a Monday
+        let start = 4 * DAY;
+        let times = profile.timestamps(at(start), at(start + 7 * DAY), 4000, &mut rng);
+        assert_eq!(times.len(), 4000);
+        assert!(times.windows(2).all(|w| w[0] <= w[1]));
+
+        let mut per_hour = [0; 24];
+        for t in &times {
+            per_hour[((secs(*t) % DAY) / HOUR) as usize] += 1;
+        }
+        assert_eq!(per_hour[3] + per_hour[15], 4000);
+        assert!((2800..3200).contains(&per_hour[15]), "{:?}", per_hour);
+    }
+
+    #[test]
+    fn it_weights_weekdays() {
+        let mut rng = StdRng::seed_from_u64(50);
+        let profile = ActivityProfile::business_hours();
+        // Monday 1970-01-05 to the next Monday
+        let times = profile.timestamps(at(4 * DAY), at(11 * DAY), 5000, &mut rng);
+        let weekend = times.iter().filter(|t| secs(**t) >= 9 * DAY).count();
+        assert!(weekend < 250, "{}", weekend);
+
+        assert_eq!(profile.weight_at(at(4 * DAY + 10 * HOUR)), 1.0);
+        assert_eq!(profile.weight_at(at(9 * DAY + 10 * HOUR)), 0.1);
+        assert!(ActivityProfile::new([0.0; 24]).is_err());
+        assert!(ActivityProfile::flat().weekdays([-1.0; 7]).is_err());
+        assert!(profile.timestamps(at(DAY), at(DAY), 5, &mut rng).is_empty());
+    }
+
+    #[test]
+    fn it_generates_bursts() {
+        let pattern = BurstPattern::new(10.0)
+            .unwrap()
+            .burst_gap(Duration::from_secs(30))
+            .unwrap()
+            .burst_size(200.0, 1.5)
+            .unwrap();
+        let mut events = pattern.events(StdRng::seed_from_u64(51));
+
+        let mut per_second = vec![0; 600];
+        let mut last = Duration::ZERO;
+        for at in events.by_ref() {
+            assert!(at >= last);
+            last = at;
+            match per_second.get_mut(at.as_secs() as usize) {
+                Some(count) => *count += 1,
+                None => break,
+            }
+        }
+        // Quiet seconds stay near the baseline, burst seconds go far above it
+        per_second.sort_unstable();
+        assert!((5..=15).contains(&per_second[300]), "{:?}", per_second);
+        assert!(per_second[599] > 40, "{:?}", per_second);
+
+        assert!(BurstPattern::new(-1.0).is_err());
+        assert!(BurstPattern::new(1.0)
+            .unwrap()
+            .burst_size(0.0, 1.0)
+            .is_err());
+        assert!(BurstPattern::new(1.0)
+            .unwrap()
+            .burst_gap(Duration::ZERO)
+            .is_err());
+    }
+
+    #[test]
+    fn it_paces_events() {
+        let pattern = BurstPattern::new(100.0).unwrap();
+        let mut controller = RateController::new(pattern.events(StdRng::seed_from_u64(51)));
+
+        let (first, delay) = controller.next_delay(Duration::ZERO).unwrap();
+        assert_eq!(delay, first);
+        // Running late means no waiting
+        let (_, delay) = controller.next_delay(Duration::from_secs(60)).unwrap();
+        assert_eq!(delay, Duration::ZERO);
+        assert!(controller.rate() >= 100.0);
+    }
+}
@@ -0,0 +1,277 @@
+//! Strings that break text-processing code: byte-order marks, bidi controls, zero-width
+//! characters, huge grapheme clusters, the same text in different normalization forms and
+//! code points at every UTF-8 encoding-length boundary. Everything is valid UTF-8 (a
+//! `String` can't hold anything else); the point is text that is legal but unusual.
+
+use crate::probability;
+use rand::prelude::*;
+use somelib::error::Error;
+
+/// A kind of awkward text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hazard {
+    /// U+FEFF, mostly at the start where it reads as a byte-order mark
+    Bom,
+    /// Bidi overrides, embeddings and isolates, often left unterminated
+    Bidi,
+    /// Zero-width spaces, joiners and non-joiners
+    ZeroWidth,
+    /// One base character carrying a long run of combining marks
+    LongGrapheme,
+    /// Accented letters, some precomposed (NFC) and some decomposed (NFD)
+    MixedNormalization,
+    /// The first and last code points of each UTF-8 length, and the edges around the
+    /// surrogate range
+    EncodingBoundary,
+    /// Noncharacters such as U+FFFE and U+FDD0, legal but never meant to be interchanged
+    Noncharacter,
+    /// Emoji joined with ZWJ, skin tones, variation selectors and flags
+    Emoji,
+    /// Control characters and unusual whitespace, including NUL and U+2028
+    Control,
+}
+
+impl Hazard {
+    pub const ALL: [Hazard; 9] = [
+        Hazard::Bom,
+        Hazard::Bidi,
+        Hazard::ZeroWidth,
+        Hazard::LongGrapheme,
+        Hazard::MixedNormalization,
+        Hazard::EncodingBoundary,
+        Hazard::Noncharacter,
+        Hazard::Emoji,
+        Hazard::Control,
+    ];
+
+    /// Fixed examples, for tables of test cases
+    pub fn examples(&self) -> &'static [&'static str] {
+        match self {
+            Hazard::Bom => &["\u{feff}", "\u{feff}\u{feff}", "a\u{feff}b"],
+            Hazard::Bidi => &[
+                "\u{202e}abc",
+                "\u{202b}x\u{202c}",
+                "\u{2067}abc\u{2069}",
+                "\u{200f}\u{200e}",
+                "\u{2066}\u{2067}\u{2068}",
+            ],
+            Hazard::ZeroWidth => &["\u{200b}", "a\u{200c}b", "\u{200d}", "\u{2060}", "\u{180e}"],
+            Hazard::LongGrapheme => &["e\u{301}\u{302}\u{303}\u{304}\u{305}\u{306}\u{307}"],
+            Hazard::MixedNormalization => &[
+                "caf\u{e9} cafe\u{301}",
+                "\u{c5}ngstr\u{f6}m A\u{30a}ngstro\u{308}m \u{212b}",
+                "\u{1e69} s\u{323}\u{307} s\u{307}\u{323}",
+                "\u{ac00} \u{1100}\u{1161}",
+            ],
+            Hazard::EncodingBoundary => &[
+                "\u{0}",
+                "\u{7f}",
+                "\u{80}",
+                "\u{7ff}",
+                "\u{800}",
+                "\u{d7ff}",
+                "\u{e000}",
+                "\u{ffff}",
+                "\u{10000}",
+                "\u{10ffff}",
+            ],
+            Hazard::Noncharacter => &[
+                "\u{fffe}",
+                "\u{ffff}",
+                "\u{fdd0}",
+                "\u{fdef}",
+                "\u{1fffe}",
+                "\u{10fffe}",
+            ],
+            Hazard::Emoji => &[
+                "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\u{200d}\u{1f466}",
+                "\u{1f44d}\u{1f3fd}",
+                "\u{2764}\u{fe0f}",
+                "\u{1f1fa}\u{1f1f8}\u{1f1e9}",
+                "\u{1f3f4}\u{e0067}\u{e0062}\u{e0073}\u{e0063}\u{e0074}\u{e007f}",
+            ],
+            Hazard::Control => &[
+                "\u{0}",
+                "\r\n",
+                "\r",
+                "\u{85}",
+                "\u{2028}",
+                "\u{2029}",
+                "\u{a0}",
+                "\u{3000}",
+                "\u{1b}[31m",
+                "\u{8}",
+            ],
+        }
+    }
+
+    /// One random instance. Most kinds pick an example; `LongGrapheme` builds a cluster
+    /// of up to `max_marks` combining marks.
+    pub fn sample<R>(&self, max_marks: usize, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        match self {
+            Hazard::LongGrapheme => {
+                let mut out = String::from(*['a', 'e', 'Z', '\u{3042}'].choose(rng).unwrap());
+                // Combining Diacritical Marks, U+0300..=U+036F
+                for _ in 0..rng.gen_range(1..=max_marks.max(1)) {
+                    out.push(char::from_u32(rng.gen_range(0x300..=0x36f)).unwrap());
+                }
+                out
+            }
+            _ => self.examples().choose(rng).unwrap().to_string(),
+        }
+    }
+}
+
+/// Generates ordinary-looking text with hazards mixed in, so code under test gets past
+/// any quick checks before meeting the awkward parts
+pub struct AdversarialGen {
+    hazards: Vec<Hazard>,
+    hazard_rate: f64,
+    max_marks: usize,
+}
+
+impl AdversarialGen {
+    pub fn new() -> Self {
+        AdversarialGen {
+            hazards: Hazard::ALL.to_vec(),
+            hazard_rate: 0.3,
+            max_marks: 64,
+        }
+    }
+
+    /// Which kinds to use, at least one
+    pub fn hazards(mut self, hazards: &[Hazard]) -> Result<Self, Error> {
+        if hazards.is_empty() {
+            return Err(Error::InvalidParameter(
+                "at least one hazard is needed".into(),
+            ));
+        }
+        self.hazards = hazards.to_vec();
+        Ok(self)
+    }
+
+    /// Chance of each piece being a hazard rather than a plain word
+    pub fn hazard_rate(mut self, rate: f64) -> Self {
+        self.hazard_rate = probability(rate);
+        self
+    }
+
+    /// Most combining marks on one grapheme. Renderers and grapheme-aware code often
+    /// assume a handful; Unicode sets no limit.
+    pub fn max_marks(mut self, max: usize) -> Self {
+        self.max_marks = max;
+        self
+    }
+
+    /// A string of `pieces` words and hazards, at least one of them a hazard
+    pub fn string<R>(&self, pieces: usize, rng: &mut R) -> String
+    where
+        R: Rng + ?Sized,
+    {
+        let pieces = pieces.max(1);
+        let forced = rng.gen_range(0..pieces);
+        let mut out = String::new();
+        for i in 0..pieces {
+            if i == forced || rng.gen_bool(self.hazard_rate) {
+                let hazard = self.hazards.choose(rng).unwrap();
+                // A BOM is most dangerous where it looks like one
+                if *hazard == Hazard::Bom && rng.gen_bool(0.5) {
+                    out.insert(0, '\u{feff}');
+                } else {
+                    out.push_str(&hazard.sample(self.max_marks, rng));
+                }
+            } else {
+                if !out.is_empty() {
+                    out.push(' ');
+                }
+                let len = rng.gen_range(1..=8);
+                out.extend((0..len).map(|_| rng.gen_range('a'..='z')));
+            }
+        }
+        out
+    }
+}
+
+impl Default for AdversarialGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Every fixed example, for exhaustive tests
+pub fn corpus() -> Vec<&'static str> {
+    Hazard::ALL
+        .iter()
+        .flat_map(|h| h.examples().iter().copied())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_covers_every_encoding_length() {
+        let lengths = Hazard::EncodingBoundary
+            .examples()
+            .iter()
+            .map(|s| s.len())
+            .collect::<Vec<_>>();
+        for len in 1..=4 {
+            assert!(lengths.contains(&len));
+        }
+        assert!(corpus().len() > 40);
+    }
+
+    #[test]
+    fn it_mixes_normalization_forms() {
+        // Same letters, different bytes
+        let [composed, decomposed] = ["caf\u{e9}", "cafe\u{301}"];
+        assert_ne!(composed, decomposed);
+        assert_eq!(composed.chars().count() + 1, decomposed.chars().count());
+        assert!(Hazard::MixedNormalization.examples()[0].contains(composed));
+        assert!(Hazard::MixedNormalization.examples()[0].contains(decomposed));
+    }
+
+    #[test]
+    fn it_generates_hazardous_strings() {
+        let mut rng = StdRng::seed_from_u64(48);
+        let gen = AdversarialGen::new()
+            .hazards(&[Hazard::LongGrapheme])
+            .unwrap()
+            .max_marks(200);
+
+        let longest = (0..50)
+            .map(|_| gen.string(3, &mut rng))
+            .map(|s| {
+                s.chars()
+                    .filter(|c| ('\u{300}'..='\u{36f}').contains(c))
+                    .count()
+            })
+            .max()
+            .unwrap();
+        assert!(longest > 100);
+
+        let gen = AdversarialGen::new()
+            .hazards(&[Hazard::Bidi, Hazard::ZeroWidth])
+            .unwrap();
+        for _ in 0..50 {
+            let s = gen.string(5, &mut rng);
+            assert!(s
+                .chars()
+                .any(|c| matches!(c, '\u{200b}'..='\u{206f}' | '\u{180e}')));
+        }
+        assert!(AdversarialGen::new().hazards(&[]).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let nan = AdversarialGen::new().hazard_rate(f64::NAN);
+        let zero = AdversarialGen::new().hazard_rate(0.0);
+        let string = |gen: AdversarialGen| gen.string(20, &mut StdRng::seed_from_u64(248));
+        assert_eq!(string(nan), string(zero));
+    }
+}
@@ -0,0 +1,468 @@
+//! Random URLs for testing routers, sanitizers and URL parsers: schemes, IDN hosts, IP
+//! literals, ports, deep paths, query strings and fragments, with percent-encoding edge
+//! cases mixed in.
+//!
+//! Each `Url` keeps the decoded components it was built from next to its text, so a
+//! parser's output can be compared with what was meant. Path segments are as written,
+//! before any `.`/`..` removal.
+
+use crate::{fake::IDN_DOMAINS, probability};
+use rand::prelude::*;
+use somelib::error::Error;
+use std::{
+    fmt::{self, Write},
+    ops::RangeInclusive,
+};
+
+/// A generated URL. The fields are decoded; `Display` (and `as_str`) gives the encoded
+/// text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    pub scheme: String,
+    /// As written: a domain (Unicode or punycode), an IPv4 address or a bracketed IPv6
+    /// address
+    pub host: String,
+    pub port: Option<u16>,
+    pub path: Vec<String>,
+    /// `None` for a bare key without `=`
+    pub query: Vec<(String, Option<String>)>,
+    pub fragment: Option<String>,
+    text: String,
+}
+
+impl Url {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// Where a character may appear unencoded. Path segments come encoded from
+/// `edge_segment`, so they don't need a variant.
+#[derive(Clone, Copy)]
+enum Component {
+    Query,
+    Fragment,
+}
+
+/// Percent-encode everything but RFC 3986's unreserved characters, plus what each
+/// component allows on top. A query encodes spaces as `+` half the time, like HTML forms.
+fn encode<R>(decoded: &str, component: Component, rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let plus_for_space = matches!(component, Component::Query) && rng.gen();
+    let mut out = String::new();
+    for b in decoded.bytes() {
+        let allowed = b.is_ascii_alphanumeric()
+            || matches!(b, b'-' | b'.' | b'_' | b'~')
+            || match component {
+                Component::Query => matches!(b, b':' | b'@' | b'/' | b'?' | b'!' | b'$' | b'\''),
+                Component::Fragment => matches!(b, b':' | b'@' | b'/' | b'?' | b'&' | b'=' | b'+'),
+            };
+        if allowed {
+            out.push(b as char);
+        } else if b == b' ' && plus_for_space {
+            out.push('+');
+        } else {
+            write!(out, "%{:02X}", b).unwrap();
+        }
+    }
+    out
+}
+
+/// `(decoded, encoded)` for an awkward path segment
+fn edge_segment<R>(rng: &mut R) -> (String, String)
+where
+    R: Rng + ?Sized,
+{
+    let pick = |options: &[(&str, &str)], rng: &mut R| {
+        let (decoded, encoded) = options.choose(rng).unwrap();
+        (decoded.to_string(), encoded.to_string())
+    };
+    match rng.gen_range(0..6) {
+        // Characters with meaning elsewhere in a URL
+        0 => pick(
+            &[
+                ("a/b", "a%2Fb"),
+                ("what?", "what%3F"),
+                ("c#", "c%23"),
+                ("50%", "50%25"),
+                ("a b", "a%20b"),
+                ("a+b", "a+b"),
+                ("semi;colon", "semi%3Bcolon"),
+            ],
+            rng,
+        ),
+        // UTF-8, and lowercase hex, which is equivalent but not canonical
+        1 => pick(
+            &[
+                ("café", "caf%C3%A9"),
+                ("café", "caf%c3%a9"),
+                ("日本", "%E6%97%A5%E6%9C%AC"),
+                ("😀", "%F0%9F%98%80"),
+            ],
+            rng,
+        ),
+        // Unreserved characters encoded when they needn't be
+        2 => pick(
+            &[("abc", "%61bc"), ("a-b", "a%2Db"), ("~user", "%7Euser")],
+            rng,
+        ),
+        // Dot segments, which resolve away, including encoded ones
+        3 => pick(
+            &[(".", "."), ("..", ".."), ("..", "%2E%2E"), (".", "%2e")],
+            rng,
+        ),
+        // Double encoding: the decoded text itself looks encoded
+        4 => pick(&[("%41", "%2541"), ("%2F", "%252F"), ("%00", "%2500")], rng),
+        // Empty, i.e. `//`
+        _ => (String::new(), String::new()),
+    }
+}
+
+/// Generates `Url`s
+#[derive(Debug, Clone, PartialEq)]
+pub struct UrlGen {
+    schemes: Vec<String>,
+    port_rate: f64,
+    idn_rate: f64,
+    ip_rate: f64,
+    depth: RangeInclusive<usize>,
+    params: RangeInclusive<usize>,
+    fragment_rate: f64,
+    edge_rate: f64,
+}
+
+impl UrlGen {
+    /// `http` and `https` URLs on example domains, with some of everything
+    pub fn new() -> Self {
+        UrlGen {
+            schemes: vec!["http".into(), "https".into()],
+            port_rate: 0.2,
+            idn_rate: 0.1,
+            ip_rate: 0.1,
+            depth: 0..=4,
+            params: 0..=3,
+            fragment_rate: 0.2,
+            edge_rate: 0.1,
+        }
+    }
+
+    /// Schemes to pick from, e.g. `["ws", "wss"]`. Checked against RFC 3986: a letter, then
+    /// letters, digits, `+`, `-` or `.`.
+    pub fn schemes(mut self, schemes: &[&str]) -> Result<Self, Error> {
+        let valid = |s: &&str| {
+            s.starts_with(|c: char| c.is_ascii_alphabetic())
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        };
+        if schemes.is_empty() || !schemes.iter().all(valid) {
+            return Err(Error::InvalidParameter(format!(
+                "invalid schemes {:?}",
+                schemes
+            )));
+        }
+        self.schemes = schemes.iter().map(|s| s.to_string()).collect();
+        Ok(self)
+    }
+
+    /// Chance of an explicit port, sometimes the scheme's default or an extreme like 65535
+    pub fn port_rate(mut self, rate: f64) -> Self {
+        self.port_rate = probability(rate);
+        self
+    }
+
+    /// Chance of an internationalized host, in Unicode or punycode
+    pub fn idn_rate(mut self, rate: f64) -> Self {
+        self.idn_rate = probability(rate);
+        self
+    }
+
+    /// Chance of an IPv4 or IPv6 literal instead of a name
+    pub fn ip_rate(mut self, rate: f64) -> Self {
+        self.ip_rate = probability(rate);
+        self
+    }
+
+    /// How many path segments
+    pub fn depth(mut self, depth: RangeInclusive<usize>) -> Result<Self, Error> {
+        if depth.is_empty() {
+            return Err(Error::InvalidParameter("empty depth range".into()));
+        }
+        self.depth = depth;
+        Ok(self)
+    }
+
+    /// How many query parameters
+    pub fn query_params(mut self, params: RangeInclusive<usize>) -> Result<Self, Error> {
+        if params.is_empty() {
+            return Err(Error::InvalidParameter(
+                "empty query parameter range".into(),
+            ));
+        }
+        self.params = params;
+        Ok(self)
+    }
+
+    pub fn fragment_rate(mut self, rate: f64) -> Self {
+        self.fragment_rate = probability(rate);
+        self
+    }
+
+    /// Chance of each path segment, query parameter and fragment being an encoding edge
+    /// case rather than a plain word
+    pub fn edge_rate(mut self, rate: f64) -> Self {
+        self.edge_rate = probability(rate);
+        self
+    }
+
+    pub fn url<R>(&self, rng: &mut R) -> Url
+    where
+        R: Rng + ?Sized,
+    {
+        let scheme = self.schemes.choose(rng).unwrap().clone();
+        let host = if rng.gen_bool(self.ip_rate) {
+            let ips = [
+                "192.0.2.1",
+                "198.51.100.7",
+                "203.0.113.255",
+                "[2001:db8::1]",
+                "[::1]",
+            ];
+            ips.choose(rng).unwrap().to_string()
+        } else if rng.gen_bool(self.idn_rate) {
+            let (unicode, ascii) = IDN_DOMAINS.choose(rng).unwrap();
+            if rng.gen() { unicode } else { ascii }.to_string()
+        } else {
+            let subdomain = ["", "www.", "api.", "a.b.c."].choose(rng).unwrap();
+            let domain = ["example.com", "example.org", "example.net"]
+                .choose(rng)
+                .unwrap();
+            format!("{}{}", subdomain, domain)
+        };
+        let port = rng
+            .gen_bool(self.port_rate)
+            .then(|| match rng.gen_range(0..4) {
+                0 => {
+                    if scheme == "https" {
+                        443
+                    } else {
+                        80
+                    }
+                }
+                1 => *[1, 65535].choose(rng).unwrap(),
+                _ => rng.gen_range(1024..=65535),
+            });
+
+        let mut text = format!("{}://{}", scheme, host);
+        if let Some(port) = port {
+            write!(text, ":{}", port).unwrap();
+        }
+        let mut path = Vec::new();
+        for _ in 0..rng.gen_range(self.depth.clone()) {
+            let (decoded, encoded) = if rng.gen_bool(self.edge_rate) {
+                edge_segment(rng)
+            } else {
+                let word = word(rng);
+                (word.clone(), word)
+            };
+            write!(text, "/{}", encoded).unwrap();
+            path.push(decoded);
+        }
+        // An authority with no path still gets a `/` half the time, the other canonical form
+        if path.is_empty() && rng.gen() {
+            text.push('/');
+        }
+
+        let mut query = Vec::new();
+        for i in 0..rng.gen_range(self.params.clone()) {
+            // Repeating a key is legal and handled differently by every framework
+            let key = match query.first() {
+                Some((first, _)) if rng.gen_bool(0.2) => String::clone(first),
+                _ => word(rng),
+            };
+            let value = if rng.gen_bool(self.edge_rate) {
+                let awkward = ["a b", "a&b=c", "1+1", "100%", "ü", "", "?#"];
+                awkward.choose(rng).map(|v| v.to_string())
+            } else if rng.gen_bool(0.1) {
+                None
+            } else {
+                Some(word(rng))
+            };
+            text.push(if i == 0 { '?' } else { '&' });
+            text.push_str(&encode(&key, Component::Query, rng));
+            if let Some(value) = &value {
+                write!(text, "={}", encode(value, Component::Query, rng)).unwrap();
+            }
+            query.push((key, value));
+        }
+
+        let fragment = rng.gen_bool(self.fragment_rate).then(|| {
+            if rng.gen_bool(self.edge_rate) {
+                ["", "a b", "#", "/deep/link?x=1", "日本"]
+                    .choose(rng)
+                    .unwrap()
+                    .to_string()
+            } else {
+                word(rng)
+            }
+        });
+        if let Some(fragment) = &fragment {
+            write!(text, "#{}", encode(fragment, Component::Fragment, rng)).unwrap();
+        }
+
+        Url {
+            scheme,
+            host,
+            port,
+            path,
+            query,
+            fragment,
+            text,
+        }
+    }
+}
+
+impl Default for UrlGen {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A plain lowercase word
+fn word<R>(rng: &mut R) -> String
+where
+    R: Rng + ?Sized,
+{
+    let len = rng.gen_range(1..10);
+    (0..len)
+        .map(|_| rng.gen_range(b'a'..=b'z') as char)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(encoded: &str, plus_is_space: bool) -> String {
+        let bytes = encoded.as_bytes();
+        let mut out = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' => {
+                    out.push(u8::from_str_radix(&encoded[i + 1..i + 3], 16).unwrap());
+                    i += 2;
+                }
+                b'+' if plus_is_space => out.push(b' '),
+                b => out.push(b),
+            }
+            i += 1;
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    /// Split a URL at its delimiters and decode each piece, the way a parser would
+    fn parse(text: &str) -> Url {
+        let (scheme, rest) = text.split_once("://").unwrap();
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(decode(fragment, false))),
+            None => (rest, None),
+        };
+        let (rest, query) = match rest.split_once('?') {
+            Some((rest, query)) => (rest, query.split('&').collect::<Vec<_>>()),
+            None => (rest, Vec::new()),
+        };
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        // The last `:` after any `]` separates the port
+        let (host, port) = match authority
+            .rfind(':')
+            .filter(|i| !authority[*i..].contains(']'))
+        {
+            Some(i) => (&authority[..i], Some(authority[i + 1..].parse().unwrap())),
+            None => (authority, None),
+        };
+        let path = match path {
+            "" | "/" => Vec::new(),
+            path => path[1..].split('/').map(|s| decode(s, false)).collect(),
+        };
+        let query = query
+            .iter()
+            .map(|pair| match pair.split_once('=') {
+                Some((k, v)) => (decode(k, true), Some(decode(v, true))),
+                None => (decode(pair, true), None),
+            })
+            .collect();
+        Url {
+            scheme: scheme.into(),
+            host: host.into(),
+            port,
+            path,
+            query,
+            fragment,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn it_encodes_its_components() {
+        let mut rng = StdRng::seed_from_u64(262);
+        let gen = UrlGen::new().edge_rate(0.5).depth(0..=6).unwrap();
+
+        for _ in 0..2000 {
+            let url = gen.url(&mut rng);
+            let mut parsed = parse(url.as_str());
+            // A single empty segment is written as `/`, the same as no path
+            if url.path == [""] {
+                parsed.path = vec![String::new()];
+            }
+            assert_eq!(parsed, url);
+            assert!(url.as_str().is_ascii() || url.host.contains('ü') || url.host.contains('é'));
+        }
+    }
+
+    #[test]
+    fn it_follows_the_settings() {
+        let mut rng = StdRng::seed_from_u64(262);
+        let gen = UrlGen::new()
+            .schemes(&["wss"])
+            .unwrap()
+            .port_rate(1.0)
+            .ip_rate(1.0)
+            .fragment_rate(0.0)
+            .query_params(2..=2)
+            .unwrap();
+
+        let url = gen.url(&mut rng);
+        assert!(url.as_str().starts_with("wss://"));
+        assert!(url.port.is_some() && url.fragment.is_none());
+        assert!(url.host.chars().next().unwrap().is_ascii_digit() || url.host.starts_with('['));
+        assert_eq!(url.query.len(), 2);
+
+        assert!(UrlGen::new().schemes(&["1http"]).is_err());
+        assert!(UrlGen::new().schemes(&[]).is_err());
+        #[allow(clippy::reversed_empty_ranges)]
+        let empty = 3..=1;
+        assert!(UrlGen::new().depth(empty).is_err());
+    }
+
+    #[test]
+    fn it_treats_nan_rates_as_zero() {
+        let gen = UrlGen::new()
+            .port_rate(f64::NAN)
+            .idn_rate(f64::NAN)
+            .ip_rate(f64::NAN)
+            .fragment_rate(f64::NAN)
+            .edge_rate(f64::NAN);
+        let mut rng = StdRng::seed_from_u64(262);
+        for _ in 0..20 {
+            assert!(gen.url(&mut rng).port.is_none());
+        }
+    }
+}
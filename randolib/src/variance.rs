@@ -0,0 +1,189 @@
+//! Variance reduction for simulations. Both techniques make estimates from the same number
+//! of samples less noisy by controlling *which* random numbers get used, rather than by
+//! changing the model.
+
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha256};
+
+/// Antithetic sampling: every sample is paired with a mirror image that uses the
+/// complement of each random word, turning a uniform `u` into (almost exactly) `1 - u`.
+/// For monotone models the two halves of a pair are negatively correlated, so their
+/// average varies less than the average of two independent samples.
+///
+/// The first run of each pair is recorded and the second replays it complemented, so
+/// the pairing holds even when the sampler consumes a variable number of words. If the
+/// mirrored run needs more words than were recorded, it continues with fresh ones.
+pub struct Antithetic<R> {
+    rng: R,
+    tape: Vec<u64>,
+}
+
+impl<R: RngCore> Antithetic<R> {
+    pub fn new(rng: R) -> Self {
+        Antithetic {
+            rng,
+            tape: Vec::new(),
+        }
+    }
+
+    /// Run `sample` twice, returning the original and its antithetic twin
+    pub fn pair<T, F>(&mut self, mut sample: F) -> (T, T)
+    where
+        F: FnMut(&mut dyn RngCore) -> T,
+    {
+        self.tape.clear();
+        let first = sample(&mut Tape {
+            rng: &mut self.rng,
+            tape: &mut self.tape,
+            replay: None,
+        });
+        let second = sample(&mut Tape {
+            rng: &mut self.rng,
+            tape: &mut self.tape,
+            replay: Some(0),
+        });
+        (first, second)
+    }
+
+    /// Estimate `E[sample]` from `pairs` antithetic pairs
+    pub fn mean<F>(&mut self, pairs: usize, mut sample: F) -> f64
+    where
+        F: FnMut(&mut dyn RngCore) -> f64,
+    {
+        let total = (0..pairs)
+            .map(|_| {
+                let (a, b) = self.pair(&mut sample);
+                a + b
+            })
+            .sum::<f64>();
+        total / (2 * pairs) as f64
+    }
+}
+
+/// Records words from `rng` into `tape`, or replays them complemented when `replay` holds
+/// the position to read next
+struct Tape<'a, R> {
+    rng: &'a mut R,
+    tape: &'a mut Vec<u64>,
+    replay: Option<usize>,
+}
+
+impl<R: RngCore> RngCore for Tape<'_, R> {
+    fn next_u32(&mut self) -> u32 {
+        // Everything goes through `next_u64` so there is only one kind of word on the tape.
+        // The high bits of a complement are the complement of the high bits.
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match &mut self.replay {
+            Some(pos) if *pos < self.tape.len() => {
+                *pos += 1;
+                !self.tape[*pos - 1]
+            }
+            Some(_) => self.rng.next_u64(),
+            None => {
+                let word = self.rng.next_u64();
+                self.tape.push(word);
+                word
+            }
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Common random numbers: when comparing two variants of a system, drive the same
+/// decision in both with the same random stream, so differences in the results come from
+/// the variants and not from luck. Each labeled decision gets its own generator, derived
+/// from the run's seed and the label, so adding a decision in one branch doesn't shift the
+/// numbers every other decision sees.
+///
+/// Streams come from `ChaCha20Rng` so they stay the same across `rand` releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommonRandomNumbers {
+    seed: u64,
+}
+
+impl CommonRandomNumbers {
+    pub fn new(seed: u64) -> Self {
+        CommonRandomNumbers { seed }
+    }
+
+    /// The generator for the decision called `label`
+    pub fn rng(&self, label: &str) -> ChaCha20Rng {
+        self.rng_indexed(label, 0)
+    }
+
+    /// The generator for the `index`th occurrence of a decision, e.g. the 5th customer's
+    /// arrival, so repeated decisions line up one to one across branches
+    pub fn rng_indexed(&self, label: &str, index: u64) -> ChaCha20Rng {
+        let mut hash = Sha256::new();
+        hash.update(self.seed.to_le_bytes());
+        hash.update(index.to_le_bytes());
+        hash.update(label.as_bytes());
+        ChaCha20Rng::from_seed(hash.finalize().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_mirrors_uniforms() {
+        let mut anti = Antithetic::new(StdRng::seed_from_u64(5));
+
+        for _ in 0..100 {
+            let (u, v) = anti.pair(|rng| rng.gen::<f64>());
+            assert!((u + v - 1.0).abs() < 1e-15, "{} {}", u, v);
+        }
+    }
+
+    #[test]
+    fn it_reduces_variance() {
+        // E[exp(U)] = e - 1, estimated 200 times with 100 samples each
+        let spread = |estimates: Vec<f64>| {
+            let mean = estimates.iter().sum::<f64>() / estimates.len() as f64;
+            estimates.iter().map(|e| (e - mean).powi(2)).sum::<f64>()
+        };
+        let mut rng = StdRng::seed_from_u64(6);
+        let plain = (0..200)
+            .map(|_| (0..100).map(|_| rng.gen::<f64>().exp()).sum::<f64>() / 100.0)
+            .collect();
+        let mut anti = Antithetic::new(StdRng::seed_from_u64(6));
+        let antithetic = (0..200)
+            .map(|_| anti.mean(50, |rng| rng.gen::<f64>().exp()))
+            .collect();
+
+        assert!(spread(antithetic) * 10.0 < spread(plain));
+    }
+
+    #[test]
+    fn it_shares_streams_by_label() {
+        let crn = CommonRandomNumbers::new(9);
+        let draw = |mut rng: ChaCha20Rng| rng.gen::<u64>();
+
+        assert_eq!(draw(crn.rng("arrival")), draw(crn.rng("arrival")));
+        assert_ne!(draw(crn.rng("arrival")), draw(crn.rng("service")));
+        assert_ne!(
+            draw(crn.rng_indexed("arrival", 1)),
+            draw(crn.rng_indexed("arrival", 2))
+        );
+        assert_ne!(
+            draw(crn.rng("arrival")),
+            draw(CommonRandomNumbers::new(10).rng("arrival"))
+        );
+    }
+}
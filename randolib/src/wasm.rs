@@ -0,0 +1,141 @@
+//! Generators for JavaScript, through `wasm-bindgen`. Build with the `wasm` feature for
+//! `wasm32-unknown-unknown`, e.g. `wasm-pack build randolib --features wasm`, then:
+//!
+//! ```js
+//! const rando = RandoJs.fromSeed(42n);
+//! rando.int(1, 6);
+//! rando.logs(10, "json", Date.now());
+//! ```
+//!
+//! `#[wasm_bindgen]` generates the glue on both sides. Only types it knows how to pass
+//! across go in and out: numbers, `bool`, `char`, strings, and our own exported structs.
+//! Our `Error` becomes a JavaScript `Error` with the same message.
+
+use crate::{
+    fake,
+    geojson::GeoJsonGen,
+    logs::{LogFormat, LogGen},
+    strings,
+};
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+use somelib::error::Error;
+use std::time::{Duration, UNIX_EPOCH};
+use wasm_bindgen::prelude::*;
+
+/// One generator and the things it can make. JavaScript has no `Rng` trait to be generic
+/// over, so this holds a concrete `ChaCha20Rng`, the generator `from_seed` uses everywhere.
+#[wasm_bindgen]
+pub struct RandoJs {
+    rng: ChaCha20Rng,
+}
+
+#[wasm_bindgen]
+impl RandoJs {
+    /// `new RandoJs()`, seeded from the platform's secure source. On the web that's
+    /// `crypto.getRandomValues`, via `getrandom`'s `js` feature.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        RandoJs {
+            rng: ChaCha20Rng::from_entropy(),
+        }
+    }
+
+    /// `RandoJs.fromSeed(42n)`. A `u64` is a `BigInt` on the JavaScript side, hence the `n`.
+    #[wasm_bindgen(js_name = fromSeed)]
+    pub fn from_seed(seed: u64) -> Self {
+        RandoJs {
+            rng: ChaCha20Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Any Unicode scalar value, like `RandoA<char>`
+    pub fn char(&mut self) -> char {
+        self.rng.gen()
+    }
+
+    /// A float in `[0, 1)`, like `Math.random()`
+    pub fn float(&mut self) -> f64 {
+        self.rng.gen()
+    }
+
+    /// An integer from `min` to `max`, both included
+    pub fn int(&mut self, min: i32, max: i32) -> Result<i32, JsError> {
+        if min > max {
+            return Err(js_error(Error::InvalidParameter(format!(
+                "min {} is more than max {}",
+                min, max
+            ))));
+        }
+        Ok(self.rng.gen_range(min..=max))
+    }
+
+    /// `len` letters and digits
+    pub fn alphanumeric(&mut self, len: usize) -> String {
+        strings::alphanumeric_with(len, &mut self.rng)
+    }
+
+    #[wasm_bindgen(js_name = fullName)]
+    pub fn full_name(&mut self) -> String {
+        fake::full_name(&mut self.rng)
+    }
+
+    pub fn email(&mut self) -> String {
+        fake::email(&mut self.rng)
+    }
+
+    /// A GeoJSON `FeatureCollection` of `features` random features, as a string for
+    /// `JSON.parse`
+    pub fn geojson(&mut self, features: usize) -> String {
+        GeoJsonGen::new()
+            .collection(features, &mut self.rng)
+            .to_json()
+    }
+
+    /// `count` access log lines in `format` (`combined`, `json` or `syslog`), one per line.
+    /// `SystemTime::now` isn't available in the browser, so they start at `start_ms`
+    /// milliseconds since the epoch, e.g. `Date.now()`.
+    pub fn logs(&mut self, count: usize, format: &str, start_ms: f64) -> Result<String, JsError> {
+        let format = format.parse::<LogFormat>().map_err(js_error)?;
+        let start = UNIX_EPOCH + Duration::from_secs_f64(start_ms.max(0.0) / 1000.0);
+        let gen = LogGen::new();
+        let entries = gen.entries(start, 10.0, &mut self.rng).map_err(js_error)?;
+        let lines = entries
+            .take(count)
+            .map(|entry| entry.format(format))
+            .collect::<Vec<_>>();
+        Ok(lines.join("\n"))
+    }
+}
+
+impl Default for RandoJs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `JsError` takes a message, and `Display` is the message we'd show anyway
+fn js_error(err: Error) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Only the happy paths: building a `JsError` calls into JavaScript, which panics
+    // outside of wasm
+    #[test]
+    fn it_generates_from_a_seed() {
+        let mut a = RandoJs::from_seed(278);
+        let mut b = RandoJs::from_seed(278);
+        assert_eq!(a.email(), b.email());
+        assert!((1..=6).contains(&a.int(1, 6).unwrap()));
+        assert!((0.0..1.0).contains(&a.float()));
+        assert_eq!(a.alphanumeric(12).len(), 12);
+        let logs = a.logs(3, "combined", 1.7e12).unwrap();
+        assert_eq!(logs.lines().count(), 3);
+        assert!(logs.contains("/Nov/2023:"));
+        assert!(a.geojson(2).starts_with("{\"type\":\"FeatureCollection\""));
+    }
+}
@@ -0,0 +1,158 @@
+//! A generator per HTTP request, seeded from the request's ID, so randomized behavior in a
+//! handler (jitter, sampling, A/B splits) can be replayed by resending the same ID.
+//!
+//! With the `axum` or `actix` feature, `RequestRng` is an extractor:
+//!
+//! ```ignore
+//! async fn handler(mut rng: RequestRng) -> String {
+//!     format!("request {} rolled {}", rng.request_id(), rng.gen_range(1..=6))
+//! }
+//! ```
+
+use crate::seed::Seed;
+use rand::prelude::*;
+use rand_chacha::ChaCha20Rng;
+
+/// The header the request ID is read from. Requests without one get a random ID.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// A generator derived from a request ID, see `Seed::from_label`
+pub struct RequestRng {
+    request_id: String,
+    rng: ChaCha20Rng,
+}
+
+impl RequestRng {
+    pub fn from_request_id(request_id: &str) -> Self {
+        RequestRng {
+            request_id: request_id.to_string(),
+            rng: Seed::from_label(request_id).rng(),
+        }
+    }
+
+    /// For a request that came without an ID: make one up so the generator can still be
+    /// reproduced, as long as the ID is logged
+    fn from_optional_id(request_id: Option<&str>) -> Self {
+        match request_id {
+            Some(id) => Self::from_request_id(id),
+            None => {
+                let id = format!("{:032x}", rand::rngs::OsRng.gen::<u128>());
+                Self::from_request_id(&id)
+            }
+        }
+    }
+
+    /// The ID the generator was derived from
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+}
+
+impl RngCore for RequestRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(feature = "axum")]
+impl<S> axum_core::extract::FromRequestParts<S> for RequestRng
+where
+    S: Send + Sync,
+{
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut http::request::Parts,
+        _state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let id = parts.headers.get(REQUEST_ID_HEADER);
+        Ok(Self::from_optional_id(id.and_then(|v| v.to_str().ok())))
+    }
+}
+
+#[cfg(feature = "actix")]
+impl actix_web::FromRequest for RequestRng {
+    type Error = std::convert::Infallible;
+    type Future = std::future::Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        let id = req.headers().get(REQUEST_ID_HEADER);
+        std::future::ready(Ok(Self::from_optional_id(id.and_then(|v| v.to_str().ok()))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    /// The extractors never wait on anything, so polling once is enough
+    fn ready<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("extractor didn't complete immediately"),
+        }
+    }
+
+    #[test]
+    fn it_derives_from_the_request_id() {
+        let mut a = RequestRng::from_request_id("req-1");
+        let mut b = RequestRng::from_request_id("req-1");
+        assert_eq!(a.gen::<u64>(), b.gen::<u64>());
+
+        let mut anonymous = RequestRng::from_optional_id(None);
+        let id = anonymous.request_id().to_string();
+        assert_eq!(
+            anonymous.gen::<u64>(),
+            RequestRng::from_request_id(&id).gen::<u64>()
+        );
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn it_extracts_in_axum() {
+        use axum_core::extract::FromRequestParts;
+
+        let request = http::Request::builder()
+            .header(REQUEST_ID_HEADER, "req-2")
+            .body(())
+            .unwrap();
+        let (mut parts, _) = request.into_parts();
+        let mut rng = ready(RequestRng::from_request_parts(&mut parts, &())).unwrap();
+
+        assert_eq!(rng.request_id(), "req-2");
+        assert_eq!(
+            rng.gen::<u64>(),
+            RequestRng::from_request_id("req-2").gen::<u64>()
+        );
+    }
+
+    #[cfg(feature = "actix")]
+    #[test]
+    fn it_extracts_in_actix() {
+        use actix_web::FromRequest;
+
+        let request = actix_web::test::TestRequest::default()
+            .insert_header((REQUEST_ID_HEADER, "req-3"))
+            .to_http_request();
+        let rng = ready(RequestRng::extract(&request)).unwrap();
+
+        assert_eq!(rng.request_id(), "req-3");
+    }
+}
@@ -0,0 +1,267 @@
+//! Key-value workloads in the style of YCSB (the Yahoo! Cloud Serving Benchmark): a stream
+//! of reads, updates and inserts over a keyspace where a few keys are far more popular than
+//! the rest, for benchmarking storage engines.
+//!
+//! A benchmark first loads `key(0)` to `key(records - 1)`, then replays `ops`.
+
+use rand::prelude::*;
+use rand_distr::Zipf;
+use somelib::error::Error;
+use std::{fmt, ops::RangeInclusive};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Read { key: String },
+    Update { key: String, value: Vec<u8> },
+    Insert { key: String, value: Vec<u8> },
+}
+
+impl Operation {
+    pub fn key(&self) -> &str {
+        match self {
+            Operation::Read { key }
+            | Operation::Update { key, .. }
+            | Operation::Insert { key, .. } => key,
+        }
+    }
+}
+
+/// `READ key`, or `UPDATE key size` / `INSERT key size` with the value's size in bytes
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Read { key } => write!(f, "READ {}", key),
+            Operation::Update { key, value } => write!(f, "UPDATE {} {}", key, value.len()),
+            Operation::Insert { key, value } => write!(f, "INSERT {} {}", key, value.len()),
+        }
+    }
+}
+
+/// The name of record `index`. Records are ranked by popularity, so the name is scrambled
+/// (through a bijection, so names never collide) to keep hot keys from clustering at one
+/// end of the keyspace, as in YCSB.
+pub fn key(index: u64) -> String {
+    // The splitmix64 finalizer: every step is invertible, so distinct inputs stay distinct
+    let mut x = index;
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^= x >> 31;
+    format!("user{:016x}", x)
+}
+
+/// A workload description. Proportions are relative, they needn't add up to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workload {
+    records: u64,
+    read: f64,
+    update: f64,
+    insert: f64,
+    zipf_exponent: f64,
+    value_size: RangeInclusive<usize>,
+}
+
+impl Workload {
+    /// `records` preloaded keys (at least 1), YCSB workload A's mix, Zipf 0.99 popularity
+    /// and 100 to 1000 byte values
+    pub fn new(records: u64) -> Result<Self, Error> {
+        if records == 0 {
+            return Err(Error::InvalidParameter(
+                "a workload needs at least one record".into(),
+            ));
+        }
+        Ok(Workload {
+            records,
+            read: 0.5,
+            update: 0.5,
+            insert: 0.0,
+            zipf_exponent: 0.99,
+            value_size: 100..=1000,
+        })
+    }
+
+    /// YCSB's core workloads: `a` (update heavy, 50/50), `b` (read mostly, 95/5), `c`
+    /// (read only) and `d` (95% reads, 5% inserts). YCSB's `d` favors recent inserts; here
+    /// they're the least popular keys instead.
+    pub fn preset(name: &str, records: u64) -> Result<Self, Error> {
+        let (read, update, insert) = match name.to_ascii_lowercase().as_str() {
+            "a" => (0.5, 0.5, 0.0),
+            "b" => (0.95, 0.05, 0.0),
+            "c" => (1.0, 0.0, 0.0),
+            "d" => (0.95, 0.0, 0.05),
+            _ => {
+                return Err(Error::InvalidParameter(format!(
+                    "unknown workload {:?}, expected a, b, c or d",
+                    name
+                )))
+            }
+        };
+        Self::new(records)?.mix(read, update, insert)
+    }
+
+    /// Relative proportions of reads, updates and inserts
+    pub fn mix(mut self, read: f64, update: f64, insert: f64) -> Result<Self, Error> {
+        let all = [read, update, insert];
+        if all.iter().any(|p| !p.is_finite() || *p < 0.0) || all.iter().sum::<f64>() <= 0.0 {
+            return Err(Error::InvalidParameter(
+                "proportions must be non-negative and not all zero".into(),
+            ));
+        }
+        (self.read, self.update, self.insert) = (read, update, insert);
+        Ok(self)
+    }
+
+    /// Skew of key popularity: 0 is uniform, YCSB uses 0.99
+    pub fn zipf(mut self, exponent: f64) -> Result<Self, Error> {
+        if !exponent.is_finite() || exponent < 0.0 {
+            return Err(Error::InvalidParameter(
+                "zipf exponent must be finite and non-negative".into(),
+            ));
+        }
+        self.zipf_exponent = exponent;
+        Ok(self)
+    }
+
+    pub fn value_size(mut self, size: RangeInclusive<usize>) -> Result<Self, Error> {
+        if size.is_empty() {
+            return Err(Error::InvalidParameter("empty value size range".into()));
+        }
+        self.value_size = size;
+        Ok(self)
+    }
+
+    /// Keys to load before running `ops`
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// An endless stream of operations. Inserted keys join the keyspace as its least
+    /// popular records.
+    pub fn ops<R: Rng>(&self, rng: R) -> Ops<R> {
+        Ops {
+            workload: self.clone(),
+            rng,
+            records: self.records,
+        }
+    }
+}
+
+/// Iterator over a `Workload`'s operations
+pub struct Ops<R> {
+    workload: Workload,
+    rng: R,
+    /// Records so far, inserts included
+    records: u64,
+}
+
+impl<R: Rng> Ops<R> {
+    fn value(&mut self) -> Vec<u8> {
+        let len = self.rng.gen_range(self.workload.value_size.clone());
+        let mut value = vec![0; len];
+        self.rng.fill_bytes(&mut value);
+        value
+    }
+
+    /// An existing record, by popularity
+    fn existing_key(&mut self) -> String {
+        // `Zipf::new` only precomputes a few constants, so making one per draw is cheap
+        // and follows the keyspace as it grows. It can't fail: `records` is at least 1
+        // and the exponent was checked.
+        let zipf = Zipf::new(self.records, self.workload.zipf_exponent).unwrap();
+        // Ranks are 1-based floats
+        let rank = self.rng.sample(zipf) as u64;
+        key(rank - 1)
+    }
+}
+
+impl<R: Rng> Iterator for Ops<R> {
+    type Item = Operation;
+
+    fn next(&mut self) -> Option<Operation> {
+        let Workload {
+            read,
+            update,
+            insert,
+            ..
+        } = self.workload;
+        let roll = self.rng.gen_range(0.0..read + update + insert);
+        Some(if roll < read {
+            Operation::Read {
+                key: self.existing_key(),
+            }
+        } else if roll < read + update {
+            let key = self.existing_key();
+            Operation::Update {
+                key,
+                value: self.value(),
+            }
+        } else {
+            let key = key(self.records);
+            self.records += 1;
+            Operation::Insert {
+                key,
+                value: self.value(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn it_mixes_operations() {
+        let workload = Workload::preset("b", 1000).unwrap();
+        let ops = workload
+            .ops(StdRng::seed_from_u64(257))
+            .take(10_000)
+            .collect::<Vec<_>>();
+
+        let reads = ops
+            .iter()
+            .filter(|op| matches!(op, Operation::Read { .. }))
+            .count();
+        assert!((9350..9650).contains(&reads), "{}", reads);
+        for op in &ops {
+            if let Operation::Update { value, .. } = op {
+                assert!((100..=1000).contains(&value.len()));
+            }
+        }
+        assert!(Workload::preset("z", 10).is_err());
+        assert!(Workload::new(0).is_err());
+        assert!(Workload::new(1).unwrap().mix(0.0, 0.0, 0.0).is_err());
+    }
+
+    #[test]
+    fn it_skews_key_popularity() {
+        let workload = Workload::preset("c", 10_000).unwrap();
+        let mut counts = HashMap::new();
+        for op in workload.ops(StdRng::seed_from_u64(257)).take(20_000) {
+            *counts.entry(op.key().to_string()).or_insert(0) += 1;
+        }
+
+        // The hottest record alone gets several percent of all reads
+        assert!(counts[&key(0)] > 1000, "{}", counts[&key(0)]);
+        assert!(counts.len() < 10_000);
+    }
+
+    #[test]
+    fn it_inserts_new_keys() {
+        let loaded = (0..100).map(key).collect::<HashSet<_>>();
+        assert_eq!(loaded.len(), 100);
+
+        let workload = Workload::preset("d", 100).unwrap();
+        let mut inserted = HashSet::new();
+        for op in workload.ops(StdRng::seed_from_u64(257)).take(2000) {
+            match op {
+                Operation::Insert { key, .. } => {
+                    assert!(!loaded.contains(&key));
+                    assert!(inserted.insert(key));
+                }
+                op => assert!(loaded.contains(op.key()) || inserted.contains(op.key())),
+            }
+        }
+        assert!(!inserted.is_empty());
+    }
+}
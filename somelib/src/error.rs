@@ -1,11 +1,131 @@
+// In std's prelude, but a no_std crate has to import it
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
 /// `Error` for hello. There is no std lib `Error` so we can call this `Error` if we want. We
 /// could also call it `HelloError` or whatever makes sense.
 ///
 /// If we use the fully qualified `thiserror::Error`, we don't have to use `use thiserror`. In this
 /// case we use `thiserror::Error` to prevent a collision with our `Error`
-#[derive(Debug, thiserror::Error)]
+///
+/// With the `serde` feature errors serialize like any other enum, e.g. `"Exhausted"` or
+/// `{"InvalidParameter": "..."}`.
+///
+/// thiserror needs std, so without the `std` feature the derive and its `#[error(..)]`
+/// attributes are switched off with `cfg_attr`, and the `Display` impl below takes over.
+#[derive(Debug)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
     // Automatically gives use the required `Display` impl
-    #[error("two consecutive random values found")]
-    ConsecutiveRandom,
+    #[cfg_attr(feature = "std", error("two consecutive random values found: {value}"))]
+    ConsecutiveRandom { value: String },
+    // Named fields work like tuple fields in the format string
+    #[cfg_attr(
+        feature = "std",
+        error("random value repeated the one {distance} draws back")
+    )]
+    RecentRepeat { distance: usize },
+    // A generator gave up, e.g. it couldn't find a value it hadn't produced before
+    #[cfg_attr(
+        feature = "std",
+        error("ran out of attempts to find an unused random value")
+    )]
+    Exhausted,
+    // A generator set to re-roll repeats drew nothing but repeats
+    #[cfg_attr(
+        feature = "std",
+        error("all {attempts} draws repeated a recent random value")
+    )]
+    RetriesExhausted { attempts: usize },
+    // Problems with the choices given to a weighted picker
+    #[cfg_attr(feature = "std", error("nothing to choose from"))]
+    NoItems,
+    #[cfg_attr(
+        feature = "std",
+        error("invalid weight {0}, weights must be finite and positive")
+    )]
+    InvalidWeight(f64),
+    // Variants can carry data, which the `#[error(..)]` format string can refer to
+    #[cfg_attr(feature = "std", error("invalid parameter: {0}"))]
+    InvalidParameter(String),
+    // `#[from]` generates `From<std::io::Error> for Error` so `?` converts for us and
+    // `transparent` forwards `Display` and `source` to the wrapped error. A plain `cfg` on
+    // a variant removes it entirely, so no_std code never sees `std::io`.
+    #[cfg(feature = "std")]
+    #[error(transparent)]
+    Io(
+        #[from]
+        #[cfg_attr(feature = "serde", serde(with = "io_error"))]
+        std::io::Error,
+    ),
+}
+
+/// The same messages as the `#[error(..)]` attributes above, for when thiserror is off
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::ConsecutiveRandom { value } => {
+                write!(f, "two consecutive random values found: {}", value)
+            }
+            Error::RecentRepeat { distance } => {
+                write!(f, "random value repeated the one {} draws back", distance)
+            }
+            Error::Exhausted => write!(f, "ran out of attempts to find an unused random value"),
+            Error::RetriesExhausted { attempts } => {
+                write!(f, "all {} draws repeated a recent random value", attempts)
+            }
+            Error::NoItems => write!(f, "nothing to choose from"),
+            Error::InvalidWeight(weight) => write!(
+                f,
+                "invalid weight {}, weights must be finite and positive",
+                weight
+            ),
+            Error::InvalidParameter(msg) => write!(f, "invalid parameter: {}", msg),
+        }
+    }
+}
+
+/// `std::io::Error` has no serde support, so it goes over the wire as its message and comes
+/// back as an `ErrorKind::Other` with that message
+#[cfg(all(feature = "serde", feature = "std"))]
+mod io_error {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(error: &std::io::Error, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(error)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<std::io::Error, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer).map(std::io::Error::other)
+    }
+}
+
+#[cfg(all(test, feature = "serde", feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_through_json() {
+        let json = serde_json::to_string(&Error::RecentRepeat { distance: 3 }).unwrap();
+        assert_eq!(json, r#"{"RecentRepeat":{"distance":3}}"#);
+        let back: Error = serde_json::from_str(&json).unwrap();
+        assert!(matches!(back, Error::RecentRepeat { distance: 3 }));
+
+        let io = Error::from(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no such file",
+        ));
+        let json = serde_json::to_string(&io).unwrap();
+        assert_eq!(json, r#"{"Io":"no such file"}"#);
+        let back: Error = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.to_string(), "no such file");
+    }
 }
@@ -1,5 +1,13 @@
-/// Recreate some std lib stuff to learn about Rust features
+//! Recreate some std lib stuff to learn about Rust features
+//!
+//! With `default-features = false` this is a `#![no_std]` crate, for embedded targets that
+//! have an allocator but no operating system. Tests always get std, they need it to run.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+/// `String` lives in `alloc`, which std re-exports. Without std we name the crate ourselves.
+extern crate alloc;
 
 /// Export our child modules
+pub mod error;
+pub mod my_option;
 pub mod my_result;
-pub mod error;
\ No newline at end of file
@@ -0,0 +1,251 @@
+use crate::my_result::MyResult;
+use core::fmt::{Debug, Formatter};
+
+/// Partially recreate `std::option::Option`, the other ADT every Rust program leans on.
+/// Where `MyResult` is "a value or an error", this is "a value or nothing", Rust's answer to
+/// `null`: the compiler makes us handle the `None` case before we can get at a value.
+///
+/// Unlike `MyResult` we derive these. A derive only adds the bound it needs, so
+/// `MyOption<T>` is `Copy` exactly when `T` is, which makes `as_ref`'s output `Copy` too.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MyOption<T>
+where
+    T: Debug,
+{
+    Some(T),
+    // A unit variant: it carries no data, so there's exactly one `None` for every `T`
+    None,
+}
+
+impl<T> MyOption<T>
+where
+    T: Debug,
+{
+    pub fn is_some(&self) -> bool {
+        // `matches!` is shorthand for a `match` with `true` and `_ => false` arms
+        matches!(self, MyOption::Some(_))
+    }
+
+    pub fn is_none(&self) -> bool {
+        !self.is_some()
+    }
+
+    pub fn unwrap(self) -> T {
+        match self {
+            MyOption::Some(val) => val,
+            MyOption::None => panic!("attempting to unwrap a None value"),
+        }
+    }
+
+    /// Like `unwrap`, with a message saying why we expected a value
+    pub fn expect(self, msg: &str) -> T {
+        match self {
+            MyOption::Some(val) => val,
+            MyOption::None => panic!("{}", msg),
+        }
+    }
+
+    pub fn unwrap_or(self, default: T) -> T {
+        match self {
+            MyOption::Some(val) => val,
+            MyOption::None => default,
+        }
+    }
+
+    /// The closure takes no arguments, there's no error to build the value from
+    pub fn unwrap_or_else<F>(self, f: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        match self {
+            MyOption::Some(val) => val,
+            MyOption::None => f(),
+        }
+    }
+
+    pub fn map<U, F>(self, f: F) -> MyOption<U>
+    where
+        U: Debug,
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            MyOption::Some(val) => MyOption::Some(f(val)),
+            MyOption::None => MyOption::None,
+        }
+    }
+
+    /// Chain a step that may itself come up empty, like `MyResult::and_then`
+    pub fn and_then<U, F>(self, f: F) -> MyOption<U>
+    where
+        U: Debug,
+        F: FnOnce(T) -> MyOption<U>,
+    {
+        match self {
+            MyOption::Some(val) => f(val),
+            MyOption::None => MyOption::None,
+        }
+    }
+
+    /// Borrow the contents, see `MyResult::as_ref`
+    pub fn as_ref(&self) -> MyOption<&T> {
+        match self {
+            MyOption::Some(val) => MyOption::Some(val),
+            MyOption::None => MyOption::None,
+        }
+    }
+
+    /// Move the value out and leave `None` behind. We only have `&mut self`, so we can't
+    /// move out of it directly; `core::mem::replace` swaps in the `None` and hands us the
+    /// old value in one step.
+    pub fn take(&mut self) -> MyOption<T> {
+        core::mem::replace(self, MyOption::None)
+    }
+
+    /// Turn "nothing" into an error, e.g.
+    /// `MyOption::from(map.get(key)).ok_or(Error::NoItems)`
+    pub fn ok_or<E>(self, err: E) -> MyResult<T, E>
+    where
+        E: Debug,
+    {
+        match self {
+            MyOption::Some(val) => MyResult::Ok(val),
+            MyOption::None => MyResult::Err(err),
+        }
+    }
+
+    /// `ok_or` with the error only made when it's needed
+    pub fn ok_or_else<E, F>(self, f: F) -> MyResult<T, E>
+    where
+        E: Debug,
+        F: FnOnce() -> E,
+    {
+        match self {
+            MyOption::Some(val) => MyResult::Ok(val),
+            MyOption::None => MyResult::Err(f()),
+        }
+    }
+
+    /// Convert to a std `Option`, e.g. to use `?` or `if let Some(..)`
+    pub fn into_option(self) -> Option<T> {
+        self.into()
+    }
+}
+
+impl<T> Debug for MyOption<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MyOption::Some(val) => write!(f, "Some({:?})", val),
+            MyOption::None => write!(f, "None"),
+        }
+    }
+}
+
+/// Empty by default, like `Option`
+impl<T> Default for MyOption<T>
+where
+    T: Debug,
+{
+    fn default() -> Self {
+        MyOption::None
+    }
+}
+
+impl<T> From<MyOption<T>> for Option<T>
+where
+    T: Debug,
+{
+    fn from(value: MyOption<T>) -> Self {
+        match value {
+            MyOption::Some(val) => Some(val),
+            MyOption::None => None,
+        }
+    }
+}
+
+impl<T> From<Option<T>> for MyOption<T>
+where
+    T: Debug,
+{
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(val) => MyOption::Some(val),
+            None => MyOption::None,
+        }
+    }
+}
+
+/// The value of a `MyResult`, dropping the error: the `MyOption` version of `MyResult::ok`
+impl<T, E> From<MyResult<T, E>> for MyOption<T>
+where
+    T: Debug,
+    E: Debug,
+{
+    fn from(value: MyResult<T, E>) -> Self {
+        match value {
+            MyResult::Ok(val) => MyOption::Some(val),
+            MyResult::Err(_) => MyOption::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "attempting to unwrap a None value")]
+    fn it_should_panic() {
+        MyOption::None::<u8>.unwrap();
+    }
+
+    #[test]
+    fn it_unwraps_with_fallbacks() {
+        let some = MyOption::Some(1u8);
+        let none = MyOption::<u8>::None;
+
+        assert!(some.is_some() && none.is_none());
+        assert_eq!(some.as_ref().unwrap(), &1);
+        assert_eq!(some.expect("is some"), 1);
+        assert_eq!(none.unwrap_or(2), 2);
+        assert_eq!(none.unwrap_or_else(|| 3), 3);
+        assert_eq!(MyOption::<u8>::default(), MyOption::None);
+        assert_eq!(format!("{:?} {:?}", some, none), "Some(1) None");
+    }
+
+    #[test]
+    fn it_maps_and_chains() {
+        let half = |n: u8| {
+            if n.is_multiple_of(2) {
+                MyOption::Some(n / 2)
+            } else {
+                MyOption::None
+            }
+        };
+        assert_eq!(MyOption::Some(2u8).map(|n| n * 2), MyOption::Some(4));
+        assert_eq!(MyOption::Some(8u8).and_then(half), MyOption::Some(4));
+        assert_eq!(MyOption::Some(7u8).and_then(half), MyOption::None);
+        assert_eq!(MyOption::None.and_then(half), MyOption::None);
+    }
+
+    #[test]
+    fn it_takes_the_value() {
+        let mut slot = MyOption::Some("value".to_string());
+        assert_eq!(slot.take().unwrap(), "value");
+        assert!(slot.is_none());
+        assert!(slot.take().is_none());
+    }
+
+    #[test]
+    fn it_converts_to_options_and_results() {
+        assert_eq!(MyOption::from(Some(1u8)).into_option(), Some(1));
+        assert_eq!(Option::<u8>::from(MyOption::None), None);
+        assert_eq!(MyOption::Some(1u8).ok_or("empty").unwrap(), 1);
+        assert_eq!(MyOption::<u8>::None.ok_or("empty").unwrap_err(), "empty");
+        assert_eq!(MyOption::<u8>::None.ok_or_else(|| 5).unwrap_err(), 5);
+        let from_result: MyOption<u8> = MyResult::<u8, ()>::Err(()).into();
+        assert!(from_result.is_none());
+    }
+}
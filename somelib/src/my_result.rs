@@ -1,8 +1,15 @@
-use std::fmt::{Debug, Formatter};
+// `core` is the part of std that works without an operating system, std re-exports it
+use core::fmt::{Debug, Formatter};
+#[cfg(feature = "std")]
 use std::process::{ExitCode, Termination};
 
 /// Partially recreate `std::result::Result` to show how Rust `enum`s / ADTs work
 /// Name this `MyResult` as `std::result::Result` is imported automatically
+///
+/// `cfg_attr` only applies the attribute when the `serde` feature is on. The derived
+/// representation is externally tagged, `{"Ok": value}` or `{"Err": error}`, the same as serde
+/// uses for `std::result::Result`, so the two are interchangeable on the wire.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MyResult<T, E>
 where
     T: Debug, // Since we want to be able to implement `Debug` below, our `T` and `E` should be `Debug`
@@ -55,13 +62,136 @@ where
         }
     }
 
-    /// Write the scaffold for returning the error but finish it later
+    /// The mirror image of `unwrap`: return `E` or panic
     pub fn unwrap_err(self) -> E {
         match self {
-            MyResult::Err(_err) => todo!(), // Incomplete, but will compile
-            _ => unimplemented!(),          // Same as `todo`
+            MyResult::Err(err) => err,
+            // While scaffolding, `todo!()` and `unimplemented!()` compile in any arm, but
+            // they panic with a message about unfinished code rather than about the value
+            _ => panic!("attempting to unwrap a nonexistent error"),
         }
     }
+
+    /// The mirror image of `expect`, e.g. in tests that a bad input is rejected
+    pub fn expect_err(self, msg: &str) -> E {
+        match self {
+            MyResult::Ok(val) => panic!("{}: {:?}", msg, val),
+            MyResult::Err(err) => err,
+        }
+    }
+
+    /// Like `unwrap` but with our own panic message, which should say what we expected:
+    /// `config.expect("config was validated at startup")`
+    pub fn expect(self, msg: &str) -> T {
+        match self {
+            MyResult::Ok(val) => val,
+            // `{:?}` is why `E` has to be `Debug`
+            MyResult::Err(err) => panic!("{}: {:?}", msg, err),
+        }
+    }
+
+    /// The value, or `default` for an error. `default` is evaluated even when it isn't
+    /// needed, so prefer `unwrap_or_else` if it's expensive to make.
+    pub fn unwrap_or(self, default: T) -> T {
+        match self {
+            MyResult::Ok(val) => val,
+            MyResult::Err(_) => default,
+        }
+    }
+
+    /// The value, or one computed from the error. `FnOnce` is the loosest closure bound:
+    /// we only call it once, so it may move things out of its environment.
+    pub fn unwrap_or_else<F>(self, f: F) -> T
+    where
+        F: FnOnce(E) -> T,
+    {
+        match self {
+            MyResult::Ok(val) => val,
+            MyResult::Err(err) => f(err),
+        }
+    }
+
+    /// Borrow the contents: `&MyResult<T, E>` becomes `MyResult<&T, &E>`, which lets us
+    /// use the consuming methods below without giving up the original
+    pub fn as_ref(&self) -> MyResult<&T, &E> {
+        match self {
+            MyResult::Ok(val) => MyResult::Ok(val),
+            MyResult::Err(err) => MyResult::Err(err),
+        }
+    }
+
+    /// The value as an `Option`, dropping the error
+    pub fn ok(self) -> Option<T> {
+        match self {
+            MyResult::Ok(val) => Some(val),
+            MyResult::Err(_) => None,
+        }
+    }
+
+    /// The error as an `Option`, dropping the value
+    pub fn err(self) -> Option<E> {
+        match self {
+            MyResult::Ok(_) => None,
+            MyResult::Err(err) => Some(err),
+        }
+    }
+
+    /// Transform the value and leave an error alone. The output type `U` is a new generic,
+    /// so a `MyResult<i32, E>` can become a `MyResult<String, E>`.
+    pub fn map<U, F>(self, f: F) -> MyResult<U, E>
+    where
+        U: Debug,
+        F: FnOnce(T) -> U,
+    {
+        match self {
+            MyResult::Ok(val) => MyResult::Ok(f(val)),
+            MyResult::Err(err) => MyResult::Err(err),
+        }
+    }
+
+    /// Transform the error and leave a value alone, e.g. to wrap a low-level error
+    pub fn map_err<G, F>(self, f: F) -> MyResult<T, G>
+    where
+        G: Debug,
+        F: FnOnce(E) -> G,
+    {
+        match self {
+            MyResult::Ok(val) => MyResult::Ok(val),
+            MyResult::Err(err) => MyResult::Err(f(err)),
+        }
+    }
+
+    /// Chain a step that can fail itself. `map` with such a closure would give us a nested
+    /// `MyResult<MyResult<U, E>, E>`; `and_then` flattens it.
+    pub fn and_then<U, F>(self, f: F) -> MyResult<U, E>
+    where
+        U: Debug,
+        F: FnOnce(T) -> MyResult<U, E>,
+    {
+        match self {
+            MyResult::Ok(val) => f(val),
+            MyResult::Err(err) => MyResult::Err(err),
+        }
+    }
+
+    /// The mirror image of `and_then`: try to recover from an error, e.g. with a fallback
+    pub fn or_else<G, F>(self, f: F) -> MyResult<T, G>
+    where
+        G: Debug,
+        F: FnOnce(E) -> MyResult<T, G>,
+    {
+        match self {
+            MyResult::Ok(val) => MyResult::Ok(val),
+            MyResult::Err(err) => f(err),
+        }
+    }
+
+    /// Convert to a std `Result`, e.g. to use `?`: `rando.get_random_item().into_result()?`.
+    /// The `From` impl below does the same, but a method reads better in a chain and
+    /// doesn't need the target type spelled out.
+    pub fn into_result(self) -> Result<T, E> {
+        self.into()
+    }
 }
 
 /// Here we manually implement `Debug` which will give us a string rep
@@ -76,7 +206,7 @@ where
     E: Debug,
 {
     /// We generally don't need to leave doc comments like this for `trait` implementations
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             // If we did not constrain `T` and `E` to `Debug`, we could not format them with `{:?}` here
             MyResult::Ok(val) => write!(f, "Ok({:?})", val),
@@ -86,7 +216,9 @@ where
 }
 
 /// We don't normally have to implement this, but we're doing it here so `MyResult`
-/// can be used as a return type for `main`.
+/// can be used as a return type for `main`. Exit codes are an operating system thing, so
+/// this needs std.
+#[cfg(feature = "std")]
 impl<T, E> Termination for MyResult<T, E>
 where
     T: Debug,
@@ -114,12 +246,26 @@ where
     }
 }
 
-/// Marker traits have empty implementations
-/// Can be moved across thread boundaries
-unsafe impl<T, E> Send for MyResult<T, E> {}
+/// And the other direction, so `Result`-returning code can hand back a `MyResult` with
+/// `.into()`
+impl<T, E> From<Result<T, E>> for MyResult<T, E>
+where
+    T: Debug,
+    E: Debug,
+{
+    fn from(value: Result<T, E>) -> Self {
+        match value {
+            Ok(val) => MyResult::Ok(val),
+            Err(err) => MyResult::Err(err),
+        }
+    }
+}
 
-/// Can have shared references between thread boundaries, requires `Send`
-unsafe impl<T, E> Sync for MyResult<T, E> {}
+// `Send` (can be moved across thread boundaries) and `Sync` (can have shared references
+// across thread boundaries) are *auto traits*: the compiler implements them for `MyResult`
+// exactly when both `T` and `E` implement them. Writing `unsafe impl<T, E> Send` by hand
+// would promise that even for `MyResult<Rc<u8>, E>`, which isn't true, so we don't.
+// `tests/ui/` has compile-fail tests showing a non-`Send` payload keeps it non-`Send`.
 
 /// Customary to put a test module in source files for *unit* tests
 #[cfg(test)]
@@ -139,4 +285,144 @@ mod tests {
         let result = MyResult::Err::<(), ()>(()); // The `::<_>` here is called *turbofish*
         result.unwrap();
     }
+
+    #[test]
+    fn it_converts_to_and_from_result() {
+        let ok: MyResult<u8, String> = Ok(1).into();
+        assert!(ok.is_ok());
+        assert_eq!(ok.into_result(), Ok(1));
+
+        let err = MyResult::<u8, String>::from(Err("nope".to_string()));
+        assert_eq!(err.into_result(), Err("nope".to_string()));
+
+        // `?` works on the converted value
+        fn sum(results: Vec<MyResult<u8, String>>) -> Result<u8, String> {
+            let mut total = 0;
+            for result in results {
+                total += result.into_result()?;
+            }
+            Ok(total)
+        }
+        assert_eq!(sum(vec![MyResult::Ok(1), MyResult::Ok(2)]), Ok(3));
+        assert!(sum(vec![MyResult::Ok(1), MyResult::Err("bad".into())]).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "attempting to unwrap a nonexistent error")]
+    fn it_should_panic_unwrapping_the_error_of_ok() {
+        let result = MyResult::Ok::<(), ()>(());
+        result.unwrap_err();
+    }
+
+    #[test]
+    #[should_panic(expected = "input should be rejected: 42")]
+    fn it_should_panic_with_the_expect_err_message() {
+        MyResult::Ok::<u8, ()>(42).expect_err("input should be rejected");
+    }
+
+    #[test]
+    fn it_unwraps_errors() {
+        assert_eq!(MyResult::Err::<(), &str>("bad").unwrap_err(), "bad");
+        assert_eq!(
+            MyResult::Err::<(), &str>("bad").expect_err("is an error"),
+            "bad"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "needed a number: \"nope\"")]
+    fn it_should_panic_with_the_expect_message() {
+        MyResult::Err::<u8, &str>("nope").expect("needed a number");
+    }
+
+    #[test]
+    fn it_unwraps_with_fallbacks() {
+        let ok = MyResult::Ok::<u8, &str>(1);
+        let err = MyResult::Err::<u8, &str>("bad");
+
+        assert_eq!(ok.as_ref().unwrap(), &1);
+        assert_eq!(ok.expect("is ok"), 1);
+        assert_eq!(err.as_ref().unwrap_or(&0), &0);
+        assert_eq!(
+            err.as_ref().map(|n| *n).unwrap_or_else(|e| e.len() as u8),
+            3
+        );
+        // `as_ref` left `err` usable
+        assert_eq!(err.unwrap_or(2), 2);
+        assert_eq!(MyResult::Ok::<u8, &str>(1).unwrap_or_else(|_| 5), 1);
+    }
+
+    #[test]
+    fn it_converts_to_options() {
+        assert_eq!(MyResult::Ok::<u8, &str>(1).ok(), Some(1));
+        assert_eq!(MyResult::Err::<u8, &str>("bad").ok(), None);
+        assert_eq!(MyResult::Ok::<u8, &str>(1).err(), None);
+        assert_eq!(MyResult::Err::<u8, &str>("bad").err(), Some("bad"));
+    }
+
+    #[test]
+    fn it_maps_values_and_errors() {
+        let doubled = MyResult::Ok::<u8, &str>(2).map(|n| n * 2);
+        assert_eq!(doubled.unwrap(), 4);
+        let described = MyResult::Ok::<u8, &str>(2).map(|n| format!("#{}", n));
+        assert_eq!(described.unwrap(), "#2");
+        assert_eq!(
+            MyResult::Err::<u8, &str>("bad").map(|n| n * 2).err(),
+            Some("bad")
+        );
+
+        let wrapped = MyResult::Err::<u8, &str>("bad").map_err(|e| format!("wrapped: {}", e));
+        assert_eq!(wrapped.err().unwrap(), "wrapped: bad");
+        assert_eq!(
+            MyResult::Ok::<u8, &str>(1).map_err(|e| e.len()).ok(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn it_chains_fallible_steps() {
+        let parse =
+            |s: &str| -> MyResult<u8, String> { s.parse::<u8>().map_err(|e| e.to_string()).into() };
+        let halve = |n: u8| {
+            if n.is_multiple_of(2) {
+                MyResult::Ok(n / 2)
+            } else {
+                MyResult::Err(format!("{} is odd", n))
+            }
+        };
+
+        assert_eq!(parse("8").and_then(halve).ok(), Some(4));
+        assert_eq!(parse("7").and_then(halve).err().unwrap(), "7 is odd");
+        assert!(parse("x").and_then(halve).is_err());
+
+        let fallback = parse("x").or_else(|_| parse("3"));
+        assert_eq!(fallback.ok(), Some(3));
+        let still_bad = parse("x").or_else(|e| MyResult::Err::<u8, usize>(e.len()));
+        assert!(still_bad.err().unwrap() > 0);
+        assert_eq!(parse("1").or_else(|_| parse("2")).ok(), Some(1));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_serializes_like_std_result() {
+        let ok = MyResult::<u8, String>::Ok(1);
+        let err = MyResult::<u8, String>::Err("nope".into());
+
+        assert_eq!(serde_json::to_string(&ok).unwrap(), r#"{"Ok":1}"#);
+        assert_eq!(
+            serde_json::to_string(&err).unwrap(),
+            serde_json::to_string(&Err::<u8, String>("nope".into())).unwrap()
+        );
+        let back: MyResult<u8, String> = serde_json::from_str(r#"{"Err":"nope"}"#).unwrap();
+        assert_eq!(back.unwrap_err(), "nope");
+    }
+
+    /// Compiles only if `T: Send + Sync`, a static check with nothing to run
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn it_is_send_and_sync_when_its_payloads_are() {
+        assert_send_sync::<MyResult<u8, String>>();
+        assert_send_sync::<MyResult<Vec<u8>, crate::error::Error>>();
+    }
 }
@@ -0,0 +1,9 @@
+//! Some guarantees are about what *doesn't* compile. `trybuild` compiles each file in
+//! `tests/ui/` and checks it fails with the error saved next to it in a `.stderr` file.
+//! After an intended change to the errors, regenerate those with `TRYBUILD=overwrite`.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
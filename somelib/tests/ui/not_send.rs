@@ -0,0 +1,9 @@
+// An `Rc` can't cross threads, so neither can a `MyResult` holding one
+use somelib::my_result::MyResult;
+use std::rc::Rc;
+
+fn assert_send<T: Send>() {}
+
+fn main() {
+    assert_send::<MyResult<Rc<u8>, String>>();
+}
@@ -0,0 +1,9 @@
+// A `Cell` can be sent but not shared, and the same goes for a `MyResult` holding one
+use somelib::my_result::MyResult;
+use std::cell::Cell;
+
+fn assert_sync<T: Sync>() {}
+
+fn main() {
+    assert_sync::<MyResult<u8, Cell<u8>>>();
+}